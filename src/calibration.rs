@@ -0,0 +1,179 @@
+// src/calibration.rs
+// 校准结果持久化：保存到 data/calibration.json，下次连接时可直接载入；
+// 记录板卡/导联/通道数，不一致时拒绝载入，避免把过期的校准套到另一套电极上
+use crate::montage::Montage;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CalibrationError {
+    #[error("no saved calibration: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid calibration file: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("saved calibration has {saved} channels, the board has {actual}")]
+    ChannelCount { saved: usize, actual: usize },
+    #[error("saved calibration was taken on {saved}, connected board is {actual}")]
+    Board { saved: String, actual: String },
+    #[error("saved calibration used skipped channels {saved:?}, montage now skips {actual:?}")]
+    Montage {
+        saved: Vec<usize>,
+        actual: Vec<usize>,
+    },
+}
+
+/// Where the default threshold sits between the rest and action peaks (the midpoint).
+pub const DEFAULT_THRESHOLD_BLEND: f64 = 0.5;
+
+/// Rest and action peaks (|µV|) captured by one guided calibration.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GuidedCapture {
+    pub rest_channels: Vec<f64>,
+    pub rest_max: f64,
+    pub action_channels: Vec<f64>,
+    pub action_max: f64,
+}
+
+/// Threshold `blend` of the way from the rest peak (0) to the action peak (1);
+/// `None` when the action peak does not clear the rest peak.
+pub fn blend_threshold(rest: f64, action: f64, blend: f64) -> Option<f64> {
+    (rest > 0.0 && action > rest).then(|| rest + (action - rest) * blend.clamp(0.0, 1.0))
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationProfile {
+    /// Board the calibration was taken on, e.g. "Cyton+Daisy" or "Simulation".
+    pub board: String,
+    /// Reference/bias channels the montage excluded from decode at the time.
+    pub skip_channels: Vec<usize>,
+    /// Overall decode threshold sent to the engine.
+    pub threshold: f64,
+    /// Each channel's threshold between its rest and action peaks, or the overall one where
+    /// the action peak does not clear rest; its length is the channel count.
+    pub channel_thresholds: Vec<f64>,
+}
+
+impl CalibrationProfile {
+    pub fn store_path() -> PathBuf {
+        PathBuf::from("data/calibration.json")
+    }
+
+    pub fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    /// Loads the profile at `path` without checking it against any setup.
+    pub fn load(path: &Path) -> Result<Self, CalibrationError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Loads the profile at `path` and checks it belongs to the connected setup.
+    pub fn load_matching(
+        path: &Path,
+        board: &str,
+        channel_count: usize,
+        montage: &Montage,
+    ) -> Result<Self, CalibrationError> {
+        let profile = Self::load(path)?;
+        profile.check_matches(board, channel_count, montage)?;
+        Ok(profile)
+    }
+
+    pub fn check_matches(
+        &self,
+        board: &str,
+        channel_count: usize,
+        montage: &Montage,
+    ) -> Result<(), CalibrationError> {
+        if self.board != board {
+            return Err(CalibrationError::Board {
+                saved: self.board.clone(),
+                actual: board.to_owned(),
+            });
+        }
+        if self.channel_thresholds.len() != channel_count {
+            return Err(CalibrationError::ChannelCount {
+                saved: self.channel_thresholds.len(),
+                actual: channel_count,
+            });
+        }
+        let mut saved = self.skip_channels.clone();
+        let mut actual = montage.skip_channels.clone();
+        saved.sort_unstable();
+        actual.sort_unstable();
+        if saved != actual {
+            return Err(CalibrationError::Montage { saved, actual });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_thresholds_round_trip_and_reject_other_channel_counts() {
+        let dir = std::env::temp_dir().join(format!("neurostick_calib_{}", std::process::id()));
+        let path = dir.join("calibration.json");
+        let mut montage = Montage::default();
+        montage.set_skip_first(2);
+        let profile = CalibrationProfile {
+            board: "Cyton+Daisy".to_owned(),
+            skip_channels: montage.skip_channels.clone(),
+            threshold: 182.5,
+            channel_thresholds: (0..16).map(|ch| 100.0 + ch as f64).collect(),
+        };
+        profile.save_to(&path).unwrap();
+        let loaded = CalibrationProfile::load_matching(&path, "Cyton+Daisy", 16, &montage).unwrap();
+        assert_eq!(loaded, profile);
+        assert!(matches!(
+            CalibrationProfile::load_matching(&path, "Cyton+Daisy", 8, &montage),
+            Err(CalibrationError::ChannelCount {
+                saved: 16,
+                actual: 8
+            })
+        ));
+        assert!(matches!(
+            CalibrationProfile::load_matching(&path, "Cyton+Daisy", 16, &Montage::default()),
+            Err(CalibrationError::Montage { .. })
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn threshold_sits_the_blend_fraction_from_rest_to_action() {
+        assert_eq!(
+            blend_threshold(40.0, 240.0, DEFAULT_THRESHOLD_BLEND),
+            Some(140.0)
+        );
+        assert_eq!(blend_threshold(40.0, 240.0, 0.6), Some(160.0));
+        assert_eq!(blend_threshold(40.0, 240.0, 2.0), Some(240.0));
+        // 动作峰值不高于静息时不给阈值
+        assert_eq!(blend_threshold(40.0, 40.0, 0.5), None);
+        assert_eq!(blend_threshold(0.0, 240.0, 0.5), None);
+    }
+
+    #[test]
+    fn missing_and_malformed_files_are_told_apart() {
+        let dir = std::env::temp_dir().join(format!("neurostick_calib_bad_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("calibration.json");
+        assert!(matches!(
+            CalibrationProfile::load(&path),
+            Err(CalibrationError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound
+        ));
+        fs::write(&path, "{\"threshold\": ").unwrap();
+        assert!(matches!(
+            CalibrationProfile::load(&path),
+            Err(CalibrationError::Parse(_))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
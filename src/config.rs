@@ -0,0 +1,401 @@
+// src/config.rs
+use crate::calibration::DEFAULT_THRESHOLD_BLEND;
+use crate::drivers::{CytonLeadOff, EegBand};
+use crate::engine::FeatureKind;
+use crate::montage::Montage;
+use crate::openbci::BoardKind;
+use crate::types::{AxisGains, SimKey, StickResponse, TriggerDebounce};
+use crate::visualizer::ControllerStyle;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+/// UI settings persisted between launches in `data/config.toml`.
+///
+/// Every field has a default, so a missing or partial file still loads.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// UI language code ("en" / "zh"); unset falls back to the legacy
+    /// `data/last_language.txt` written by older versions.
+    pub language: Option<String>,
+    pub theme_dark: bool,
+    /// Serial port of the last session; ignored when that port is no longer present.
+    pub selected_port: String,
+    /// Visible waveform span in seconds.
+    pub wave_window_seconds: f64,
+    pub signal_sensitivity: f64,
+    /// Trace smoothing factor (0 or 1 = off), applied as the running mean of matching lag.
+    pub smooth_alpha: f64,
+    /// Waveform band-pass preset; `None` is broadband.
+    pub wave_band: Option<EegBand>,
+    /// Mains notch on every channel; `wave_notch_channels` keeps per-channel picks.
+    pub wave_notch_50hz: bool,
+    pub wave_notch_channels: Vec<bool>,
+    /// Spectrum tab FFT size in samples.
+    pub fft_size: usize,
+    /// Live waveform line width in points.
+    pub trace_width: f32,
+    /// Live waveform line opacity (0..1).
+    pub trace_alpha: f32,
+    /// Channel count of the last session, used for the empty placeholder lanes.
+    pub last_channel_count: usize,
+    /// While the waveform is frozen, compute the spectrum from the scrubbed window.
+    pub spectrum_follows_scrub: bool,
+    /// Per-lane vertical zoom in the stacked view (display only; missing lanes are 1x).
+    pub channel_zoom: Vec<f32>,
+    /// Trailing window (seconds) the drop-rate stat is averaged over.
+    pub drop_rate_window_secs: f64,
+    /// Show the measured sample rate and sample count next to the drop rate.
+    pub show_effective_rate: bool,
+    /// Shade controller buttons by how far each decoded action cleared its threshold.
+    pub show_decode_confidence: bool,
+    /// Run the DSP/FFT self-test when the app starts.
+    pub self_test_on_startup: bool,
+    /// How often (Hz) the per-lane min/max/y-range text is refreshed.
+    pub stats_refresh_hz: f64,
+    /// Flash a warning when a channel becomes railed mid-session.
+    pub lead_off_alert: bool,
+    /// Also play the system alert sound.
+    pub lead_off_beep: bool,
+    /// How long a channel must stay railed (or recovered) before its state flips.
+    pub lead_off_debounce_secs: f64,
+    /// Reference/bias channels excluded from decode (and optionally hidden).
+    pub montage: Montage,
+    /// Show the inter-channel correlation heatmap in the impedance tab.
+    pub show_correlation: bool,
+    /// Per-channel acceptable impedance in kΩ; 0 (or missing) uses the global bands.
+    pub impedance_limits_kohms: Vec<f32>,
+    /// Cyton lead-off drive current and series resistor used for impedance readings.
+    pub cyton_lead_off: CytonLeadOff,
+    /// Raw samples beyond ±this many µV are treated as glitches and replaced.
+    pub sample_clamp_uv: f64,
+    /// Let the engine stream a Hann-windowed sliding spectrum instead of computing it on demand.
+    pub live_spectrum: bool,
+    /// Live spectra per second.
+    pub live_spectrum_hz: f32,
+    /// Band shown by the focused (decode band) spectrum view.
+    pub focus_band_hz: (f32, f32),
+    /// Show per-channel SNR of `snr_band` against the broadband noise floor.
+    pub show_band_snr: bool,
+    pub snr_band: EegBand,
+    /// How many recent spectra the spectral-flux readout keeps; older ones are dropped.
+    pub spectrum_history_len: usize,
+    /// Decode through the same per-channel filters as the waveform display.
+    pub link_filters: bool,
+    /// Mains frequency notched out of the display and decode paths (50 or 60 Hz).
+    pub mains_hz: f32,
+    /// Append the display filters' magnitude response to feedback reports.
+    pub report_filter_response: bool,
+    /// BrainFlow board opened in hardware mode; Synthetic needs no serial port.
+    pub board: BoardKind,
+    /// Reconnect automatically when a hardware stream stops delivering data.
+    pub auto_reconnect: bool,
+    /// UDP `host:port` the OSC output sends to.
+    pub osc_addr: String,
+    /// `host:port` the WebSocket server listens on.
+    pub ws_addr: String,
+    /// Simulation-mode key (`egui::Key` name) per input slot; slots left out keep
+    /// the default WASD / IJKL / ZXC layout.
+    pub sim_key_bindings: BTreeMap<SimKey, String>,
+    /// Board sample rate the simulator emulates (125, 250 or 500 Hz).
+    pub sim_sample_rate_hz: f32,
+    /// Where the calibrated threshold sits from the rest peak (0) to the action peak (1).
+    pub calibration_blend: f64,
+    /// Pad drawn by the controller visualizer.
+    pub controller_style: ControllerStyle,
+    /// Cap how often decoded states are written to vJoy (games poll at their own rate).
+    pub vjoy_rate_limit: bool,
+    pub vjoy_rate_hz: f32,
+    /// Stick sensitivity per axis, applied before vJoy scaling.
+    pub axis_gains: AxisGains,
+    /// Stick dead zone and response curve, applied before vJoy scaling.
+    pub stick_response: StickResponse,
+    /// Release hysteresis and hold time for decoded buttons.
+    pub trigger_debounce: TriggerDebounce,
+    /// Apply the calibration saved in `data/calibration.json` on connect instead of only offering it.
+    pub reload_calibration: bool,
+    /// What the decoder thresholds: the raw sample or a sliding-window feature.
+    pub decode_feature: FeatureKind,
+    /// Window length of the decode feature, in samples.
+    pub decode_feature_window: usize,
+    /// Native window size/position/maximized state from the last session.
+    pub window: WindowGeometry,
+}
+/// Native window geometry, in egui points.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowGeometry {
+    pub size: [f32; 2],
+    /// Outer top-left corner; `None` lets the OS place the window.
+    pub position: Option<[f32; 2]>,
+    pub maximized: bool,
+    /// Monitor the window was last on, so a restore can be clamped before the first frame.
+    pub monitor_size: Option<[f32; 2]>,
+    /// That monitor's top-left corner on the virtual desktop; `None` where the platform
+    /// does not report it.
+    pub monitor_origin: Option<[f32; 2]>,
+}
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            size: [1463.0, 915.0],
+            position: None,
+            maximized: true,
+            monitor_size: None,
+            monitor_origin: None,
+        }
+    }
+}
+impl WindowGeometry {
+    pub const MIN_SIZE: [f32; 2] = [1200.0, 760.0];
+    /// Shrinks the window to fit `monitor` and moves it fully onto that monitor, whose
+    /// top-left corner is `origin` in virtual-desktop coordinates, e.g. after the monitor
+    /// it was saved on has been unplugged or its resolution lowered. Without an origin
+    /// only the size is clamped, since the position may be on any monitor.
+    pub fn clamped_to(&self, origin: Option<[f32; 2]>, monitor: [f32; 2]) -> Self {
+        let mut out = self.clone();
+        out.size = [0, 1].map(|axis| self.size[axis].max(Self::MIN_SIZE[axis]).min(monitor[axis]));
+        if let Some(origin) = origin {
+            out.position = self.position.map(|pos| {
+                [0, 1].map(|axis| {
+                    let min = origin[axis];
+                    let max = min + (monitor[axis] - out.size[axis]).max(0.0);
+                    if pos[axis].is_finite() {
+                        pos[axis].clamp(min, max)
+                    } else {
+                        min
+                    }
+                })
+            });
+        }
+        out.monitor_size = Some(monitor);
+        out.monitor_origin = origin;
+        out
+    }
+    /// The saved geometry clamped to the last known monitor, if any.
+    pub fn restored(&self) -> Self {
+        match self.monitor_size {
+            Some(monitor) => self.clamped_to(self.monitor_origin, monitor),
+            None => self.clone(),
+        }
+    }
+}
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            language: None,
+            theme_dark: false,
+            selected_port: String::new(),
+            wave_window_seconds: 30.0,
+            signal_sensitivity: 1.0,
+            smooth_alpha: 0.18,
+            wave_band: None,
+            wave_notch_50hz: false,
+            wave_notch_channels: Vec::new(),
+            fft_size: 256,
+            trace_width: 1.0,
+            trace_alpha: 1.0,
+            last_channel_count: 16,
+            spectrum_follows_scrub: true,
+            channel_zoom: Vec::new(),
+            drop_rate_window_secs: 5.0,
+            show_effective_rate: false,
+            show_decode_confidence: false,
+            self_test_on_startup: false,
+            stats_refresh_hz: 2.0,
+            lead_off_alert: true,
+            lead_off_beep: false,
+            lead_off_debounce_secs: 2.0,
+            montage: Montage::default(),
+            show_correlation: false,
+            impedance_limits_kohms: Vec::new(),
+            cyton_lead_off: CytonLeadOff::default(),
+            sample_clamp_uv: crate::engine::DEFAULT_SAMPLE_CLAMP_UV,
+            live_spectrum: false,
+            live_spectrum_hz: 10.0,
+            spectrum_history_len: 120,
+            focus_band_hz: (8.0, 30.0),
+            show_band_snr: false,
+            snr_band: EegBand::Alpha,
+            link_filters: false,
+            mains_hz: 50.0,
+            report_filter_response: true,
+            board: BoardKind::default(),
+            auto_reconnect: false,
+            osc_addr: "127.0.0.1:9000".to_owned(),
+            ws_addr: "127.0.0.1:8765".to_owned(),
+            sim_key_bindings: BTreeMap::new(),
+            sim_sample_rate_hz: 250.0,
+            calibration_blend: DEFAULT_THRESHOLD_BLEND,
+            controller_style: ControllerStyle::Xbox,
+            vjoy_rate_limit: false,
+            vjoy_rate_hz: 125.0,
+            axis_gains: AxisGains::default(),
+            stick_response: StickResponse::default(),
+            trigger_debounce: TriggerDebounce::default(),
+            reload_calibration: false,
+            decode_feature: FeatureKind::Instant,
+            decode_feature_window: 25,
+            window: WindowGeometry::default(),
+        }
+    }
+}
+impl AppConfig {
+    pub fn store_path() -> PathBuf {
+        PathBuf::from("data/config.toml")
+    }
+    pub fn load() -> Self {
+        Self::load_reporting().0
+    }
+    /// Like `load`, plus one message per setting that could not be read.
+    pub fn load_reporting() -> (Self, Vec<String>) {
+        let Ok(raw) = fs::read_to_string(Self::store_path()) else {
+            return (Self::default(), Vec::new());
+        };
+        let (config, problems) = Self::from_toml_lenient(&raw);
+        for problem in &problems {
+            log::warn!("{}: {problem}", Self::store_path().display());
+        }
+        (config, problems)
+    }
+    /// Parses one field at a time, so a field with an unknown variant or the wrong type
+    /// keeps its default without discarding the rest of the file.
+    pub fn from_toml_lenient(raw: &str) -> (Self, Vec<String>) {
+        let table: toml::Table = match raw.parse() {
+            Ok(table) => table,
+            Err(e) => return (Self::default(), vec![format!("not valid TOML: {e}")]),
+        };
+        let mut kept = toml::Table::new();
+        let mut problems = Vec::new();
+        for (key, value) in table {
+            let single = toml::Table::from_iter([(key.clone(), value.clone())]);
+            match toml::Value::Table(single).try_into::<Self>() {
+                Ok(_) => {
+                    kept.insert(key, value);
+                }
+                Err(e) => problems.push(format!("{key}: {}", e.message())),
+            }
+        }
+        let config = toml::Value::Table(kept).try_into().unwrap_or_default();
+        (config, problems)
+    }
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = Self::store_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // 无法解析的旧文件先另存为 .bak，不被默认值覆盖
+        if let Ok(old) = fs::read_to_string(&path) {
+            if old.parse::<toml::Table>().is_err() {
+                fs::rename(&path, path.with_extension("toml.bak"))?;
+            }
+        }
+        let raw = toml::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        fs::write(path, raw)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn partial_config_fills_defaults() {
+        let cfg: AppConfig = toml::from_str("trace_width = 2.5").unwrap();
+        assert_eq!(cfg.trace_width, 2.5);
+        assert_eq!(cfg.trace_alpha, AppConfig::default().trace_alpha);
+    }
+    #[test]
+    fn old_band_power_feature_loads_as_log_power() {
+        let cfg: AppConfig = toml::from_str("decode_feature = \"band_power\"").unwrap();
+        assert_eq!(cfg.decode_feature, FeatureKind::LogPower);
+    }
+    #[test]
+    fn one_bad_field_keeps_the_others_loaded() {
+        let raw = "trace_width = 2.5\ncontroller_style = \"gamecube\"\nfft_size = \"big\"\nmains_hz = 60.0";
+        let (cfg, problems) = AppConfig::from_toml_lenient(raw);
+        assert_eq!((cfg.trace_width, cfg.mains_hz), (2.5, 60.0));
+        assert_eq!(cfg.controller_style, AppConfig::default().controller_style);
+        assert_eq!(cfg.fft_size, AppConfig::default().fft_size);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(
+            problems[0].starts_with("controller_style: "),
+            "{problems:?}"
+        );
+        let (_, problems) = AppConfig::from_toml_lenient("trace_width = [");
+        assert_eq!(problems.len(), 1);
+    }
+    #[test]
+    fn ui_settings_round_trip_and_old_files_keep_their_defaults() {
+        let cfg = AppConfig {
+            language: Some("zh".into()),
+            theme_dark: true,
+            selected_port: "COM7".into(),
+            wave_window_seconds: 10.0,
+            signal_sensitivity: 2.5,
+            smooth_alpha: 0.5,
+            wave_band: Some(EegBand::Theta),
+            wave_notch_50hz: true,
+            wave_notch_channels: vec![true; 4],
+            fft_size: 512,
+            sim_key_bindings: BTreeMap::from([(SimKey::W, "Z".to_owned())]),
+            ..AppConfig::default()
+        };
+        let back: AppConfig = toml::from_str(&toml::to_string_pretty(&cfg).unwrap()).unwrap();
+        assert_eq!(back.language.as_deref(), Some("zh"));
+        assert!(back.theme_dark && back.wave_notch_50hz);
+        assert_eq!(back.selected_port, "COM7");
+        assert_eq!((back.wave_window_seconds, back.fft_size), (10.0, 512));
+        assert_eq!((back.signal_sensitivity, back.smooth_alpha), (2.5, 0.5));
+        assert_eq!(back.wave_notch_channels, vec![true; 4]);
+        assert_eq!(back.wave_band, Some(EegBand::Theta));
+        assert_eq!(back.sim_key_bindings, cfg.sim_key_bindings);
+        // 旧版配置文件没有这些字段
+        let old: AppConfig = toml::from_str("trace_width = 2.0\nmains_hz = 60.0").unwrap();
+        assert_eq!((old.language, old.wave_band), (None, None));
+        assert_eq!(old.fft_size, 256);
+        assert_eq!(old.wave_window_seconds, 30.0);
+        assert!(old.selected_port.is_empty());
+        assert!(old.sim_key_bindings.is_empty());
+    }
+    #[test]
+    fn off_screen_window_is_brought_back_on_screen() {
+        let saved = WindowGeometry {
+            size: [1600.0, 900.0],
+            // last seen on a second monitor to the right that is now gone
+            position: Some([2400.0, -50.0]),
+            maximized: false,
+            monitor_size: Some([3840.0, 1080.0]),
+            monitor_origin: None,
+        };
+        let restored = saved.clamped_to(Some([0.0, 0.0]), [1920.0, 1080.0]);
+        assert_eq!(restored.size, [1600.0, 900.0]);
+        assert_eq!(restored.position, Some([320.0, 0.0]));
+        let tiny = saved.clamped_to(Some([0.0, 0.0]), [1024.0, 700.0]);
+        assert_eq!(tiny.size, [1024.0, 700.0]);
+        assert_eq!(tiny.position, Some([0.0, 0.0]));
+    }
+    #[test]
+    fn window_on_a_second_monitor_stays_there() {
+        let saved = WindowGeometry {
+            size: [1600.0, 900.0],
+            position: Some([2000.0, 40.0]),
+            maximized: false,
+            monitor_size: Some([1920.0, 1080.0]),
+            monitor_origin: Some([1920.0, 0.0]),
+        };
+        assert_eq!(saved.restored(), saved);
+        // Hanging off the right edge of that monitor, it is pulled back onto it.
+        let off = WindowGeometry {
+            position: Some([3500.0, 40.0]),
+            ..saved.clone()
+        };
+        assert_eq!(off.restored().position, Some([2240.0, 40.0]));
+        // Without an origin the position is left to the OS coordinates it came from.
+        let unknown = WindowGeometry {
+            monitor_origin: None,
+            ..saved.clone()
+        };
+        assert_eq!(unknown.restored().position, saved.position);
+    }
+}
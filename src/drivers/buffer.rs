@@ -1,4 +1,6 @@
 use std::collections::VecDeque;
+#[cfg(test)]
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use crate::drivers::ModelizeError;
 use crate::drivers::SignalBatch;
 /// Flattened view of the current time-domain buffer.
@@ -7,6 +9,8 @@ pub struct TimeSeriesFrame {
     pub sample_rate_hz: f32,
     pub channel_labels: Vec<String>,
     pub samples: Vec<Vec<f32>>, // channels x samples
+    /// Time of each sample in seconds, parallel to every channel; empty when unknown.
+    pub timestamps: Vec<f64>,
 }
 impl TimeSeriesFrame {
     pub fn duration_seconds(&self) -> f32 {
@@ -15,10 +19,43 @@ impl TimeSeriesFrame {
             .map(|c| c.len() as f32 / self.sample_rate_hz)
             .unwrap_or(0.0)
     }
+    /// Pearson correlation between every pair of channels over the frame.
+    /// A flat channel correlates with nothing, so its off-diagonal entries are 0.
+    pub fn correlation_matrix(&self) -> Vec<Vec<f32>> {
+        let centered: Vec<(Vec<f32>, f32)> = self
+            .samples
+            .iter()
+            .map(|ch| {
+                let mean = ch.iter().sum::<f32>() / ch.len().max(1) as f32;
+                let dev: Vec<f32> = ch.iter().map(|v| v - mean).collect();
+                let norm = dev.iter().map(|d| d * d).sum::<f32>().sqrt();
+                (dev, norm)
+            })
+            .collect();
+        let n = centered.len();
+        (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| {
+                        let ((a, na), (b, nb)) = (&centered[i], &centered[j]);
+                        if i == j {
+                            1.0
+                        } else if *na <= f32::EPSILON || *nb <= f32::EPSILON {
+                            0.0
+                        } else {
+                            let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+                            (dot / (na * nb)).clamp(-1.0, 1.0)
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
 }
 /// Rolling buffer that stores recent samples per channel.
 pub struct SignalBuffer {
     per_channel: Vec<VecDeque<f32>>, // channel -> samples
+    timestamps: VecDeque<f64>,
     channel_labels: Vec<String>,
     sample_rate_hz: f32,
     capacity: usize,
@@ -39,11 +76,13 @@ impl SignalBuffer {
             .collect();
         Ok(Self {
             per_channel,
+            timestamps: VecDeque::with_capacity(capacity),
             channel_labels,
             sample_rate_hz,
             capacity,
         })
     }
+    #[cfg(test)]
     pub fn sample_rate_hz(&self) -> f32 {
         self.sample_rate_hz
     }
@@ -72,6 +111,23 @@ impl SignalBuffer {
                 channel_queue.push_back(sample);
             }
         }
+        // 设备没有时间戳时按采样率从上一个时间 (或批次开始时刻) 往后推
+        let dt = 1.0 / self.sample_rate_hz as f64;
+        let mut next = self.timestamps.back().map(|t| t + dt).unwrap_or_else(|| {
+            batch
+                .started_at
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs_f64())
+                .unwrap_or(0.0)
+        });
+        for idx in 0..batch.samples_per_channel().unwrap_or(0) {
+            let t = batch.timestamps.get(idx).copied().unwrap_or(next);
+            if self.timestamps.len() == self.capacity {
+                self.timestamps.pop_front();
+            }
+            self.timestamps.push_back(t);
+            next = t + dt;
+        }
         Ok(())
     }
     pub fn snapshot(&self, seconds: f32) -> TimeSeriesFrame {
@@ -85,9 +141,133 @@ impl SignalBuffer {
             sample_rate_hz: self.sample_rate_hz,
             channel_labels: self.channel_labels.clone(),
             samples,
+            timestamps: self.timestamps.iter().rev().take(take).rev().cloned().collect(),
         }
     }
     pub fn full_frame(&self) -> TimeSeriesFrame {
         self.snapshot(self.capacity as f32 / self.sample_rate_hz)
     }
 }
+/// `SignalBuffer` shared between a producer thread and any number of readers. Each call
+/// holds the lock for exactly one `push_batch` or one snapshot copy; readers get owned
+/// frames, so no lock is held while they process the data. The app keeps the
+/// engine-owned buffer and ships snapshots over its channel, so nothing outside the
+/// tests builds one yet.
+#[cfg(test)]
+#[derive(Clone)]
+pub struct SharedSignalBuffer(Arc<Mutex<SignalBuffer>>);
+#[cfg(test)]
+impl SharedSignalBuffer {
+    pub fn new(buffer: SignalBuffer) -> Self {
+        Self(Arc::new(Mutex::new(buffer)))
+    }
+    pub fn push_batch(&self, batch: &SignalBatch) -> Result<(), ModelizeError> {
+        lock(&self.0).push_batch(batch)
+    }
+    pub fn snapshot(&self, seconds: f32) -> TimeSeriesFrame {
+        lock(&self.0).snapshot(seconds)
+    }
+    /// Read-only handle for another thread; it sees every batch pushed through `self`.
+    pub fn subscribe(&self) -> SignalBufferReader {
+        SignalBufferReader(Arc::clone(&self.0))
+    }
+}
+/// Snapshot-only view of a `SharedSignalBuffer`.
+#[cfg(test)]
+#[derive(Clone)]
+pub struct SignalBufferReader(Arc<Mutex<SignalBuffer>>);
+#[cfg(test)]
+impl SignalBufferReader {
+    pub fn snapshot(&self, seconds: f32) -> TimeSeriesFrame {
+        lock(&self.0).snapshot(seconds)
+    }
+    pub fn sample_rate_hz(&self) -> f32 {
+        lock(&self.0).sample_rate_hz()
+    }
+}
+/// A writer that panicked mid-push leaves whole samples behind, so readers keep going.
+#[cfg(test)]
+fn lock(buffer: &Mutex<SignalBuffer>) -> MutexGuard<'_, SignalBuffer> {
+    buffer.lock().unwrap_or_else(PoisonError::into_inner)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn identical_channels_correlate_and_orthogonal_ones_do_not() {
+        let n = 1000;
+        let tone = |f: f32| -> Vec<f32> {
+            (0..n)
+                .map(|i| (2.0 * std::f32::consts::PI * f * i as f32 / n as f32).sin())
+                .collect()
+        };
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: vec!["A".into(), "B".into(), "C".into()],
+            samples: vec![tone(7.0), tone(7.0), tone(13.0)],
+            timestamps: Vec::new(),
+        };
+        let m = frame.correlation_matrix();
+        assert!((m[0][1] - 1.0).abs() < 1e-3);
+        assert!(m[0][2].abs() < 1e-3);
+        assert_eq!(m[2][0], m[0][2]);
+    }
+    #[test]
+    fn device_timestamps_are_kept_and_missing_ones_follow_the_sample_rate() {
+        let labels = vec!["A".to_owned()];
+        let mut buffer = SignalBuffer::with_history_seconds(labels.clone(), 250.0, 1.0).unwrap();
+        let mut batch = crate::drivers::pipeline::make_batch(250.0, vec![vec![0.0; 2]], labels);
+        batch.timestamps = vec![50.0, 50.004];
+        buffer.push_batch(&batch).unwrap();
+        batch.timestamps.clear();
+        buffer.push_batch(&batch).unwrap();
+        let ts = buffer.snapshot(1.0).timestamps;
+        assert_eq!(ts.len(), 4);
+        assert_eq!(&ts[..2], &[50.0, 50.004]);
+        assert!((ts[2] - 50.008).abs() < 1e-9 && (ts[3] - 50.012).abs() < 1e-9);
+
+        batch.timestamps = vec![1.0];
+        assert!(matches!(
+            buffer.push_batch(&batch),
+            Err(ModelizeError::TimestampMismatch { expected: 2, actual: 1 })
+        ));
+    }
+    #[test]
+    fn writer_and_reader_threads_share_the_buffer_without_deadlock() {
+        let labels = vec!["A".to_owned(), "B".to_owned()];
+        let buffer = SignalBuffer::with_history_seconds(labels.clone(), 250.0, 2.0).unwrap();
+        let shared = SharedSignalBuffer::new(buffer);
+        let reader = shared.subscribe();
+        let writer = {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                for i in 0..200 {
+                    let batch = crate::drivers::pipeline::make_batch(
+                        250.0,
+                        vec![vec![i as f32; 10]; 2],
+                        labels.clone(),
+                    );
+                    shared.push_batch(&batch).unwrap();
+                }
+            })
+        };
+        let reading = std::thread::spawn(move || {
+            assert_eq!(reader.sample_rate_hz(), 250.0);
+            let mut longest = 0;
+            for _ in 0..200 {
+                let frame = reader.snapshot(1.0);
+                assert!(frame
+                    .samples
+                    .iter()
+                    .all(|ch| ch.len() == frame.samples[0].len()));
+                longest = longest.max(frame.samples[0].len());
+            }
+            longest
+        });
+        writer.join().unwrap();
+        assert!(reading.join().unwrap() <= 250);
+        let frame = shared.snapshot(1.0);
+        assert_eq!(frame.samples[0].len(), 250);
+        assert_eq!(*frame.samples[1].last().unwrap(), 199.0);
+    }
+}
@@ -7,10 +7,22 @@ pub enum ModelizeError {
     SampleRateMismatch { expected: f32, actual: f32 },
     #[error("channel count mismatch: expected {expected}, got {actual}")]
     ChannelMismatch { expected: usize, actual: usize },
+    #[error("timestamp count mismatch: expected {expected}, got {actual}")]
+    TimestampMismatch { expected: usize, actual: usize },
     #[error("buffer not initialized yet; feed at least one batch first")]
     BufferUninitialized,
     #[error("failed to render plot: {0}")]
     Plot(String),
+    #[error("LSL outlet error: {0}")]
+    Lsl(String),
+    #[error("OSC output error: {0}")]
+    Osc(String),
+    #[error("WebSocket server error: {0}")]
+    WebSocket(String),
+    #[error("failed to read recording: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("malformed CSV at line {line}: {reason}")]
+    MalformedCsv { line: usize, reason: String },
 }
 impl<E: std::error::Error + Send + Sync + 'static> From<plotters::drawing::DrawingAreaErrorKind<E>>
     for ModelizeError
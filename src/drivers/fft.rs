@@ -1,5 +1,12 @@
-use rustfft::{num_complex::Complex32, FftPlanner};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use rustfft::{num_complex::Complex32, Fft, FftPlanner};
+use serde::{Deserialize, Serialize};
 use crate::drivers::TimeSeriesFrame;
+/// Broadband range the noise floor is estimated over, in Hz.
+const NOISE_FLOOR_HZ: (f32, f32) = (0.5, 45.0);
 /// Magnitude spectrum for each channel.
 #[derive(Clone, Debug)]
 pub struct FrequencySpectrum {
@@ -8,17 +15,312 @@ pub struct FrequencySpectrum {
     pub magnitudes: Vec<Vec<f32>>, // channel -> bins
     pub channel_labels: Vec<String>,
 }
-/// Helper that computes FFTs for a given window size.
+impl FrequencySpectrum {
+    /// One channel with only the bins inside `low_hz..=high_hz`.
+    pub fn band(&self, channel: usize, low_hz: f32, high_hz: f32) -> FrequencySpectrum {
+        let (low_hz, high_hz) = (low_hz.min(high_hz), low_hz.max(high_hz));
+        let keep: Vec<usize> = (0..self.frequencies_hz.len())
+            .filter(|&k| (low_hz..=high_hz).contains(&self.frequencies_hz[k]))
+            .collect();
+        FrequencySpectrum {
+            sample_rate_hz: self.sample_rate_hz,
+            frequencies_hz: keep.iter().map(|&k| self.frequencies_hz[k]).collect(),
+            magnitudes: self
+                .magnitudes
+                .get(channel)
+                .map(|mags| vec![keep.iter().filter_map(|&k| mags.get(k).copied()).collect()])
+                .unwrap_or_default(),
+            channel_labels: self.channel_labels.get(channel).cloned().into_iter().collect(),
+        }
+    }
+    /// Per-channel sum of magnitude² over the bins in `low_hz..high_hz`.
+    /// The upper edge is exclusive so adjacent bands never share a bin.
+    pub fn band_power(&self, low_hz: f32, high_hz: f32) -> Vec<f32> {
+        let (low_hz, high_hz) = (low_hz.min(high_hz), low_hz.max(high_hz));
+        self.magnitudes
+            .iter()
+            .map(|mags| {
+                self.frequencies_hz
+                    .iter()
+                    .zip(mags)
+                    .filter(|(f, _)| (low_hz..high_hz).contains(*f))
+                    .map(|(_, m)| m * m)
+                    .sum()
+            })
+            .collect()
+    }
+    /// Power in the five standard EEG bands, one value per channel each.
+    pub fn band_powers(&self) -> BandPowers {
+        let power = |band: EegBand| {
+            let (low, high) = band.range_hz();
+            self.band_power(low, high)
+        };
+        BandPowers {
+            delta: power(EegBand::Delta),
+            theta: power(EegBand::Theta),
+            alpha: power(EegBand::Alpha),
+            beta: power(EegBand::Beta),
+            gamma: power(EegBand::Gamma),
+        }
+    }
+    /// Frequency and magnitude of the largest bin of `channel`, skipping the DC bin.
+    pub fn peak_frequency(&self, channel: usize) -> Option<(f32, f32)> {
+        let mags = self.magnitudes.get(channel)?;
+        self.frequencies_hz
+            .iter()
+            .zip(mags)
+            .skip(1)
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(f, m)| (*f, *m))
+    }
+    /// The [`EegBand`] holding the most power on `channel`; `None` without any in-band power.
+    pub fn dominant_band(&self, channel: usize) -> Option<EegBand> {
+        let mags = self.magnitudes.get(channel)?;
+        EegBand::ALL
+            .into_iter()
+            .map(|band| {
+                let (low, high) = band.range_hz();
+                let power: f32 = self
+                    .frequencies_hz
+                    .iter()
+                    .zip(mags)
+                    .filter(|(f, _)| (low..high).contains(*f))
+                    .map(|(_, m)| m * m)
+                    .sum();
+                (band, power)
+            })
+            .filter(|(_, power)| *power > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(band, _)| band)
+    }
+    /// Per-channel noise floor: the median magnitude² over the 0.5–45 Hz bins.
+    /// A median ignores the few bins a rhythm or artifact lifts.
+    pub fn noise_floor(&self) -> Vec<f32> {
+        let (low, high) = NOISE_FLOOR_HZ;
+        self.magnitudes
+            .iter()
+            .map(|mags| {
+                let mut powers: Vec<f32> = self
+                    .frequencies_hz
+                    .iter()
+                    .zip(mags)
+                    .filter(|(f, _)| (low..high).contains(*f))
+                    .map(|(_, m)| m * m)
+                    .collect();
+                if powers.is_empty() {
+                    return 0.0;
+                }
+                powers.sort_by(|a, b| a.total_cmp(b));
+                powers[powers.len() / 2]
+            })
+            .collect()
+    }
+    /// Per-channel SNR of `band` in dB: mean bin power inside the band over the noise floor.
+    pub fn band_snr_db(&self, band: EegBand) -> Vec<f32> {
+        let (low, high) = band.range_hz();
+        let bins = self
+            .frequencies_hz
+            .iter()
+            .filter(|f| (low..high).contains(*f))
+            .count()
+            .max(1) as f32;
+        self.band_power(low, high)
+            .iter()
+            .zip(self.noise_floor())
+            .map(|(power, floor)| {
+                10.0 * ((power / bins).max(f32::MIN_POSITIVE) / floor.max(f32::MIN_POSITIVE)).log10()
+            })
+            .collect()
+    }
+}
+/// The standard EEG frequency bands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EegBand {
+    Delta,
+    Theta,
+    Alpha,
+    Beta,
+    Gamma,
+}
+impl EegBand {
+    pub const ALL: [EegBand; 5] = [
+        EegBand::Delta,
+        EegBand::Theta,
+        EegBand::Alpha,
+        EegBand::Beta,
+        EegBand::Gamma,
+    ];
+    /// `low..high` in Hz; the upper edge belongs to the next band.
+    pub fn range_hz(self) -> (f32, f32) {
+        match self {
+            EegBand::Delta => (0.5, 4.0),
+            EegBand::Theta => (4.0, 8.0),
+            EegBand::Alpha => (8.0, 13.0),
+            EegBand::Beta => (13.0, 30.0),
+            EegBand::Gamma => (30.0, 45.0),
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            EegBand::Delta => "Delta (0.5-4 Hz)",
+            EegBand::Theta => "Theta (4-8 Hz)",
+            EegBand::Alpha => "Alpha (8-13 Hz)",
+            EegBand::Beta => "Beta (13-30 Hz)",
+            EegBand::Gamma => "Gamma (30-45 Hz)",
+        }
+    }
+}
+/// Band power per channel for each [`EegBand`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BandPowers {
+    pub delta: Vec<f32>,
+    pub theta: Vec<f32>,
+    pub alpha: Vec<f32>,
+    pub beta: Vec<f32>,
+    pub gamma: Vec<f32>,
+}
+/// Taper applied to each channel before the FFT to limit spectral leakage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum WindowFunction {
+    /// No taper; an off-bin tone smears across the whole spectrum.
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+    Blackman,
+}
+impl WindowFunction {
+    pub const ALL: [WindowFunction; 4] = [
+        WindowFunction::Rectangular,
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::Blackman,
+    ];
+    pub fn label(self) -> &'static str {
+        match self {
+            WindowFunction::Rectangular => "Rectangular",
+            WindowFunction::Hann => "Hann",
+            WindowFunction::Hamming => "Hamming",
+            WindowFunction::Blackman => "Blackman",
+        }
+    }
+    /// Symmetric window coefficients of length `n`.
+    pub fn coefficients(self, n: usize) -> Vec<f32> {
+        let denom = n.saturating_sub(1).max(1) as f32;
+        (0..n)
+            .map(|i| {
+                let x = 2.0 * PI * i as f32 / denom;
+                match self {
+                    WindowFunction::Rectangular => 1.0,
+                    WindowFunction::Hann => 0.5 - 0.5 * x.cos(),
+                    WindowFunction::Hamming => 0.54 - 0.46 * x.cos(),
+                    WindowFunction::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+                }
+            })
+            .collect()
+    }
+}
+/// Trend removed from each channel before windowing, so a DC offset or slow drift
+/// does not leak into the low bins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Detrend {
+    None,
+    /// Subtract the channel mean.
+    #[default]
+    Mean,
+    /// Subtract the least-squares straight line.
+    Linear,
+}
+impl Detrend {
+    pub const ALL: [Detrend; 3] = [Detrend::None, Detrend::Mean, Detrend::Linear];
+    pub fn label(self) -> &'static str {
+        match self {
+            Detrend::None => "No detrend",
+            Detrend::Mean => "Remove mean",
+            Detrend::Linear => "Remove linear trend",
+        }
+    }
+    /// `samples` with the trend removed; sums in f64 so large board offsets keep their precision.
+    pub fn apply(self, samples: &[f32]) -> Vec<f32> {
+        let n = samples.len();
+        if self == Detrend::None || n == 0 {
+            return samples.to_vec();
+        }
+        let mean = samples.iter().map(|&v| v as f64).sum::<f64>() / n as f64;
+        // x 以窗口中点为 0，斜率与截距互不相关
+        let mid = (n - 1) as f64 / 2.0;
+        let slope = match self {
+            Detrend::Linear if n > 1 => {
+                let sxx: f64 = (0..n).map(|i| (i as f64 - mid).powi(2)).sum();
+                let sxy: f64 = samples
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &v)| (i as f64 - mid) * (v as f64 - mean))
+                    .sum();
+                sxy / sxx
+            }
+            _ => 0.0,
+        };
+        samples
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (v as f64 - mean - slope * (i as f64 - mid)) as f32)
+            .collect()
+    }
+}
+/// Helper that computes FFTs for a given window size. Plans are made once per length
+/// and kept, so a builder reused across frames does not re-plan.
 pub struct SpectrumBuilder {
     fft_size: usize,
+    window: WindowFunction,
+    detrend: Detrend,
+    /// `fft_size` first, then every other Welch segment length asked for.
+    plans: RefCell<Vec<Arc<dyn Fft<f32>>>>,
+    scratch: RefCell<Vec<Complex32>>,
 }
 impl SpectrumBuilder {
     pub fn with_size(fft_size: usize) -> Self {
-        Self { fft_size }
+        let fft = FftPlanner::<f32>::new().plan_fft_forward(fft_size);
+        let scratch = vec![Complex32::ZERO; fft.get_inplace_scratch_len()];
+        Self {
+            fft_size,
+            window: WindowFunction::default(),
+            detrend: Detrend::default(),
+            plans: RefCell::new(vec![fft]),
+            scratch: RefCell::new(scratch),
+        }
+    }
+    /// Cached plan for `len`, planned on first use.
+    fn plan(&self, len: usize) -> Arc<dyn Fft<f32>> {
+        let mut plans = self.plans.borrow_mut();
+        if let Some(fft) = plans.iter().find(|fft| fft.len() == len) {
+            return Arc::clone(fft);
+        }
+        let fft = FftPlanner::<f32>::new().plan_fft_forward(len);
+        plans.push(Arc::clone(&fft));
+        fft
+    }
+    /// In-place FFT reusing the builder's scratch buffer.
+    fn process(&self, fft: &dyn Fft<f32>, buffer: &mut [Complex32]) {
+        let mut scratch = self.scratch.borrow_mut();
+        scratch.resize(fft.get_inplace_scratch_len(), Complex32::ZERO);
+        fft.process_with_scratch(buffer, &mut scratch);
+    }
+    pub fn with_window(mut self, window: WindowFunction) -> Self {
+        self.window = window;
+        self
+    }
+    pub fn with_detrend(mut self, detrend: Detrend) -> Self {
+        self.detrend = detrend;
+        self
     }
     pub fn compute(&self, frame: &TimeSeriesFrame) -> FrequencySpectrum {
-        let mut planner = FftPlanner::<f32>::new();
-        let fft = planner.plan_fft_forward(self.fft_size);
+        let fft = self.plan(self.fft_size);
+        let window = self.window.coefficients(self.fft_size);
+        // dividing by the window sum (N × coherent gain) keeps a tone's peak amplitude
+        // the same whichever window is used
+        let window_sum: f32 = window.iter().sum();
         let mut frequencies = Vec::with_capacity(self.fft_size / 2);
         for k in 0..self.fft_size / 2 {
             let freq = k as f32 * (frame.sample_rate_hz / self.fft_size as f32);
@@ -28,18 +330,20 @@ impl SpectrumBuilder {
             .samples
             .iter()
             .map(|channel| {
-                let mut buffer: Vec<Complex32> = channel
+                let used = &channel[..channel.len().min(self.fft_size)];
+                let mut buffer: Vec<Complex32> = self
+                    .detrend
+                    .apply(used)
                     .iter()
-                    .copied()
-                    .take(self.fft_size)
-                    .map(|v| Complex32::new(v, 0.0))
+                    .zip(&window)
+                    .map(|(v, w)| Complex32::new(v * w, 0.0))
                     .collect();
                 buffer.resize(self.fft_size, Complex32::ZERO);
-                fft.process(&mut buffer);
+                self.process(fft.as_ref(), &mut buffer);
                 buffer
                     .iter()
                     .take(self.fft_size / 2)
-                    .map(|c| c.norm() / self.fft_size as f32)
+                    .map(|c| c.norm() / window_sum)
                     .collect()
             })
             .collect();
@@ -50,4 +354,499 @@ impl SpectrumBuilder {
             channel_labels: frame.channel_labels.clone(),
         }
     }
+    /// Welch's method: splits each channel into `segment_len`-sample windowed segments
+    /// that overlap by `overlap` samples and averages their periodograms. The returned
+    /// magnitudes are the square root of the averaged power, on the same scale as
+    /// [`compute`](Self::compute), with `segment_len` bins of resolution.
+    pub fn compute_welch(
+        &self,
+        frame: &TimeSeriesFrame,
+        segment_len: usize,
+        overlap: usize,
+    ) -> FrequencySpectrum {
+        let segment_len = segment_len.max(2);
+        let step = segment_len - overlap.min(segment_len - 1);
+        let fft = self.plan(segment_len);
+        let window = self.window.coefficients(segment_len);
+        let window_sum: f32 = window.iter().sum();
+        let frequencies = (0..segment_len / 2)
+            .map(|k| k as f32 * frame.sample_rate_hz / segment_len as f32)
+            .collect();
+        let magnitudes = frame
+            .samples
+            .iter()
+            .map(|channel| {
+                // 不足一段时按一段补零处理，与 compute 一致
+                let segments = if channel.len() > segment_len {
+                    (channel.len() - segment_len) / step + 1
+                } else {
+                    1
+                };
+                let mut power = vec![0.0f32; segment_len / 2];
+                for s in 0..segments {
+                    let start = s * step;
+                    let end = (start + segment_len).min(channel.len());
+                    let mut buffer: Vec<Complex32> = self
+                        .detrend
+                        .apply(&channel[start..end])
+                        .iter()
+                        .zip(&window)
+                        .map(|(v, w)| Complex32::new(v * w, 0.0))
+                        .collect();
+                    buffer.resize(segment_len, Complex32::ZERO);
+                    self.process(fft.as_ref(), &mut buffer);
+                    for (p, c) in power.iter_mut().zip(&buffer) {
+                        *p += (c.norm() / window_sum).powi(2);
+                    }
+                }
+                power.iter().map(|p| (p / segments as f32).sqrt()).collect()
+            })
+            .collect();
+        FrequencySpectrum {
+            sample_rate_hz: frame.sample_rate_hz,
+            frequencies_hz: frequencies,
+            magnitudes,
+            channel_labels: frame.channel_labels.clone(),
+        }
+    }
+}
+/// Magnitudes over time from overlapping Hann windows, one column every `hop` samples.
+#[derive(Clone, Debug, Default)]
+pub struct Spectrogram {
+    /// Centre of each window, in seconds from the start of the frame.
+    pub times_s: Vec<f32>,
+    pub frequencies_hz: Vec<f32>,
+    pub magnitudes: Vec<Vec<Vec<f32>>>, // channel -> window -> bins
+    pub channel_labels: Vec<String>,
+}
+impl Spectrogram {
+    /// Frames shorter than one window give a spectrogram with no columns.
+    pub fn from_frame(frame: &TimeSeriesFrame, fft_size: usize, hop: usize) -> Self {
+        let fft_size = fft_size.max(2);
+        let hop = hop.max(1);
+        let len = frame.samples.iter().map(Vec::len).min().unwrap_or(0);
+        let windows = if len >= fft_size { (len - fft_size) / hop + 1 } else { 0 };
+        let fft = FftPlanner::<f32>::new().plan_fft_forward(fft_size);
+        let window = WindowFunction::Hann.coefficients(fft_size);
+        let window_sum: f32 = window.iter().sum();
+        let dt = if frame.sample_rate_hz > 0.0 { 1.0 / frame.sample_rate_hz } else { 0.0 };
+        let times_s = (0..windows)
+            .map(|w| (w * hop) as f32 * dt + fft_size as f32 * dt / 2.0)
+            .collect();
+        let frequencies_hz = (0..fft_size / 2)
+            .map(|k| k as f32 * frame.sample_rate_hz / fft_size as f32)
+            .collect();
+        let magnitudes = frame
+            .samples
+            .iter()
+            .map(|channel| {
+                (0..windows)
+                    .map(|w| {
+                        let mut buffer: Vec<Complex32> = channel[w * hop..w * hop + fft_size]
+                            .iter()
+                            .zip(&window)
+                            .map(|(v, c)| Complex32::new(v * c, 0.0))
+                            .collect();
+                        fft.process(&mut buffer);
+                        buffer
+                            .iter()
+                            .take(fft_size / 2)
+                            .map(|c| c.norm() / window_sum)
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            times_s,
+            frequencies_hz,
+            magnitudes,
+            channel_labels: frame.channel_labels.clone(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.times_s.is_empty()
+    }
+}
+/// Continuously updated spectrum: keeps the newest `fft_size` samples per
+/// channel and emits a Hann-windowed FFT every `hop` samples (overlapping windows).
+pub struct SlidingSpectrum {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    window_sum: f32,
+    hop: usize,
+    since_emit: usize,
+    sample_rate_hz: f32,
+    channel_labels: Vec<String>,
+    rings: Vec<VecDeque<f32>>,
+}
+impl SlidingSpectrum {
+    /// `rate_hz` is how many spectra per second to emit once the window is full.
+    pub fn new(
+        channel_labels: Vec<String>,
+        sample_rate_hz: f32,
+        fft_size: usize,
+        rate_hz: f32,
+    ) -> Self {
+        let fft_size = fft_size.max(2);
+        let fft = FftPlanner::<f32>::new().plan_fft_forward(fft_size);
+        let window = WindowFunction::Hann.coefficients(fft_size);
+        let window_sum = window.iter().sum();
+        let hop = (sample_rate_hz / rate_hz.max(0.1)).round().max(1.0) as usize;
+        let rings = channel_labels.iter().map(|_| VecDeque::with_capacity(fft_size)).collect();
+        Self {
+            fft,
+            window,
+            window_sum,
+            hop,
+            since_emit: hop,
+            sample_rate_hz,
+            channel_labels,
+            rings,
+        }
+    }
+    /// Appends one multi-channel sample; returns a spectrum when one is due.
+    pub fn push(&mut self, sample: &[f32]) -> Option<FrequencySpectrum> {
+        let fft_size = self.window.len();
+        for (ring, v) in self.rings.iter_mut().zip(sample) {
+            if ring.len() == fft_size {
+                ring.pop_front();
+            }
+            ring.push_back(*v);
+        }
+        self.since_emit += 1;
+        let full = self.rings.first().map(|r| r.len() == fft_size).unwrap_or(false);
+        if !full || self.since_emit < self.hop {
+            return None;
+        }
+        self.since_emit = 0;
+        let frequencies_hz = (0..fft_size / 2)
+            .map(|k| k as f32 * self.sample_rate_hz / fft_size as f32)
+            .collect();
+        let magnitudes = self
+            .rings
+            .iter()
+            .map(|ring| {
+                let mut buffer: Vec<Complex32> = ring
+                    .iter()
+                    .zip(&self.window)
+                    .map(|(v, w)| Complex32::new(v * w, 0.0))
+                    .collect();
+                self.fft.process(&mut buffer);
+                buffer
+                    .iter()
+                    .take(fft_size / 2)
+                    .map(|c| c.norm() / self.window_sum)
+                    .collect()
+            })
+            .collect();
+        Some(FrequencySpectrum {
+            sample_rate_hz: self.sample_rate_hz,
+            frequencies_hz,
+            magnitudes,
+            channel_labels: self.channel_labels.clone(),
+        })
+    }
+}
+/// Most recent spectra, oldest evicted first once `cap` is reached.
+/// Bounds memory for features that look back over time (spectral flux).
+pub struct SpectrumHistory {
+    cap: usize,
+    spectra: VecDeque<FrequencySpectrum>,
+}
+impl SpectrumHistory {
+    pub fn new(cap: usize) -> Self {
+        let cap = cap.max(2);
+        Self { cap, spectra: VecDeque::with_capacity(cap) }
+    }
+    pub fn set_cap(&mut self, cap: usize) {
+        self.cap = cap.max(2);
+        while self.spectra.len() > self.cap {
+            self.spectra.pop_front();
+        }
+    }
+    /// A spectrum with a different bin layout (new FFT size) restarts the history.
+    pub fn push(&mut self, spec: FrequencySpectrum) {
+        if self.spectra.back().map(|s| s.frequencies_hz != spec.frequencies_hz).unwrap_or(false) {
+            self.spectra.clear();
+        }
+        if self.spectra.len() == self.cap {
+            self.spectra.pop_front();
+        }
+        self.spectra.push_back(spec);
+    }
+    pub fn len(&self) -> usize {
+        self.spectra.len()
+    }
+    pub fn clear(&mut self) {
+        self.spectra.clear();
+    }
+    /// Mean per-channel spectral flux (sum of positive magnitude increases between
+    /// consecutive spectra) over the retained history.
+    pub fn spectral_flux(&self) -> Option<Vec<f32>> {
+        if self.spectra.len() < 2 {
+            return None;
+        }
+        let channels = self.spectra.iter().map(|s| s.magnitudes.len()).min().unwrap_or(0);
+        let mut flux = vec![0.0f32; channels];
+        for (prev, next) in self.spectra.iter().zip(self.spectra.iter().skip(1)) {
+            for (ch, acc) in flux.iter_mut().enumerate() {
+                *acc += prev.magnitudes[ch]
+                    .iter()
+                    .zip(&next.magnitudes[ch])
+                    .map(|(a, b)| (b - a).max(0.0))
+                    .sum::<f32>();
+            }
+        }
+        let pairs = (self.spectra.len() - 1) as f32;
+        Some(flux.into_iter().map(|f| f / pairs).collect())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn spectrum(level: f32) -> FrequencySpectrum {
+        FrequencySpectrum {
+            sample_rate_hz: 250.0,
+            frequencies_hz: vec![0.0, 1.0],
+            magnitudes: vec![vec![level, level]],
+            channel_labels: vec!["Ch1".into()],
+        }
+    }
+    #[test]
+    fn history_beyond_cap_keeps_most_recent_spectra() {
+        let mut history = SpectrumHistory::new(3);
+        for i in 0..10 {
+            history.push(spectrum(i as f32));
+        }
+        assert_eq!(history.len(), 3);
+        let kept: Vec<f32> = history.spectra.iter().map(|s| s.magnitudes[0][0]).collect();
+        assert_eq!(kept, vec![7.0, 8.0, 9.0]);
+        // two bins each rising by 1.0 per step
+        assert_eq!(history.spectral_flux(), Some(vec![2.0]));
+        history.set_cap(2);
+        assert_eq!(history.len(), 2);
+    }
+    #[test]
+    fn focused_band_keeps_only_bins_inside_the_band() {
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 256.0,
+            channel_labels: vec!["Ch1".into(), "Ch2".into()],
+            samples: vec![vec![0.0; 256], vec![1.0; 256]],
+            timestamps: Vec::new(),
+        };
+        let spec = SpectrumBuilder::with_size(256).compute(&frame);
+        let focused = spec.band(1, 8.0, 30.0);
+        assert_eq!(focused.frequencies_hz.first(), Some(&8.0));
+        assert_eq!(focused.frequencies_hz.last(), Some(&30.0));
+        assert_eq!(focused.magnitudes.len(), 1);
+        assert_eq!(focused.magnitudes[0].len(), focused.frequencies_hz.len());
+        assert_eq!(focused.channel_labels, vec!["Ch2".to_string()]);
+    }
+    #[test]
+    fn hann_window_keeps_a_15hz_peak_sharper_than_rectangular() {
+        let sr = 250.0;
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: sr,
+            channel_labels: vec!["Ch1".into()],
+            // 15 Hz falls between bins at 250 Hz / 256 points
+            samples: vec![(0..256)
+                .map(|i| (2.0 * PI * 15.0 * i as f32 / sr).sin() * 10.0)
+                .collect()],
+            timestamps: Vec::new(),
+        };
+        // share of spectral energy within ±2 bins of the peak
+        let concentration = |window: WindowFunction| {
+            let spec = SpectrumBuilder::with_size(256).with_window(window).compute(&frame);
+            let mags = &spec.magnitudes[0];
+            let peak = (0..mags.len()).max_by(|&a, &b| mags[a].total_cmp(&mags[b])).unwrap();
+            let total: f32 = mags.iter().map(|m| m * m).sum();
+            let near: f32 = mags[peak - 2..=peak + 2].iter().map(|m| m * m).sum();
+            (spec.frequencies_hz[peak], mags[peak], near / total)
+        };
+        let (rect_hz, rect_peak, rect_share) = concentration(WindowFunction::Rectangular);
+        let (hann_hz, hann_peak, hann_share) = concentration(WindowFunction::Hann);
+        assert!((rect_hz - 15.0).abs() < 1.0 && (hann_hz - 15.0).abs() < 1.0);
+        assert!(hann_share > 0.99, "hann {hann_share}");
+        assert!(hann_share > rect_share, "hann {hann_share} vs rect {rect_share}");
+        // coherent-gain correction keeps peak amplitudes comparable (within scalloping loss)
+        assert!((hann_peak / rect_peak - 1.0).abs() < 0.25, "{hann_peak} vs {rect_peak}");
+    }
+    #[test]
+    fn a_12hz_sine_peaks_within_one_bin_in_the_alpha_band() {
+        let sr = 250.0;
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: sr,
+            channel_labels: vec!["Ch1".into(), "Ch2".into()],
+            samples: vec![
+                // 加一个直流偏置，确认 DC 桶被跳过
+                (0..512)
+                    .map(|i| 8.0 + (2.0 * PI * 12.0 * i as f32 / sr).sin() * 10.0)
+                    .collect(),
+                vec![0.0; 512],
+            ],
+            timestamps: Vec::new(),
+        };
+        let spec = SpectrumBuilder::with_size(512).compute(&frame);
+        let bin_hz = sr / 512.0;
+        let (peak_hz, peak_mag) = spec.peak_frequency(0).expect("peak");
+        assert!((peak_hz - 12.0).abs() <= bin_hz, "{peak_hz}");
+        assert!(peak_mag > 1.0);
+        assert_eq!(spec.dominant_band(0), Some(EegBand::Alpha));
+        assert_eq!(spec.dominant_band(1), None);
+        assert_eq!(spec.peak_frequency(2), None);
+    }
+    #[test]
+    fn detrending_leaves_a_near_zero_dc_bin_under_an_offset_sine() {
+        let sr = 250.0;
+        // 正好落在第 10 个桶 (~9.8 Hz)，Hann 窗的泄漏到不了 DC 桶
+        let tone_hz = sr * 10.0 / 256.0;
+        let offset_sine = |i: usize| 20_000.0 + (2.0 * PI * tone_hz * i as f32 / sr).sin() * 10.0;
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: sr,
+            channel_labels: vec!["Ch1".into(), "Ch2".into()],
+            samples: vec![
+                (0..256).map(offset_sine).collect(),
+                // 同样的信号再叠加一条慢漂移
+                (0..256).map(|i| offset_sine(i) + 0.5 * i as f32).collect(),
+            ],
+            timestamps: Vec::new(),
+        };
+        let dc = |detrend: Detrend, ch: usize| {
+            SpectrumBuilder::with_size(256).with_detrend(detrend).compute(&frame).magnitudes[ch][0]
+        };
+        assert!(dc(Detrend::None, 0) > 1000.0);
+        assert!(dc(Detrend::Mean, 0) < 0.1, "{}", dc(Detrend::Mean, 0));
+        assert!(dc(Detrend::Linear, 1) < 0.1, "{}", dc(Detrend::Linear, 1));
+        assert!(dc(Detrend::Mean, 1) > dc(Detrend::Linear, 1));
+        let spec = SpectrumBuilder::with_size(256).compute(&frame);
+        let (peak_hz, _) = spec.peak_frequency(0).expect("peak");
+        assert!((peak_hz - tone_hz).abs() < 1e-3, "{peak_hz}");
+    }
+    #[test]
+    fn welch_flattens_white_noise_compared_to_a_single_fft() {
+        let mut seed = 777u32;
+        let samples: Vec<f32> = (0..1024)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (seed >> 16) as f32 / 32_768.0 - 1.0
+            })
+            .collect();
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: vec!["Ch1".into()],
+            samples: vec![samples],
+            timestamps: Vec::new(),
+        };
+        // 以相对方差 (方差 / 均值²) 比较，两种分辨率下的功率量级不同
+        let relative_variance = |spec: &FrequencySpectrum| {
+            let power: Vec<f32> = spec.magnitudes[0].iter().skip(1).map(|m| m * m).collect();
+            let mean = power.iter().sum::<f32>() / power.len() as f32;
+            let var = power.iter().map(|p| (p - mean).powi(2)).sum::<f32>() / power.len() as f32;
+            var / (mean * mean)
+        };
+        let builder = SpectrumBuilder::with_size(1024);
+        let single = builder.compute(&frame);
+        let welch = builder.compute_welch(&frame, 128, 64);
+        assert_eq!(welch.frequencies_hz.len(), 64);
+        assert_eq!(welch.frequencies_hz[1], 250.0 / 128.0);
+        let (single_var, welch_var) = (relative_variance(&single), relative_variance(&welch));
+        assert!(welch_var < single_var / 4.0, "welch {welch_var} vs single {single_var}");
+    }
+    #[test]
+    fn repeated_computes_reuse_one_plan_per_length() {
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: vec!["Ch1".into()],
+            samples: vec![(0..512).map(|i| (i as f32 * 0.3).sin()).collect()],
+            timestamps: Vec::new(),
+        };
+        let builder = SpectrumBuilder::with_size(256);
+        let first_plan = builder.plan(256);
+        let first = builder.compute(&frame);
+        for _ in 0..10 {
+            assert_eq!(builder.compute(&frame).magnitudes, first.magnitudes);
+            builder.compute_welch(&frame, 128, 64);
+        }
+        // 256 from the constructor plus the 128-sample Welch segment, each planned once
+        let plans = builder.plans.borrow();
+        assert_eq!(plans.iter().map(|p| p.len()).collect::<Vec<_>>(), vec![256, 128]);
+        assert!(Arc::ptr_eq(&plans[0], &first_plan));
+    }
+    #[test]
+    fn spectrogram_follows_a_tone_that_changes_frequency() {
+        let sr = 256.0;
+        // 前 2 s 为 10 Hz，后 2 s 为 30 Hz
+        let samples: Vec<f32> = (0..1024)
+            .map(|i| {
+                let f = if i < 512 { 10.0 } else { 30.0 };
+                (2.0 * PI * f * i as f32 / sr).sin() * 20.0
+            })
+            .collect();
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: sr,
+            channel_labels: vec!["Ch1".into()],
+            samples: vec![samples],
+            timestamps: Vec::new(),
+        };
+        let spec = Spectrogram::from_frame(&frame, 128, 32);
+        assert_eq!(spec.times_s.len(), (1024 - 128) / 32 + 1);
+        assert_eq!(spec.magnitudes[0].len(), spec.times_s.len());
+        let peak_hz = |column: &Vec<f32>| {
+            let k = (0..column.len()).max_by(|&a, &b| column[a].total_cmp(&column[b])).unwrap();
+            spec.frequencies_hz[k]
+        };
+        assert_eq!(peak_hz(spec.magnitudes[0].first().unwrap()), 10.0);
+        assert_eq!(peak_hz(spec.magnitudes[0].last().unwrap()), 30.0);
+
+        let short = TimeSeriesFrame {
+            samples: vec![vec![0.0; 100]],
+            ..frame
+        };
+        let empty = Spectrogram::from_frame(&short, 128, 32);
+        assert!(empty.is_empty());
+        assert!(empty.magnitudes[0].is_empty());
+    }
+    #[test]
+    fn alpha_band_dominates_for_a_10hz_sine() {
+        let sr = 256.0;
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: sr,
+            channel_labels: vec!["Ch1".into()],
+            samples: vec![(0..512)
+                .map(|i| (2.0 * PI * 10.0 * i as f32 / sr).sin() * 20.0)
+                .collect()],
+            timestamps: Vec::new(),
+        };
+        let powers = SpectrumBuilder::with_size(512).compute(&frame).band_powers();
+        let alpha = powers.alpha[0];
+        assert!(alpha > 0.0);
+        for other in [powers.delta[0], powers.theta[0], powers.beta[0], powers.gamma[0]] {
+            assert!(alpha > 10.0 * other, "alpha {alpha} vs {other}");
+        }
+    }
+    #[test]
+    fn alpha_dominant_signal_has_high_alpha_snr_and_low_gamma_snr() {
+        let sr = 256.0;
+        // 10 Hz rhythm over a small pseudo-random broadband floor
+        let mut seed = 12345u32;
+        let samples = (0..512)
+            .map(|i| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                let noise = (seed >> 16) as f32 / 32_768.0 - 1.0;
+                (2.0 * PI * 10.0 * i as f32 / sr).sin() * 20.0 + noise
+            })
+            .collect();
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: sr,
+            channel_labels: vec!["Ch1".into()],
+            samples: vec![samples],
+            timestamps: Vec::new(),
+        };
+        let spec = SpectrumBuilder::with_size(512).compute(&frame);
+        let alpha = spec.band_snr_db(EegBand::Alpha)[0];
+        let gamma = spec.band_snr_db(EegBand::Gamma)[0];
+        assert!(alpha > 20.0, "alpha SNR {alpha} dB");
+        assert!(gamma < 6.0, "gamma SNR {gamma} dB");
+    }
 }
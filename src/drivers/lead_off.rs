@@ -0,0 +1,100 @@
+//! Live lead-off (railed electrode) detection with debounce.
+//!
+//! A channel is considered railed when its impedance estimated from the recent window
+//! exceeds [`RAILED_IMPEDANCE_OHMS`] (same cut-off as the impedance grid). The state only
+//! flips after the raw condition has held for the debounce time, so a single noisy window
+//! neither raises nor clears an alert.
+use crate::drivers::cyton_impedance_from_std;
+/// Above this estimated impedance a channel is shown as "Railed".
+pub const RAILED_IMPEDANCE_OHMS: f32 = 5_000_000.0;
+/// Raw (undebounced) railed test for one channel of recent µV samples.
+pub fn is_railed(samples_uv: &[f32]) -> bool {
+    if samples_uv.is_empty() {
+        return false;
+    }
+    let mean = samples_uv.iter().sum::<f32>() / samples_uv.len() as f32;
+    let var = samples_uv
+        .iter()
+        .map(|v| (v - mean) * (v - mean))
+        .sum::<f32>()
+        / samples_uv.len() as f32;
+    let ohms = cyton_impedance_from_std(var.sqrt());
+    !ohms.is_finite() || ohms > RAILED_IMPEDANCE_OHMS
+}
+#[derive(Clone, Copy, Debug, Default)]
+struct ChannelState {
+    railed: bool,
+    /// When the raw condition started disagreeing with `railed`.
+    pending_since: Option<f64>,
+}
+pub struct LeadOffMonitor {
+    debounce_secs: f64,
+    channels: Vec<ChannelState>,
+}
+impl LeadOffMonitor {
+    pub fn new(debounce_secs: f64) -> Self {
+        Self {
+            debounce_secs: debounce_secs.max(0.0),
+            channels: Vec::new(),
+        }
+    }
+    pub fn debounce_secs(&self) -> f64 {
+        self.debounce_secs
+    }
+    pub fn set_debounce_secs(&mut self, secs: f64) {
+        self.debounce_secs = secs.max(0.0);
+    }
+    pub fn reset(&mut self) {
+        self.channels.clear();
+    }
+    /// Channels currently confirmed as railed.
+    pub fn railed_channels(&self) -> Vec<usize> {
+        self.channels
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.railed)
+            .map(|(i, _)| i)
+            .collect()
+    }
+    /// Feeds the raw per-channel condition at `now_secs`; returns the channels that just
+    /// became railed (sustained for the debounce time).
+    pub fn update(&mut self, now_secs: f64, railed_now: &[bool]) -> Vec<usize> {
+        if self.channels.len() != railed_now.len() {
+            self.channels = vec![ChannelState::default(); railed_now.len()];
+        }
+        let mut fired = Vec::new();
+        for (idx, (state, &raw)) in self.channels.iter_mut().zip(railed_now).enumerate() {
+            if raw == state.railed {
+                state.pending_since = None;
+                continue;
+            }
+            let since = *state.pending_since.get_or_insert(now_secs);
+            if now_secs - since >= self.debounce_secs {
+                state.railed = raw;
+                state.pending_since = None;
+                if raw {
+                    fired.push(idx);
+                }
+            }
+        }
+        fired
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn only_sustained_transition_fires() {
+        let mut monitor = LeadOffMonitor::new(1.0);
+        assert!(monitor.update(0.0, &[false, false]).is_empty());
+        // Short glitch on channel 1: railed for 0.5 s, then back.
+        assert!(monitor.update(0.5, &[false, true]).is_empty());
+        assert!(monitor.update(0.9, &[false, true]).is_empty());
+        assert!(monitor.update(1.2, &[false, false]).is_empty());
+        // Sustained: fires once after the debounce time, and not again while it stays railed.
+        assert!(monitor.update(2.0, &[false, true]).is_empty());
+        assert_eq!(monitor.update(3.0, &[false, true]), vec![1]);
+        assert!(monitor.update(4.0, &[false, true]).is_empty());
+        assert_eq!(monitor.railed_channels(), vec![1]);
+    }
+}
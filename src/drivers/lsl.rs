@@ -0,0 +1,218 @@
+// src/drivers/lsl.rs
+// Lab Streaming Layer 输出：把干净数据推给 LSL，方便 MNE/OpenViBE/LabRecorder 等工具直接接收。
+// 与 vJoy/BrainFlow 一样在运行时加载 liblsl 动态库；需启用 `lsl` feature 才会真正加载。
+use super::{ModelizeError, TimeSeriesFrame};
+
+/// One LSL float32 stream of type "EEG".
+pub struct LslOutlet {
+    channel_count: usize,
+    sample_rate_hz: f32,
+    #[cfg(feature = "lsl")]
+    raw: ffi::RawOutlet,
+}
+
+impl LslOutlet {
+    /// Opens an outlet advertising `channel_labels.len()` channels at `sample_rate_hz`,
+    /// with the labels in the stream description so consumers can auto-configure.
+    pub fn new(
+        name: &str,
+        channel_labels: &[String],
+        sample_rate_hz: f32,
+    ) -> Result<Self, ModelizeError> {
+        if sample_rate_hz <= 0.0 {
+            return Err(ModelizeError::InvalidSampleRate);
+        }
+        #[cfg(feature = "lsl")]
+        {
+            let raw = ffi::RawOutlet::open(name, channel_labels, sample_rate_hz as f64)
+                .map_err(|e| ModelizeError::Lsl(format!("{e:#}")))?;
+            Ok(Self {
+                channel_count: channel_labels.len(),
+                sample_rate_hz,
+                raw,
+            })
+        }
+        #[cfg(not(feature = "lsl"))]
+        {
+            let _ = (name, channel_labels);
+            Err(ModelizeError::Lsl(
+                "built without LSL support (enable the `lsl` feature)".to_owned(),
+            ))
+        }
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.channel_count
+    }
+
+    pub fn sample_rate_hz(&self) -> f32 {
+        self.sample_rate_hz
+    }
+
+    /// Pushes every sample of `frame` as one chunk.
+    pub fn push_frame(&mut self, frame: &TimeSeriesFrame) -> Result<(), ModelizeError> {
+        let chunk = interleave(frame, self.channel_count)?;
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        #[cfg(feature = "lsl")]
+        self.raw
+            .push_chunk(&chunk)
+            .map_err(|e| ModelizeError::Lsl(format!("{e:#}")))?;
+        Ok(())
+    }
+}
+
+/// Channels x samples to LSL's sample-major layout, truncated to the shortest channel.
+fn interleave(frame: &TimeSeriesFrame, channel_count: usize) -> Result<Vec<f32>, ModelizeError> {
+    if frame.samples.len() != channel_count {
+        return Err(ModelizeError::ChannelMismatch {
+            expected: channel_count,
+            actual: frame.samples.len(),
+        });
+    }
+    let len = frame.samples.iter().map(Vec::len).min().unwrap_or(0);
+    Ok((0..len)
+        .flat_map(|i| frame.samples.iter().map(move |ch| ch[i]))
+        .collect())
+}
+
+#[cfg(feature = "lsl")]
+mod ffi {
+    use anyhow::{anyhow, Context, Result};
+    use libloading::Library;
+    use std::ffi::CString;
+    use std::os::raw::{c_char, c_double, c_int, c_ulong, c_void};
+
+    const CFT_FLOAT32: c_int = 1;
+    #[cfg(windows)]
+    const LIB_NAMES: &[&str] = &["lsl.dll", "liblsl64.dll"];
+    #[cfg(not(windows))]
+    const LIB_NAMES: &[&str] = &["liblsl.so", "liblsl.dylib"];
+
+    type Handle = *mut c_void;
+
+    pub struct RawOutlet {
+        #[allow(dead_code)]
+        lib: Library,
+        outlet: Handle,
+        push_chunk_f: unsafe extern "C" fn(Handle, *const f32, c_ulong) -> c_int,
+        destroy_outlet: unsafe extern "C" fn(Handle),
+    }
+
+    // The outlet handle is only used from the engine thread that owns it.
+    unsafe impl Send for RawOutlet {}
+
+    impl RawOutlet {
+        pub fn open(name: &str, labels: &[String], srate: c_double) -> Result<Self> {
+            let lib = LIB_NAMES
+                .iter()
+                .find_map(|n| unsafe { Library::new(n) }.ok())
+                .ok_or_else(|| anyhow!("liblsl not found (tried {})", LIB_NAMES.join(", ")))?;
+            let c_name = CString::new(name)?;
+            let c_type = CString::new("EEG")?;
+            let c_source = CString::new(format!("neurostick-{name}"))?;
+            // Safety: signatures follow lsl_c.h of liblsl 1.16.
+            unsafe {
+                let create_info: libloading::Symbol<
+                    unsafe extern "C" fn(
+                        *const c_char,
+                        *const c_char,
+                        c_int,
+                        c_double,
+                        c_int,
+                        *const c_char,
+                    ) -> Handle,
+                > = lib.get(b"lsl_create_streaminfo\0")?;
+                let get_desc: libloading::Symbol<unsafe extern "C" fn(Handle) -> Handle> =
+                    lib.get(b"lsl_get_desc\0")?;
+                let append_child: libloading::Symbol<
+                    unsafe extern "C" fn(Handle, *const c_char) -> Handle,
+                > = lib.get(b"lsl_append_child\0")?;
+                let append_value: libloading::Symbol<
+                    unsafe extern "C" fn(Handle, *const c_char, *const c_char) -> Handle,
+                > = lib.get(b"lsl_append_child_value\0")?;
+                let create_outlet: libloading::Symbol<
+                    unsafe extern "C" fn(Handle, c_int, c_int) -> Handle,
+                > = lib.get(b"lsl_create_outlet\0")?;
+                let destroy_info: libloading::Symbol<unsafe extern "C" fn(Handle)> =
+                    lib.get(b"lsl_destroy_streaminfo\0")?;
+                let push_chunk_f = *lib.get(b"lsl_push_chunk_f\0")?;
+                let destroy_outlet = *lib.get(b"lsl_destroy_outlet\0")?;
+
+                let info = create_info(
+                    c_name.as_ptr(),
+                    c_type.as_ptr(),
+                    labels.len() as c_int,
+                    srate,
+                    CFT_FLOAT32,
+                    c_source.as_ptr(),
+                );
+                if info.is_null() {
+                    return Err(anyhow!("lsl_create_streaminfo failed"));
+                }
+                let channels = append_child(get_desc(info), c"channels".as_ptr());
+                for label in labels {
+                    let channel = append_child(channels, c"channel".as_ptr());
+                    let c_label = CString::new(label.as_str()).context("channel label")?;
+                    append_value(channel, c"label".as_ptr(), c_label.as_ptr());
+                    append_value(channel, c"unit".as_ptr(), c"microvolts".as_ptr());
+                    append_value(channel, c"type".as_ptr(), c"EEG".as_ptr());
+                }
+                // 0 = 按采样推送，360 s 缓冲 (liblsl 默认)
+                let outlet = create_outlet(info, 0, 360);
+                destroy_info(info);
+                if outlet.is_null() {
+                    return Err(anyhow!("lsl_create_outlet failed"));
+                }
+                Ok(Self {
+                    lib,
+                    outlet,
+                    push_chunk_f,
+                    destroy_outlet,
+                })
+            }
+        }
+
+        pub fn push_chunk(&mut self, data: &[f32]) -> Result<()> {
+            let code =
+                unsafe { (self.push_chunk_f)(self.outlet, data.as_ptr(), data.len() as c_ulong) };
+            if code < 0 {
+                return Err(anyhow!("lsl_push_chunk_f returned {code}"));
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for RawOutlet {
+        fn drop(&mut self) {
+            unsafe { (self.destroy_outlet)(self.outlet) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_interleave_sample_major_and_reject_wrong_channel_count() {
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: vec!["Ch1".into(), "Ch2".into()],
+            samples: vec![vec![1.0, 2.0, 3.0], vec![10.0, 20.0, 30.0]],
+            timestamps: Vec::new(),
+        };
+        assert_eq!(
+            interleave(&frame, 2).unwrap(),
+            vec![1.0, 10.0, 2.0, 20.0, 3.0, 30.0]
+        );
+        assert!(matches!(
+            interleave(&frame, 16),
+            Err(ModelizeError::ChannelMismatch {
+                expected: 16,
+                actual: 2
+            })
+        ));
+    }
+}
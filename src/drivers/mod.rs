@@ -3,17 +3,29 @@
 pub mod buffer;
 pub mod error;
 pub mod fft;
+pub mod lead_off;
+pub mod lsl;
+pub mod osc;
 pub mod pipeline;
 pub mod plot;
 pub mod resistance_detection;
 pub mod source;
+pub mod ws;
 // 公开导出这些模块里的结构体，方便外部调用
 pub use buffer::{SignalBuffer, TimeSeriesFrame};
 pub use error::ModelizeError;
-pub use fft::{FrequencySpectrum, SpectrumBuilder};
+pub use fft::{
+    Detrend, EegBand, FrequencySpectrum, SlidingSpectrum, Spectrogram, SpectrumBuilder,
+    SpectrumHistory, WindowFunction,
+};
+pub use lead_off::LeadOffMonitor;
 pub use pipeline::SignalPipeline;
-pub use plot::{render_spectrum_png, render_waveform_png, PlotStyle};
+pub use plot::{
+    render_overlay_png, render_spectrogram_png, render_spectrum_png, render_waveform_png,
+    GridSpacing, OverlayStyle, PlotStyle,
+};
 pub use resistance_detection::{
-    cyton_impedance_from_std, cyton_impedances_from_samples, ganglion_display_impedance_kohms,
+    channel_is_railed, cyton_impedance_from_std, cyton_impedance_from_std_with,
+    ganglion_display_impedance_kohms, CytonLeadOff, ImpedanceModel,
 };
-pub use source::{ManualSource, SignalBatch, SignalSource};
+pub use source::{CsvFileSource, ManualSource, SignalBatch, SignalSource};
@@ -0,0 +1,189 @@
+// src/drivers/osc.rs
+// OSC 输出：把解码后的手柄状态和各频段功率经 UDP 发给 TouchDesigner / Max / Pure Data 等工具。
+// OSC 1.0 编码在这里手写 (不引入新依赖)；需启用 `osc` feature 才会真正打开套接字。
+//
+// 地址表，每次更新打成一个立即生效的 bundle：
+//   /qnmd/stick/{lx,ly,rx,ry}                  f    -1..=1
+//   /qnmd/button/{a,b,x,y,lb,rb,lt,rt}         i    0 / 1
+//   /qnmd/dpad/{up,down,left,right}            i    0 / 1
+//   /qnmd/band/{delta,theta,alpha,beta,gamma}  f... 每通道一个值，顺序同通道号
+use super::fft::BandPowers;
+use super::ModelizeError;
+use crate::types::GamepadState;
+use std::net::SocketAddr;
+
+/// One OSC argument; only the two types the address schema uses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OscArg {
+    Float(f32),
+    Int(i32),
+}
+
+/// Sends gamepad states and band powers to one UDP endpoint.
+pub struct OscSender {
+    addr: SocketAddr,
+    #[cfg(feature = "osc")]
+    socket: std::net::UdpSocket,
+}
+
+impl OscSender {
+    pub fn new(addr: SocketAddr) -> Result<Self, ModelizeError> {
+        #[cfg(feature = "osc")]
+        {
+            let local: SocketAddr = if addr.is_ipv4() {
+                ([0, 0, 0, 0], 0).into()
+            } else {
+                (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+            };
+            let socket = std::net::UdpSocket::bind(local)
+                .map_err(|e| ModelizeError::Osc(format!("bind {local}: {e}")))?;
+            Ok(Self { addr, socket })
+        }
+        #[cfg(not(feature = "osc"))]
+        {
+            let _ = addr;
+            Err(ModelizeError::Osc(
+                "built without OSC support (enable the `osc` feature)".to_owned(),
+            ))
+        }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Sticks, buttons and d-pad as one bundle.
+    pub fn send_gamepad(&self, gp: &GamepadState) -> Result<(), ModelizeError> {
+        self.send(&gamepad_packet(gp))
+    }
+
+    /// Every band's per-channel power as one bundle.
+    pub fn send_band_powers(&self, powers: &BandPowers) -> Result<(), ModelizeError> {
+        self.send(&band_power_packet(powers))
+    }
+
+    fn send(&self, packet: &[u8]) -> Result<(), ModelizeError> {
+        #[cfg(feature = "osc")]
+        self.socket
+            .send_to(packet, self.addr)
+            .map_err(|e| ModelizeError::Osc(format!("send to {}: {e}", self.addr)))?;
+        #[cfg(not(feature = "osc"))]
+        let _ = packet;
+        Ok(())
+    }
+}
+
+fn gamepad_packet(gp: &GamepadState) -> Vec<u8> {
+    let sticks = [("lx", gp.lx), ("ly", gp.ly), ("rx", gp.rx), ("ry", gp.ry)]
+        .map(|(name, v)| message(&format!("/qnmd/stick/{name}"), &[OscArg::Float(v)]));
+    let buttons = [
+        ("a", gp.a),
+        ("b", gp.b),
+        ("x", gp.x),
+        ("y", gp.y),
+        ("lb", gp.lb),
+        ("rb", gp.rb),
+        ("lt", gp.lt_pressed()),
+        ("rt", gp.rt_pressed()),
+    ]
+    .map(|(name, on)| message(&format!("/qnmd/button/{name}"), &[OscArg::Int(on.into())]));
+    let dpad = [
+        ("up", gp.dpad_up),
+        ("down", gp.dpad_down),
+        ("left", gp.dpad_left),
+        ("right", gp.dpad_right),
+    ]
+    .map(|(name, on)| message(&format!("/qnmd/dpad/{name}"), &[OscArg::Int(on.into())]));
+    bundle(sticks.iter().chain(&buttons).chain(&dpad))
+}
+
+fn band_power_packet(powers: &BandPowers) -> Vec<u8> {
+    let bands = [
+        ("delta", &powers.delta),
+        ("theta", &powers.theta),
+        ("alpha", &powers.alpha),
+        ("beta", &powers.beta),
+        ("gamma", &powers.gamma),
+    ]
+    .map(|(name, values)| {
+        let args: Vec<OscArg> = values.iter().map(|&v| OscArg::Float(v)).collect();
+        message(&format!("/qnmd/band/{name}"), &args)
+    });
+    bundle(bands.iter())
+}
+
+/// `#bundle` with the "immediately" time tag; each element is size-prefixed.
+fn bundle<'a>(messages: impl Iterator<Item = &'a Vec<u8>>) -> Vec<u8> {
+    let mut out = Vec::new();
+    push_str(&mut out, "#bundle");
+    out.extend_from_slice(&1u64.to_be_bytes());
+    for msg in messages {
+        out.extend_from_slice(&(msg.len() as i32).to_be_bytes());
+        out.extend_from_slice(msg);
+    }
+    out
+}
+
+fn message(address: &str, args: &[OscArg]) -> Vec<u8> {
+    let mut out = Vec::new();
+    push_str(&mut out, address);
+    let tags: String = std::iter::once(',')
+        .chain(args.iter().map(|arg| match arg {
+            OscArg::Float(_) => 'f',
+            OscArg::Int(_) => 'i',
+        }))
+        .collect();
+    push_str(&mut out, &tags);
+    for arg in args {
+        match arg {
+            OscArg::Float(v) => out.extend_from_slice(&v.to_be_bytes()),
+            OscArg::Int(v) => out.extend_from_slice(&v.to_be_bytes()),
+        }
+    }
+    out
+}
+
+/// OSC string: NUL-terminated, then padded with NULs to a multiple of 4 bytes.
+fn push_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(s.as_bytes());
+    out.push(0);
+    out.resize(out.len().div_ceil(4) * 4, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_gamepad_state_encodes_to_the_expected_bundle() {
+        let gp = GamepadState {
+            lx: 0.5,
+            a: true,
+            ..GamepadState::default()
+        };
+        let packet = gamepad_packet(&gp);
+        let mut expected = b"#bundle\0".to_vec();
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        // "/qnmd/stick/lx" is 14 bytes + NUL, padded to 16; ",f" padded to 4; 0.5f32.
+        expected.extend_from_slice(&[0, 0, 0, 24]);
+        expected.extend_from_slice(b"/qnmd/stick/lx\0\0,f\0\0");
+        expected.extend_from_slice(&[0x3f, 0x00, 0x00, 0x00]);
+        assert_eq!(&packet[..expected.len()], expected.as_slice());
+
+        let a = b"/qnmd/button/a\0\0,i\0\0\0\0\0\x01";
+        assert!(
+            packet.windows(a.len()).any(|w| w == a),
+            "button a not encoded as 1"
+        );
+        // 4 sticks + 8 buttons + 4 d-pad directions, each a size-prefixed element.
+        let mut count = 0;
+        let mut pos = 16;
+        while pos < packet.len() {
+            let size = i32::from_be_bytes(packet[pos..pos + 4].try_into().unwrap()) as usize;
+            assert_eq!(size % 4, 0);
+            pos += 4 + size;
+            count += 1;
+        }
+        assert_eq!((count, pos), (16, packet.len()));
+    }
+}
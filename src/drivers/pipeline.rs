@@ -70,6 +70,7 @@ pub fn make_batch(
         sample_rate_hz,
         samples,
         channel_labels,
+        timestamps: Vec::new(),
     }
 }
 #[cfg(test)]
@@ -1,16 +1,36 @@
 use std::io::Cursor;
-use image::{DynamicImage, ImageBuffer, ImageFormat, Rgb};
+use image::{DynamicImage, ImageBuffer, ImageFormat, Rgb, Rgba};
 use plotters::prelude::LineSeries;
+use plotters::coord::ranged1d::{DefaultFormatting, KeyPointHint};
+use plotters::coord::types::RangedCoordf32;
 use plotters::prelude::*;
+use std::ops::Range;
 use crate::drivers::error::ModelizeError;
-use crate::drivers::fft::FrequencySpectrum;
+use crate::drivers::fft::{FrequencySpectrum, Spectrogram};
 use crate::drivers::TimeSeriesFrame;
+/// Gridline spacing along one axis; a `minor` of 0 draws major lines only.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridSpacing {
+    pub major: f32,
+    pub minor: f32,
+}
+impl GridSpacing {
+    pub fn new(major: f32, minor: f32) -> Self {
+        Self { major, minor }
+    }
+}
 #[derive(Clone, Debug)]
 pub struct PlotStyle {
     pub width: u32,
     pub height: u32,
     pub background: RGBColor,
     pub palette: Vec<RGBColor>,
+    /// Waveform time-axis grid, in seconds.
+    pub time_grid: GridSpacing,
+    /// Waveform value-axis grid, in µV; `None` picks a spacing from the data range.
+    pub value_grid: Option<GridSpacing>,
+    /// Waveform value-axis title.
+    pub value_unit: String,
 }
 impl Default for PlotStyle {
     fn default() -> Self {
@@ -19,9 +39,96 @@ impl Default for PlotStyle {
             height: 400,
             background: RGBColor(10, 10, 10),
             palette: vec![BLUE, RED, GREEN, CYAN, MAGENTA, YELLOW, WHITE],
+            time_grid: GridSpacing::new(1.0, 0.2),
+            value_grid: None,
+            value_unit: "µV".to_owned(),
+        }
+    }
+}
+/// Transparent single-channel strip sized to sit on top of gameplay video.
+#[derive(Clone, Debug)]
+pub struct OverlayStyle {
+    pub width: u32,
+    pub height: u32,
+    pub color: RGBColor,
+    pub line_width: u32,
+    /// Seconds of the newest data shown; older samples scroll off the left edge.
+    pub window_seconds: f32,
+}
+impl Default for OverlayStyle {
+    fn default() -> Self {
+        Self {
+            width: 640,
+            height: 120,
+            color: RGBColor(0, 230, 118),
+            line_width: 2,
+            window_seconds: 5.0,
         }
     }
 }
+/// Multiples of `step` inside `lo..=hi`; empty for a non-positive step.
+fn grid_ticks(lo: f32, hi: f32, step: f32) -> Vec<f32> {
+    if !(step > 0.0 && step.is_finite()) || hi < lo {
+        return Vec::new();
+    }
+    let first = (lo / step - 1e-4).ceil() as i64;
+    let last = (hi / step + 1e-4).floor() as i64;
+    (first..=last).map(|k| k as f32 * step).collect()
+}
+/// A 1/2/5 x 10^n spacing giving roughly `target` intervals across `span`.
+fn nice_step(span: f32, target: f32) -> f32 {
+    let raw = (span / target).max(f32::EPSILON);
+    let magnitude = 10f32.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .find(|m| m * magnitude >= raw)
+        .unwrap_or(10.0);
+    step * magnitude
+}
+/// Major and minor tick positions for `lo..=hi`.
+fn axis_ticks(lo: f32, hi: f32, spacing: GridSpacing) -> (Vec<f32>, Vec<f32>) {
+    let major = grid_ticks(lo, hi, spacing.major);
+    let minor = grid_ticks(lo, hi, spacing.minor)
+        .into_iter()
+        .filter(|v| !major.iter().any(|m| (m - v).abs() < spacing.minor * 1e-3))
+        .collect();
+    (major, minor)
+}
+/// f32 axis with fixed major (bold, labelled) and minor (light) gridlines.
+/// plotters' own `WithKeyPoints` loses the f32 value formatter, so the mesh
+/// could not be labelled through it.
+struct GridAxis {
+    inner: RangedCoordf32,
+    major: Vec<f32>,
+    minor: Vec<f32>,
+}
+impl GridAxis {
+    fn new(range: Range<f32>, spacing: GridSpacing) -> Self {
+        let (major, minor) = axis_ticks(range.start, range.end, spacing);
+        Self {
+            inner: range.into(),
+            major,
+            minor,
+        }
+    }
+}
+impl Ranged for GridAxis {
+    type FormatOption = DefaultFormatting;
+    type ValueType = f32;
+    fn map(&self, value: &f32, limit: (i32, i32)) -> i32 {
+        self.inner.map(value, limit)
+    }
+    fn key_points<Hint: KeyPointHint>(&self, hint: Hint) -> Vec<f32> {
+        if hint.weight().allow_light_points() {
+            self.minor.clone()
+        } else {
+            self.major.clone()
+        }
+    }
+    fn range(&self) -> Range<f32> {
+        self.inner.range()
+    }
+}
 pub fn render_waveform_png(
     frame: &TimeSeriesFrame,
     style: PlotStyle,
@@ -51,19 +158,35 @@ pub fn render_waveform_png(
         } else {
             (y_min, y_max)
         };
+        let dt = if frame.sample_rate_hz > 0.0 { 1.0 / frame.sample_rate_hz } else { 1.0 };
+        let duration = frame.samples[0].len() as f32 * dt;
+        let value_grid = style.value_grid.unwrap_or_else(|| {
+            let major = nice_step(y_bounds.1 - y_bounds.0, 5.0);
+            GridSpacing::new(major, major / 5.0)
+        });
         let mut chart = ChartBuilder::on(&root)
             .margin(10)
             .caption("Time Series", ("sans-serif", 20).into_font().color(&WHITE))
-            .set_label_area_size(LabelAreaPosition::Left, 45)
+            .set_label_area_size(LabelAreaPosition::Left, 55)
             .set_label_area_size(LabelAreaPosition::Bottom, 40)
-            .build_cartesian_2d(0f32..frame.samples[0].len() as f32, y_bounds.0..y_bounds.1)?;
+            .build_cartesian_2d(
+                GridAxis::new(0f32..duration, style.time_grid),
+                GridAxis::new(y_bounds.0..y_bounds.1, value_grid),
+            )?;
         chart
             .configure_mesh()
+            .bold_line_style(WHITE.mix(0.25))
             .light_line_style(&WHITE.mix(0.1))
+            .x_desc("Time (s)")
+            .y_desc(style.value_unit.as_str())
+            .x_label_formatter(&|v| format!("{v:.1}"))
+            .y_label_formatter(&|v| format!("{v:.0}"))
+            .label_style(("sans-serif", 12).into_font().color(&WHITE))
+            .axis_desc_style(("sans-serif", 14).into_font().color(&WHITE))
             .draw()?;
         for (idx, channel) in frame.samples.iter().enumerate() {
             let color = style.palette[idx % style.palette.len()];
-            let series = channel.iter().enumerate().map(|(i, v)| (i as f32, *v));
+            let series = channel.iter().enumerate().map(|(i, v)| (i as f32 * dt, *v));
             chart
                 .draw_series(LineSeries::new(series, &color))?
                 .label(
@@ -144,6 +267,149 @@ pub fn render_spectrum_png(
     }
     encode_png(&buffer, style.width, style.height)
 }
+/// Dynamic range of the spectrogram colour scale, in dB below the loudest cell.
+const SPECTROGRAM_RANGE_DB: f32 = 60.0;
+/// Black -> purple -> orange -> pale yellow, for 0..=1.
+fn heat_color(t: f32) -> RGBColor {
+    const STOPS: [(f32, f32, f32); 4] = [
+        (0.0, 0.0, 4.0),
+        (120.0, 28.0, 109.0),
+        (237.0, 105.0, 37.0),
+        (252.0, 255.0, 164.0),
+    ];
+    let x = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let i = (x.floor() as usize).min(STOPS.len() - 2);
+    let f = x - i as f32;
+    let (a, b) = (STOPS[i], STOPS[i + 1]);
+    let mix = |p: f32, q: f32| (p + (q - p) * f).round() as u8;
+    RGBColor(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+/// One channel of `spectrogram` as a time x frequency heatmap, in dB relative to
+/// its loudest cell.
+pub fn render_spectrogram_png(
+    spectrogram: &Spectrogram,
+    channel: usize,
+    style: PlotStyle,
+) -> Result<Vec<u8>, ModelizeError> {
+    let columns = spectrogram
+        .magnitudes
+        .get(channel)
+        .filter(|_| !spectrogram.is_empty())
+        .ok_or_else(|| ModelizeError::Plot(format!("channel {channel} has no spectrogram")))?;
+    let times = &spectrogram.times_s;
+    let freqs = &spectrogram.frequencies_hz;
+    let col_width = match times.as_slice() {
+        [a, b, ..] => b - a,
+        [a] => 2.0 * a,
+        [] => 1.0,
+    };
+    let bin_height = match freqs.as_slice() {
+        [a, b, ..] => b - a,
+        _ => 1.0,
+    };
+    let loudest = columns
+        .iter()
+        .flat_map(|c| c.iter().copied())
+        .fold(0.0f32, f32::max)
+        .max(1e-12);
+    let x_max = times.last().copied().unwrap_or(0.0) + col_width / 2.0;
+    let y_max = freqs.last().copied().unwrap_or(0.0) + bin_height;
+    let mut buffer = vec![0u8; (style.width * style.height * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (style.width, style.height))
+            .into_drawing_area();
+        root.fill(&style.background)?;
+        let label = spectrogram
+            .channel_labels
+            .get(channel)
+            .cloned()
+            .unwrap_or_else(|| format!("Ch {channel}"));
+        let mut chart = ChartBuilder::on(&root)
+            .margin(10)
+            .caption(
+                format!("Spectrogram - {label}"),
+                ("sans-serif", 20).into_font().color(&WHITE),
+            )
+            .set_label_area_size(LabelAreaPosition::Left, 45)
+            .set_label_area_size(LabelAreaPosition::Bottom, 40)
+            .build_cartesian_2d(
+                (times[0] - col_width / 2.0).max(0.0)..x_max,
+                0f32..y_max,
+            )?;
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .x_desc("s")
+            .y_desc("Hz")
+            .axis_style(WHITE.mix(0.5))
+            .label_style(("sans-serif", 12).into_font().color(&WHITE))
+            .draw()?;
+        chart.draw_series(columns.iter().zip(times).flat_map(|(column, &t)| {
+            column.iter().zip(freqs).map(move |(&m, &f)| {
+                let db = 20.0 * (m.max(1e-12) / loudest).log10();
+                let color = heat_color(1.0 + db / SPECTROGRAM_RANGE_DB);
+                Rectangle::new(
+                    [(t - col_width / 2.0, f), (t + col_width / 2.0, f + bin_height)],
+                    color.filled(),
+                )
+            })
+        }))?;
+        root.present()?;
+    }
+    encode_png(&buffer, style.width, style.height)
+}
+/// One channel of `frame` as a trace on a fully transparent background, newest
+/// sample at the right edge; no axes, labels or grid.
+pub fn render_overlay_png(
+    frame: &TimeSeriesFrame,
+    channel: usize,
+    style: &OverlayStyle,
+) -> Result<Vec<u8>, ModelizeError> {
+    let samples = frame
+        .samples
+        .get(channel)
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| ModelizeError::Plot(format!("channel {channel} has no samples")))?;
+    let dt = if frame.sample_rate_hz > 0.0 { 1.0 / frame.sample_rate_hz } else { 1.0 };
+    let window = style.window_seconds.max(dt);
+    let visible = &samples[samples.len().saturating_sub((window / dt).ceil() as usize)..];
+    let (lo, hi) = visible
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(lo, hi), v| (lo.min(*v), hi.max(*v)));
+    let pad = ((hi - lo) * 0.1).max(1.0);
+    // 先在纯色底上绘制，再把底色像素变成全透明；底色取与轨迹不同的颜色
+    let key = if style.color == BLACK { WHITE } else { BLACK };
+    let mut buffer = vec![0u8; (style.width * style.height * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (style.width, style.height))
+            .into_drawing_area();
+        root.fill(&key)?;
+        let mut chart = ChartBuilder::on(&root)
+            .build_cartesian_2d(0f32..window, (lo - pad)..(hi + pad))?;
+        let start = window - visible.len() as f32 * dt;
+        let series = visible
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (start + (i + 1) as f32 * dt, *v));
+        chart.draw_series(LineSeries::new(
+            series,
+            style.color.stroke_width(style.line_width),
+        ))?;
+        root.present()?;
+    }
+    let rgba: Vec<u8> = buffer
+        .chunks_exact(3)
+        .flat_map(|px| {
+            let alpha = if px == [key.0, key.1, key.2] { 0 } else { 255 };
+            [px[0], px[1], px[2], alpha]
+        })
+        .collect();
+    let image = ImageBuffer::<Rgba<u8>, _>::from_raw(style.width, style.height, rgba)
+        .ok_or_else(|| ModelizeError::Plot("failed to allocate image buffer".into()))?;
+    let mut output = Vec::new();
+    DynamicImage::ImageRgba8(image).write_to(&mut Cursor::new(&mut output), ImageFormat::Png)?;
+    Ok(output)
+}
 fn encode_png(buffer: &[u8], width: u32, height: u32) -> Result<Vec<u8>, ModelizeError> {
     let image = ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, buffer.to_vec())
         .ok_or_else(|| ModelizeError::Plot("failed to allocate image buffer".into()))?;
@@ -152,3 +418,70 @@ fn encode_png(buffer: &[u8], width: u32, height: u32) -> Result<Vec<u8>, Modeliz
     dynamic.write_to(&mut Cursor::new(&mut output), ImageFormat::Png)?;
     Ok(output)
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn one_second_grid_over_five_seconds() {
+        let spacing = GridSpacing::new(1.0, 0.5);
+        let (major, minor) = axis_ticks(0.0, 5.0, spacing);
+        assert_eq!(major, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(minor.len(), 5);
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: vec!["Ch1".into()],
+            samples: vec![(0..1250).map(|i| (i as f32 * 0.1).sin() * 40.0).collect()],
+            timestamps: Vec::new(),
+        };
+        let style = PlotStyle {
+            time_grid: spacing,
+            ..PlotStyle::default()
+        };
+        assert!(render_waveform_png(&frame, style).is_ok());
+    }
+    #[test]
+    fn overlay_background_is_transparent_away_from_the_trace() {
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: vec!["Ch1".into(), "Ch2".into()],
+            samples: vec![
+                vec![0.0; 2500],
+                (0..2500).map(|i| (i as f32 * 0.05).sin() * 30.0).collect(),
+            ],
+            timestamps: Vec::new(),
+        };
+        let style = OverlayStyle::default();
+        let png = render_overlay_png(&frame, 1, &style).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (style.width, style.height));
+        // 曲线上下各留 10% 边距，四角不会有轨迹
+        for (x, y) in [(0, 0), (style.width - 1, 0), (0, style.height - 1)] {
+            assert_eq!(image.get_pixel(x, y).0[3], 0, "pixel ({x}, {y})");
+        }
+        assert!(image.pixels().any(|p| p.0 == [0, 230, 118, 255]));
+        assert!(render_overlay_png(&frame, 5, &style).is_err());
+    }
+    #[test]
+    fn spectrogram_renders_and_rejects_an_empty_channel() {
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: vec!["Ch1".into()],
+            samples: vec![(0..1000).map(|i| (i as f32 * 0.25).sin() * 20.0).collect()],
+            timestamps: Vec::new(),
+        };
+        let spectrogram = Spectrogram::from_frame(&frame, 128, 32);
+        let png = render_spectrogram_png(&spectrogram, 0, PlotStyle::default()).unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!(image.width(), PlotStyle::default().width);
+        assert!(render_spectrogram_png(&spectrogram, 1, PlotStyle::default()).is_err());
+        let short = Spectrogram::from_frame(
+            &TimeSeriesFrame {
+                samples: vec![vec![0.0; 50]],
+                ..frame
+            },
+            128,
+            32,
+        );
+        assert!(render_spectrogram_png(&short, 0, PlotStyle::default()).is_err());
+    }
+}
@@ -8,20 +8,61 @@
 //!
 //! Ganglion:
 //! - Firmware returns impedance-like values on the resistance channels.
+//! - The estimate per channel is the mean reading over the recent window.
 //! - GUI halves the value to account for the driven-ground leg (see W_GanglionImpedance.pde).
 //! - Values are displayed as kΩ in the GUI.
+use serde::{Deserialize, Serialize};
 /// Series resistor used on the Cyton board (ohms).
 pub const SERIES_RESISTOR_OHMS: f32 = 2200.0;
 /// Lead-off drive current configured on Cyton (amps).
 pub const LEAD_OFF_DRIVE_AMPS: f32 = 6.0e-9;
+/// Cyton ADS1299 input range at the default gain of 24 (±4.5 V / 24), in µV.
+pub const CYTON_RAIL_UV: f32 = 187_500.0;
+/// Share of the rail the window mean must reach to count as railed (GUI uses 90%).
+pub const RAIL_NEAR_FRACTION: f32 = 0.9;
+/// Windows with fewer samples than this mean the channel stopped delivering data.
+pub const MIN_RAIL_CHECK_SAMPLES: usize = 8;
+/// Cyton lead-off settings the impedance math depends on.
+///
+/// The ADS1299 lead-off register selects one of [`CytonLeadOff::STANDARD_DRIVE_AMPS`];
+/// the GUI assumes the firmware default of 6 nA.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CytonLeadOff {
+    pub drive_amps: f32,
+    pub series_ohms: f32,
+}
+impl Default for CytonLeadOff {
+    fn default() -> Self {
+        Self {
+            drive_amps: LEAD_OFF_DRIVE_AMPS,
+            series_ohms: SERIES_RESISTOR_OHMS,
+        }
+    }
+}
+impl CytonLeadOff {
+    /// Drive currents the firmware can select: 6 nA, 24 nA, 6 µA, 24 µA.
+    pub const STANDARD_DRIVE_AMPS: [f32; 4] = [6.0e-9, 24.0e-9, 6.0e-6, 24.0e-6];
+    /// Drive current as shown in the GUI, e.g. "24 nA".
+    pub fn drive_label(&self) -> String {
+        if self.drive_amps >= 1.0e-6 {
+            format!("{} µA", (self.drive_amps * 1.0e6).round())
+        } else {
+            format!("{} nA", (self.drive_amps * 1.0e9).round())
+        }
+    }
+}
 /// Compute Cyton-style impedance (ohms) from a channel's standard deviation (microvolts).
 ///
 /// Equivalent to the GUI calculation:
 /// `impedance = sqrt(2) * std_uV * 1e-6 / LEAD_OFF_DRIVE_AMPS - SERIES_RESISTOR_OHMS`
 /// Negative values are clamped to zero.
 pub fn cyton_impedance_from_std(std_microvolts: f32) -> f32 {
+    cyton_impedance_from_std_with(std_microvolts, &CytonLeadOff::default())
+}
+/// [`cyton_impedance_from_std`] with the drive current and series resistor of `lead_off`.
+pub fn cyton_impedance_from_std_with(std_microvolts: f32, lead_off: &CytonLeadOff) -> f32 {
     let mut impedance_ohms =
-        (2.0_f32.sqrt() * std_microvolts * 1.0e-6) / LEAD_OFF_DRIVE_AMPS - SERIES_RESISTOR_OHMS;
+        (2.0_f32.sqrt() * std_microvolts * 1.0e-6) / lead_off.drive_amps - lead_off.series_ohms;
     if impedance_ohms.is_nan() || impedance_ohms < 0.0 {
         impedance_ohms = 0.0;
     }
@@ -31,12 +72,12 @@ pub fn cyton_impedance_from_std(std_microvolts: f32) -> f32 {
 ///
 /// Each slice in `channels_uv` should be the recent samples for one channel (same length).
 /// The function measures standard deviation per channel, then converts to impedance (ohms).
-pub fn cyton_impedances_from_samples(channels_uv: &[&[f32]]) -> Vec<f32> {
+pub fn cyton_impedances_from_samples(channels_uv: &[&[f32]], lead_off: &CytonLeadOff) -> Vec<f32> {
     channels_uv
         .iter()
         .map(|channel| {
             let std = std_dev(channel);
-            cyton_impedance_from_std(std)
+            cyton_impedance_from_std_with(std, lead_off)
         })
         .collect()
 }
@@ -56,6 +97,39 @@ fn std_dev(data: &[f32]) -> f32 {
         / data.len() as f32;
     variance.sqrt()
 }
+/// True when a channel of raw µV samples is pinned at the Cyton ADC rail.
+///
+/// A disconnected electrode often sits at the rail with almost no variance, so the
+/// std-dev based impedance looks excellent; this catches it from the mean instead.
+pub fn channel_is_railed(raw_samples: &[f32]) -> bool {
+    channel_is_railed_at(raw_samples, CYTON_RAIL_UV)
+}
+/// [`channel_is_railed`] against a rail of `rail_uv` (e.g. for another gain setting).
+/// Too few samples also count as railed.
+pub fn channel_is_railed_at(raw_samples: &[f32], rail_uv: f32) -> bool {
+    if raw_samples.len() < MIN_RAIL_CHECK_SAMPLES {
+        return true;
+    }
+    let mean = raw_samples.iter().copied().sum::<f32>() / raw_samples.len() as f32;
+    !mean.is_finite() || mean.abs() >= rail_uv.abs() * RAIL_NEAR_FRACTION
+}
+/// Which board's impedance math applies to the measured channels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImpedanceModel {
+    /// Lead-off drive current through the series resistor (std dev based).
+    Cyton,
+    /// Firmware resistance readings, averaged over the window and halved like the GUI.
+    Ganglion,
+}
+impl ImpedanceModel {
+    /// Impedance (ohms) per channel of recent samples; `lead_off` only affects Cyton.
+    pub fn impedances_ohms(self, channels: &[&[f32]], lead_off: &CytonLeadOff) -> Vec<f32> {
+        match self {
+            ImpedanceModel::Cyton => cyton_impedances_from_samples(channels, lead_off),
+            ImpedanceModel::Ganglion => ganglion_impedances_from_samples(channels),
+        }
+    }
+}
 /// Convert Ganglion resistance channel readings to the displayed impedance (kΩ).
 ///
 /// In the GUI the raw value is divided by two before being shown as kilo-ohms.
@@ -63,6 +137,28 @@ fn std_dev(data: &[f32]) -> f32 {
 pub fn ganglion_display_impedance_kohms(raw_value: f32) -> f32 {
     raw_value / 2.0
 }
+/// Ganglion counterpart of [`cyton_impedances_from_samples`]: impedances (ohms) per channel.
+///
+/// Each slice in `channels_uv` holds the recent resistance-channel readings for one channel.
+/// The readings are averaged, then halved for the driven-ground leg exactly like
+/// [`ganglion_display_impedance_kohms`]. Empty, negative or NaN estimates become zero.
+pub fn ganglion_impedances_from_samples(channels_uv: &[&[f32]]) -> Vec<f32> {
+    channels_uv
+        .iter()
+        .map(|channel| {
+            if channel.is_empty() {
+                return 0.0;
+            }
+            let raw = channel.iter().copied().sum::<f32>() / channel.len() as f32;
+            let impedance_ohms = ganglion_display_impedance_kohms(raw / 1000.0) * 1000.0;
+            if impedance_ohms.is_nan() || impedance_ohms < 0.0 {
+                0.0
+            } else {
+                impedance_ohms
+            }
+        })
+        .collect()
+}
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -78,4 +174,73 @@ mod tests {
     fn ganglion_impedance_scaling() {
         assert_eq!(ganglion_display_impedance_kohms(100.0), 50.0);
     }
+    #[test]
+    fn ganglion_samples_average_then_halve_like_the_gui() {
+        // 读数在 20 kΩ 上下波动，GUI 显示为一半即 10 kΩ
+        let steady = [19_000.0_f32, 21_000.0, 20_000.0, 20_000.0];
+        let railed = [-500.0_f32, -500.0];
+        let imps = ganglion_impedances_from_samples(&[&steady, &railed, &[]]);
+        assert_eq!(imps.len(), 3);
+        let expected = ganglion_display_impedance_kohms(20_000.0 / 1000.0) * 1000.0;
+        assert!((imps[0] - expected).abs() < 1e-3);
+        assert!((imps[0] - 10_000.0).abs() < 1e-3);
+        assert_eq!(imps[1], 0.0);
+        assert_eq!(imps[2], 0.0);
+        assert_eq!(
+            ImpedanceModel::Ganglion.impedances_ohms(&[&steady], &CytonLeadOff::default()),
+            vec![imps[0]]
+        );
+    }
+    #[test]
+    fn flat_lined_rail_is_railed_but_a_noisy_channel_is_not() {
+        // 脱落电极贴在负电源轨上几乎不动，按标准差算出的阻抗反而很低
+        let flat: Vec<f32> = (0..250)
+            .map(|i| -CYTON_RAIL_UV + 0.01 * (i % 3) as f32)
+            .collect();
+        assert!(channel_is_railed(&flat));
+        assert_eq!(cyton_impedance_from_std(std_dev(&flat)), 0.0);
+        let noisy: Vec<f32> = (0..250)
+            .map(|i| 40.0 * (i as f32 * 0.7).sin() + 250.0)
+            .collect();
+        assert!(!channel_is_railed(&noisy));
+        assert!(channel_is_railed(&noisy[..MIN_RAIL_CHECK_SAMPLES - 1]));
+        // 低增益下电源轨更高，同样的平坦信号不再算贴轨
+        assert!(!channel_is_railed_at(&flat, CYTON_RAIL_UV * 24.0));
+    }
+    #[test]
+    fn each_standard_drive_current_scales_the_cyton_estimate() {
+        let std_uv = 100.0;
+        let with_drive = |drive_amps| CytonLeadOff {
+            drive_amps,
+            ..CytonLeadOff::default()
+        };
+        assert_eq!(
+            cyton_impedance_from_std_with(std_uv, &CytonLeadOff::default()),
+            cyton_impedance_from_std(std_uv)
+        );
+        let labels: Vec<String> = CytonLeadOff::STANDARD_DRIVE_AMPS
+            .iter()
+            .map(|&amps| with_drive(amps).drive_label())
+            .collect();
+        assert_eq!(labels, ["6 nA", "24 nA", "6 µA", "24 µA"]);
+        // 同一个 10 kΩ 电极在各驱动电流下产生的标准差不同，换算回来都应是 10 kΩ
+        for amps in CytonLeadOff::STANDARD_DRIVE_AMPS {
+            let lead_off = with_drive(amps);
+            let std = (10_000.0 + SERIES_RESISTOR_OHMS) * amps / (2.0_f32.sqrt() * 1.0e-6);
+            let imp = cyton_impedance_from_std_with(std, &lead_off);
+            assert!((imp - 10_000.0).abs() < 1.0, "{amps} A: {imp}");
+        }
+        // 100 µV 在 24 µA 驱动下远小于串联电阻，钳位为 0
+        assert_eq!(
+            cyton_impedance_from_std_with(std_uv, &with_drive(24.0e-6)),
+            0.0
+        );
+        let custom = CytonLeadOff {
+            drive_amps: 6.0e-9,
+            series_ohms: 10_000.0,
+        };
+        let delta =
+            cyton_impedance_from_std(std_uv) - cyton_impedance_from_std_with(std_uv, &custom);
+        assert!((delta - 7_800.0).abs() < 1.0, "{delta}");
+    }
 }
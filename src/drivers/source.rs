@@ -1,4 +1,6 @@
 use std::collections::VecDeque;
+use std::io::BufRead;
+use std::path::Path;
 use std::time::{Duration, SystemTime};
 use crate::drivers::ModelizeError;
 /// Single batch of multi-channel EEG/EMG samples.
@@ -8,6 +10,9 @@ pub struct SignalBatch {
     pub sample_rate_hz: f32,
     pub samples: Vec<Vec<f32>>, // channels x samples
     pub channel_labels: Vec<String>,
+    /// Device time of each sample in seconds; empty when the source has no clock
+    /// and the buffer should synthesize one from `started_at`.
+    pub timestamps: Vec<f64>,
 }
 impl SignalBatch {
     pub fn validate(&self) -> Result<(), ModelizeError> {
@@ -21,6 +26,13 @@ impl SignalBatch {
                 actual: channel_count,
             });
         }
+        let samples = self.samples_per_channel().unwrap_or(0);
+        if !self.timestamps.is_empty() && self.timestamps.len() != samples {
+            return Err(ModelizeError::TimestampMismatch {
+                expected: samples,
+                actual: self.timestamps.len(),
+            });
+        }
         Ok(())
     }
     pub fn num_channels(&self) -> usize {
@@ -54,3 +66,132 @@ impl SignalSource for ManualSource {
         Ok(self.queue.pop_front())
     }
 }
+/// Replays a `DataRecorder` CSV (`[SampleIndex,]Timestamp,Ch0,...`) in batches of
+/// `batch_size` samples. The sample rate is inferred from the timestamp column.
+pub struct CsvFileSource {
+    channel_labels: Vec<String>,
+    sample_rate_hz: f32,
+    samples: Vec<Vec<f32>>, // channels x samples
+    timestamps: Vec<f64>,
+    batch_size: usize,
+    cursor: usize,
+    opened_at: SystemTime,
+}
+impl CsvFileSource {
+    pub fn open(path: impl AsRef<Path>, batch_size: usize) -> Result<Self, ModelizeError> {
+        let file = std::fs::File::open(path)?;
+        Self::from_reader(std::io::BufReader::new(file), batch_size)
+    }
+    /// Parses the whole recording up front so malformed rows fail before playback starts.
+    pub fn from_reader(reader: impl BufRead, batch_size: usize) -> Result<Self, ModelizeError> {
+        let mut lines = reader.lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        let columns: Vec<&str> = header.trim().split(',').collect();
+        let time_col = columns
+            .iter()
+            .position(|c| *c == "Timestamp")
+            .ok_or_else(|| ModelizeError::MalformedCsv {
+                line: 1,
+                reason: "no Timestamp column".to_owned(),
+            })?;
+        let channel_labels: Vec<String> = columns[time_col + 1..]
+            .iter()
+            .map(|c| (*c).to_owned())
+            .collect();
+        let mut samples = vec![Vec::new(); channel_labels.len()];
+        let mut timestamps = Vec::new();
+        for (idx, line) in lines.enumerate() {
+            let line = line?;
+            let line_no = idx + 2;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let cells: Vec<&str> = line.trim().split(',').collect();
+            if cells.len() != columns.len() {
+                return Err(ModelizeError::MalformedCsv {
+                    line: line_no,
+                    reason: format!("expected {} cells, got {}", columns.len(), cells.len()),
+                });
+            }
+            let number = |cell: &str| {
+                cell.trim()
+                    .parse::<f64>()
+                    .map_err(|_| ModelizeError::MalformedCsv {
+                        line: line_no,
+                        reason: format!("not a number: {cell:?}"),
+                    })
+            };
+            timestamps.push(number(cells[time_col])?);
+            for (channel, cell) in samples.iter_mut().zip(&cells[time_col + 1..]) {
+                channel.push(number(cell)? as f32);
+            }
+        }
+        let sample_rate_hz =
+            infer_sample_rate(&timestamps).ok_or(ModelizeError::InvalidSampleRate)?;
+        Ok(Self {
+            channel_labels,
+            sample_rate_hz,
+            samples,
+            timestamps,
+            batch_size: batch_size.max(1),
+            cursor: 0,
+            opened_at: SystemTime::now(),
+        })
+    }
+    pub fn sample_rate_hz(&self) -> f32 {
+        self.sample_rate_hz
+    }
+}
+impl SignalSource for CsvFileSource {
+    fn next_batch(&mut self) -> Result<Option<SignalBatch>, ModelizeError> {
+        let total = self.timestamps.len();
+        if self.cursor >= total {
+            return Ok(None);
+        }
+        let end = (self.cursor + self.batch_size).min(total);
+        let offset = self.timestamps[self.cursor] - self.timestamps[0];
+        let batch = SignalBatch {
+            started_at: self.opened_at + Duration::from_secs_f64(offset.max(0.0)),
+            sample_rate_hz: self.sample_rate_hz,
+            samples: self
+                .samples
+                .iter()
+                .map(|ch| ch[self.cursor..end].to_vec())
+                .collect(),
+            channel_labels: self.channel_labels.clone(),
+            timestamps: self.timestamps[self.cursor..end].to_vec(),
+        };
+        self.cursor = end;
+        Ok(Some(batch))
+    }
+}
+/// Median of the positive timestamp steps, so a gap or a rotated part doesn't skew it.
+fn infer_sample_rate(timestamps: &[f64]) -> Option<f32> {
+    let mut deltas: Vec<f64> = timestamps
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .filter(|d| *d > 0.0)
+        .collect();
+    if deltas.is_empty() {
+        return None;
+    }
+    deltas.sort_by(|a, b| a.total_cmp(b));
+    Some((1.0 / deltas[deltas.len() / 2]) as f32)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn ragged_rows_and_non_numeric_cells_are_rejected() {
+        let ragged = "Timestamp,Ch0,Ch1\n0.0000,1.00,2.00\n0.0040,1.00\n";
+        assert!(matches!(
+            CsvFileSource::from_reader(ragged.as_bytes(), 8),
+            Err(ModelizeError::MalformedCsv { line: 3, .. })
+        ));
+        let text = "Timestamp,Ch0\n0.0000,1.00\n0.0040,railed\n";
+        assert!(matches!(
+            CsvFileSource::from_reader(text.as_bytes(), 8),
+            Err(ModelizeError::MalformedCsv { line: 3, .. })
+        ));
+    }
+}
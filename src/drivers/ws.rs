@@ -0,0 +1,398 @@
+// src/drivers/ws.rs
+// WebSocket 输出：浏览器仪表盘连上后，每批干净数据 (降采样后) 以 JSON 文本帧推送：
+//   {"sample_rate": 250.0, "channels": [[...], [...]]}
+// 只用标准库 (握手所需的 SHA-1/base64 在这里手写)；需启用 `ws` feature 才会真正监听端口。
+// 每个客户端一个写线程和一个有界队列，客户端跟不上时丢弃新帧，不会阻塞引擎线程。
+use super::{ModelizeError, TimeSeriesFrame};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Points per channel in each pushed frame; longer frames are averaged down.
+pub const WS_MAX_POINTS: usize = 32;
+
+type Clients = Arc<Mutex<Vec<SyncSender<Arc<Vec<u8>>>>>>;
+
+fn lock(clients: &Clients) -> MutexGuard<'_, Vec<SyncSender<Arc<Vec<u8>>>>> {
+    clients.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Listening server; dropping it stops accepting and closes every client.
+pub struct WsServer {
+    addr: SocketAddr,
+    clients: Clients,
+    #[cfg(feature = "ws")]
+    _accept: server::AcceptLoop,
+}
+
+/// Starts listening on `addr` (port 0 picks a free one, see [`WsServer::local_addr`]).
+pub fn start_ws_server(addr: SocketAddr) -> Result<WsServer, ModelizeError> {
+    #[cfg(feature = "ws")]
+    {
+        let clients = Clients::default();
+        let accept = server::AcceptLoop::spawn(addr, clients.clone())?;
+        Ok(WsServer {
+            addr: accept.local_addr,
+            clients,
+            _accept: accept,
+        })
+    }
+    #[cfg(not(feature = "ws"))]
+    {
+        let _ = addr;
+        Err(ModelizeError::WebSocket(
+            "built without WebSocket support (enable the `ws` feature)".to_owned(),
+        ))
+    }
+}
+
+impl WsServer {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn client_count(&self) -> usize {
+        lock(&self.clients).len()
+    }
+
+    /// Queues `frame` for every client without blocking: a client whose queue is
+    /// full skips it, and a disconnected one is forgotten.
+    pub fn broadcast(&self, frame: &TimeSeriesFrame) -> Result<(), ModelizeError> {
+        let payload = Arc::new(text_frame(&frame_json(frame, WS_MAX_POINTS)?));
+        lock(&self.clients).retain(|tx| {
+            !matches!(
+                tx.try_send(payload.clone()),
+                Err(TrySendError::Disconnected(_))
+            )
+        });
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct WsFrame {
+    sample_rate: f32,
+    channels: Vec<Vec<f32>>,
+}
+
+/// JSON for one frame, each channel averaged in equal buckets down to at most
+/// `max_points`; `sample_rate` is the rate after that reduction.
+fn frame_json(frame: &TimeSeriesFrame, max_points: usize) -> Result<String, ModelizeError> {
+    let len = frame.samples.iter().map(Vec::len).max().unwrap_or(0);
+    let factor = len.div_ceil(max_points.max(1)).max(1);
+    let channels = frame
+        .samples
+        .iter()
+        .map(|ch| {
+            ch.chunks(factor)
+                .map(|bucket| bucket.iter().sum::<f32>() / bucket.len() as f32)
+                .collect()
+        })
+        .collect();
+    let out = WsFrame {
+        sample_rate: frame.sample_rate_hz / factor as f32,
+        channels,
+    };
+    serde_json::to_string(&out).map_err(|e| ModelizeError::WebSocket(e.to_string()))
+}
+
+/// Unmasked server-to-client text frame (FIN set, opcode 1).
+fn text_frame(payload: &str) -> Vec<u8> {
+    let bytes = payload.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len() + 10);
+    out.push(0x81);
+    match bytes.len() {
+        n if n < 126 => out.push(n as u8),
+        n if n <= u16::MAX as usize => {
+            out.push(126);
+            out.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n => {
+            out.push(127);
+            out.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(bytes);
+    out
+}
+
+#[cfg(feature = "ws")]
+mod server {
+    use super::{lock, Clients, ModelizeError};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+
+    /// Frames a slow client may have queued before newer ones are dropped for it.
+    const CLIENT_QUEUE_FRAMES: usize = 8;
+
+    const ACCEPT_POLL: Duration = Duration::from_millis(50);
+    const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+    /// RFC 6455 handshake GUID appended to the client's key.
+    const WS_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+    /// Accept thread; it stops polling once this is dropped.
+    pub struct AcceptLoop {
+        pub local_addr: SocketAddr,
+        stop: Arc<AtomicBool>,
+    }
+
+    impl AcceptLoop {
+        pub fn spawn(addr: SocketAddr, clients: Clients) -> Result<Self, ModelizeError> {
+            let ws_err = |e: std::io::Error| ModelizeError::WebSocket(format!("{addr}: {e}"));
+            let listener = TcpListener::bind(addr).map_err(ws_err)?;
+            listener.set_nonblocking(true).map_err(ws_err)?;
+            let local_addr = listener.local_addr().map_err(ws_err)?;
+            let stop = Arc::new(AtomicBool::new(false));
+            let flag = stop.clone();
+            thread::spawn(move || {
+                while !flag.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let clients = clients.clone();
+                            thread::spawn(move || serve_client(stream, clients));
+                        }
+                        Err(_) => thread::sleep(ACCEPT_POLL),
+                    }
+                }
+            });
+            Ok(Self { local_addr, stop })
+        }
+    }
+
+    impl Drop for AcceptLoop {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Upgrades the connection, registers its queue and writes queued frames until
+    /// the client goes away or the server drops the queue.
+    fn serve_client(mut stream: TcpStream, clients: Clients) {
+        // 某些平台上 accept 出来的套接字会继承监听端的非阻塞模式
+        if stream.set_nonblocking(false).is_err() {
+            return;
+        }
+        let Some(key) = read_handshake_key(&stream) else {
+            return;
+        };
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(&key)
+        );
+        if stream.write_all(response.as_bytes()).is_err() {
+            return;
+        }
+        let (tx, rx) = mpsc::sync_channel(CLIENT_QUEUE_FRAMES);
+        lock(&clients).push(tx);
+        drop(clients);
+        for payload in rx {
+            if stream.write_all(&payload).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// `Sec-WebSocket-Key` from the upgrade request, `None` for anything else.
+    fn read_handshake_key(stream: &TcpStream) -> Option<String> {
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).ok()?;
+        let mut reader = BufReader::new(stream.try_clone().ok()?);
+        let mut key = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                return key;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                    key = Some(value.trim().to_owned());
+                }
+            }
+        }
+    }
+
+    fn accept_key(key: &str) -> String {
+        base64(&sha1(format!("{key}{WS_GUID}").as_bytes()))
+    }
+
+    fn sha1(data: &[u8]) -> [u8; 20] {
+        let mut h: [u32; 5] = [
+            0x6745_2301,
+            0xEFCD_AB89,
+            0x98BA_DCFE,
+            0x1032_5476,
+            0xC3D2_E1F0,
+        ];
+        let mut msg = data.to_vec();
+        msg.push(0x80);
+        while msg.len() % 64 != 56 {
+            msg.push(0);
+        }
+        msg.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+        for block in msg.chunks(64) {
+            let mut w = [0u32; 80];
+            for (i, word) in block.chunks(4).enumerate() {
+                w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+            }
+            for i in 16..80 {
+                w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+            }
+            let [mut a, mut b, mut c, mut d, mut e] = h;
+            for (i, &wi) in w.iter().enumerate() {
+                let (f, k) = match i {
+                    0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                    20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                    40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                    _ => (b ^ c ^ d, 0xCA62_C1D6),
+                };
+                let t = a
+                    .rotate_left(5)
+                    .wrapping_add(f)
+                    .wrapping_add(e)
+                    .wrapping_add(k)
+                    .wrapping_add(wi);
+                e = d;
+                d = c;
+                c = b.rotate_left(30);
+                b = a;
+                a = t;
+            }
+            for (hi, v) in h.iter_mut().zip([a, b, c, d, e]) {
+                *hi = hi.wrapping_add(v);
+            }
+        }
+        let mut out = [0u8; 20];
+        for (chunk, v) in out.chunks_mut(4).zip(h) {
+            chunk.copy_from_slice(&v.to_be_bytes());
+        }
+        out
+    }
+
+    fn base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let n = u32::from(chunk[0]) << 16
+                | u32::from(*chunk.get(1).unwrap_or(&0)) << 8
+                | u32::from(*chunk.get(2).unwrap_or(&0));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn accept_key_matches_the_rfc_6455_example() {
+            assert_eq!(
+                accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+                "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_frames_are_averaged_down_to_the_point_budget() {
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: Vec::new(),
+            samples: vec![(0..100).map(|i| i as f32).collect(), vec![2.0; 100]],
+            timestamps: Vec::new(),
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&frame_json(&frame, WS_MAX_POINTS).unwrap()).unwrap();
+        // 100 samples in buckets of 4 -> 25 points at a quarter of the rate.
+        assert_eq!(json["sample_rate"], 62.5);
+        let channels = json["channels"].as_array().unwrap();
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[0].as_array().unwrap().len(), 25);
+        assert_eq!(channels[0][0], 1.5);
+        assert!(channels[1].as_array().unwrap().iter().all(|v| v == 2.0));
+
+        let frame = text_frame(&"x".repeat(300));
+        assert_eq!(&frame[..4], &[0x81, 126, 0x01, 0x2c]);
+        assert_eq!(frame.len(), 304);
+    }
+
+    #[cfg(feature = "ws")]
+    #[test]
+    fn connected_client_receives_a_well_formed_frame() {
+        use std::io::{Read, Write};
+        use std::time::{Duration, Instant};
+
+        let server = start_ws_server(([127, 0, 0, 1], 0).into()).expect("server");
+        let mut client = std::net::TcpStream::connect(server.local_addr()).expect("connect");
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        write!(
+            client,
+            "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = Vec::new();
+        let mut byte = [0u8; 1];
+        while !response.ends_with(b"\r\n\r\n") {
+            client.read_exact(&mut byte).unwrap();
+            response.push(byte[0]);
+        }
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 101"), "{response}");
+        assert!(
+            response.contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="),
+            "{response}"
+        );
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.client_count() == 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: Vec::new(),
+            samples: vec![vec![1.0; 25]; 4],
+            timestamps: Vec::new(),
+        };
+        server.broadcast(&frame).unwrap();
+        let mut header = [0u8; 2];
+        client.read_exact(&mut header).unwrap();
+        assert_eq!(header[0], 0x81);
+        let len = match header[1] {
+            126 => {
+                let mut ext = [0u8; 2];
+                client.read_exact(&mut ext).unwrap();
+                u16::from_be_bytes(ext) as usize
+            }
+            n => n as usize,
+        };
+        let mut payload = vec![0u8; len];
+        client.read_exact(&mut payload).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(json["sample_rate"], 250.0);
+        let channels = json["channels"].as_array().unwrap();
+        assert_eq!(channels.len(), 4);
+        assert!(channels.iter().all(|ch| ch.as_array().unwrap().len() == 25));
+    }
+}
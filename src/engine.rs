@@ -1,10 +1,32 @@
 // src/engine.rs
-use crate::drivers::{SignalBatch, SignalBuffer};
-use crate::openbci::OpenBciSession;
-use crate::recorder::DataRecorder;
+mod classifier;
+mod debounce;
+mod features;
+mod mapping;
+mod replay;
+mod synthetic;
+
+use crate::calibration::GuidedCapture;
+use crate::drivers::lsl::LslOutlet;
+use crate::drivers::osc::OscSender;
+use crate::drivers::ws::{start_ws_server, WsServer};
+use crate::drivers::{SignalBatch, SignalBuffer, SlidingSpectrum, TimeSeriesFrame};
+use crate::montage::Montage;
+use crate::openbci::{BoardKind, OpenBciSession};
+use crate::recorder::{DataRecorder, RecordMode};
 use crate::types::*;
-use crate::vjoy::VJoyClient;
+use crate::waveform::{filter::FilterChain, FilterKind};
+use crate::vjoy::{VJoyClient, VigemPad, VirtualGamepad};
+use classifier::{argmax, band_power_features, Classifier, LogisticModel};
+use debounce::TriggerDebouncer;
+use features::FeatureWindow;
+use synthetic::SyntheticEeg;
+pub use features::FeatureKind;
+pub use mapping::GamepadAction;
+use mapping::MappingTable;
+pub use replay::{replay_decode, DecodeTimeline, ReplayConfig, ReplayError};
 use std::f64::consts::PI;
+use std::path::Path;
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
 use std::time::{Duration, Instant, SystemTime};
@@ -69,7 +91,7 @@ impl Biquad {
 struct SimpleFilter {
     // 级联滤波器：先高通，再陷波
     hp: Vec<BiquadState>, // Per channel
-    notch: Vec<BiquadState>, // Per channel
+    notch: Vec<Vec<BiquadState>>, // Per channel: 50Hz (+ 可选谐波 100/150Hz)
     fs: f64,
 }
 
@@ -92,14 +114,21 @@ impl BiquadState {
 }
 
 impl SimpleFilter {
-    fn new(channels: usize, fs: f64) -> Self {
+    fn new(channels: usize, fs: f64, mains_hz: f64, harmonics: bool) -> Self {
         let mut hp = Vec::with_capacity(channels);
         let mut notch = Vec::with_capacity(channels);
         
         // 1. 3Hz 高通 (去漂移)
         let hp_coeffs = Self::calc_coeffs(fs, 3.0, 0.707, true);
-        // 2. 50Hz 陷波 (去工频干扰 - 国内50Hz，如果是欧美改60Hz)
-        let notch_coeffs = Self::calc_coeffs(fs, 50.0, 10.0, false);
+        // 2. 工频陷波 (国内 50Hz，北美 60Hz，由 SetMainsFrequency 切换)
+        //    可选再陷掉前两个谐波 (如 100/150Hz)，只保留低于奈奎斯特频率的
+        let harmonic_count = if harmonics { 3 } else { 1 };
+        let notch_coeffs: Vec<BiquadState> = (1..=harmonic_count)
+            .map(|k| mains_hz * k as f64)
+            .filter(|&f| f < fs / 2.0)
+            // Q 随频率放大，保持各谐波的绝对带宽一致
+            .map(|f| Self::calc_coeffs(fs, f, 10.0 * f / mains_hz, false))
+            .collect();
 
         for _ in 0..channels {
             hp.push(hp_coeffs.clone());
@@ -134,391 +163,1643 @@ impl SimpleFilter {
     fn process_sample(&mut self, channel_idx: usize, sample: f64) -> f64 {
         if channel_idx >= self.hp.len() { return sample; }
         let s1 = self.hp[channel_idx].process(sample);
-        self.notch[channel_idx].process(s1)
+        self.notch[channel_idx].iter_mut().fold(s1, |s, stage| stage.process(s))
+    }
+}
+
+/// 设置该环境变量 (任意值) 后，开始推流时自动打开 LSL 输出
+pub const LSL_ENV_VAR: &str = "NEUROSTICK_LSL";
+
+/// 采集入口的合理性检查：BrainFlow 偶发的离谱值 (如 1e30 / NaN) 直接用该通道上一次的正常值顶替
+pub const DEFAULT_SAMPLE_CLAMP_UV: f64 = 1e7;
+
+struct SampleGuard {
+    limit_uv: f64,
+    last_good: Vec<f64>,
+    rejected: u64,
+}
+
+impl SampleGuard {
+    fn new(channels: usize, limit_uv: f64) -> Self {
+        Self { limit_uv, last_good: vec![0.0; channels], rejected: 0 }
+    }
+
+    fn reset(&mut self) {
+        self.last_good.fill(0.0);
+        self.rejected = 0;
+    }
+
+    /// `uv_per_unit` converts the raw value to µV (1e6 for BrainFlow volts).
+    fn admit(&mut self, channel_idx: usize, raw: f64, uv_per_unit: f64) -> f64 {
+        let Some(last) = self.last_good.get_mut(channel_idx) else { return raw; };
+        if raw.is_finite() && (raw * uv_per_unit).abs() <= self.limit_uv {
+            *last = raw;
+            raw
+        } else {
+            self.rejected += 1;
+            *last
+        }
+    }
+}
+
+/// InjectArtifact (仅模拟模式)：约 100 ms、峰值 500 µV 的 Hann 形瞬变，叠加到所有通道，
+/// 起落平滑，用来检查高通/陷波和丢包统计
+const ARTIFACT_UV: f64 = 500.0;
+const ARTIFACT_SECONDS: f64 = 0.1;
+/// 模拟信号的固定种子，同样的按键序列得到同样的演示
+const SIM_SEED: u64 = 0x5EED_2024;
+
+/// 摇杆偏移 (-1..=1) 转成 vJoy 轴值，中心 16384
+fn vjoy_axis(v: f32) -> i32 {
+    (16384.0 + v.clamp(-1.0, 1.0) * 16000.0) as i32
+}
+
+/// vJoy 按键号：A/B/X/Y = 1-4，LB/RB/LT/RT = 5-8，方向键上下左右 = 9-12
+fn vjoy_buttons(gp: &GamepadState) -> [(u8, bool); 12] {
+    [(1, gp.a), (2, gp.b), (3, gp.x), (4, gp.y), (5, gp.lb), (6, gp.rb), (7, gp.lt_pressed()), (8, gp.rt_pressed()),
+     (9, gp.dpad_up), (10, gp.dpad_down), (11, gp.dpad_left), (12, gp.dpad_right)]
+}
+
+/// 扳机按下深度 (0..=1) 转成 vJoy 轴值，松开为 0
+fn vjoy_trigger(v: f32) -> i32 {
+    (v.clamp(0.0, 1.0) * 32768.0) as i32
+}
+
+/// vJoy 轴：X/Y = 左摇杆，RX/RY = 右摇杆，滑块 0/1 = LT/RT 的模拟量；摇杆缩放前先过死区和响应曲线
+fn vjoy_axes(gp: &GamepadState, response: &StickResponse) -> [(u32, i32); 6] {
+    let (lx, ly) = response.shape(gp.lx, gp.ly);
+    let (rx, ry) = response.shape(gp.rx, gp.ry);
+    [(0x30, vjoy_axis(lx)), (0x31, vjoy_axis(ly)), (0x33, vjoy_axis(rx)), (0x34, vjoy_axis(ry)),
+     (0x36, vjoy_trigger(gp.lt)), (0x37, vjoy_trigger(gp.rt))]
+}
+
+/// 方向键转成 POV 帽：(4 向值, 连续角度 1/100 度)，松开为 (-1, -1)；4 向 POV 表示不了斜向，取上/下
+fn vjoy_pov(gp: &GamepadState) -> (i32, i32) {
+    let y = i32::from(gp.dpad_up) - i32::from(gp.dpad_down);
+    let x = i32::from(gp.dpad_right) - i32::from(gp.dpad_left);
+    match (x, y) {
+        (0, 0) => (-1, -1),
+        (0, 1) => (0, 0),
+        (1, 1) => (0, 4500),
+        (1, 0) => (1, 9000),
+        (1, -1) => (2, 13500),
+        (0, -1) => (2, 18000),
+        (-1, -1) => (2, 22500),
+        (-1, 0) => (3, 27000),
+        _ => (0, 31500),
+    }
+}
+
+/// 推流与映射助手共用：把手柄状态的每个按键和轴都写入 vJoy；方向键同时作为按键 9-12 和 POV 1 输出，
+/// 设备配置成 4 向或连续 POV 均可 (不匹配的那个调用会被 vJoy 忽略)
+fn apply_gamepad(joy: &dyn VirtualGamepad, gp: &GamepadState, response: &StickResponse) {
+    for (id, down) in vjoy_buttons(gp) { joy.set_button(id, down); }
+    for (id, value) in vjoy_axes(gp, response) { joy.set_axis(id, value); }
+    let (direction, angle) = vjoy_pov(gp);
+    joy.set_disc_pov(1, direction);
+    joy.set_cont_pov(1, angle);
+    joy.flush();
+}
+
+/// vJoy 输出限速：解码每个采样都会跑，但游戏按自己的频率轮询；只按设定频率把最新状态写入 vJoy
+/// (中间状态被合并)。按固定节拍推进，避免采样间隔不整除时频率漂移
+struct OutputLimiter {
+    interval: Option<Duration>,
+    next_due: Option<Instant>,
+}
+
+impl OutputLimiter {
+    fn new(rate_hz: Option<f32>) -> Self {
+        let mut limiter = Self { interval: None, next_due: None };
+        limiter.set_rate(rate_hz);
+        limiter
+    }
+
+    /// `None` or a non-positive rate writes every decoded state.
+    fn set_rate(&mut self, rate_hz: Option<f32>) {
+        self.interval = rate_hz.filter(|&hz| hz > 0.0).map(|hz| Duration::from_secs_f64(1.0 / hz as f64));
+        self.next_due = None;
+    }
+
+    /// True when the latest decoded state should be applied at `now`.
+    fn due(&mut self, now: Instant) -> bool {
+        let Some(interval) = self.interval else { return true; };
+        match self.next_due {
+            Some(due) if now < due => false,
+            // 落后超过一个周期 (如推流暂停) 时重新对齐，不补发
+            Some(due) => {
+                self.next_due = Some(if now - due >= interval { now + interval } else { due + interval });
+                true
+            }
+            None => {
+                self.next_due = Some(now + interval);
+                true
+            }
+        }
     }
 }
 
 // =========================================================================
 // 2. 神经意图解码器 (逻辑判定)
 // =========================================================================
+/// 解码输入整形：链接的显示滤波 → 特征窗口 → 导联屏蔽；实时解码与回放共用同一条链
+fn decode_input(
+    clean: &[f64],
+    decode_filters: Option<&mut Vec<FilterChain>>,
+    features: &mut FeatureWindow,
+    montage: &Montage,
+) -> Vec<f64> {
+    let mut data = clean.to_vec();
+    if let Some(chains) = decode_filters {
+        for (v, chain) in data.iter_mut().zip(chains.iter_mut()) {
+            *v = chain.process_sample(*v as f32) as f64;
+        }
+    }
+    features.apply(&mut data);
+    montage.mask_decode(&mut data);
+    data
+}
+
 fn process_neural_intent(
     data: &[f64],
-    threshold: f64,
-    calib_mode: bool,
-    calib_max: &mut f64,
+    thresholds: &[f64],
+    mapping: &MappingTable,
+    calib_mode: &mut bool,
+    calib_peaks: &mut CalibPeaks,
     start_time: Instant,
     tx: &Sender<BciMessage>,
-) -> GamepadState {
-    let mut gp = GamepadState::default();
-
+) -> (GamepadState, Vec<(GamepadAction, f32)>) {
     // 此时进来的 data 已经是滤波后的干净数据了
-    let is_active = |idx: usize| -> bool { 
-        data.get(idx).map(|&v| v.abs() > threshold).unwrap_or(false) 
-    };
-    let match_pattern = |indices: &[usize]| -> bool { indices.iter().all(|&i| is_active(i)) };
-
-    // --- 游戏映射逻辑 (保持不变，但现在更准了) ---
-    // 左摇杆 (WASD)
-    if match_pattern(&[0, 4, 8]) { gp.ly += 1.0; } // W
-    if match_pattern(&[1, 5, 9]) { gp.ly -= 1.0; } // S
-    if match_pattern(&[2, 6, 10]) { gp.lx -= 1.0; } // A
-    if match_pattern(&[3, 7, 11]) { gp.lx += 1.0; } // D
-
-    // 动作键
-    if match_pattern(&[0, 1, 2]) { gp.a = true; } 
-    if match_pattern(&[3, 4, 5]) { gp.b = true; } 
-    if match_pattern(&[6, 7, 8]) { gp.x = true; } 
-    if match_pattern(&[9, 10, 11]) { gp.y = true; } 
-
-    // 右摇杆 (IJKL)
-    if match_pattern(&[12, 0]) { gp.ry += 1.0; }
-    if match_pattern(&[13, 1]) { gp.ry -= 1.0; }
-    if match_pattern(&[14, 2]) { gp.rx -= 1.0; }
-    if match_pattern(&[15, 3]) { gp.rx += 1.0; }
-
-    // 触发器/肩键
-    if match_pattern(&[0, 15]) && gp.ry == 0.0 { gp.lb = true; }
-    if match_pattern(&[2, 13]) && gp.rx == 0.0 { gp.rb = true; }
-    if match_pattern(&[1, 14]) && gp.rx == 0.0 { gp.lt = true; }
-    if match_pattern(&[3, 12]) && gp.ry == 0.0 { gp.rt = true; }
+    // --- 游戏映射逻辑：由映射表决定 (默认与原硬编码一致，每个动作可单独缩放阈值) ---
+    // 同时给出每个已触发动作的置信度 (最弱通道超出阈值的余量)，便于调参
+    let (gp, confidence) = mapping.decode_with_confidence(data, thresholds);
 
     // 校准逻辑
-    if calib_mode {
-        let max_s = data.iter().fold(0.0f64, |a, &b| a.max(b.abs()));
-        if max_s > *calib_max {
-            *calib_max = max_s;
-        }
+    if *calib_mode {
+        calib_peaks.update(data);
+        // 只上报一次，之后退出校准模式
         if start_time.elapsed().as_secs() >= 3 {
-            tx.send(BciMessage::CalibrationResult((), *calib_max)).ok();
+            tx.send(BciMessage::CalibrationResult(calib_peaks.channels.clone(), calib_peaks.overall)).ok();
+            *calib_mode = false;
         }
     }
 
-    gp
+    (gp, confidence)
 }
 
-pub fn spawn_thread(tx: Sender<BciMessage>, rx_cmd: Receiver<GuiCommand>) {
-    thread::spawn(move || {
+/// 实测采样率：收到的采样数 / 实际经过时间；不足 0.5 s 时样本太少，不报告
+fn effective_rate_hz(samples: u64, elapsed: Duration) -> Option<f64> {
+    let secs = elapsed.as_secs_f64();
+    (secs >= 0.5).then(|| samples as f64 / secs)
+}
+
+/// 已重连 `attempts` 次后下一次重连前的等待：第一次立即，之后 1 s、2 s、4 s ... 封顶 RECONNECT_BACKOFF_MAX
+fn reconnect_backoff(attempts: u32) -> Duration {
+    match attempts {
+        0 => Duration::ZERO,
+        n => RECONNECT_BACKOFF_BASE.saturating_mul(1 << (n - 1).min(16)).min(RECONNECT_BACKOFF_MAX),
+    }
+}
+
+/// 校准窗口内的峰值 |µV|：总体与逐通道
+#[derive(Default)]
+struct CalibPeaks {
+    overall: f64,
+    channels: Vec<f64>,
+}
+
+impl CalibPeaks {
+    fn update(&mut self, data: &[f64]) {
+        self.overall = data.iter().fold(self.overall, |a, &b| a.max(b.abs()));
+        self.channels.resize(data.len(), 0.0);
+        for (peak, v) in self.channels.iter_mut().zip(data) {
+            *peak = peak.max(v.abs());
+        }
+    }
+}
+
+/// 引导式校准每个阶段的时长
+const GUIDED_CALIB_SECONDS: f32 = 3.0;
+
+/// 引导式校准：一次启动，先采静息窗口、再采动作窗口，按采样计数计时
+struct GuidedCalibration {
+    phase: CalibrationPhase,
+    samples: usize,
+    window: usize,
+    rest: CalibPeaks,
+    action: CalibPeaks,
+}
+
+impl GuidedCalibration {
+    fn new(sample_rate_hz: f32) -> Self {
+        Self {
+            phase: CalibrationPhase::Rest,
+            samples: 0,
+            window: ((GUIDED_CALIB_SECONDS * sample_rate_hz) as usize).max(1),
+            rest: CalibPeaks::default(),
+            action: CalibPeaks::default(),
+        }
+    }
+
+    /// Feeds one decode sample. Returns progress about ten times per phase, the
+    /// switch to the action phase, and finally the capture of both windows.
+    // `is_multiple_of` 需要 Rust 1.87，这里保留取余写法
+    #[allow(clippy::manual_is_multiple_of)]
+    fn push(&mut self, data: &[f64]) -> Option<BciMessage> {
+        match self.phase {
+            CalibrationPhase::Rest => self.rest.update(data),
+            CalibrationPhase::Action => self.action.update(data),
+        }
+        self.samples += 1;
+        if self.samples >= self.window {
+            if self.phase == CalibrationPhase::Rest {
+                self.phase = CalibrationPhase::Action;
+                self.samples = 0;
+                return Some(BciMessage::CalibrationProgress(CalibrationPhase::Action, 0.0));
+            }
+            return Some(BciMessage::GuidedCalibrationResult(GuidedCapture {
+                rest_channels: std::mem::take(&mut self.rest.channels),
+                rest_max: self.rest.overall,
+                action_channels: std::mem::take(&mut self.action.channels),
+                action_max: self.action.overall,
+            }));
+        }
+        let every = (self.window / 10).max(1);
+        (self.samples % every == 0).then(|| BciMessage::CalibrationProgress(self.phase, self.samples as f32 / self.window as f32))
+    }
+}
+
+// =========================================================================
+// 3. 引擎状态与主循环
+// =========================================================================
+struct Engine {
+    tx: Sender<BciMessage>,
+    joystick: Option<Box<dyn VirtualGamepad>>,
+    recorder: DataRecorder,
+    /// 录制时同时写 labels_<id>.csv (解码出的手柄状态)
+    record_labels: bool,
+    openbci: Option<OpenBciSession>,
+    signal_buffer: Option<SignalBuffer>,
+    current_sample_rate_hz: f32,
+    /// 模拟模式模仿的板卡采样率 (如 125/250/500 Hz)
+    sim_sample_rate_hz: f32,
+    mains_hz: f64,
+    notch_harmonics: bool,
+    filters: SimpleFilter,
+    current_mode: ConnectionMode,
+    is_active: bool,
+    is_streaming: bool,
+    /// 每个通道各自的触发阈值 (µV)，电极质量不同的通道可分别设置
+    thresholds: [f64; 16],
+    mapping: MappingTable,
+    montage: Montage,
+    sample_guard: SampleGuard,
+    live_spectrum_config: Option<LiveSpectrumConfig>,
+    live_spectrum: Option<SlidingSpectrum>,
+    /// 分类器及其专用滑动 FFT (与显示用的 live_spectrum 互不影响)
+    classifier: Option<Box<dyn Classifier>>,
+    classifier_spectrum: Option<SlidingSpectrum>,
+    /// 是否让得分最高的类别驱动手柄，以及当前被驱动的动作
+    classifier_drive: bool,
+    classifier_action: Option<GamepadAction>,
+    // 与显示端共享的滤波配置：链接后解码输入再经过每个通道的显示滤波器
+    decode_filter_kinds: Option<Vec<Vec<FilterKind>>>,
+    decode_filters: Option<Vec<FilterChain>>,
+    decode_features: FeatureWindow,
+    /// 模拟模式的信号源；每次连接模拟模式都用同一种子重建
+    synthetic: SyntheticEeg,
+    current_sim_input: SimInputIntent,
+    /// 正在注入的伪迹进行到第几个采样
+    artifact_step: Option<usize>,
+    vjoy_limiter: OutputLimiter,
+    axis_gains: AxisGains,
+    stick_response: StickResponse,
+    /// 按键迟滞/保持时间；采样率变化时按新采样率重建去抖状态
+    trigger_debounce: TriggerDebounce,
+    debouncer: TriggerDebouncer,
+    mapping_helper: MappingHelperCommand,
+    mapping_helper_until: Instant,
+    mapping_helper_step: usize,
+    mapping_helper_last_step: Instant,
+    calib_mode: bool,
+    calib_peaks: CalibPeaks,
+    guided_calibration: Option<GuidedCalibration>,
+    calib_start_time: Instant,
+    // 缓存区
+    raw_channel_data: Vec<f64>,
+    clean_channel_data: Vec<f64>,
+    // 循环控制
+    last_vjoy_update: Instant,
+    /// 最近一次解码的各动作置信度；开启后随手柄状态一起发给 UI
+    confidence_output: bool,
+    last_confidence: Vec<(GamepadAction, f32)>,
+    // 本次推流已处理的采样数
+    samples_processed: u64,
+    /// 本次推流以来的采样数与起始时间，用于计算实测采样率
+    stream_samples: u64,
+    /// 其中成功写入显示缓冲的采样数，与 stream_samples 之差即丢包
+    stream_buffered: u64,
+    stream_started_at: Instant,
+    last_rate_report: Instant,
+    // 断线重连：记住端口，推流中超过 RECONNECT_AFTER 没有数据或连续读取出错就重新连接
+    port: String,
+    board: BoardKind,
+    auto_reconnect: bool,
+    lsl_enabled: bool,
+    lsl_outlet: Option<LslOutlet>,
+    osc: Option<OscSender>,
+    ws: Option<WsServer>,
+    /// 攒够 WS_FRAME_SECS 的干净数据再推给 WebSocket 客户端
+    ws_pending: Vec<Vec<f32>>,
+    /// BrainFlow 给出的当前采样设备时间 (UNIX 秒)；板子没有时间戳时为 None
+    sample_timestamp: Option<f64>,
+    last_sample_at: Instant,
+    last_reconnect_attempt: Instant,
+    /// 本轮断线已尝试的重连次数，成功后清零
+    reconnect_attempts: u32,
+    /// 连续读取失败的次数，读到数据即清零
+    read_errors: u32,
+}
+
+const RECONNECT_AFTER: Duration = Duration::from_secs(3);
+/// 连续这么多次读取失败就不等 RECONNECT_AFTER，直接重连
+const RECONNECT_AFTER_ERRORS: u32 = 20;
+/// 超过这么多次仍连不上就放弃并停止推流
+const MAX_RECONNECT_ATTEMPTS: u32 = 6;
+const RECONNECT_BACKOFF_BASE: Duration = Duration::from_secs(1);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// 每个 WebSocket 帧覆盖的时长
+const WS_FRAME_SECS: f32 = 0.1;
+/// 分类器特征窗口 (约 1 s @ 250 Hz) 与每秒预测次数
+const CLASSIFIER_FFT_SIZE: usize = 256;
+const CLASSIFIER_RATE_HZ: f32 = 4.0;
+/// 驱动手柄时得分最高的类别至少要达到的得分
+const CLASSIFIER_MIN_SCORE: f32 = 0.5;
+
+impl Engine {
+    fn new(tx: Sender<BciMessage>) -> Self {
         tx.send(BciMessage::Log("⚙️ Engine V14.0 (DSP Integrated)".to_owned())).ok();
 
-        // --- 初始化 vJoy ---
-        let mut joystick = VJoyClient::new(1).ok();
-        if joystick.is_some() {
-            tx.send(BciMessage::VJoyStatus(true)).ok();
-        } else {
-            tx.send(BciMessage::VJoyStatus(false)).ok();
-            tx.send(BciMessage::Log("⚠️ vJoy not found. Gamepad disabled.".to_owned())).ok();
+        // --- 初始化虚拟手柄：优先 vJoy，没装时退回 ViGEm 模拟的 Xbox 360 手柄 ---
+        let joystick: Option<Box<dyn VirtualGamepad>> = match VJoyClient::new(1) {
+            Ok(joy) => Some(Box::new(joy)),
+            Err(_) => VigemPad::new().ok().map(|pad| Box::new(pad) as Box<dyn VirtualGamepad>),
+        };
+        tx.send(BciMessage::VJoyStatus(joystick.as_ref().map(|pad| pad.name()))).ok();
+        match &joystick {
+            Some(pad) => { tx.send(BciMessage::Log(format!("🎮 Virtual gamepad: {}", pad.name()))).ok(); }
+            None => { tx.send(BciMessage::Log("⚠️ Neither vJoy nor ViGEmBus found. Gamepad disabled.".to_owned())).ok(); }
         }
 
-        let mut recorder = DataRecorder::new();
-        let mut openbci: Option<OpenBciSession> = None;
-        let mut signal_buffer: Option<SignalBuffer> = None;
-        
         // 默认采样率
-        let mut current_sample_rate_hz: f32 = 250.0; 
-        
-        // --- 初始化 DSP 滤波器 ---
-        let mut filters = SimpleFilter::new(16, current_sample_rate_hz as f64);
-
-        let mut current_mode = ConnectionMode::Simulation;
-        let mut is_active = false;
-        let mut is_streaming = false;
-        let mut threshold = 150.0; // 默认阈值稍微调低，因为去了直流
-
-        let mut sim_phase: f64 = 0.0;
-        let mut current_sim_input = SimInputIntent::default();
-        let mut mapping_helper: MappingHelperCommand = MappingHelperCommand::Off;
-        let mut mapping_helper_until = Instant::now();
-        let mut mapping_helper_step: usize = 0;
-        let mut mapping_helper_last_step = Instant::now();
-        let mut calib_mode = false;
-        let mut calib_max_val = 0.0;
-        let mut calib_start_time = Instant::now();
-
-        // 缓存区
-        let mut raw_channel_data = vec![0.0f64; 16];
-        let mut clean_channel_data = vec![0.0f64; 16];
-
-        // 循环控制
-        let mut last_vjoy_update = Instant::now();
+        let current_sample_rate_hz: f32 = 250.0;
+        let mains_hz = 50.0;
+        let notch_harmonics = false;
+        let mapping = Self::load_mapping(&tx, 16);
+        let classifier = Self::load_classifier(&tx, &LogisticModel::store_path(), false);
 
-        loop {
-            // 1. 处理 GUI 命令 (非阻塞)
-            while let Ok(cmd) = rx_cmd.try_recv() {
-                match cmd {
-                    GuiCommand::Connect(mode, port) => {
-                        current_mode = mode;
-                        if mode == ConnectionMode::Hardware {
-                            match OpenBciSession::connect(&port) {
-                                Ok(session) => {
-                                    current_sample_rate_hz = session.sample_rate_hz();
-                                    // 重置滤波器以匹配新采样率
-                                    filters = SimpleFilter::new(16, current_sample_rate_hz as f64);
-                                    openbci = Some(session);
-                                    is_active = true;
-                                    tx.send(BciMessage::Status(true)).ok();
-                                    tx.send(BciMessage::Log(format!("✅ OpenBCI Connected ({} Hz)", current_sample_rate_hz))).ok();
-                                }
-                                Err(e) => { tx.send(BciMessage::Log(format!("❌ Failed: {}", e))).ok(); }
-                            }
-                        } else {
-                            is_active = true;
-                            tx.send(BciMessage::Status(true)).ok();
-                            tx.send(BciMessage::Log("✅ Simulation Mode".to_owned())).ok();
+        Self {
+            tx,
+            joystick,
+            recorder: DataRecorder::new(),
+            record_labels: false,
+            openbci: None,
+            signal_buffer: None,
+            current_sample_rate_hz,
+            sim_sample_rate_hz: current_sample_rate_hz,
+            mains_hz,
+            notch_harmonics,
+            // --- 初始化 DSP 滤波器 ---
+            filters: SimpleFilter::new(16, current_sample_rate_hz as f64, mains_hz, notch_harmonics),
+            current_mode: ConnectionMode::Simulation,
+            is_active: false,
+            is_streaming: false,
+            thresholds: [150.0; 16], // 默认阈值稍微调低，因为去了直流
+            mapping,
+            montage: Montage::default(),
+            sample_guard: SampleGuard::new(16, DEFAULT_SAMPLE_CLAMP_UV),
+            live_spectrum_config: None,
+            live_spectrum: None,
+            classifier,
+            classifier_spectrum: None,
+            classifier_drive: false,
+            classifier_action: None,
+            decode_filter_kinds: None,
+            decode_filters: None,
+            decode_features: FeatureWindow::new(FeatureKind::Instant, 1, 16),
+            synthetic: SyntheticEeg::new(16, 250.0, SIM_SEED),
+            current_sim_input: SimInputIntent::default(),
+            artifact_step: None,
+            vjoy_limiter: OutputLimiter::new(None),
+            axis_gains: AxisGains::default(),
+            stick_response: StickResponse::default(),
+            trigger_debounce: TriggerDebounce::default(),
+            debouncer: TriggerDebouncer::new(TriggerDebounce::default(), current_sample_rate_hz),
+            mapping_helper: MappingHelperCommand::Off,
+            mapping_helper_until: Instant::now(),
+            mapping_helper_step: 0,
+            mapping_helper_last_step: Instant::now(),
+            calib_mode: false,
+            calib_peaks: CalibPeaks::default(),
+            guided_calibration: None,
+            calib_start_time: Instant::now(),
+            raw_channel_data: vec![0.0f64; 16],
+            clean_channel_data: vec![0.0f64; 16],
+            last_vjoy_update: Instant::now(),
+            confidence_output: false,
+            last_confidence: Vec::new(),
+            samples_processed: 0,
+            stream_samples: 0,
+            stream_buffered: 0,
+            stream_started_at: Instant::now(),
+            last_rate_report: Instant::now(),
+            port: String::new(),
+            board: BoardKind::default(),
+            auto_reconnect: false,
+            lsl_enabled: std::env::var_os(LSL_ENV_VAR).is_some(),
+            lsl_outlet: None,
+            osc: None,
+            ws: None,
+            ws_pending: Vec::new(),
+            sample_timestamp: None,
+            last_sample_at: Instant::now(),
+            last_reconnect_attempt: Instant::now(),
+            reconnect_attempts: 0,
+            read_errors: 0,
+        }
+    }
+
+    /// 读取 data/mapping.toml；不存在或校验失败时回退到默认映射，并把问题逐条报告给 UI
+    fn load_mapping(tx: &Sender<BciMessage>, channel_count: usize) -> MappingTable {
+        let path = MappingTable::store_path();
+        if !path.exists() {
+            return MappingTable::default_for(channel_count);
+        }
+        match MappingTable::load(&path, channel_count) {
+            Ok(table) => {
+                tx.send(BciMessage::Log(format!("🎮 Mapping loaded: {} ({} patterns)", path.display(), table.entries.len()))).ok();
+                table
+            }
+            Err(errors) => {
+                for e in &errors {
+                    tx.send(BciMessage::Log(format!("❌ Mapping: {}", e))).ok();
+                }
+                tx.send(BciMessage::Log("⚠️ Using default mapping".to_owned())).ok();
+                MappingTable::default_for(channel_count)
+            }
+        }
+    }
+
+    /// 读取逻辑回归模型；默认路径不存在时静默跳过 (`report_missing` 为手动加载时报错)
+    fn load_classifier(tx: &Sender<BciMessage>, path: &Path, report_missing: bool) -> Option<Box<dyn Classifier>> {
+        if !report_missing && !path.exists() {
+            return None;
+        }
+        match LogisticModel::load(path) {
+            Ok(model) => {
+                tx.send(BciMessage::Log(format!("🧠 Classifier loaded: {} ({} classes, {} features = channels × 5 bands)", path.display(), model.classes.len(), model.feature_len()))).ok();
+                let classifier: Box<dyn Classifier> = Box::new(model);
+                tx.send(BciMessage::ClassifierClasses(classifier.classes().to_vec())).ok();
+                Some(classifier)
+            }
+            Err(e) => {
+                tx.send(BciMessage::Log(format!("❌ Classifier: {e}"))).ok();
+                None
+            }
+        }
+    }
+
+    /// 推流中热替换映射表：整表解析+校验通过后才替换，失败则保留当前映射
+    fn swap_mapping(&mut self, path: &Path) {
+        match MappingTable::load(path, self.montage.layout.channel_count()) {
+            Ok(table) => {
+                let old = std::mem::replace(&mut self.mapping, table);
+                self.tx.send(BciMessage::Log(format!("🎮 Mapping swapped: {} ({} → {} patterns)", path.display(), old.entries.len(), self.mapping.entries.len()))).ok();
+            }
+            Err(errors) => {
+                for e in &errors {
+                    self.tx.send(BciMessage::Log(format!("❌ Mapping: {}", e))).ok();
+                }
+                self.tx.send(BciMessage::Log("⚠️ Mapping not swapped, keeping the active table".to_owned())).ok();
+            }
+        }
+    }
+
+    fn rebuild_filters(&mut self) {
+        self.filters = SimpleFilter::new(16, self.current_sample_rate_hz as f64, self.mains_hz, self.notch_harmonics);
+        self.rebuild_decode_filters();
+        self.rebuild_live_spectrum();
+        self.debouncer = TriggerDebouncer::new(self.trigger_debounce, self.current_sample_rate_hz);
+    }
+
+    fn rebuild_decode_filters(&mut self) {
+        let fs = self.current_sample_rate_hz;
+        self.decode_filters = self.decode_filter_kinds.as_ref().map(|kinds| {
+            (0..16).map(|i| kinds.get(i).map(|k| FilterChain::from_kinds(fs, k)).unwrap_or_else(FilterChain::empty)).collect()
+        });
+    }
+
+    /// 引擎侧滑动 FFT：采样率/配置变化或清空历史时重建
+    fn rebuild_live_spectrum(&mut self) {
+        self.live_spectrum = self.live_spectrum_config.map(|cfg| {
+            let labels = self.montage.layout.default_labels();
+            SlidingSpectrum::new(labels, self.current_sample_rate_hz, cfg.fft_size, cfg.rate_hz)
+        });
+        self.classifier_spectrum = self.classifier.as_ref().map(|_| {
+            SlidingSpectrum::new(self.montage.layout.default_labels(), self.current_sample_rate_hz, CLASSIFIER_FFT_SIZE, CLASSIFIER_RATE_HZ)
+        });
+        self.classifier_action = None;
+    }
+
+    /// 切换板卡通道布局：按新通道数重建缓冲/频谱/LSL，并换上对应的映射表
+    fn apply_layout(&mut self) {
+        let layout = self.montage.layout;
+        self.mapping = Self::load_mapping(&self.tx, layout.channel_count());
+        self.signal_buffer = None;
+        self.raw_channel_data.fill(0.0);
+        self.clean_channel_data.fill(0.0);
+        self.rebuild_live_spectrum();
+        if self.lsl_outlet.take().is_some() {
+            self.open_lsl_outlet();
+        }
+        self.tx.send(BciMessage::Log(format!("🧩 Layout: {} ({} mapping patterns)", layout.label(), self.mapping.entries.len()))).ok();
+    }
+
+    /// 模拟模式切到 sim_sample_rate_hz：缓冲、滤波器和信号源都按新采样率重建
+    fn apply_sim_sample_rate(&mut self) {
+        self.current_sample_rate_hz = self.sim_sample_rate_hz;
+        self.signal_buffer = None;
+        self.rebuild_filters();
+        self.synthetic = SyntheticEeg::new(16, self.current_sample_rate_hz as f64, SIM_SEED);
+    }
+
+    /// 清空缓存与统计，但保持连接/推流状态不变
+    fn clear_history(&mut self) {
+        self.signal_buffer = None;
+        self.rebuild_filters();
+        self.raw_channel_data.fill(0.0);
+        self.clean_channel_data.fill(0.0);
+        self.samples_processed = 0;
+        // 速率统计从清空时刻重新计，不再平均清空前的整段会话
+        self.stream_samples = 0;
+        self.stream_buffered = 0;
+        self.stream_started_at = Instant::now();
+        self.last_rate_report = Instant::now();
+        self.sample_guard.reset();
+        self.decode_features.reset();
+        self.calib_peaks = CalibPeaks::default();
+        self.tx.send(BciMessage::Log("🧹 History cleared".to_owned())).ok();
+    }
+
+    fn handle_command(&mut self, cmd: GuiCommand) {
+        let tx = &self.tx;
+        match cmd {
+            GuiCommand::Connect(mode, port, board) => {
+                self.current_mode = mode;
+                self.port = port.clone();
+                self.board = board;
+                if mode == ConnectionMode::Hardware {
+                    match OpenBciSession::connect_board(board, &port) {
+                        Ok(session) => {
+                            self.current_sample_rate_hz = session.sample_rate_hz();
+                            // 重置缓冲和滤波器以匹配新采样率 (之前可能是别的模拟采样率)
+                            self.signal_buffer = None;
+                            self.rebuild_filters();
+                            self.openbci = Some(session);
+                            self.is_active = true;
+                            self.tx.send(BciMessage::Status(true)).ok();
+                            self.tx.send(BciMessage::Log(format!("✅ {} Connected ({} Hz)", board.label(), self.current_sample_rate_hz))).ok();
                         }
+                        Err(e) => { tx.send(BciMessage::Log(format!("❌ Failed: {}", e))).ok(); }
                     }
-                    GuiCommand::Disconnect => {
-                        is_active = false; is_streaming = false;
-                        openbci = None;
-                        tx.send(BciMessage::Status(false)).ok();
-                    }
-                    GuiCommand::StartStream => { if is_active { 
-                        is_streaming = true; 
-                        if let Some(s) = openbci.as_mut() { s.start_stream().ok(); }
-                        tx.send(BciMessage::Log("🌊 Stream Started".to_owned())).ok();
-                    }}
-                    GuiCommand::StopStream => { 
-                        is_streaming = false; 
-                        if let Some(s) = openbci.as_mut() { s.stop_stream().ok(); }
-                        tx.send(BciMessage::Log("🛑 Stream Stopped".to_owned())).ok();
-                    }
-                    GuiCommand::SetThreshold(v) => threshold = v,
-                    GuiCommand::StartCalibration(_) => { calib_mode = true; calib_max_val = 0.0; calib_start_time = Instant::now(); }
-                    GuiCommand::UpdateSimInput(input) => current_sim_input = input,
-                    GuiCommand::StartRecording(l) => { recorder.start(&l); tx.send(BciMessage::RecordingStatus(true)).ok(); }
-                    GuiCommand::StopRecording => { recorder.stop(); tx.send(BciMessage::RecordingStatus(false)).ok(); }
-                    GuiCommand::SetMappingHelper(cmd) => {
-                        mapping_helper = cmd;
-                        mapping_helper_until = Instant::now() + Duration::from_millis(600);
-                        mapping_helper_step = 0;
-                        mapping_helper_last_step = Instant::now();
-                    }
-                    _ => {}
+                } else {
+                    self.apply_sim_sample_rate();
+                    self.is_active = true;
+                    self.tx.send(BciMessage::Status(true)).ok();
+                    self.tx.send(BciMessage::Log(format!("✅ Simulation Mode ({} Hz)", self.current_sample_rate_hz))).ok();
                 }
             }
+            GuiCommand::SetSimSampleRate(hz) if hz.is_finite() && hz > 0.0 => {
+                self.sim_sample_rate_hz = hz;
+                if self.is_active && self.current_mode == ConnectionMode::Simulation {
+                    self.apply_sim_sample_rate();
+                    self.tx.send(BciMessage::Log(format!("⏱️ Simulation rate: {} Hz", hz))).ok();
+                }
+            }
+            GuiCommand::Disconnect => {
+                self.is_active = false; self.is_streaming = false;
+                self.openbci = None;
+                self.guided_calibration = None;
+                tx.send(BciMessage::Status(false)).ok();
+            }
+            GuiCommand::StartStream => { if self.is_active { 
+                self.is_streaming = true; 
+                self.sample_guard.rejected = 0;
+                self.last_sample_at = Instant::now();
+                self.stream_samples = 0;
+                self.stream_buffered = 0;
+                self.stream_started_at = Instant::now();
+                self.last_rate_report = Instant::now();
+                self.reconnect_attempts = 0;
+                self.read_errors = 0;
+                if let Some(s) = self.openbci.as_mut() { s.start_stream().ok(); }
+                tx.send(BciMessage::Log("🌊 Stream Started".to_owned())).ok();
+                self.open_lsl_outlet();
+            }}
+            GuiCommand::StopStream => { 
+                self.is_streaming = false; 
+                if let Some(s) = self.openbci.as_mut() { s.stop_stream().ok(); }
+                self.lsl_outlet = None;
+                let msg = match self.sample_guard.rejected {
+                    0 => format!("🛑 Stream Stopped ({} samples)", self.samples_processed),
+                    n => format!("🛑 Stream Stopped ({} samples, {} out-of-range values replaced)", self.samples_processed, n),
+                };
+                tx.send(BciMessage::Log(msg)).ok();
+            }
+            GuiCommand::SetThreshold(v) => self.thresholds = [v; 16],
+            GuiCommand::SetChannelThreshold(idx, v) => {
+                if let Some(t) = self.thresholds.get_mut(idx) {
+                    *t = v;
+                }
+            }
+            GuiCommand::StartCalibration(_) => {
+                self.calib_mode = true;
+                self.calib_peaks = CalibPeaks::default();
+                self.calib_start_time = Instant::now();
+            }
+            GuiCommand::StartGuidedCalibration => {
+                self.guided_calibration = Some(GuidedCalibration::new(self.current_sample_rate_hz));
+                tx.send(BciMessage::CalibrationProgress(CalibrationPhase::Rest, 0.0)).ok();
+            }
+            GuiCommand::UpdateSimInput(input) => self.current_sim_input = input,
+            GuiCommand::StartRecording(l) => {
+                self.recorder.set_sample_rate(self.current_sample_rate_hz as f64);
+                // 硬件以板卡实际的 EEG 通道数为准，模拟模式按当前通道布局
+                let channels = self
+                    .openbci
+                    .as_ref()
+                    .map_or(self.montage.layout.channel_count(), |s| s.channel_count())
+                    .min(self.raw_channel_data.len());
+                if self.record_labels { self.recorder.start_with_labels(&l, channels); } else { self.recorder.start(&l, channels); }
+                tx.send(BciMessage::RecordingStatus(true)).ok();
+            }
+            GuiCommand::StopRecording => {
+                if let Err(e) = self.recorder.stop() {
+                    tx.send(BciMessage::Log(format!("❌ MNE export failed: {}", e))).ok();
+                }
+                tx.send(BciMessage::RecordingStatus(false)).ok();
+            }
+            GuiCommand::MarkEvent(label) if self.recorder.is_recording() => {
+                self.recorder.mark_event(&label);
+                tx.send(BciMessage::Log(format!("📍 Event: {}", label))).ok();
+            }
+            GuiCommand::SetEventAlignment(alignment) => self.recorder.set_event_alignment(alignment),
+            GuiCommand::SetRecordingRotation(rotation) => self.recorder.set_rotation(rotation),
+            GuiCommand::SetRecordSampleIndex(enabled) => self.recorder.set_sample_index_column(enabled),
+            GuiCommand::SetRecordMneExport(enabled) => self.recorder.set_mne_export(enabled),
+            GuiCommand::SetRecordLabels(enabled) => self.record_labels = enabled,
+            GuiCommand::SetRecordMode(mode) => self.recorder.set_record_mode(mode),
+            GuiCommand::SetNotchHarmonics(enabled) => {
+                self.notch_harmonics = enabled;
+                self.rebuild_filters();
+            }
+            GuiCommand::SetMainsFrequency(hz) if hz > 0.0 && hz != self.mains_hz => {
+                self.mains_hz = hz;
+                self.rebuild_filters();
+                self.tx.send(BciMessage::Log(format!("⚡ Mains notch: {:.0} Hz", hz))).ok();
+            }
+            GuiCommand::SetMainsFrequency(_) => {}
+            GuiCommand::ClearHistory => self.clear_history(),
+            GuiCommand::SetMontage(montage) => {
+                let relayout = montage.layout != self.montage.layout;
+                self.montage = montage;
+                if relayout {
+                    self.apply_layout();
+                }
+            }
+            GuiCommand::SetAutoReconnect(enabled) => self.auto_reconnect = enabled,
+            GuiCommand::EnableLsl(enabled) => {
+                self.lsl_enabled = enabled;
+                if !enabled {
+                    self.lsl_outlet = None;
+                } else if self.is_streaming && self.lsl_outlet.is_none() {
+                    self.open_lsl_outlet();
+                }
+            }
+            GuiCommand::EnableOsc(addr) => match OscSender::new(addr) {
+                Ok(sender) => {
+                    tx.send(BciMessage::Log(format!("📡 OSC output -> {}", sender.addr()))).ok();
+                    self.osc = Some(sender);
+                }
+                Err(e) => { tx.send(BciMessage::Log(format!("⚠️ OSC unavailable: {e}"))).ok(); }
+            },
+            GuiCommand::DisableOsc => self.osc = None,
+            GuiCommand::EnableWebSocket(addr) => match start_ws_server(addr) {
+                Ok(server) => {
+                    tx.send(BciMessage::Log(format!("🌐 WebSocket server on ws://{}", server.local_addr()))).ok();
+                    self.ws = Some(server);
+                    self.ws_pending.clear();
+                }
+                Err(e) => { tx.send(BciMessage::Log(format!("⚠️ WebSocket unavailable: {e}"))).ok(); }
+            },
+            GuiCommand::DisableWebSocket => self.ws = None,
+            GuiCommand::SetSampleClamp(limit_uv) => self.sample_guard.limit_uv = limit_uv,
+            GuiCommand::SetLiveSpectrum(cfg) => {
+                self.live_spectrum_config = cfg;
+                self.rebuild_live_spectrum();
+            }
+            GuiCommand::SetVJoyRate(rate_hz) => self.vjoy_limiter.set_rate(rate_hz),
+            GuiCommand::SetAxisGains(gains) => self.axis_gains = gains,
+            GuiCommand::SetStickResponse(response) => self.stick_response = response,
+            GuiCommand::SetTriggerDebounce(config) => {
+                self.trigger_debounce = config;
+                self.debouncer = TriggerDebouncer::new(config, self.current_sample_rate_hz);
+            }
+            GuiCommand::SetDecodeConfidence(enabled) => self.confidence_output = enabled,
+            GuiCommand::SetDecodeFeature(kind, window) => self.decode_features = FeatureWindow::new(kind, window, 16),
+            GuiCommand::SetDecodeFilters(kinds) => {
+                self.decode_filter_kinds = kinds;
+                self.rebuild_decode_filters();
+            }
+            GuiCommand::LoadMapping(path) => {
+                let path = path.unwrap_or_else(MappingTable::store_path);
+                self.swap_mapping(&path);
+            }
+            GuiCommand::LoadClassifier(path) => {
+                let path = path.unwrap_or_else(LogisticModel::store_path);
+                if let Some(classifier) = Self::load_classifier(tx, &path, true) {
+                    self.classifier = Some(classifier);
+                    self.rebuild_live_spectrum();
+                }
+            }
+            GuiCommand::SetClassifierDrive(enabled) => {
+                self.classifier_drive = enabled;
+                self.classifier_action = None;
+            }
+            GuiCommand::InjectArtifact if self.current_mode == ConnectionMode::Simulation => {
+                self.artifact_step = Some(0);
+                tx.send(BciMessage::Log(format!("⚡ Artifact injected ({:.0} µV, {:.0} ms)", ARTIFACT_UV, ARTIFACT_SECONDS * 1000.0))).ok();
+            }
+            GuiCommand::SetMappingHelper(cmd) => {
+                self.mapping_helper = cmd;
+                self.mapping_helper_until = Instant::now() + Duration::from_millis(600);
+                self.mapping_helper_step = 0;
+                self.mapping_helper_last_step = Instant::now();
+            }
+            _ => {}
+        }
+    }
 
-            // Steam mapping helper: drive vJoy directly (no focus / no streaming dependency)
-            if mapping_helper != MappingHelperCommand::Off {
-                let now = Instant::now();
-                let mut gp = GamepadState::default();
+    fn tick(&mut self) {
+        // Steam mapping helper: drive vJoy directly (no focus / no streaming dependency)
+        if self.mapping_helper != MappingHelperCommand::Off {
+            self.drive_mapping_helper();
+        }
 
-                if mapping_helper == MappingHelperCommand::AutoCycle {
-                    if mapping_helper_last_step.elapsed() >= Duration::from_millis(650) {
-                        mapping_helper_step = (mapping_helper_step + 1) % 8;
-                        mapping_helper_last_step = now;
-                    }
-                    match mapping_helper_step {
-                        0 => gp.a = true,
-                        1 => gp.b = true,
-                        2 => gp.x = true,
-                        3 => gp.y = true,
-                        4 => gp.ly = 1.0,
-                        5 => gp.ly = -1.0,
-                        6 => gp.lx = -1.0,
-                        _ => gp.lx = 1.0,
-                    }
-                } else if now <= mapping_helper_until {
-                    match mapping_helper {
-                        MappingHelperCommand::PulseA => gp.a = true,
-                        MappingHelperCommand::PulseB => gp.b = true,
-                        MappingHelperCommand::PulseX => gp.x = true,
-                        MappingHelperCommand::PulseY => gp.y = true,
-                        MappingHelperCommand::PulseDpadUp => gp.dpad_up = true,
-                        MappingHelperCommand::PulseDpadDown => gp.dpad_down = true,
-                        MappingHelperCommand::PulseDpadLeft => gp.dpad_left = true,
-                        MappingHelperCommand::PulseDpadRight => gp.dpad_right = true,
-                        MappingHelperCommand::PulseLeftStickUp => gp.ly = 1.0,
-                        MappingHelperCommand::PulseLeftStickDown => gp.ly = -1.0,
-                        MappingHelperCommand::PulseLeftStickLeft => gp.lx = -1.0,
-                        MappingHelperCommand::PulseLeftStickRight => gp.lx = 1.0,
-                        MappingHelperCommand::AutoCycle | MappingHelperCommand::Off => {}
-                    }
+        // 2. 数据采集与处理
+        if self.is_streaming {
+            self.step_stream();
+        } else {
+            // 未推流时，降低 CPU 占用
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    fn drive_mapping_helper(&mut self) {
+        let now = Instant::now();
+        let mut gp = GamepadState::default();
+
+        if self.mapping_helper == MappingHelperCommand::AutoCycle {
+            if self.mapping_helper_last_step.elapsed() >= Duration::from_millis(650) {
+                self.mapping_helper_step = (self.mapping_helper_step + 1) % 8;
+                self.mapping_helper_last_step = now;
+            }
+            match self.mapping_helper_step {
+                0 => gp.a = true,
+                1 => gp.b = true,
+                2 => gp.x = true,
+                3 => gp.y = true,
+                4 => gp.ly = 1.0,
+                5 => gp.ly = -1.0,
+                6 => gp.lx = -1.0,
+                _ => gp.lx = 1.0,
+            }
+        } else if now <= self.mapping_helper_until {
+            match self.mapping_helper {
+                MappingHelperCommand::PulseA => gp.a = true,
+                MappingHelperCommand::PulseB => gp.b = true,
+                MappingHelperCommand::PulseX => gp.x = true,
+                MappingHelperCommand::PulseY => gp.y = true,
+                MappingHelperCommand::PulseDpadUp => gp.dpad_up = true,
+                MappingHelperCommand::PulseDpadDown => gp.dpad_down = true,
+                MappingHelperCommand::PulseDpadLeft => gp.dpad_left = true,
+                MappingHelperCommand::PulseDpadRight => gp.dpad_right = true,
+                MappingHelperCommand::PulseLeftStickUp => gp.ly = 1.0,
+                MappingHelperCommand::PulseLeftStickDown => gp.ly = -1.0,
+                MappingHelperCommand::PulseLeftStickLeft => gp.lx = -1.0,
+                MappingHelperCommand::PulseLeftStickRight => gp.lx = 1.0,
+                MappingHelperCommand::AutoCycle | MappingHelperCommand::Off => {}
+            }
+        }
+
+        if let Some(joy) = &self.joystick {
+            apply_gamepad(joy.as_ref(), &gp, &self.stick_response);
+        }
+
+        if self.last_vjoy_update.elapsed().as_millis() > 30 {
+            self.tx.send(BciMessage::GamepadUpdate(gp)).ok();
+            self.last_vjoy_update = Instant::now();
+        }
+
+        // Keep a light tick so Steam sees changes even if streaming is stopped.
+        if !self.is_streaming {
+            thread::sleep(Duration::from_millis(16));
+        }
+    }
+
+    fn step_stream(&mut self) {
+        let mut has_new_data = false;
+
+        if self.current_mode == ConnectionMode::Simulation {
+            // Steam mapping helper (works even when Steam window is focused).
+            // SIM keyboard shortcuts require Neurostick focus; this helper generates vJoy inputs in the background.
+            let mut sim = self.current_sim_input;
+            if self.mapping_helper == MappingHelperCommand::AutoCycle {
+                if self.mapping_helper_last_step.elapsed() >= Duration::from_millis(650) {
+                    self.mapping_helper_step = (self.mapping_helper_step + 1) % 8;
+                    self.mapping_helper_last_step = Instant::now();
+                }
+                sim = SimInputIntent::default();
+                match self.mapping_helper_step {
+                    0 => sim.space = true, // A
+                    1 => sim.key_z = true, // B
+                    2 => sim.key_x = true, // X
+                    3 => sim.key_c = true, // Y
+                    4 => sim.w = true,     // LS up
+                    5 => sim.s = true,     // LS down
+                    6 => sim.a = true,     // LS left
+                    _ => sim.d = true,     // LS right
                 }
+            } else if self.mapping_helper != MappingHelperCommand::Off
+                && Instant::now() <= self.mapping_helper_until
+            {
+                sim = SimInputIntent::default();
+                match self.mapping_helper {
+                    MappingHelperCommand::PulseA => sim.space = true,
+                    MappingHelperCommand::PulseB => sim.key_z = true,
+                    MappingHelperCommand::PulseX => sim.key_x = true,
+                    MappingHelperCommand::PulseY => sim.key_c = true,
+                    MappingHelperCommand::PulseLeftStickUp => sim.w = true,
+                    MappingHelperCommand::PulseLeftStickDown => sim.s = true,
+                    MappingHelperCommand::PulseLeftStickLeft => sim.a = true,
+                    MappingHelperCommand::PulseLeftStickRight => sim.d = true,
+                    _ => {}
+                }
+            }
 
-                if let Some(joy) = &mut joystick {
-                    joy.set_button(1, gp.a);
-                    joy.set_button(2, gp.b);
-                    joy.set_button(3, gp.x);
-                    joy.set_button(4, gp.y);
-                    joy.set_button(9, gp.dpad_up);
-                    joy.set_button(10, gp.dpad_down);
-                    joy.set_button(11, gp.dpad_left);
-                    joy.set_button(12, gp.dpad_right);
-                    let axis = |v: f32| -> i32 {
-                        let v = v.clamp(-1.0, 1.0) as f64;
-                        (16384.0 + v * 16000.0) as i32
-                    };
-                    joy.set_axis(0x30, axis(gp.lx));
-                    joy.set_axis(0x31, axis(gp.ly));
+            // Simulation input -> channel activation patterns expected by process_neural_intent:
+            // each held key adds an EMG burst to its channels instead of a DC step.
+            let mut emg_gain = [0.0; 16];
+            let mut bump = |idx: usize| {
+                if let Some(g) = emg_gain.get_mut(idx) {
+                    *g += 1.0;
                 }
+            };
+            if sim.w { for &i in &[0, 4, 8] { bump(i); } }
+            if sim.s { for &i in &[1, 5, 9] { bump(i); } }
+            if sim.a { for &i in &[2, 6, 10] { bump(i); } }
+            if sim.d { for &i in &[3, 7, 11] { bump(i); } }
+            if sim.space { for &i in &[0, 1, 2] { bump(i); } } // A
+            if sim.key_z { for &i in &[3, 4, 5] { bump(i); } } // B
+            if sim.key_x { for &i in &[6, 7, 8] { bump(i); } } // X
+            if sim.key_c { for &i in &[9, 10, 11] { bump(i); } } // Y
 
-                if last_vjoy_update.elapsed().as_millis() > 30 {
-                    tx.send(BciMessage::GamepadUpdate(gp)).ok();
-                    last_vjoy_update = Instant::now();
+            // 粉红噪声 + alpha 节律 + 眨眼伪迹，频谱和波形页看起来像真实 EEG
+            self.synthetic.next_sample(&emg_gain, &mut self.raw_channel_data);
+            let raw_channel_data = &mut self.raw_channel_data;
+            if let Some(step) = self.artifact_step {
+                let len = ((ARTIFACT_SECONDS * self.current_sample_rate_hz as f64).round() as usize).max(2);
+                let envelope = 0.5 - 0.5 * (2.0 * PI * step as f64 / len as f64).cos();
+                for v in raw_channel_data.iter_mut() { *v += ARTIFACT_UV * envelope; }
+                self.artifact_step = (step + 1 < len).then_some(step + 1);
+            }
+            
+            has_new_data = true;
+            thread::sleep(Duration::from_secs_f32(1.0 / self.current_sample_rate_hz)); // 按模拟采样率出数
+        } else if let Some(session) = self.openbci.as_mut() {
+            match session.next_samples() {
+                Ok(block) if block.channels.iter().any(|ch| !ch.is_empty()) => {
+                    // 逐个采样走完整处理链，缓冲/录制/解码都不丢数据
+                    let len = block.channels.iter().map(Vec::len).min().unwrap_or(0);
+                    for idx in 0..len {
+                        for (i, ch) in block.channels.iter().take(16).enumerate() {
+                            self.raw_channel_data[i] = ch[idx];
+                        }
+                        self.sample_timestamp = block.timestamps.get(idx).copied();
+                        self.process_sample();
+                    }
+                    self.last_sample_at = Instant::now();
+                    // 真正读到数据才算恢复，重连后仍无数据时继续退避
+                    self.read_errors = 0;
+                    self.reconnect_attempts = 0;
                 }
+                Ok(_) => {
+                    // 没有数据时短暂休眠，避免死循环烧CPU
+                    // 关键优化：休眠时间要极短
+                    thread::sleep(Duration::from_micros(500)); 
+                }
+                Err(_) => { self.read_errors += 1; thread::sleep(Duration::from_millis(10)); }
+            }
+        }
+
+        if self.current_mode == ConnectionMode::Hardware && !has_new_data {
+            self.watch_connection();
+        }
+
+        if has_new_data {
+            self.process_sample();
+        }
+    }
 
-                // Keep a light tick so Steam sees changes even if streaming is stopped.
-                if !is_streaming {
-                    thread::sleep(Duration::from_millis(16));
+    /// 推流中长时间无数据或连续读取出错时按指数退避重连板子，超过 MAX_RECONNECT_ATTEMPTS 次放弃；
+    /// 录制中则在记录里写入缺口标记 (估算丢失的采样数)，保持时间线完整
+    fn watch_connection(&mut self) {
+        if !self.auto_reconnect || (self.board.needs_serial_port() && self.port.is_empty()) {
+            return;
+        }
+        let stalled = self.last_sample_at.elapsed() >= RECONNECT_AFTER || self.read_errors >= RECONNECT_AFTER_ERRORS;
+        if !stalled || self.last_reconnect_attempt.elapsed() < reconnect_backoff(self.reconnect_attempts) {
+            if self.openbci.is_none() { thread::sleep(Duration::from_millis(10)); }
+            return;
+        }
+        if self.reconnect_attempts >= MAX_RECONNECT_ATTEMPTS {
+            self.openbci = None;
+            self.is_streaming = false;
+            self.is_active = false;
+            self.reconnect_attempts = 0;
+            self.tx.send(BciMessage::Reconnecting(None)).ok();
+            self.tx.send(BciMessage::Status(false)).ok();
+            self.tx.send(BciMessage::Log(format!("❌ Gave up reconnecting {} after {} attempts; check the dongle and connect again", self.port, MAX_RECONNECT_ATTEMPTS))).ok();
+            return;
+        }
+        self.reconnect_attempts += 1;
+        self.read_errors = 0;
+        self.last_reconnect_attempt = Instant::now();
+        // 先释放失效的句柄，串口才能重新打开
+        self.openbci = None;
+        self.tx.send(BciMessage::Reconnecting(Some((self.reconnect_attempts, MAX_RECONNECT_ATTEMPTS)))).ok();
+        self.tx.send(BciMessage::Log(format!("🔌 Stream lost, reconnecting {} (attempt {}/{})...", self.port, self.reconnect_attempts, MAX_RECONNECT_ATTEMPTS))).ok();
+        match OpenBciSession::connect_board(self.board, &self.port) {
+            Ok(mut session) => {
+                if let Err(e) = session.start_stream() {
+                    self.tx.send(BciMessage::Log(format!("❌ Reconnect stream failed: {}", e))).ok();
+                    return;
+                }
+                if session.sample_rate_hz() != self.current_sample_rate_hz {
+                    self.current_sample_rate_hz = session.sample_rate_hz();
+                    self.signal_buffer = None;
+                }
+                self.rebuild_filters();
+                self.openbci = Some(session);
+                self.last_sample_at = Instant::now();
+                self.tx.send(BciMessage::Reconnecting(None)).ok();
+                if self.recorder.is_recording() {
+                    let missing = self.recorder.mark_gap(self.current_sample_rate_hz as f64);
+                    self.tx.send(BciMessage::Log(format!("✅ Reconnected, gap of ~{} samples marked in recording", missing))).ok();
+                } else {
+                    self.tx.send(BciMessage::Log("✅ Reconnected".to_owned())).ok();
                 }
             }
+            Err(e) => { self.tx.send(BciMessage::Log(format!("❌ Reconnect failed: {}", e))).ok(); }
+        }
+    }
 
-            // 2. 数据采集与处理
-            if is_streaming {
-                let mut has_new_data = false;
-
-                if current_mode == ConnectionMode::Simulation {
-                    // 模拟数据生成
-                    sim_phase += 0.1;
-                    let noise = (sim_phase * 0.5).sin() * 5.0; // 模拟一些底噪
-                    
-                    raw_channel_data.fill(0.0);
-                    // ... (此处省略太长的模拟输入判定，保持原样即可，重点是后面)
-                    // 为了演示简单，这里只保留一部分模拟逻辑
-                    // Steam mapping helper (works even when Steam window is focused).
-                    // SIM keyboard shortcuts require Neurostick focus; this helper generates vJoy inputs in the background.
-                    let mut sim = current_sim_input;
-                    if mapping_helper == MappingHelperCommand::AutoCycle {
-                        if mapping_helper_last_step.elapsed() >= Duration::from_millis(650) {
-                            mapping_helper_step = (mapping_helper_step + 1) % 8;
-                            mapping_helper_last_step = Instant::now();
-                        }
-                        sim = SimInputIntent::default();
-                        match mapping_helper_step {
-                            0 => sim.space = true, // A
-                            1 => sim.key_z = true, // B
-                            2 => sim.key_x = true, // X
-                            3 => sim.key_c = true, // Y
-                            4 => sim.w = true,     // LS up
-                            5 => sim.s = true,     // LS down
-                            6 => sim.a = true,     // LS left
-                            _ => sim.d = true,     // LS right
-                        }
-                    } else if mapping_helper != MappingHelperCommand::Off
-                        && Instant::now() <= mapping_helper_until
-                    {
-                        sim = SimInputIntent::default();
-                        match mapping_helper {
-                            MappingHelperCommand::PulseA => sim.space = true,
-                            MappingHelperCommand::PulseB => sim.key_z = true,
-                            MappingHelperCommand::PulseX => sim.key_x = true,
-                            MappingHelperCommand::PulseY => sim.key_c = true,
-                            MappingHelperCommand::PulseLeftStickUp => sim.w = true,
-                            MappingHelperCommand::PulseLeftStickDown => sim.s = true,
-                            MappingHelperCommand::PulseLeftStickLeft => sim.a = true,
-                            MappingHelperCommand::PulseLeftStickRight => sim.d = true,
-                            _ => {}
-                        }
-                    }
+    /// 神经解码 (使用干净数据)，导联中的参考通道先置零
+    fn decode(&mut self) -> GamepadState {
+        let data = decode_input(
+            &self.clean_channel_data,
+            self.decode_filters.as_mut(),
+            &mut self.decode_features,
+            &self.montage,
+        );
+        let (gp, confidence) = process_neural_intent(
+            &data,
+            &self.thresholds,
+            &self.mapping,
+            &mut self.calib_mode,
+            &mut self.calib_peaks,
+            self.calib_start_time,
+            &self.tx
+        );
+        let (gp, confidence) = self.debouncer.apply(&self.mapping, &data, &self.thresholds, gp, confidence);
+        self.last_confidence = confidence;
+        if let Some(msg) = self.guided_calibration.as_mut().and_then(|guided| guided.push(&data)) {
+            if matches!(msg, BciMessage::GuidedCalibrationResult(_)) {
+                self.guided_calibration = None;
+            }
+            self.tx.send(msg).ok();
+        }
+        gp
+    }
 
-                    // Simulation input -> channel activation patterns expected by process_neural_intent.
-                    let mut bump = |idx: usize| {
-                        if let Some(v) = raw_channel_data.get_mut(idx) {
-                            *v += 500.0;
-                        }
-                    };
-                    if sim.w { for &i in &[0, 4, 8] { bump(i); } }
-                    if sim.s { for &i in &[1, 5, 9] { bump(i); } }
-                    if sim.a { for &i in &[2, 6, 10] { bump(i); } }
-                    if sim.d { for &i in &[3, 7, 11] { bump(i); } }
-                    if sim.space { for &i in &[0, 1, 2] { bump(i); } } // A
-                    if sim.key_z { for &i in &[3, 4, 5] { bump(i); } } // B
-                    if sim.key_x { for &i in &[6, 7, 8] { bump(i); } } // X
-                    if sim.key_c { for &i in &[9, 10, 11] { bump(i); } } // Y
-                    
-                    // 模拟模式也加上一点随机漂移，测试滤波器
-                    for v in raw_channel_data.iter_mut() { *v += noise; }
-                    
-                    has_new_data = true;
-                    thread::sleep(Duration::from_millis(4)); // 250Hz approx
-                } else if let Some(session) = openbci.as_mut() {
-                    match session.next_sample() {
-                        Ok(Some(sample)) => {
-                            for (i, v) in sample.iter().take(16).enumerate() {
-                                raw_channel_data[i] = *v;
-                            }
-                            has_new_data = true;
-                        }
-                        Ok(None) => {
-                            // 没有数据时短暂休眠，避免死循环烧CPU
-                            // 关键优化：休眠时间要极短
-                            thread::sleep(Duration::from_micros(500)); 
-                        }
-                        Err(_) => { thread::sleep(Duration::from_millis(10)); }
-                    }
+    /// LSL 输出：通道数/采样率与当前会话一致，下游工具据此自动配置
+    fn open_lsl_outlet(&mut self) {
+        if !self.lsl_enabled {
+            return;
+        }
+        let labels = self.montage.layout.default_labels();
+        match LslOutlet::new("Neurostick", &labels, self.current_sample_rate_hz) {
+            Ok(outlet) => {
+                self.tx.send(BciMessage::Log(format!(
+                    "📡 LSL outlet: {} ch @ {} Hz",
+                    outlet.channel_count(),
+                    outlet.sample_rate_hz()
+                ))).ok();
+                self.lsl_outlet = Some(outlet);
+            }
+            Err(e) => {
+                self.tx.send(BciMessage::Log(format!("⚠️ LSL unavailable: {e}"))).ok();
+            }
+        }
+    }
+
+    /// 解码结果乘上各轴增益，即写入 vJoy / 显示的状态
+    fn output_state(&mut self) -> GamepadState {
+        let mut gp = self.decode();
+        if let Some(action) = self.classifier_action {
+            action.apply(&mut gp, 1.0);
+        }
+        self.axis_gains.apply(&mut gp);
+        gp
+    }
+
+    fn process_sample(&mut self) {
+        self.samples_processed += 1;
+        self.stream_samples += 1;
+        if self.last_rate_report.elapsed() >= Duration::from_secs(1) {
+            self.last_rate_report = Instant::now();
+            if let Some(hz) = effective_rate_hz(self.stream_samples, self.stream_started_at.elapsed()) {
+                self.tx.send(BciMessage::StreamRate(StreamRate {
+                    samples: self.stream_samples,
+                    buffered: self.stream_buffered,
+                    nominal_hz: self.current_sample_rate_hz,
+                    effective_hz: hz as f32,
+                })).ok();
+            }
+        }
+        // === 入口钳位：先剔除物理上不可能的值，避免污染滤波器/录制/解码 ===
+        let uv_per_unit = if self.current_mode == ConnectionMode::Hardware { self.board.uv_per_unit() } else { 1.0 };
+        let rejected_before = self.sample_guard.rejected;
+        for i in 0..16 {
+            self.raw_channel_data[i] = self.sample_guard.admit(i, self.raw_channel_data[i], uv_per_unit);
+        }
+        if rejected_before == 0 && self.sample_guard.rejected > 0 {
+            self.tx.send(BciMessage::Log(format!("⚠️ Out-of-range sample replaced (limit ±{:.0e} µV)", self.sample_guard.limit_uv))).ok();
+        }
+        // === 关键步骤：实时滤波 ===
+        // OpenBCI 的原始数据可能有几万的直流偏置，必须滤掉
+        for i in 0..16 {
+            let filtered = self.filters.process_sample(i, self.raw_channel_data[i]);
+            // BrainFlow 返回的 Cyton 数据是伏特级别，UI/阈值逻辑使用微伏，统一缩放
+            self.clean_channel_data[i] = filtered * uv_per_unit;
+        }
+
+        // 录制原始数据(Raw)还是干净数据(Clean)? 
+        // 建议录制 Raw，方便以后调整算法。但为了演示效果，这里我们把 Clean 发给 UI
+        if self.recorder.is_recording() {
+            match self.recorder.record_mode() {
+                RecordMode::RawOnly => self.recorder.write_record(&self.raw_channel_data, self.sample_timestamp),
+                RecordMode::Dual => self.recorder.write_record_dual(&self.raw_channel_data, &self.clean_channel_data, self.sample_timestamp),
+            }
+        }
+
+        // === 发送数据给 UI 渲染 ===
+        let channels = self.montage.layout.channel_count();
+        // 初始化 Buffer (如果为空)
+        if self.signal_buffer.is_none() {
+            let labels = self.montage.layout.default_labels();
+            self.signal_buffer = SignalBuffer::with_history_seconds(labels, self.current_sample_rate_hz, 10.0).ok();
+        }
+
+        if let Some(buf) = self.signal_buffer.as_mut() {
+            // 把 clean_channel_data 包装成 Batch
+            let batch = SignalBatch {
+                started_at: SystemTime::now(),
+                sample_rate_hz: self.current_sample_rate_hz,
+                channel_labels: buf.channel_labels().to_vec(),
+                samples: self.clean_channel_data.iter().take(channels).map(|&v| vec![v as f32]).collect(),
+                // 没有设备时间 (模拟模式) 时由缓冲区按采样率补齐
+                timestamps: self.sample_timestamp.take().into_iter().collect(),
+            };
+            if buf.push_batch(&batch).is_ok() { self.stream_buffered += 1; }
+            
+            // 降低 UI 刷新频率，比如每 4 个采样发一次 GUI，或者只发最新的 snapshot
+            // 为了流畅度，这里每次都发，但 GUI 端要注意性能
+            self.tx.send(BciMessage::DataFrame(buf.snapshot(5.0))).ok();
+        }
+        if let Some(outlet) = self.lsl_outlet.as_mut() {
+            let frame = TimeSeriesFrame {
+                sample_rate_hz: self.current_sample_rate_hz,
+                channel_labels: Vec::new(),
+                samples: self.clean_channel_data.iter().take(channels).map(|&v| vec![v as f32]).collect(),
+                timestamps: Vec::new(),
+            };
+            if let Err(e) = outlet.push_frame(&frame) {
+                self.tx.send(BciMessage::Log(format!("⚠️ LSL push failed, outlet closed: {e}"))).ok();
+                self.lsl_outlet = None;
+            }
+        }
+        if let Some(ws) = self.ws.as_ref() {
+            if self.ws_pending.len() != channels { self.ws_pending = vec![Vec::new(); channels]; }
+            for (pending, &v) in self.ws_pending.iter_mut().zip(&self.clean_channel_data) { pending.push(v as f32); }
+            if self.ws_pending.first().map_or(0, Vec::len) as f32 >= self.current_sample_rate_hz * WS_FRAME_SECS {
+                let frame = TimeSeriesFrame {
+                    sample_rate_hz: self.current_sample_rate_hz,
+                    channel_labels: Vec::new(),
+                    samples: std::mem::replace(&mut self.ws_pending, vec![Vec::new(); channels]),
+                    timestamps: Vec::new(),
+                };
+                if ws.client_count() == 0 {
+                    // 没有客户端时不必编码 JSON
+                } else if let Err(e) = ws.broadcast(&frame) {
+                    self.tx.send(BciMessage::Log(format!("⚠️ WebSocket frame dropped: {e}"))).ok();
                 }
+            }
+        }
+        if let Some(live) = self.live_spectrum.as_mut() {
+            let sample: Vec<f32> = self.clean_channel_data.iter().take(channels).map(|&v| v as f32).collect();
+            if let Some(spec) = live.push(&sample) {
+                if let Some(Err(e)) = self.osc.as_ref().map(|osc| osc.send_band_powers(&spec.band_powers())) {
+                    self.tx.send(BciMessage::Log(format!("⚠️ OSC send failed, output stopped: {e}"))).ok();
+                    self.osc = None;
+                }
+                self.tx.send(BciMessage::Spectrum(spec)).ok();
+            }
+        }
+        if let (Some(classifier), Some(spectrum)) = (self.classifier.as_ref(), self.classifier_spectrum.as_mut()) {
+            let sample: Vec<f32> = self.clean_channel_data.iter().take(channels).map(|&v| v as f32).collect();
+            if let Some(spec) = spectrum.push(&sample) {
+                let scores = classifier.predict(&band_power_features(&spec.band_powers()));
+                self.classifier_action = argmax(&scores, CLASSIFIER_MIN_SCORE)
+                    .filter(|_| self.classifier_drive)
+                    .and_then(|class| classifier.action(class));
+                self.tx.send(BciMessage::ModelPrediction(scores)).ok();
+            }
+        }
 
-                if has_new_data {
-                    // === 关键步骤：实时滤波 ===
-                    // OpenBCI 的原始数据可能有几万的直流偏置，必须滤掉
-                    for i in 0..16 {
-                        let filtered = filters.process_sample(i, raw_channel_data[i]);
-                        // BrainFlow 返回的 Cyton 数据是伏特级别，UI/阈值逻辑使用微伏，统一缩放
-                        clean_channel_data[i] = if current_mode == ConnectionMode::Hardware {
-                            filtered * 1e6
-                        } else {
-                            filtered
-                        };
-                    }
+        let gp = self.output_state();
+        if self.recorder.is_recording() {
+            self.recorder.write_label(&gp);
+        }
 
-                    // 录制原始数据(Raw)还是干净数据(Clean)? 
-                    // 建议录制 Raw，方便以后调整算法。但为了演示效果，这里我们把 Clean 发给 UI
-                    if recorder.is_recording() {
-                        recorder.write_record(&raw_channel_data);
-                    }
+        // === 驱动 vJoy ===
+        // 默认每帧都更新；设置了输出频率时只按该频率写入最新状态，减少系统调用开销
+        let due = self.vjoy_limiter.due(Instant::now());
+        if let Some(joy) = self.joystick.as_ref().filter(|_| due) {
+            apply_gamepad(joy.as_ref(), &gp, &self.stick_response);
+        }
+        
+        // 发送手柄状态给 UI 显示
+        if self.last_vjoy_update.elapsed().as_millis() > 30 {
+            if let Some(Err(e)) = self.osc.as_ref().map(|osc| osc.send_gamepad(&gp)) {
+                self.tx.send(BciMessage::Log(format!("⚠️ OSC send failed, output stopped: {e}"))).ok();
+                self.osc = None;
+            }
+            self.tx.send(BciMessage::GamepadUpdate(gp)).ok();
+            if self.confidence_output {
+                self.tx.send(BciMessage::DecodeConfidence(self.last_confidence.clone())).ok();
+            }
+            self.last_vjoy_update = Instant::now();
+        }
+    }
+}
 
-                    // === 发送数据给 UI 渲染 ===
-                    // 初始化 Buffer (如果为空)
-                    if signal_buffer.is_none() {
-                        let labels: Vec<String> = (0..16).map(|i| format!("Ch{}", i+1)).collect();
-                        signal_buffer = SignalBuffer::with_history_seconds(labels, current_sample_rate_hz, 10.0).ok();
-                    }
+pub fn spawn_thread(tx: Sender<BciMessage>, rx_cmd: Receiver<GuiCommand>) {
+    thread::spawn(move || {
+        let mut engine = Engine::new(tx);
+        loop {
+            // 1. 处理 GUI 命令 (非阻塞)
+            while let Ok(cmd) = rx_cmd.try_recv() {
+                engine.handle_command(cmd);
+            }
+            engine.tick();
+        }
+    });
+}
 
-                    if let Some(buf) = signal_buffer.as_mut() {
-                        // 把 clean_channel_data 包装成 Batch
-                        let batch = SignalBatch {
-                            started_at: SystemTime::now(),
-                            sample_rate_hz: current_sample_rate_hz,
-                            channel_labels: buf.channel_labels().to_vec(),
-                            samples: clean_channel_data.iter().map(|&v| vec![v as f32]).collect(),
-                        };
-                        buf.push_batch(&batch).ok();
-                        
-                        // 降低 UI 刷新频率，比如每 4 个采样发一次 GUI，或者只发最新的 snapshot
-                        // 为了流畅度，这里每次都发，但 GUI 端要注意性能
-                        tx.send(BciMessage::DataFrame(buf.snapshot(5.0))).ok();
-                    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                    // === 神经解码 (使用干净数据) ===
-                    let gp = process_neural_intent(
-                        &clean_channel_data, 
-                        threshold, 
-                        calib_mode, 
-                        &mut calib_max_val, 
-                        calib_start_time, 
-                        &tx
-                    );
-
-                    // === 驱动 vJoy ===
-                    // 只有当状态发生改变 或 每隔一定时间才更新，减少系统调用开销
-                    // 这里为了响应速度，每帧都更新
-                    if let Some(joy) = &mut joystick {
-                        joy.set_button(1, gp.a);
-                        joy.set_button(2, gp.b);
-                        joy.set_axis(0x30, (16384.0 + gp.lx * 16000.0) as i32);
-                        joy.set_axis(0x31, (16384.0 + gp.ly * 16000.0) as i32);
-                        // ... 其他按键映射同理
-                    }
-                    
-                    // 发送手柄状态给 UI 显示
-                    if last_vjoy_update.elapsed().as_millis() > 30 {
-                        tx.send(BciMessage::GamepadUpdate(gp)).ok();
-                        last_vjoy_update = Instant::now();
+    fn tone_rms(filter: &mut SimpleFilter, fs: f64, freq: f64) -> f64 {
+        let n = (fs * 4.0) as usize;
+        let out: Vec<f64> = (0..n)
+            .map(|i| filter.process_sample(0, (2.0 * PI * freq * i as f64 / fs).sin()))
+            .collect();
+        // 跳过前 2 秒的滤波器暂态
+        let tail = &out[n / 2..];
+        (tail.iter().map(|v| v * v).sum::<f64>() / tail.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn reconnect_backoff_doubles_from_one_second_up_to_the_cap() {
+        let secs: Vec<u64> = (0..8).map(|n| reconnect_backoff(n).as_secs()).collect();
+        assert_eq!(secs, vec![0, 1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(reconnect_backoff(u32::MAX), RECONNECT_BACKOFF_MAX);
+    }
+
+    fn buffered_samples(engine: &Engine) -> usize {
+        engine
+            .signal_buffer
+            .as_ref()
+            .map(|b| b.snapshot(10.0).samples.first().map(|c| c.len()).unwrap_or(0))
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn sim_sample_rate_change_rebuilds_filters_and_buffer() {
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        engine.handle_command(GuiCommand::Connect(ConnectionMode::Simulation, String::new(), BoardKind::default()));
+        engine.handle_command(GuiCommand::StartStream);
+        engine.step_stream();
+        assert_eq!(engine.signal_buffer.as_ref().map(|b| b.sample_rate_hz()), Some(250.0));
+
+        engine.handle_command(GuiCommand::SetSimSampleRate(500.0));
+        engine.handle_command(GuiCommand::SetSimSampleRate(0.0));
+        engine.handle_command(GuiCommand::SetSimSampleRate(f32::NAN));
+        assert_eq!((engine.current_sample_rate_hz, engine.filters.fs), (500.0, 500.0));
+        assert!(engine.signal_buffer.is_none());
+        for _ in 0..3 {
+            engine.step_stream();
+        }
+        let frame = engine.signal_buffer.as_ref().unwrap().snapshot(10.0);
+        assert_eq!((frame.sample_rate_hz, frame.samples[0].len()), (500.0, 3));
+    }
+
+    #[test]
+    fn guided_calibration_captures_rest_then_action_peaks() {
+        let mut guided = GuidedCalibration::new(10.0);
+        let mut messages = Vec::new();
+        for _ in 0..30 {
+            messages.extend(guided.push(&[40.0, -20.0]));
+        }
+        assert!(matches!(messages.last(), Some(BciMessage::CalibrationProgress(CalibrationPhase::Action, p)) if *p == 0.0));
+        assert!(messages[..messages.len() - 1].iter().all(|m| matches!(m, BciMessage::CalibrationProgress(CalibrationPhase::Rest, _))));
+        for i in 0..30 {
+            messages.extend(guided.push(&[if i == 7 { -240.0 } else { 60.0 }, 90.0]));
+        }
+        let Some(BciMessage::GuidedCalibrationResult(capture)) = messages.pop() else { panic!("no result") };
+        assert_eq!(capture, GuidedCapture { rest_channels: vec![40.0, 20.0], rest_max: 40.0, action_channels: vec![240.0, 90.0], action_max: 240.0 });
+    }
+
+    #[test]
+    fn clear_history_resets_buffers_but_keeps_connection() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        engine.handle_command(GuiCommand::Connect(ConnectionMode::Simulation, String::new(), BoardKind::default()));
+        engine.handle_command(GuiCommand::StartStream);
+        for _ in 0..5 {
+            engine.step_stream();
+        }
+        assert_eq!(buffered_samples(&engine), 5);
+        engine.handle_command(GuiCommand::ClearHistory);
+        assert_eq!(buffered_samples(&engine), 0);
+        assert_eq!(engine.samples_processed, 0);
+        assert!(engine.is_active && engine.is_streaming);
+
+        // 下一次速率上报只统计清空后的采样
+        engine.stream_started_at -= Duration::from_secs(1);
+        engine.last_rate_report -= Duration::from_secs(2);
+        engine.step_stream();
+        let rate = rx.try_iter().find_map(|msg| match msg {
+            BciMessage::StreamRate(rate) => Some(rate),
+            _ => None,
+        });
+        assert_eq!(rate.map(|r| (r.samples, r.buffered)), Some((1, 0)));
+    }
+
+    #[test]
+    fn hot_swapped_mapping_applies_to_next_decode() {
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        engine.mapping = MappingTable::default();
+        engine.handle_command(GuiCommand::Connect(ConnectionMode::Simulation, String::new(), BoardKind::default()));
+        engine.handle_command(GuiCommand::StartStream);
+        engine.clean_channel_data[9] = 500.0;
+        assert!(!engine.output_state().a);
+
+        let dir = std::env::temp_dir().join(format!("qnmd_swap_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("good.toml");
+        std::fs::write(&good, "[[mapping]]\naction = \"a\"\nchannels = [9]\n").unwrap();
+        engine.handle_command(GuiCommand::LoadMapping(Some(good)));
+        assert!(engine.output_state().a);
+
+        // 校验失败的文件不能部分生效
+        let bad = dir.join("bad.toml");
+        std::fs::write(&bad, "[[mapping]]\naction = \"b\"\nchannels = [9]\n[[mapping]]\naction = \"x\"\nchannels = [40]\n").unwrap();
+        engine.handle_command(GuiCommand::LoadMapping(Some(bad)));
+        let gp = engine.output_state();
+        assert!(gp.a && !gp.b);
+        assert!(engine.is_streaming);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn linked_display_bandpass_changes_decode_amplitude() {
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        // 校准模式下 process_neural_intent 会记录它看到的最大幅值
+        let peak_seen = |engine: &mut Engine| {
+            engine.calib_mode = true;
+            engine.calib_peaks = CalibPeaks::default();
+            for i in 0..1000 {
+                engine.clean_channel_data[0] = 100.0 * (2.0 * PI * 40.0 * i as f64 / 250.0).sin();
+                engine.decode();
+            }
+            engine.calib_peaks.overall
+        };
+        let unlinked = peak_seen(&mut engine);
+        assert!(unlinked > 95.0);
+
+        let bandpass = vec![FilterKind::Bandpass { low_hz: 8.0, high_hz: 12.0, q: 0.707 }];
+        engine.handle_command(GuiCommand::SetDecodeFilters(Some(vec![bandpass; 16])));
+        let linked = peak_seen(&mut engine);
+        assert!(linked < unlinked * 0.5, "40 Hz should be attenuated: {linked} vs {unlinked}");
+
+        engine.handle_command(GuiCommand::SetDecodeFilters(None));
+        assert!(peak_seen(&mut engine) > 95.0);
+    }
+
+    #[test]
+    fn vjoy_apply_count_follows_configured_rate() {
+        let start = Instant::now();
+        for decode_hz in [250u64, 1000] {
+            let mut limiter = OutputLimiter::new(Some(60.0));
+            let applied = (0..decode_hz)
+                .filter(|i| limiter.due(start + Duration::from_micros(i * 1_000_000 / decode_hz)))
+                .count();
+            assert_eq!(applied, 60, "decode at {decode_hz} Hz");
+        }
+        let mut unlimited = OutputLimiter::new(None);
+        assert!((0..100).all(|_| unlimited.due(start)));
+    }
+
+    #[test]
+    fn every_gamepad_field_reaches_its_own_vjoy_slot() {
+        let gp = GamepadState { lx: -1.0, ly: 0.5, rx: 1.0, ry: -0.5, a: true, b: true, x: true, y: true, lb: true, rb: true, lt: 1.0, rt: 0.4, dpad_up: true, dpad_down: true, dpad_left: true, dpad_right: true };
+        let buttons = vjoy_buttons(&gp);
+        assert!(buttons.iter().all(|&(_, down)| down));
+        let ids: Vec<u8> = buttons.iter().map(|&(id, _)| id).collect();
+        assert_eq!(ids, (1..=12).collect::<Vec<u8>>());
+        assert!(vjoy_buttons(&GamepadState::default()).iter().all(|&(_, down)| !down));
+        assert_eq!(vjoy_axes(&gp, &StickResponse::default()),
+                   [(0x30, 384), (0x31, 24384), (0x33, 32384), (0x34, 8384), (0x36, 32768), (0x37, 13107)]);
+    }
+
+    #[test]
+    fn deflection_inside_the_dead_zone_is_exactly_neutral() {
+        let response = StickResponse { dead_zone: 0.2, curve: ResponseCurve::Quadratic };
+        let resting = GamepadState { lx: 0.1, ly: -0.15, rx: 0.19, ry: 0.0, ..GamepadState::default() };
+        assert!(vjoy_axes(&resting, &response)[..4].iter().all(|&(_, v)| v == 16384));
+        // 出了死区从 0 开始，满偏仍是满偏
+        let full = GamepadState { lx: 1.0, ry: -1.0, ..GamepadState::default() };
+        assert_eq!(vjoy_axes(&full, &response)[..4], [(0x30, 32384), (0x31, 16384), (0x33, 16384), (0x34, 384)]);
+        let (x, _) = response.shape(0.6, 0.0);
+        assert!((x - 0.25).abs() < 1e-6, "{x}");
+    }
+
+    #[test]
+    fn dpad_becomes_a_pov_hat_with_diagonals() {
+        let pov = |up, down, left, right| vjoy_pov(&GamepadState { dpad_up: up, dpad_down: down, dpad_left: left, dpad_right: right, ..GamepadState::default() });
+        assert_eq!(pov(false, false, false, false), (-1, -1));
+        assert_eq!(pov(true, false, false, false), (0, 0));
+        assert_eq!(pov(false, false, false, true), (1, 9000));
+        assert_eq!(pov(false, true, false, false), (2, 18000));
+        assert_eq!(pov(false, false, true, false), (3, 27000));
+        assert_eq!(pov(true, false, true, false), (0, 31500));
+        // 相反方向同时按下互相抵消
+        assert_eq!(pov(true, true, false, false), (-1, -1));
+    }
+
+    #[test]
+    fn effective_rate_is_samples_over_elapsed_time() {
+        assert_eq!(effective_rate_hz(2500, Duration::from_secs(10)), Some(250.0));
+        // 板子欠采样：10 s 只到了 2375 个采样
+        assert_eq!(effective_rate_hz(2375, Duration::from_secs(10)), Some(237.5));
+        assert_eq!(effective_rate_hz(50, Duration::from_millis(200)), None);
+    }
+
+    #[test]
+    fn ly_gain_halves_only_the_ly_axis_sent_to_vjoy() {
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        engine.mapping = MappingTable::default();
+        // 默认映射中通道 0,4,8 = 左摇杆上，3,7,11 = 左摇杆右
+        let held = |engine: &mut Engine| {
+            for &i in &[0, 4, 8, 3, 7, 11] {
+                engine.clean_channel_data[i] = 500.0;
+            }
+            engine.output_state()
+        };
+        let full = held(&mut engine);
+        assert!(full.ly != 0.0 && full.lx != 0.0);
+        engine.handle_command(GuiCommand::SetAxisGains(AxisGains {
+            ly_gain: 0.5,
+            ..AxisGains::default()
+        }));
+        let halved = held(&mut engine);
+        assert_eq!(halved.ly, full.ly * 0.5);
+        assert_eq!(halved.lx, full.lx);
+        assert_eq!(vjoy_axis(halved.ly) - 16384, (vjoy_axis(full.ly) - 16384) / 2);
+        assert_eq!(vjoy_axis(halved.lx), vjoy_axis(full.lx));
+    }
+
+    #[test]
+    fn mean_rectified_decode_survives_single_sample_dropouts() {
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        engine.mapping = MappingTable::default();
+        // 默认映射中 A = 通道 0,1,2 同时激活；通道 0 每 10 个采样掉一次线
+        let held = |engine: &mut Engine| {
+            (0..200)
+                .map(|i| {
+                    engine.clean_channel_data[..3].fill(500.0);
+                    if i % 10 == 5 {
+                        engine.clean_channel_data[0] = 0.0;
                     }
+                    engine.decode().a
+                })
+                .filter(|&a| a)
+                .count()
+        };
+        assert_eq!(held(&mut engine), 180);
+        engine.handle_command(GuiCommand::SetDecodeFeature(FeatureKind::MeanRectified, 25));
+        assert_eq!(held(&mut engine), 200);
+    }
+
+    #[test]
+    fn barely_triggered_pattern_reports_low_confidence_and_strong_one_high() {
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mapping = MappingTable::default();
+        let confidence_of_a = |level: f64| {
+            // 默认映射中 A = 通道 0,1,2；通道 1 最弱，决定余量
+            let mut data = vec![0.0; 16];
+            data[..3].copy_from_slice(&[level * 2.0, level, level * 3.0]);
+            let (gp, confidence) =
+                process_neural_intent(&data, &[100.0; 16], &mapping, &mut false, &mut CalibPeaks::default(), Instant::now(), &tx);
+            assert!(gp.a);
+            confidence.iter().find(|(a, _)| *a == GamepadAction::A).map(|&(_, c)| c).unwrap()
+        };
+        assert!(confidence_of_a(105.0) < 0.1);
+        assert_eq!(confidence_of_a(400.0), 1.0);
+        let (_, idle) =
+            process_neural_intent(&[0.0; 16], &[100.0; 16], &mapping, &mut false, &mut CalibPeaks::default(), Instant::now(), &tx);
+        assert!(idle.is_empty());
+    }
+
+    #[test]
+    fn channel_below_its_own_threshold_blocks_the_pattern() {
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        engine.mapping = MappingTable::default();
+        engine.handle_command(GuiCommand::SetThreshold(100.0));
+        // 默认映射中 A = 通道 0,1,2；通道 1 电极较差，单独调高阈值
+        engine.handle_command(GuiCommand::SetChannelThreshold(1, 400.0));
+        engine.clean_channel_data[..3].copy_from_slice(&[900.0, 300.0, 900.0]);
+        assert!(!engine.decode().a);
+        engine.clean_channel_data[1] = 450.0;
+        assert!(engine.decode().a);
+        engine.handle_command(GuiCommand::SetThreshold(100.0));
+        engine.clean_channel_data[1] = 300.0;
+        assert!(engine.decode().a);
+    }
+
+    #[test]
+    fn injected_artifact_shows_in_raw_and_is_damped_by_the_filters() {
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        engine.handle_command(GuiCommand::Connect(ConnectionMode::Simulation, String::new(), BoardKind::default()));
+        engine.handle_command(GuiCommand::StartStream);
+        engine.handle_command(GuiCommand::InjectArtifact);
+        let (mut raw_peak, mut clean_peak) = (0.0f64, 0.0f64);
+        for _ in 0..60 {
+            engine.step_stream();
+            raw_peak = raw_peak.max(engine.raw_channel_data[0].abs());
+            clean_peak = clean_peak.max(engine.clean_channel_data[0].abs());
+        }
+        assert!(raw_peak > 400.0, "{raw_peak}");
+        assert!(clean_peak < 400.0, "{clean_peak}");
+        assert_eq!(engine.artifact_step, None);
+    }
+
+    #[test]
+    fn skipped_reference_channel_is_excluded_from_decode_and_view() {
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        engine.mapping = MappingTable::default();
+        // 默认映射中 A = 通道 0,1,2 同时激活
+        engine.clean_channel_data[..3].fill(500.0);
+        assert!(engine.decode().a);
+
+        let mut montage = Montage::default();
+        montage.set_skip_first(1);
+        engine.handle_command(GuiCommand::SetMontage(montage.clone()));
+        assert!(!engine.decode().a);
+
+        let mut pipe = crate::waveform::WaveformPipeline::new(4, 250.0);
+        montage.apply_to_view(&mut pipe);
+        pipe.ingest_frame(0.0, &[1.0, 2.0, 3.0, 4.0]);
+        let shown: Vec<usize> = pipe.view().channels.iter().map(|c| c.index).collect();
+        assert_eq!(shown, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn cyton_layout_configures_eight_channels_cyton_impedance_and_eight_channel_mapping() {
+        use crate::drivers::ImpedanceModel;
+        use crate::montage::QuickMontage;
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        let montage = Montage {
+            layout: QuickMontage::Cyton8,
+            ..Montage::default()
+        };
+        engine.handle_command(GuiCommand::SetMontage(montage));
+        assert_eq!(engine.montage.layout.impedance_model(), ImpedanceModel::Cyton);
+        assert!(engine.mapping.validate(8).is_ok());
+        assert_eq!(engine.mapping.entries.len(), MappingTable::default_for(8).entries.len());
+        assert!(MappingTable::default().validate(8).is_err());
+        engine.process_sample();
+        let frame = engine.signal_buffer.as_ref().unwrap().snapshot(1.0);
+        assert_eq!(frame.samples.len(), 8);
+        assert_eq!(frame.channel_labels.last().map(String::as_str), Some("Ch8"));
+    }
+
+    #[test]
+    fn out_of_range_raw_value_is_replaced_and_counted() {
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        engine.raw_channel_data[3] = 42.0;
+        engine.process_sample();
+        engine.raw_channel_data[3] = 1e30;
+        engine.raw_channel_data[4] = f64::NAN;
+        engine.process_sample();
+        assert_eq!(engine.raw_channel_data[3], 42.0);
+        assert_eq!(engine.raw_channel_data[4], 0.0);
+        assert_eq!(engine.sample_guard.rejected, 2);
+        assert!(engine.clean_channel_data.iter().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn live_spectrum_peaks_at_tone_on_schedule() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        engine.handle_command(GuiCommand::SetLiveSpectrum(Some(LiveSpectrumConfig { fft_size: 250, rate_hz: 10.0 })));
+        let fs = engine.current_sample_rate_hz as f64;
+        let mut emitted_at = Vec::new();
+        for i in 0..1000 {
+            engine.raw_channel_data[0] = 100.0 * (2.0 * PI * 20.0 * i as f64 / fs).sin();
+            engine.process_sample();
+            for msg in rx.try_iter() {
+                if let BciMessage::Spectrum(spec) = msg {
+                    emitted_at.push((i, spec));
                 }
-            } else {
-                // 未推流时，降低 CPU 占用
-                thread::sleep(Duration::from_millis(50));
             }
         }
-    });
+        // 窗口填满 (250 点) 后每 25 点 (250 Hz / 10 Hz) 发一次
+        let indices: Vec<usize> = emitted_at.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indices.first(), Some(&249));
+        assert!(indices.windows(2).all(|w| w[1] - w[0] == 25));
+        assert_eq!(indices.len(), (1000 - 250) / 25 + 1);
+        let spec = &emitted_at.last().unwrap().1;
+        let peak = spec.magnitudes[0]
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(k, _)| spec.frequencies_hz[k])
+            .unwrap();
+        assert!((peak - 20.0).abs() <= 1.0, "peak at {peak} Hz");
+    }
+
+    #[test]
+    fn mains_switch_moves_the_notch_to_60hz() {
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut engine = Engine::new(tx);
+        let fs = engine.current_sample_rate_hz as f64;
+        assert!(tone_rms(&mut engine.filters, fs, 50.0) < 0.05);
+        engine.handle_command(GuiCommand::SetMainsFrequency(60.0));
+        assert_eq!(engine.filters.hp.len(), 16);
+        assert!(tone_rms(&mut engine.filters, fs, 60.0) < 0.05);
+        assert!(tone_rms(&mut engine.filters, fs, 50.0) > 0.3);
+    }
+
+    #[test]
+    fn harmonic_notch_attenuates_100hz_at_250hz() {
+        let fs = 250.0;
+        let plain = tone_rms(&mut SimpleFilter::new(1, fs, 50.0, false), fs, 100.0);
+        let notched = tone_rms(&mut SimpleFilter::new(1, fs, 50.0, true), fs, 100.0);
+        assert!(plain > 0.5, "fundamental-only filter should pass 100 Hz, got {plain}");
+        assert!(notched < 0.05, "harmonic notch should remove 100 Hz, got {notched}");
+    }
 }
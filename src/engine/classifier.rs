@@ -0,0 +1,152 @@
+// src/engine/classifier.rs
+// 分类器钩子：每通道频段功率特征 -> 各类别得分 (ModelPrediction)，可选按得分最高的类别驱动手柄。
+// 内置一对多逻辑回归，权重从 data/model.json 读取 (训练脚本导出同样的字段即可)
+use super::GamepadAction;
+use crate::drivers::fft::BandPowers;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Turns one feature vector into a score per class.
+pub trait Classifier: Send {
+    /// One score per class, in `classes()` order.
+    fn predict(&self, features: &[f32]) -> Vec<f32>;
+    fn classes(&self) -> &[String];
+    /// Gamepad action the class drives, if any.
+    fn action(&self, _class: usize) -> Option<GamepadAction> {
+        None
+    }
+}
+
+/// One-vs-rest logistic regression: class k scores `sigmoid(weights[k] · x + bias[k])`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LogisticModel {
+    pub classes: Vec<String>,
+    /// One row per class, each as long as the feature vector.
+    pub weights: Vec<Vec<f32>>,
+    pub bias: Vec<f32>,
+    /// Action per class in class order (`null` for rest classes); may be omitted.
+    #[serde(default)]
+    pub actions: Vec<Option<GamepadAction>>,
+}
+
+impl LogisticModel {
+    pub fn store_path() -> PathBuf {
+        PathBuf::from("data/model.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let raw =
+            fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        Self::from_json_str(&raw)
+    }
+
+    pub fn from_json_str(raw: &str) -> Result<Self, String> {
+        let model: Self = serde_json::from_str(raw).map_err(|e| e.to_string())?;
+        model.validate()?;
+        Ok(model)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let classes = self.classes.len();
+        if classes == 0 {
+            return Err("model has no classes".to_owned());
+        }
+        if self.weights.len() != classes || self.bias.len() != classes {
+            return Err(format!(
+                "{classes} classes but {} weight rows and {} biases",
+                self.weights.len(),
+                self.bias.len()
+            ));
+        }
+        if !self.actions.is_empty() && self.actions.len() != classes {
+            return Err(format!(
+                "{classes} classes but {} actions",
+                self.actions.len()
+            ));
+        }
+        let features = self.feature_len();
+        if self.weights.iter().any(|row| row.len() != features) {
+            return Err("weight rows differ in length".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Length of the feature vector the weights expect.
+    pub fn feature_len(&self) -> usize {
+        self.weights.first().map_or(0, Vec::len)
+    }
+}
+
+impl Classifier for LogisticModel {
+    fn predict(&self, features: &[f32]) -> Vec<f32> {
+        self.weights
+            .iter()
+            .zip(&self.bias)
+            .map(|(row, b)| {
+                let z: f32 = row.iter().zip(features).map(|(w, x)| w * x).sum::<f32>() + b;
+                1.0 / (1.0 + (-z).exp())
+            })
+            .collect()
+    }
+
+    fn classes(&self) -> &[String] {
+        &self.classes
+    }
+
+    fn action(&self, class: usize) -> Option<GamepadAction> {
+        self.actions.get(class).copied().flatten()
+    }
+}
+
+/// `ln(1 + power)` of delta, theta, alpha, beta and gamma for channel 0, then channel 1, ...
+pub fn band_power_features(powers: &BandPowers) -> Vec<f32> {
+    let bands = [
+        &powers.delta,
+        &powers.theta,
+        &powers.alpha,
+        &powers.beta,
+        &powers.gamma,
+    ];
+    (0..powers.delta.len())
+        .flat_map(|ch| bands.map(|band| band.get(ch).copied().unwrap_or(0.0).max(0.0).ln_1p()))
+        .collect()
+}
+
+/// Highest-scoring class, if it reaches `min_score`.
+pub fn argmax(scores: &[f32], min_score: f32) -> Option<usize> {
+    scores
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| **s >= min_score)
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(k, _)| k)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logistic_model_scores_known_weights_and_drives_the_top_class() {
+        let model = LogisticModel::from_json_str(
+            r#"{
+                "classes": ["rest", "attack"],
+                "weights": [[0.0, 0.0], [1.0, -2.0]],
+                "bias": [0.0, 0.5],
+                "actions": [null, "a"]
+            }"#,
+        )
+        .unwrap();
+        // attack: 1·3 - 2·0.25 + 0.5 = 3 -> sigmoid(3)
+        let scores = model.predict(&[3.0, 0.25]);
+        assert_eq!(scores[0], 0.5);
+        assert!((scores[1] - 0.952_574_1).abs() < 1e-6, "{scores:?}");
+        let top = argmax(&scores, 0.5).unwrap();
+        assert_eq!((top, model.action(top)), (1, Some(GamepadAction::A)));
+        assert_eq!(argmax(&[0.2, 0.4], 0.5), None);
+
+        let ragged = r#"{"classes": ["a"], "weights": [[1.0], [2.0]], "bias": [0.0]}"#;
+        assert!(LogisticModel::from_json_str(ragged).is_err());
+    }
+}
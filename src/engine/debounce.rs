@@ -0,0 +1,125 @@
+// src/engine/debounce.rs
+// 按键去抖：阈值附近的信号会让按键逐帧抖动。按下仍用触发阈值，松开改用更低的释放阈值 (迟滞)，
+// 且要持续低于释放阈值一段时间才真正松开。按采样计数计时，实时与回放结果一致。
+use super::{GamepadAction, MappingTable};
+use crate::types::{GamepadState, TriggerDebounce};
+
+struct Held {
+    action: GamepadAction,
+    /// Consecutive samples spent below the release threshold.
+    below_for: usize,
+}
+
+/// Per-action press/release state kept across decode calls.
+pub struct TriggerDebouncer {
+    release_ratio: f64,
+    hold_samples: usize,
+    held: Vec<Held>,
+}
+
+impl TriggerDebouncer {
+    pub fn new(config: TriggerDebounce, sample_rate_hz: f32) -> Self {
+        Self {
+            release_ratio: (1.0 - config.hysteresis).clamp(0.0, 1.0),
+            hold_samples: (config.hold_ms.max(0.0) / 1000.0 * sample_rate_hz as f64).round()
+                as usize,
+            held: Vec::new(),
+        }
+    }
+
+    /// No gap and no hold time: actions follow the trigger threshold exactly.
+    pub fn is_passthrough(&self) -> bool {
+        self.release_ratio >= 1.0 && self.hold_samples == 0
+    }
+
+    /// `pressed` crossed the trigger threshold this sample, `sustained` the release
+    /// threshold. Returns the actions held after this sample, in press order.
+    pub fn update(
+        &mut self,
+        pressed: &[GamepadAction],
+        sustained: &[GamepadAction],
+    ) -> Vec<GamepadAction> {
+        let hold = self.hold_samples;
+        self.held.retain_mut(|h| {
+            if pressed.contains(&h.action) || sustained.contains(&h.action) {
+                h.below_for = 0;
+                return true;
+            }
+            h.below_for += 1;
+            h.below_for <= hold
+        });
+        for &action in pressed {
+            if !self.held.iter().any(|h| h.action == action) {
+                self.held.push(Held {
+                    action,
+                    below_for: 0,
+                });
+            }
+        }
+        self.held.iter().map(|h| h.action).collect()
+    }
+
+    /// Debounces one decode result. `gp`/`fired` come from `decode_with_confidence`
+    /// at `thresholds`; held actions that dropped out report a confidence of 0.
+    pub fn apply(
+        &mut self,
+        mapping: &MappingTable,
+        data: &[f64],
+        thresholds: &[f64],
+        gp: GamepadState,
+        fired: Vec<(GamepadAction, f32)>,
+    ) -> (GamepadState, Vec<(GamepadAction, f32)>) {
+        if self.is_passthrough() {
+            return (gp, fired);
+        }
+        let release: Vec<f64> = thresholds.iter().map(|t| t * self.release_ratio).collect();
+        let (_, sustained) = mapping.decode_with_confidence(data, &release);
+        let pressed: Vec<GamepadAction> = fired.iter().map(|&(a, _)| a).collect();
+        let sustained: Vec<GamepadAction> = sustained.into_iter().map(|(a, _)| a).collect();
+        let held = self.update(&pressed, &sustained);
+        let confidence = held
+            .iter()
+            .map(|&a| {
+                let c = fired.iter().find(|(f, _)| *f == a).map_or(0.0, |&(_, c)| c);
+                (a, c)
+            })
+            .collect();
+        (GamepadAction::state_of(&held), confidence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noisy_signal_at_the_threshold_toggles_at_most_once() {
+        let mapping =
+            MappingTable::from_toml_str("[[mapping]]\naction = \"a\"\nchannels = [0]\n", 16)
+                .expect("mapping");
+        let thresholds = [100.0; 16];
+        let config = TriggerDebounce {
+            hysteresis: 0.2,
+            hold_ms: 50.0,
+        };
+        let mut debouncer = TriggerDebouncer::new(config, 250.0);
+        let mut plain_toggles = 0;
+        let mut toggles = 0;
+        let (mut plain_last, mut last) = (false, false);
+        // 2 s 的信号在 100 µV 阈值上下 ±15 µV 抖动
+        for i in 0..500 {
+            let noise = 15.0 * ((i as f64 * 1.7).sin() + (i as f64 * 0.31).cos()) / 2.0;
+            let mut data = [0.0; 16];
+            data[0] = 100.0 + noise;
+            let (gp, fired) = mapping.decode_with_confidence(&data, &thresholds);
+            plain_toggles += usize::from(gp.a != plain_last);
+            plain_last = gp.a;
+            let (gp, _) = debouncer.apply(&mapping, &data, &thresholds, gp, fired);
+            toggles += usize::from(gp.a != last);
+            last = gp.a;
+        }
+        assert!(plain_toggles > 10, "{plain_toggles}");
+        assert!(toggles <= 1, "{toggles}");
+        assert!(last);
+    }
+}
@@ -0,0 +1,131 @@
+// src/engine/features.rs
+// 解码前的特征提取：逐采样阈值对 EMG 太敏感，改为在最近 K 个采样上取平滑特征再判定
+use crate::brain_utils::WindowBuffer;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureKind {
+    /// The filtered sample itself (original behaviour).
+    Instant,
+    /// Mean |x| over the window, in µV like `Instant`.
+    MeanRectified,
+    /// ln(variance) over the window (`WindowBuffer::band_power`), floored at 0;
+    /// stays 0 until the window fills. Thresholds need recalibrating.
+    #[serde(alias = "band_power")]
+    LogPower,
+}
+
+impl FeatureKind {
+    pub const ALL: [FeatureKind; 3] = [
+        FeatureKind::Instant,
+        FeatureKind::MeanRectified,
+        FeatureKind::LogPower,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FeatureKind::Instant => "Instant",
+            FeatureKind::MeanRectified => "Mean rectified",
+            FeatureKind::LogPower => "Log power",
+        }
+    }
+}
+
+/// Per-channel sliding window. `MeanRectified` keeps running sums, so each sample costs
+/// O(1); `LogPower` recomputes the window variance, O(window) per sample.
+pub struct FeatureWindow {
+    kind: FeatureKind,
+    len: usize,
+    rings: Vec<VecDeque<f64>>,
+    sums: Vec<f64>,
+    /// Only used by `LogPower`, which needs the window mean, not a running sum.
+    buffers: Vec<WindowBuffer>,
+}
+
+impl FeatureWindow {
+    pub fn new(kind: FeatureKind, len: usize, channels: usize) -> Self {
+        let len = len.max(1);
+        Self {
+            kind,
+            len,
+            rings: (0..channels)
+                .map(|_| VecDeque::with_capacity(len))
+                .collect(),
+            sums: vec![0.0; channels],
+            buffers: (0..channels).map(|_| WindowBuffer::new(len)).collect(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.rings.iter_mut().for_each(VecDeque::clear);
+        self.sums.fill(0.0);
+        self.buffers = (0..self.buffers.len())
+            .map(|_| WindowBuffer::new(self.len))
+            .collect();
+    }
+
+    /// Pushes one sample per channel and replaces it with the channel's feature value.
+    pub fn apply(&mut self, data: &mut [f64]) {
+        match self.kind {
+            FeatureKind::Instant => {}
+            FeatureKind::MeanRectified => {
+                for ((v, ring), sum) in data.iter_mut().zip(&mut self.rings).zip(&mut self.sums) {
+                    let c = v.abs();
+                    if ring.len() == self.len {
+                        *sum -= ring.pop_front().unwrap_or(0.0);
+                    }
+                    ring.push_back(c);
+                    *sum += c;
+                    *v = (*sum / ring.len() as f64).max(0.0);
+                }
+            }
+            FeatureKind::LogPower => {
+                // 窗口未满时不判定，单个尖峰也只占窗口的一小部分能量
+                for (v, buf) in data.iter_mut().zip(&mut self.buffers) {
+                    buf.push(*v);
+                    *v = if buf.is_full() {
+                        buf.band_power().max(0.0)
+                    } else {
+                        0.0
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn brief_spike_stays_below_a_sustained_contraction() {
+        // 0.25 s @ 250 Hz
+        let window = 62;
+        let peak = |signal: &dyn Fn(usize) -> f64| {
+            let mut features = FeatureWindow::new(FeatureKind::LogPower, window, 1);
+            (0..250)
+                .map(|i| {
+                    let mut data = [signal(i)];
+                    features.apply(&mut data);
+                    data[0]
+                })
+                .fold(0.0f64, f64::max)
+        };
+        // 单个 300 µV 尖峰，原始幅值远超持续收缩的 150 µV
+        let spike = peak(&|i| if i == 100 { 300.0 } else { 0.0 });
+        let contraction = peak(&|i| 150.0 * (2.0 * PI * 80.0 * i as f64 / 250.0).sin());
+        assert!(
+            spike < contraction,
+            "spike {spike} vs contraction {contraction}"
+        );
+
+        let mut features = FeatureWindow::new(FeatureKind::LogPower, window, 1);
+        let mut data = [150.0];
+        features.apply(&mut data);
+        assert_eq!(data[0], 0.0, "no decision before the window is full");
+    }
+}
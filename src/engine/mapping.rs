@@ -0,0 +1,397 @@
+// src/engine/mapping.rs
+// 数据驱动的解码映射表：哪些通道同时激活 -> 哪个手柄动作
+use crate::types::GamepadState;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadAction {
+    LeftStickUp,
+    LeftStickDown,
+    LeftStickLeft,
+    LeftStickRight,
+    RightStickUp,
+    RightStickDown,
+    RightStickLeft,
+    RightStickRight,
+    A,
+    B,
+    X,
+    Y,
+    Lb,
+    Rb,
+    Lt,
+    Rt,
+}
+
+impl GamepadAction {
+    pub fn label(self) -> &'static str {
+        match self {
+            GamepadAction::LeftStickUp => "LS↑",
+            GamepadAction::LeftStickDown => "LS↓",
+            GamepadAction::LeftStickLeft => "LS←",
+            GamepadAction::LeftStickRight => "LS→",
+            GamepadAction::RightStickUp => "RS↑",
+            GamepadAction::RightStickDown => "RS↓",
+            GamepadAction::RightStickLeft => "RS←",
+            GamepadAction::RightStickRight => "RS→",
+            GamepadAction::A => "A",
+            GamepadAction::B => "B",
+            GamepadAction::X => "X",
+            GamepadAction::Y => "Y",
+            GamepadAction::Lb => "LB",
+            GamepadAction::Rb => "RB",
+            GamepadAction::Lt => "LT",
+            GamepadAction::Rt => "RT",
+        }
+    }
+
+    /// Gamepad state with each of `actions` applied once, triggers fully pulled.
+    pub fn state_of(actions: &[GamepadAction]) -> GamepadState {
+        let mut gp = GamepadState::default();
+        for action in actions {
+            action.apply(&mut gp, 1.0);
+        }
+        gp
+    }
+
+    /// Applies the action; a trigger is pulled at least `pull` (0..=1), other actions ignore it.
+    pub fn apply(self, gp: &mut GamepadState, pull: f32) {
+        match self {
+            GamepadAction::LeftStickUp => gp.ly += 1.0,
+            GamepadAction::LeftStickDown => gp.ly -= 1.0,
+            GamepadAction::LeftStickLeft => gp.lx -= 1.0,
+            GamepadAction::LeftStickRight => gp.lx += 1.0,
+            GamepadAction::RightStickUp => gp.ry += 1.0,
+            GamepadAction::RightStickDown => gp.ry -= 1.0,
+            GamepadAction::RightStickLeft => gp.rx -= 1.0,
+            GamepadAction::RightStickRight => gp.rx += 1.0,
+            GamepadAction::A => gp.a = true,
+            GamepadAction::B => gp.b = true,
+            GamepadAction::X => gp.x = true,
+            GamepadAction::Y => gp.y = true,
+            GamepadAction::Lb => gp.lb = true,
+            GamepadAction::Rb => gp.rb = true,
+            GamepadAction::Lt => gp.lt = gp.lt.max(pull),
+            GamepadAction::Rt => gp.rt = gp.rt.max(pull),
+        }
+    }
+}
+
+/// Pull of a trigger that only just cleared its threshold, so a fired trigger always shows as pressed.
+const TRIGGER_MIN_PULL: f32 = 0.2;
+
+/// Right-stick axis that suppresses an entry while it is deflected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StickAxis {
+    Rx,
+    Ry,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MappingEntry {
+    pub action: GamepadAction,
+    /// Zero-based channel indices that must all be active.
+    pub channels: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unless_axis: Option<StickAxis>,
+    /// Multiplier on the global trigger threshold; below 1.0 triggers more easily.
+    #[serde(default = "default_threshold_scale")]
+    pub threshold_scale: f64,
+}
+
+fn default_threshold_scale() -> f64 {
+    1.0
+}
+
+impl MappingEntry {
+    fn new(action: GamepadAction, channels: &[usize]) -> Self {
+        Self {
+            action,
+            channels: channels.to_vec(),
+            unless_axis: None,
+            threshold_scale: default_threshold_scale(),
+        }
+    }
+    fn unless(mut self, axis: StickAxis) -> Self {
+        self.unless_axis = Some(axis);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MappingTable {
+    #[serde(rename = "mapping", default)]
+    pub entries: Vec<MappingEntry>,
+}
+
+impl Default for MappingTable {
+    /// 与最初硬编码的 16 通道映射一致，`data/mapping.default.toml` 是它的文件版本
+    fn default() -> Self {
+        use GamepadAction::*;
+        Self {
+            entries: vec![
+                // 左摇杆 (WASD)
+                MappingEntry::new(LeftStickUp, &[0, 4, 8]),
+                MappingEntry::new(LeftStickDown, &[1, 5, 9]),
+                MappingEntry::new(LeftStickLeft, &[2, 6, 10]),
+                MappingEntry::new(LeftStickRight, &[3, 7, 11]),
+                // 动作键
+                MappingEntry::new(A, &[0, 1, 2]),
+                MappingEntry::new(B, &[3, 4, 5]),
+                MappingEntry::new(X, &[6, 7, 8]),
+                MappingEntry::new(Y, &[9, 10, 11]),
+                // 右摇杆 (IJKL)
+                MappingEntry::new(RightStickUp, &[12, 0]),
+                MappingEntry::new(RightStickDown, &[13, 1]),
+                MappingEntry::new(RightStickLeft, &[14, 2]),
+                MappingEntry::new(RightStickRight, &[15, 3]),
+                // 触发器/肩键：右摇杆动作时不触发
+                MappingEntry::new(Lb, &[0, 15]).unless(StickAxis::Ry),
+                MappingEntry::new(Rb, &[2, 13]).unless(StickAxis::Rx),
+                MappingEntry::new(Lt, &[1, 14]).unless(StickAxis::Rx),
+                MappingEntry::new(Rt, &[3, 12]).unless(StickAxis::Ry),
+            ],
+        }
+    }
+}
+
+impl MappingTable {
+    pub fn store_path() -> PathBuf {
+        PathBuf::from("data/mapping.toml")
+    }
+
+    /// Built-in table for a board with `channel_count` channels: the 16-channel
+    /// default, compact Cyton (8) and Ganglion (4) layouts, or for any other count
+    /// the default entries that fit.
+    pub fn default_for(channel_count: usize) -> Self {
+        use GamepadAction::*;
+        let entries = match channel_count {
+            16 => return Self::default(),
+            8 => vec![
+                MappingEntry::new(LeftStickUp, &[0, 4]),
+                MappingEntry::new(LeftStickDown, &[1, 5]),
+                MappingEntry::new(LeftStickLeft, &[2, 6]),
+                MappingEntry::new(LeftStickRight, &[3, 7]),
+                MappingEntry::new(A, &[0, 1]),
+                MappingEntry::new(B, &[2, 3]),
+                MappingEntry::new(X, &[4, 5]),
+                MappingEntry::new(Y, &[6, 7]),
+            ],
+            4 => vec![
+                MappingEntry::new(LeftStickUp, &[0]),
+                MappingEntry::new(LeftStickDown, &[1]),
+                MappingEntry::new(LeftStickLeft, &[2]),
+                MappingEntry::new(LeftStickRight, &[3]),
+                MappingEntry::new(A, &[0, 1]),
+                MappingEntry::new(B, &[2, 3]),
+            ],
+            n => Self::default()
+                .entries
+                .into_iter()
+                .filter(|e| e.channels.iter().all(|&ch| ch < n))
+                .collect(),
+        };
+        Self { entries }
+    }
+
+    /// Parses and validates a table; every problem is reported, not just the first.
+    pub fn from_toml_str(raw: &str, channel_count: usize) -> Result<Self, Vec<String>> {
+        let table: Self =
+            toml::from_str(raw).map_err(|e| vec![format!("invalid mapping file: {e}")])?;
+        table.validate(channel_count)?;
+        Ok(table)
+    }
+
+    pub fn load(path: &Path, channel_count: usize) -> Result<Self, Vec<String>> {
+        let raw = fs::read_to_string(path)
+            .map_err(|e| vec![format!("cannot read {}: {e}", path.display())])?;
+        Self::from_toml_str(&raw, channel_count)
+    }
+
+    /// Lists every action whose pattern cannot match on a board with `channel_count` channels.
+    pub fn validate(&self, channel_count: usize) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        for entry in &self.entries {
+            if !(entry.threshold_scale.is_finite() && entry.threshold_scale > 0.0) {
+                errors.push(format!(
+                    "{:?}: threshold_scale must be a positive number, got {}",
+                    entry.action, entry.threshold_scale
+                ));
+            }
+            if entry.channels.is_empty() {
+                errors.push(format!("{:?}: pattern has no channels", entry.action));
+            }
+            let missing: Vec<String> = entry
+                .channels
+                .iter()
+                .filter(|&&ch| ch >= channel_count)
+                .map(|ch| ch.to_string())
+                .collect();
+            if !missing.is_empty() {
+                errors.push(format!(
+                    "{:?}: channel {} out of range (board has {} channels, valid 0-{})",
+                    entry.action,
+                    missing.join(", "),
+                    channel_count,
+                    channel_count.saturating_sub(1)
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// `decode_with_confidence` with the same threshold on every channel.
+    #[cfg(test)]
+    pub fn decode(&self, data: &[f64], threshold: f64) -> GamepadState {
+        self.decode_with_confidence(data, &[threshold; 16]).0
+    }
+
+    /// Runs every pattern against `data`; an entry fires when each of its channels
+    /// exceeds its own `thresholds[ch] * threshold_scale`. Conditional entries see the
+    /// sticks already applied. Also returns the confidence of every action that fired:
+    /// the weakest channel's margin over its limit, 0 just above it and 1 at twice it.
+    /// An action fired by several entries keeps its strongest one. Triggers are pulled
+    /// in proportion to that confidence, from `TRIGGER_MIN_PULL` up to fully at twice the limit.
+    pub fn decode_with_confidence(
+        &self,
+        data: &[f64],
+        thresholds: &[f64],
+    ) -> (GamepadState, Vec<(GamepadAction, f32)>) {
+        let mut gp = GamepadState::default();
+        let mut confidence: Vec<(GamepadAction, f32)> = Vec::new();
+        let margin = |entry: &MappingEntry| {
+            // 每个通道相对自身阈值的倍数；未超过阈值记 0
+            let weakest = entry
+                .channels
+                .iter()
+                .map(|&i| {
+                    let limit =
+                        thresholds.get(i).copied().unwrap_or(f64::INFINITY) * entry.threshold_scale;
+                    let v = data.get(i).map(|v| v.abs()).unwrap_or(0.0);
+                    if v > limit {
+                        v / limit
+                    } else {
+                        0.0
+                    }
+                })
+                .fold(f64::INFINITY, f64::min);
+            (!entry.channels.is_empty() && weakest > 0.0)
+                .then(|| ((weakest - 1.0) as f32).clamp(0.0, 1.0))
+        };
+        let mut fire = |entry: &MappingEntry, gp: &mut GamepadState, c: f32| {
+            entry
+                .action
+                .apply(gp, TRIGGER_MIN_PULL + (1.0 - TRIGGER_MIN_PULL) * c);
+            match confidence.iter_mut().find(|(a, _)| *a == entry.action) {
+                Some((_, best)) => *best = best.max(c),
+                None => confidence.push((entry.action, c)),
+            }
+        };
+        for entry in self.entries.iter().filter(|e| e.unless_axis.is_none()) {
+            if let Some(c) = margin(entry) {
+                fire(entry, &mut gp, c);
+            }
+        }
+        for entry in self.entries.iter().filter(|e| e.unless_axis.is_some()) {
+            let blocked = match entry.unless_axis {
+                Some(StickAxis::Rx) => gp.rx != 0.0,
+                Some(StickAxis::Ry) => gp.ry != 0.0,
+                None => false,
+            };
+            if blocked {
+                continue;
+            }
+            if let Some(c) = margin(entry) {
+                fire(entry, &mut gp, c);
+            }
+        }
+        (gp, confidence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_beyond_board_is_reported() {
+        let raw = r#"
+            [[mapping]]
+            action = "a"
+            channels = [0, 20]
+
+            [[mapping]]
+            action = "left_stick_up"
+            channels = [1, 2]
+        "#;
+        let errors = MappingTable::from_toml_str(raw, 8).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("A") && errors[0].contains("channel 20"),
+            "{}",
+            errors[0]
+        );
+        assert!(errors[0].contains("8 channels"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn default_table_suppresses_shoulder_while_stick_moves() {
+        let table = MappingTable::default();
+        assert!(table.validate(16).is_ok());
+        let active = |idx: &[usize]| -> Vec<f64> {
+            (0..16)
+                .map(|i| if idx.contains(&i) { 500.0 } else { 0.0 })
+                .collect()
+        };
+        let gp = table.decode(&active(&[0, 15]), 150.0);
+        assert!(gp.lb);
+        // 12 + 0 moves ry, so LB (needs ry == 0) must stay off.
+        let gp = table.decode(&active(&[0, 15, 12]), 150.0);
+        assert_eq!(gp.ry, 1.0);
+        assert!(!gp.lb);
+    }
+
+    #[test]
+    fn shipped_default_file_matches_the_built_in_table() {
+        let raw = include_str!("../../data/mapping.default.toml");
+        let table = MappingTable::from_toml_str(raw, 16).unwrap();
+        assert_eq!(table, MappingTable::default());
+        let mut data = vec![0.0; 16];
+        for ch in [3, 7, 11, 9, 10] {
+            data[ch] = 500.0;
+        }
+        let gp = table.decode(&data, 150.0);
+        assert_eq!((gp.lx, gp.ly), (1.0, 0.0));
+        assert!(gp.y && !gp.a && !gp.b && !gp.x);
+        assert_eq!((gp.rx, gp.ry), (0.0, 0.0));
+    }
+
+    #[test]
+    fn stick_scale_halves_the_required_amplitude() {
+        let raw = r#"
+            [[mapping]]
+            action = "left_stick_up"
+            channels = [0]
+            threshold_scale = 0.5
+
+            [[mapping]]
+            action = "a"
+            channels = [1]
+        "#;
+        let table = MappingTable::from_toml_str(raw, 2).unwrap();
+        // 60 uV on both channels with a 100 uV threshold: only the stick (limit 50) fires.
+        let gp = table.decode(&[60.0, 60.0], 100.0);
+        assert_eq!(gp.ly, 1.0);
+        assert!(!gp.a);
+        let gp = table.decode(&[60.0, 120.0], 100.0);
+        assert!(gp.a);
+    }
+}
@@ -0,0 +1,257 @@
+// src/engine/replay.rs
+// 确定性回放：录制的原始数据 + 保存的映射/阈值/滤波配置 → 逐采样的解码时间线。
+// 与实时链路同一套钳位、滤波和解码，但不依赖时钟、vJoy 或 UI，改映射后可与旧时间线逐行 diff。
+use super::DEFAULT_SAMPLE_CLAMP_UV;
+use super::{
+    decode_input, FeatureKind, FeatureWindow, GamepadAction, MappingTable, SampleGuard,
+    SimpleFilter, TriggerDebouncer,
+};
+use crate::drivers::{CsvFileSource, ModelizeError, SignalSource};
+use crate::montage::Montage;
+use crate::types::TriggerDebounce;
+use crate::waveform::{filter::FilterChain, FilterKind};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error(transparent)]
+    Recording(#[from] ModelizeError),
+    #[error("invalid mapping: {}", .0.join("; "))]
+    Mapping(Vec<String>),
+}
+
+/// Decode settings a replay runs with; the same recording and config always give the same timeline.
+#[derive(Clone, Debug)]
+pub struct ReplayConfig {
+    /// Per-channel decode thresholds, as `SetThreshold`/`SetChannelThreshold` leave them.
+    pub thresholds: [f64; 16],
+    pub trigger_debounce: TriggerDebounce,
+    /// `None` uses `data/mapping.toml` when present, else the layout's default table.
+    pub mapping_path: Option<PathBuf>,
+    pub mains_hz: f64,
+    pub notch_harmonics: bool,
+    /// Per-channel display filter chains the decoder was linked to, if any.
+    pub decode_filters: Option<Vec<Vec<FilterKind>>>,
+    pub feature: FeatureKind,
+    pub feature_window: usize,
+    pub montage: Montage,
+    pub sample_clamp_uv: f64,
+    /// Recorded raw units to µV: 1 for simulation, `BoardKind::uv_per_unit` for hardware.
+    pub uv_per_unit: f64,
+    /// Overrides the rate inferred from the recording's timestamps.
+    pub sample_rate_hz: Option<f32>,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            thresholds: [150.0; 16],
+            trigger_debounce: TriggerDebounce::default(),
+            mapping_path: None,
+            mains_hz: 50.0,
+            notch_harmonics: false,
+            decode_filters: None,
+            feature: FeatureKind::Instant,
+            feature_window: 1,
+            montage: Montage::default(),
+            sample_clamp_uv: DEFAULT_SAMPLE_CLAMP_UV,
+            uv_per_unit: 1.0,
+            sample_rate_hz: None,
+        }
+    }
+}
+
+impl ReplayConfig {
+    fn mapping(&self) -> Result<MappingTable, ReplayError> {
+        let channels = self.montage.layout.channel_count();
+        let path = match &self.mapping_path {
+            Some(path) => path.clone(),
+            None if MappingTable::store_path().exists() => MappingTable::store_path(),
+            None => return Ok(MappingTable::default_for(channels)),
+        };
+        MappingTable::load(&path, channels).map_err(ReplayError::Mapping)
+    }
+}
+
+/// An action starting (`pressed`) or stopping on one recorded sample.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DecodeEvent {
+    /// Row of the recording, 0 for the first sample.
+    pub sample_index: u64,
+    pub action: GamepadAction,
+    pub pressed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodeTimeline {
+    pub sample_rate_hz: f32,
+    pub samples: u64,
+    pub events: Vec<DecodeEvent>,
+}
+
+impl DecodeTimeline {
+    /// `SampleIndex,Time,Action,State` rows, stable enough to diff between runs.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("SampleIndex,Time,Action,State\n");
+        for e in &self.events {
+            out.push_str(&format!(
+                "{},{:.4},{:?},{}\n",
+                e.sample_index,
+                e.sample_index as f64 / self.sample_rate_hz as f64,
+                e.action,
+                if e.pressed { "on" } else { "off" }
+            ));
+        }
+        out
+    }
+}
+
+/// Feeds a `DataRecorder` CSV sample by sample through the engine's clamp, filter
+/// and decode chain. Dual recordings replay their `RawCh` columns.
+pub fn replay_decode(path: &Path, config: &ReplayConfig) -> Result<DecodeTimeline, ReplayError> {
+    let mut source = CsvFileSource::open(path, 256)?;
+    let sample_rate_hz = config.sample_rate_hz.unwrap_or(source.sample_rate_hz());
+    if sample_rate_hz <= 0.0 {
+        return Err(ModelizeError::InvalidSampleRate.into());
+    }
+    let mapping = config.mapping()?;
+    let mut guard = SampleGuard::new(16, config.sample_clamp_uv);
+    let mut filters = SimpleFilter::new(
+        16,
+        sample_rate_hz as f64,
+        config.mains_hz,
+        config.notch_harmonics,
+    );
+    let mut decode_filters: Option<Vec<FilterChain>> =
+        config.decode_filters.as_ref().map(|kinds| {
+            (0..16)
+                .map(|i| {
+                    kinds
+                        .get(i)
+                        .map(|k| FilterChain::from_kinds(sample_rate_hz, k))
+                        .unwrap_or_else(FilterChain::empty)
+                })
+                .collect()
+        });
+    let mut features = FeatureWindow::new(config.feature, config.feature_window, 16);
+    let mut debouncer = TriggerDebouncer::new(config.trigger_debounce, sample_rate_hz);
+
+    let mut clean = vec![0.0f64; 16];
+    let mut active: Vec<GamepadAction> = Vec::new();
+    let mut events = Vec::new();
+    let mut sample_index = 0u64;
+    while let Some(batch) = source.next_batch()? {
+        let labels = &batch.channel_labels;
+        let dual = labels.iter().any(|l| l.starts_with("RawCh"));
+        let raw_columns: Vec<usize> = (0..labels.len())
+            .filter(|&i| !dual || labels[i].starts_with("RawCh"))
+            .collect();
+        let len = batch.samples.iter().map(Vec::len).min().unwrap_or(0);
+        for idx in 0..len {
+            for (i, v) in clean.iter_mut().enumerate() {
+                let raw = raw_columns
+                    .get(i)
+                    .map_or(0.0, |&c| batch.samples[c][idx] as f64);
+                let raw = guard.admit(i, raw, config.uv_per_unit);
+                *v = filters.process_sample(i, raw) * config.uv_per_unit;
+            }
+            let data = decode_input(
+                &clean,
+                decode_filters.as_mut(),
+                &mut features,
+                &config.montage,
+            );
+            let (gp, confidence) = mapping.decode_with_confidence(&data, &config.thresholds);
+            let (_, confidence) =
+                debouncer.apply(&mapping, &data, &config.thresholds, gp, confidence);
+            let fired: Vec<GamepadAction> =
+                confidence.into_iter().map(|(action, _)| action).collect();
+            for &action in active.iter().filter(|a| !fired.contains(a)) {
+                events.push(DecodeEvent {
+                    sample_index,
+                    action,
+                    pressed: false,
+                });
+            }
+            for &action in fired.iter().filter(|a| !active.contains(a)) {
+                events.push(DecodeEvent {
+                    sample_index,
+                    action,
+                    pressed: true,
+                });
+            }
+            active = fired;
+            sample_index += 1;
+        }
+    }
+    Ok(DecodeTimeline {
+        sample_rate_hz,
+        samples: sample_index,
+        events,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+    use std::fmt::Write;
+
+    #[test]
+    fn replaying_the_same_recording_twice_gives_identical_events() {
+        let dir = std::env::temp_dir().join(format!("neurostick_replay_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // DataRecorder 格式；1-2 s 内通道 0..2 出现 20 Hz 的 500 µV 爆发
+        let mut csv = String::from("Timestamp");
+        for ch in 0..16 {
+            write!(csv, ",Ch{ch}").unwrap();
+        }
+        for i in 0..750 {
+            let t = i as f64 / 250.0;
+            let burst = if (1.0..2.0).contains(&t) {
+                500.0 * (2.0 * PI * 20.0 * t).sin()
+            } else {
+                0.0
+            };
+            write!(csv, "\n{t:.4}").unwrap();
+            for ch in 0..16 {
+                write!(csv, ",{:.2}", if ch < 3 { burst } else { 0.0 }).unwrap();
+            }
+        }
+        let recording = dir.join("training_data_replay.csv");
+        std::fs::write(&recording, csv).unwrap();
+        let mapping = dir.join("mapping.toml");
+        std::fs::write(
+            &mapping,
+            "[[mapping]]\naction = \"a\"\nchannels = [0, 1, 2]\n",
+        )
+        .unwrap();
+        let config = ReplayConfig {
+            mapping_path: Some(mapping),
+            feature: FeatureKind::MeanRectified,
+            feature_window: 25,
+            ..ReplayConfig::default()
+        };
+
+        let first = replay_decode(&recording, &config).unwrap();
+        let second = replay_decode(&recording, &config).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.to_csv(), second.to_csv());
+        assert_eq!(first.samples, 750);
+        let presses: Vec<u64> = first
+            .events
+            .iter()
+            .filter(|e| e.pressed)
+            .map(|e| e.sample_index)
+            .collect();
+        assert_eq!(presses.len(), 1, "{:?}", first.events);
+        assert!((250..300).contains(&presses[0]), "{presses:?}");
+        assert!(first
+            .events
+            .iter()
+            .any(|e| !e.pressed && e.sample_index >= 500));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
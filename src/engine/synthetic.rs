@@ -0,0 +1,192 @@
+// src/engine/synthetic.rs
+// 模拟模式的合成 EEG：每通道粉红噪声 + 起伏的 alpha 节律 + 偶发眨眼伪迹；
+// 动作键只放大对应通道的 EMG 爆发幅度，不再叠加直流。固定种子，演示可复现
+use std::f64::consts::PI;
+
+/// Broadband background per channel, µV.
+const PINK_UV: f64 = 10.0;
+/// Default alpha rhythm amplitude, µV.
+const ALPHA_UV: f64 = 15.0;
+/// EMG burst amplitude per unit of gain, µV; never below 70% of it, so a held key
+/// stays above the default 200 µV threshold.
+const EMG_UV: f64 = 500.0;
+/// Blink peak on the two frontal channels (0 and 1), µV; the rest see a fifth of it.
+const BLINK_UV: f64 = 120.0;
+const BLINK_SECONDS: f64 = 0.3;
+/// Blinks are 3..7 s apart.
+const BLINK_GAP_SECONDS: (f64, f64) = (3.0, 7.0);
+
+struct Channel {
+    /// Paul Kellet's pink-noise filter state.
+    pink: [f64; 7],
+    alpha_hz: f64,
+    alpha_phase: f64,
+}
+
+/// Deterministic multi-channel EEG-like signal generator.
+pub struct SyntheticEeg {
+    sample_rate_hz: f64,
+    rng: u64,
+    channels: Vec<Channel>,
+    /// Peak alpha (8–12 Hz) amplitude, µV; 0 turns the rhythm off.
+    pub alpha_uv: f64,
+    sample: u64,
+    next_blink_at: u64,
+    blink_step: Option<usize>,
+}
+
+impl SyntheticEeg {
+    /// The same `seed` always gives the same signal.
+    pub fn new(channel_count: usize, sample_rate_hz: f64, seed: u64) -> Self {
+        let channels = (0..channel_count)
+            .map(|ch| Channel {
+                pink: [0.0; 7],
+                // 每个通道的 alpha 频率略有不同 (9.5–10.5 Hz)
+                alpha_hz: 9.5 + (ch % 4) as f64 / 3.0,
+                alpha_phase: ch as f64,
+            })
+            .collect();
+        let mut eeg = Self {
+            sample_rate_hz: sample_rate_hz.max(1.0),
+            // xorshift 的状态不能为 0
+            rng: seed.max(1),
+            channels,
+            alpha_uv: ALPHA_UV,
+            sample: 0,
+            next_blink_at: 0,
+            blink_step: None,
+        };
+        eeg.next_blink_at = eeg.blink_gap();
+        eeg
+    }
+
+    /// Uniform in -1..1 (xorshift64*).
+    fn white(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let bits = self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11;
+        bits as f64 / (1u64 << 52) as f64 - 1.0
+    }
+
+    fn blink_gap(&mut self) -> u64 {
+        let (lo, hi) = BLINK_GAP_SECONDS;
+        let secs = lo + (hi - lo) * (self.white() + 1.0) / 2.0;
+        self.sample + (secs * self.sample_rate_hz) as u64
+    }
+
+    /// Fills `out` with the next sample of every channel, in µV. `emg_gain[ch]` scales
+    /// an EMG burst on channel `ch` (0 or missing = none).
+    pub fn next_sample(&mut self, emg_gain: &[f64], out: &mut [f64]) {
+        let t = self.sample as f64 / self.sample_rate_hz;
+        if self.blink_step.is_none() && self.sample >= self.next_blink_at {
+            self.blink_step = Some(0);
+        }
+        let blink_len = ((BLINK_SECONDS * self.sample_rate_hz) as usize).max(2);
+        let blink = self.blink_step.map_or(0.0, |step| {
+            BLINK_UV * (0.5 - 0.5 * (2.0 * PI * step as f64 / blink_len as f64).cos())
+        });
+        for ch in 0..self.channels.len().min(out.len()) {
+            let white = self.white();
+            let p = &mut self.channels[ch].pink;
+            p[0] = 0.99886 * p[0] + white * 0.055_517_9;
+            p[1] = 0.99332 * p[1] + white * 0.075_075_9;
+            p[2] = 0.96900 * p[2] + white * 0.153_852;
+            p[3] = 0.86650 * p[3] + white * 0.310_485_6;
+            p[4] = 0.55000 * p[4] + white * 0.532_952_2;
+            p[5] = -0.7616 * p[5] - white * 0.016_898;
+            let pink = p[..6].iter().sum::<f64>() + p[6] + white * 0.5362;
+            p[6] = white * 0.115_926;
+
+            let c = &mut self.channels[ch];
+            c.alpha_phase += 2.0 * PI * c.alpha_hz / self.sample_rate_hz;
+            // alpha 以约 0.1 Hz 缓慢起伏，像真实的 alpha 纺锤
+            let envelope = 0.7 + 0.3 * (2.0 * PI * 0.1 * t + ch as f64).sin();
+            let alpha = self.alpha_uv * envelope * c.alpha_phase.sin();
+
+            let mut v = PINK_UV * 0.11 * pink + alpha;
+            v += if ch < 2 { blink } else { blink / 5.0 };
+            let gain = emg_gain.get(ch).copied().unwrap_or(0.0);
+            if gain > 0.0 {
+                let (sign, jitter) = (self.white(), self.white());
+                v += gain * EMG_UV * (0.85 + 0.15 * jitter) * sign.signum();
+            }
+            out[ch] = v;
+        }
+        self.blink_step = self.blink_step.and_then(|step| {
+            if step + 1 < blink_len {
+                Some(step + 1)
+            } else {
+                self.next_blink_at = self.blink_gap();
+                None
+            }
+        });
+        self.sample += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::{SpectrumBuilder, TimeSeriesFrame};
+
+    fn frame(eeg: &mut SyntheticEeg, len: usize, emg_gain: &[f64]) -> TimeSeriesFrame {
+        let mut samples: Vec<Vec<f32>> = (0..4).map(|_| Vec::with_capacity(len)).collect();
+        let mut out = [0.0; 4];
+        for _ in 0..len {
+            eeg.next_sample(emg_gain, &mut out);
+            for (ch, v) in samples.iter_mut().zip(out) {
+                ch.push(v as f32);
+            }
+        }
+        TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: (0..4).map(|i| format!("Ch{i}")).collect(),
+            samples,
+            timestamps: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn generated_spectrum_peaks_in_the_alpha_band() {
+        let mut eeg = SyntheticEeg::new(4, 250.0, 7);
+        let spec = SpectrumBuilder::with_size(1024).compute(&frame(&mut eeg, 1024, &[]));
+        let mean = |ch: usize, lo: f32, hi: f32| {
+            let band = spec.band(ch, lo, hi).magnitudes.remove(0);
+            band.iter().sum::<f32>() / band.len() as f32
+        };
+        for ch in 0..4 {
+            let (peak_hz, _) = spec.band(ch, 4.0, 40.0).peak_frequency(0).expect("peak");
+            assert!(
+                (8.0..=12.0).contains(&peak_hz),
+                "ch{ch} peaks at {peak_hz} Hz"
+            );
+            assert!(mean(ch, 8.0, 12.0) > 3.0 * mean(ch, 13.0, 30.0));
+        }
+        // 同一种子复现同一段信号
+        let again = frame(&mut SyntheticEeg::new(4, 250.0, 7), 1024, &[]);
+        assert_eq!(
+            again.samples,
+            frame(&mut SyntheticEeg::new(4, 250.0, 7), 1024, &[]).samples
+        );
+    }
+
+    #[test]
+    fn emg_gain_raises_only_its_channel_above_the_decode_threshold() {
+        let mut eeg = SyntheticEeg::new(4, 250.0, 7);
+        let burst = frame(&mut eeg, 250, &[0.0, 1.0]);
+        let min_abs = |ch: usize| {
+            burst.samples[ch]
+                .iter()
+                .fold(f32::MAX, |m, v| m.min(v.abs()))
+        };
+        let max_abs = |ch: usize| burst.samples[ch].iter().fold(0.0f32, |m, v| m.max(v.abs()));
+        assert!(min_abs(1) > 300.0, "{}", min_abs(1));
+        assert!(
+            max_abs(0) < 100.0 && max_abs(2) < 100.0,
+            "{} {}",
+            max_abs(0),
+            max_abs(2)
+        );
+    }
+}
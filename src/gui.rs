@@ -1,23 +1,44 @@
 // src/gui.rs
+mod keymap;
+mod locale;
+mod save_dialog;
 use crate::assets::APP_ICON_PNG;
+use crate::calibration::{self, CalibrationError, CalibrationProfile, GuidedCapture};
+use crate::config::{AppConfig, WindowGeometry};
 use crate::drivers::pipeline::make_batch;
 use crate::drivers::{
-    cyton_impedance_from_std, cyton_impedances_from_samples, ganglion_display_impedance_kohms,
-    render_spectrum_png, render_waveform_png, FrequencySpectrum, ManualSource, PlotStyle,
-    SignalPipeline, SignalSource, SpectrumBuilder, TimeSeriesFrame,
+    channel_is_railed, cyton_impedance_from_std_with, ganglion_display_impedance_kohms, lead_off,
+    render_overlay_png, render_spectrogram_png, render_spectrum_png, render_waveform_png,
+    CsvFileSource, CytonLeadOff, Detrend, EegBand, FrequencySpectrum, GridSpacing, ImpedanceModel,
+    LeadOffMonitor, ManualSource, ModelizeError, OverlayStyle, PlotStyle, SignalPipeline,
+    SignalSource, Spectrogram, SpectrumBuilder, SpectrumHistory, TimeSeriesFrame, WindowFunction,
 };
-use crate::engine;
+use crate::engine::{self, DecodeTimeline, FeatureKind, ReplayConfig, ReplayError};
+use crate::montage::{Montage, QuickMontage};
+use crate::openbci::BoardKind;
+use crate::recorder::{self, EventAlignment, RecordMode, Rotation};
+use crate::selftest;
 use crate::types::*;
 use crate::visualizer;
 use crate::waveform::{
-    ChannelView, FilterKind, SamplePoint, TimeWindow, WaveformPipeline, WaveformView, YScale,
+    filter::FilterChain, ChannelView, FilterKind, SamplePoint, TimeWindow, WaveformPipeline,
+    WaveformView, YScale,
 };
 use eframe::egui;
 use egui::{Color32, ColorImage, TextureHandle, TextureOptions, Vec2};
-use egui_plot::{Line, Plot, PlotBounds, PlotPoints, Text};
+use egui_plot::{Legend, Line, Plot, PlotBounds, PlotPoints, Text};
+use locale::{Language, UiText};
 use serde::Deserialize;
+use std::net::SocketAddr;
 use std::sync::mpsc::{channel, Receiver, Sender};
-use std::{fs, io::Write, path::PathBuf, time::Instant, time::SystemTime};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs,
+    io::Write,
+    path::PathBuf,
+    time::Instant,
+    time::SystemTime,
+};
 // 引入串口库
 use serialport;
 
@@ -40,21 +61,86 @@ struct BrainModelStatus {
 
 pub struct QnmdSolApp {
     is_connected: bool,
-    is_vjoy_active: bool,
+    /// Engine's auto-reconnect attempt and cap while the board is being re-opened.
+    reconnecting: Option<(u32, u32)>,
+    /// Virtual gamepad backend the engine opened, if any.
+    gamepad_backend: Option<&'static str>,
     is_streaming: bool,
     is_recording: bool,
+    snap_events_to_samples: bool,
+    record_rotation: Rotation,
+    record_sample_index: bool,
+    record_mne_export: bool,
+    record_labels: bool,
+    /// Also write the filtered channels next to the raw ones.
+    record_dual: bool,
+    montage: Montage,
+    sample_clamp_uv: f64,
+    auto_reconnect: bool,
+    /// Engine pushes clean samples to LSL while streaming; starts on if `NEUROSTICK_LSL` is set.
+    lsl_output: bool,
+    osc_output: bool,
+    osc_addr: String,
+    ws_output: bool,
+    ws_addr: String,
+    sim_key_bindings: BTreeMap<SimKey, String>,
+    sim_sample_rate_hz: f32,
+    controller_style: visualizer::ControllerStyle,
+    notch_harmonics: bool,
     connection_mode: ConnectionMode,
     follow_latest: bool,
     waveform_pipeline: Option<WaveformPipeline>,
     waveform_view: Option<WaveformView>,
     waveform_sample_rate_hz: f32,
     waveform_clock: f32,
+    /// Device time that maps to 0 on the waveform axis, and the newest device time ingested.
+    wave_time_origin: Option<f64>,
+    wave_last_ts: Option<f64>,
     waveform_last_len: usize,
     last_frame: Option<TimeSeriesFrame>,
+    /// Recorded CSV to replay into `last_frame` for offline PNG/FFT export.
+    replay_path: String,
+    /// Zero-based channel drawn in the transparent video-overlay strip.
+    overlay_channel: usize,
+    /// Active overlay PNG sequence capture.
+    overlay_sequence: Option<OverlaySequence>,
     last_spectrum: Option<FrequencySpectrum>,
+    spectrum_history: SpectrumHistory,
+    spectrum_history_len: usize,
+    /// Plot one channel over the decode band only.
+    spectrum_focus: bool,
+    spectrum_focus_channel: usize,
+    focus_band_hz: (f32, f32),
+    show_band_snr: bool,
+    snr_band: EegBand,
     wave_png: Option<Vec<u8>>,
+    png_grid_secs: f32,
+    /// Waveform PNG runs the display filters forward and backward (no phase lag).
+    png_zero_phase: bool,
+    export_units: ExportUnits,
     spectrum_png: Option<Vec<u8>>,
+    spectrogram_channel: usize,
+    /// Last rendered spectrogram, kept as PNG for saving and as a texture for display.
+    spectrogram_png: Option<Vec<u8>>,
+    spectrogram_tex: Option<TextureHandle>,
     fft_size: usize,
+    fft_window: WindowFunction,
+    fft_detrend: Detrend,
+    /// Reused across frames so its FFT plans are kept; rebuilt when size, window or detrend change.
+    spectrum_builder: SpectrumBuilder,
+    live_spectrum: bool,
+    live_spectrum_hz: f32,
+    vjoy_rate_limit: bool,
+    vjoy_rate_hz: f32,
+    axis_gains: AxisGains,
+    stick_response: StickResponse,
+    trigger_debounce: TriggerDebounce,
+    decode_feature: FeatureKind,
+    decode_feature_window: usize,
+    /// Last seen native window geometry, saved on exit.
+    window_geometry: WindowGeometry,
+    /// Set once the restored window has been checked against the real monitor.
+    window_clamped: bool,
     view_seconds: f64,
     display_gain: f64,
     vertical_spacing: f64,
@@ -63,10 +149,24 @@ pub struct QnmdSolApp {
     last_gamepad_update: Option<Instant>,
     calib_rest_max: f64,
     calib_act_max: f64,
+    calib_rest_channels: Vec<f64>,
+    calib_act_channels: Vec<f64>,
+    /// Whether the running calibration window is the action (vs. relax) one.
+    calib_action: bool,
+    calib_channel_thresholds: Vec<f64>,
+    /// Step of the running guided calibration, shown as an instruction.
+    guided_phase: Option<CalibrationPhase>,
+    calibration_blend: f64,
+    /// Matching calibration found in `data/calibration.json` on connect, not yet applied.
+    saved_calibration: Option<CalibrationProfile>,
+    reload_calibration: bool,
+    board: BoardKind,
     is_calibrating: bool,
     calib_timer: f32,
     trigger_threshold: f64,
     record_label: String,
+    /// Label of the next `MarkEvent`; empty falls back to `record_label`.
+    event_label: String,
     language: Language,
     has_started: bool,
     selected_tab: ViewTab,
@@ -79,21 +179,70 @@ pub struct QnmdSolApp {
     progress_value: f32,
     signal_sensitivity: f64,
     smooth_alpha: f64,
-    wave_smooth_state: Vec<f64>,
     wave_window_seconds: f64,
     wave_auto_scale: bool,
+    /// Auto Y spans the 1st-99th percentile instead of min/max.
+    wave_robust_scale: bool,
+    /// Band-pass on every waveform lane; `None` shows the broadband signal.
+    wave_band: Option<EegBand>,
     wave_notch_50hz: bool,
+    /// Per-channel 50 Hz notch; channels past the end have none.
+    wave_notch_channels: Vec<bool>,
+    link_filters: bool,
+    /// Mains frequency for the display and decode notches (50 or 60 Hz).
+    mains_hz: f32,
+    report_filter_response: bool,
     wave_fixed_range_uv: f32,
     wave_show_stats: bool,
+    wave_show_index_label: bool,
+    wave_show_rms_label: bool,
+    /// Per-lane alpha/beta share bars computed from the latest FFT window.
+    wave_show_band_bars: bool,
+    wave_label_side: LabelSide,
+    stats_throttle: RefreshThrottle,
+    wave_stats_cache: Vec<Option<String>>,
+    show_correlation: bool,
+    correlation_throttle: RefreshThrottle,
+    correlation: Option<Vec<Vec<f32>>>,
+    trace_width: f32,
+    trace_alpha: f32,
+    session_channel_count: usize,
+    // 冻结/回放：冻结时保留的原始数据，以及从末尾往回的偏移
+    wave_history: Option<TimeSeriesFrame>,
+    frozen_history: Option<TimeSeriesFrame>,
+    wave_frozen: bool,
+    /// Display paused: new frames skip the waveform pipeline while the engine keeps buffering.
+    paused: bool,
+    /// Set on resume so the next frame refills the whole window with the latest data.
+    resume_catch_up: bool,
+    wave_scrub_seconds: f64,
+    spectrum_follows_scrub: bool,
+    channel_zoom: Vec<f32>,
     stream_start: Option<Instant>,
-    total_samples_ingested: usize,
+    drop_meter: DropRateWindow,
+    self_test_on_startup: bool,
     last_data_at: Option<Instant>,
+    show_effective_rate: bool,
+    show_decode_confidence: bool,
+    /// Latest per-action decode confidence; empty while the display is off.
+    decode_confidence: Vec<(engine::GamepadAction, f32)>,
+    stream_rate: Option<StreamRate>,
     resistance_values: Option<Vec<f32>>,
     resistance_labels: Vec<String>,
+    /// Channels pinned at the ADC rail in the last measurement (Cyton math only).
+    resistance_railed: Vec<bool>,
+    impedance_limits_kohms: Vec<f32>,
+    cyton_lead_off: CytonLeadOff,
     resistance_window_seconds: Option<f32>,
     resistance_last_measured: Option<SystemTime>,
+    /// Past impedance measurements, oldest first, capped at `IMPEDANCE_HISTORY_LEN`.
+    impedance_history: VecDeque<(SystemTime, Vec<f32>)>,
     impedance_highlight_idx: usize,
     impedance_last_cycle: Option<Instant>,
+    lead_off_alert: bool,
+    lead_off_beep: bool,
+    lead_off: LeadOffMonitor,
+    lead_off_flash_until: f64,
     // === 新增：端口管理 ===
     available_ports: Vec<String>,
     selected_port: String,
@@ -105,6 +254,9 @@ pub struct QnmdSolApp {
     model_status: Option<BrainModelStatus>,
     model_error: Option<String>,
     model_scores: Option<Vec<f32>>,
+    /// Class names of the engine's classifier, in `model_scores` order.
+    classifier_classes: Vec<String>,
+    classifier_drive: bool,
     mapping_helper_auto: bool,
 }
 impl Default for QnmdSolApp {
@@ -124,24 +276,89 @@ impl Default for QnmdSolApp {
         } else {
             "COM3".to_string()
         };
-        let language = QnmdSolApp::load_language_from_disk().unwrap_or(Language::English);
+        let (config, config_problems) = AppConfig::load_reporting();
+        // 旧版本只把语言写在 last_language.txt，配置里没有时从那里迁移
+        let language = config
+            .language
+            .as_deref()
+            .and_then(Language::from_code)
+            .or_else(QnmdSolApp::load_language_from_disk)
+            .unwrap_or(Language::English);
+        let default_port = if !config.selected_port.is_empty()
+            && (ports.is_empty() || ports.contains(&config.selected_port))
+        {
+            config.selected_port.clone()
+        } else {
+            default_port
+        };
         let mut app = Self {
             is_connected: false,
-            is_vjoy_active: false,
+            reconnecting: None,
+            gamepad_backend: None,
             is_streaming: false,
             is_recording: false,
+            snap_events_to_samples: true,
+            record_rotation: Rotation::Off,
+            record_sample_index: false,
+            record_mne_export: false,
+            record_labels: false,
+            record_dual: false,
+            montage: config.montage.clone(),
+            sample_clamp_uv: config.sample_clamp_uv,
+            auto_reconnect: config.auto_reconnect,
+            lsl_output: std::env::var_os(engine::LSL_ENV_VAR).is_some(),
+            osc_output: false,
+            osc_addr: config.osc_addr.clone(),
+            ws_output: false,
+            ws_addr: config.ws_addr.clone(),
+            sim_key_bindings: config.sim_key_bindings.clone(),
+            sim_sample_rate_hz: config.sim_sample_rate_hz,
+            controller_style: config.controller_style,
+            notch_harmonics: false,
             connection_mode: ConnectionMode::Hardware,
             follow_latest: true,
             waveform_pipeline: None,
             waveform_view: None,
             waveform_sample_rate_hz: 0.0,
             waveform_clock: 0.0,
+            wave_time_origin: None,
+            wave_last_ts: None,
             waveform_last_len: 0,
             last_frame: None,
+            replay_path: String::new(),
+            overlay_channel: 0,
+            overlay_sequence: None,
             last_spectrum: None,
+            spectrum_history: SpectrumHistory::new(config.spectrum_history_len),
+            spectrum_history_len: config.spectrum_history_len,
+            spectrum_focus: false,
+            spectrum_focus_channel: 0,
+            focus_band_hz: config.focus_band_hz,
+            show_band_snr: config.show_band_snr,
+            snr_band: config.snr_band,
             wave_png: None,
+            png_grid_secs: 1.0,
+            png_zero_phase: true,
+            export_units: ExportUnits::RawMicrovolts,
             spectrum_png: None,
-            fft_size: 256,
+            spectrogram_channel: 0,
+            spectrogram_png: None,
+            spectrogram_tex: None,
+            fft_size: config.fft_size.clamp(32, 1024),
+            fft_window: WindowFunction::default(),
+            fft_detrend: Detrend::default(),
+            spectrum_builder: SpectrumBuilder::with_size(config.fft_size.clamp(32, 1024)),
+            live_spectrum: config.live_spectrum,
+            live_spectrum_hz: config.live_spectrum_hz,
+            vjoy_rate_limit: config.vjoy_rate_limit,
+            vjoy_rate_hz: config.vjoy_rate_hz,
+            axis_gains: config.axis_gains,
+            stick_response: config.stick_response,
+            trigger_debounce: config.trigger_debounce,
+            decode_feature: config.decode_feature,
+            decode_feature_window: config.decode_feature_window,
+            window_geometry: config.window.clone(),
+            window_clamped: false,
             view_seconds: 30.0,
             display_gain: 0.35,
             vertical_spacing: 420.0,
@@ -150,35 +367,87 @@ impl Default for QnmdSolApp {
             last_gamepad_update: None,
             calib_rest_max: 0.0,
             calib_act_max: 0.0,
+            calib_rest_channels: Vec::new(),
+            calib_act_channels: Vec::new(),
+            calib_action: false,
+            guided_phase: None,
+            calibration_blend: config.calibration_blend,
+            calib_channel_thresholds: Vec::new(),
+            saved_calibration: None,
+            reload_calibration: config.reload_calibration,
+            board: config.board,
             is_calibrating: false,
             calib_timer: 0.0,
             selected_tab: ViewTab::Waveform,
             log_messages: vec![],
             trigger_threshold: 200.0,
             record_label: language.default_record_label().to_owned(),
+            event_label: String::new(),
             language,
             has_started: false,
-            theme_dark: false,
+            theme_dark: config.theme_dark,
             icon_tex: None,
             progress_label: None,
             progress_value: 0.0,
-            signal_sensitivity: 1.0,
-            smooth_alpha: 0.18,
-            wave_smooth_state: Vec::new(),
-            wave_window_seconds: 30.0,
+            signal_sensitivity: config.signal_sensitivity,
+            smooth_alpha: config.smooth_alpha.clamp(0.0, 1.0),
+            wave_window_seconds: config.wave_window_seconds,
             wave_auto_scale: false,
-            wave_notch_50hz: false,
+            wave_robust_scale: true,
+            wave_band: config.wave_band,
+            wave_notch_50hz: config.wave_notch_50hz,
+            wave_notch_channels: if config.wave_notch_channels.is_empty() {
+                vec![config.wave_notch_50hz; config.last_channel_count]
+            } else {
+                config.wave_notch_channels.clone()
+            },
+            link_filters: config.link_filters,
+            mains_hz: config.mains_hz,
+            report_filter_response: config.report_filter_response,
             wave_fixed_range_uv: 200.0,
             wave_show_stats: true,
+            wave_show_index_label: true,
+            wave_show_rms_label: true,
+            wave_show_band_bars: false,
+            wave_label_side: LabelSide::Left,
+            stats_throttle: RefreshThrottle::new(config.stats_refresh_hz),
+            wave_stats_cache: Vec::new(),
+            show_correlation: config.show_correlation,
+            correlation_throttle: RefreshThrottle::new(2.0),
+            correlation: None,
+            trace_width: config.trace_width,
+            trace_alpha: config.trace_alpha,
+            session_channel_count: config.last_channel_count.max(1),
+            wave_history: None,
+            frozen_history: None,
+            wave_frozen: false,
+            paused: false,
+            resume_catch_up: false,
+            wave_scrub_seconds: 0.0,
+            spectrum_follows_scrub: config.spectrum_follows_scrub,
+            channel_zoom: config.channel_zoom,
             stream_start: None,
-            total_samples_ingested: 0,
+            drop_meter: DropRateWindow::new(config.drop_rate_window_secs),
+            self_test_on_startup: config.self_test_on_startup,
             last_data_at: None,
+            show_effective_rate: config.show_effective_rate,
+            show_decode_confidence: config.show_decode_confidence,
+            decode_confidence: Vec::new(),
+            stream_rate: None,
             resistance_values: None,
             resistance_labels: Vec::new(),
+            resistance_railed: Vec::new(),
+            impedance_limits_kohms: config.impedance_limits_kohms.clone(),
+            cyton_lead_off: config.cyton_lead_off,
             resistance_window_seconds: None,
             resistance_last_measured: None,
+            impedance_history: VecDeque::new(),
             impedance_highlight_idx: 0,
             impedance_last_cycle: None,
+            lead_off_alert: config.lead_off_alert,
+            lead_off_beep: config.lead_off_beep,
+            lead_off: LeadOffMonitor::new(config.lead_off_debounce_secs),
+            lead_off_flash_until: 0.0,
             rx,
             tx_cmd,
             // === 初始化端口字段 ===
@@ -190,9 +459,53 @@ impl Default for QnmdSolApp {
             model_status: None,
             model_error: None,
             model_scores: None,
+            classifier_classes: Vec::new(),
+            classifier_drive: false,
             mapping_helper_auto: false,
         };
+        app.tx_cmd
+            .send(GuiCommand::SetMontage(app.montage.clone()))
+            .ok();
+        app.tx_cmd
+            .send(GuiCommand::SetSampleClamp(app.sample_clamp_uv))
+            .ok();
+        app.send_live_spectrum_config();
+        app.send_decode_filters();
+        app.tx_cmd
+            .send(GuiCommand::SetMainsFrequency(app.mains_hz as f64))
+            .ok();
+        app.send_vjoy_rate();
+        app.tx_cmd
+            .send(GuiCommand::SetAxisGains(app.axis_gains))
+            .ok();
+        app.tx_cmd
+            .send(GuiCommand::SetStickResponse(app.stick_response))
+            .ok();
+        app.tx_cmd
+            .send(GuiCommand::SetTriggerDebounce(app.trigger_debounce))
+            .ok();
+        app.tx_cmd
+            .send(GuiCommand::SetDecodeConfidence(app.show_decode_confidence))
+            .ok();
+        app.send_decode_feature();
+        app.tx_cmd
+            .send(GuiCommand::SetAutoReconnect(app.auto_reconnect))
+            .ok();
+        app.tx_cmd
+            .send(GuiCommand::SetSimSampleRate(app.sim_sample_rate_hz))
+            .ok();
+        for problem in config_problems {
+            let msg = match app.language {
+                Language::English => format!("Setting reset to default: {problem}"),
+                Language::Chinese => format!("设置项无法读取，已恢复默认：{problem}"),
+            };
+            app.log(&msg);
+        }
+        app.load_startup_calibration();
         app.autoload_model();
+        if app.self_test_on_startup {
+            app.run_self_test();
+        }
         app
     }
 }
@@ -242,6 +555,39 @@ impl QnmdSolApp {
         }
         (c_railed, "Railed")
     }
+    /// Status for one grid row: a per-channel limit (kΩ, 0 = none) replaces the
+    /// global bands, so e.g. an ear reference can be judged more leniently.
+    fn channel_impedance_status(
+        value_ohms: f32,
+        limits_kohms: &[f32],
+        row: usize,
+        lang: Language,
+    ) -> (Color32, &'static str) {
+        let limit = limits_kohms.get(row).copied().filter(|&k| k > 0.0);
+        let global = Self::impedance_status(value_ohms, lang);
+        let Some(limit_kohms) = limit else {
+            return global;
+        };
+        if global.1 == Self::impedance_status(f32::NAN, lang).1 {
+            return global;
+        }
+        if value_ohms <= limit_kohms * 1000.0 {
+            // Same palette as the global "good"/"poor" bands
+            let (color, _) = Self::impedance_status(0.0, lang);
+            let label = match lang {
+                Language::English => "Within channel limit",
+                Language::Chinese => "在通道阈值内",
+            };
+            (color, label)
+        } else {
+            let (color, _) = Self::impedance_status(5_000_000.0, lang);
+            let label = match lang {
+                Language::English => "Over channel limit",
+                Language::Chinese => "超出通道阈值",
+            };
+            (color, label)
+        }
+    }
     fn apply_theme(&self, ctx: &egui::Context) {
         if self.theme_dark {
             let visuals = egui::Visuals::dark();
@@ -321,6 +667,25 @@ impl QnmdSolApp {
             bool_text(self.is_recording, self.language)
         )?;
         writeln!(f, "{port_label}: {}", self.selected_port)?;
+        if self.report_filter_response {
+            let sr = if self.waveform_sample_rate_hz > 0.0 {
+                self.waveform_sample_rate_hz
+            } else {
+                250.0
+            };
+            let channels = self.session_channel_count.max(1);
+            write!(
+                f,
+                "{}",
+                filter_response_report(
+                    &self.wave_notch_channels,
+                    self.wave_band,
+                    channels,
+                    sr,
+                    self.mains_hz
+                )
+            )?;
+        }
         writeln!(f, "{}", self.text(UiText::ReportLogs))?;
         for msg in &self.log_messages {
             writeln!(f, "  {msg}")?;
@@ -382,35 +747,132 @@ impl QnmdSolApp {
     fn language_store_path() -> PathBuf {
         PathBuf::from("data/last_language.txt")
     }
+    /// Language saved by versions that predate `AppConfig::language`.
     fn load_language_from_disk() -> Option<Language> {
-        let path = Self::language_store_path();
-        if let Ok(raw) = fs::read_to_string(path) {
-            match raw.trim() {
-                "zh" | "cn" => Some(Language::Chinese),
-                "en" => Some(Language::English),
-                _ => None,
-            }
-        } else {
-            None
-        }
+        let raw = fs::read_to_string(Self::language_store_path()).ok()?;
+        Language::from_code(&raw)
+    }
+    fn save_config(&self) {
+        let config = AppConfig {
+            language: Some(self.language.code().to_owned()),
+            theme_dark: self.theme_dark,
+            selected_port: self.selected_port.clone(),
+            wave_window_seconds: self.wave_window_seconds,
+            signal_sensitivity: self.signal_sensitivity,
+            smooth_alpha: self.smooth_alpha,
+            wave_band: self.wave_band,
+            wave_notch_50hz: self.wave_notch_50hz,
+            wave_notch_channels: self.wave_notch_channels.clone(),
+            fft_size: self.fft_size,
+            trace_width: self.trace_width,
+            trace_alpha: self.trace_alpha,
+            last_channel_count: self.session_channel_count,
+            spectrum_follows_scrub: self.spectrum_follows_scrub,
+            channel_zoom: self.channel_zoom.clone(),
+            drop_rate_window_secs: self.drop_meter.window_secs(),
+            show_effective_rate: self.show_effective_rate,
+            show_decode_confidence: self.show_decode_confidence,
+            self_test_on_startup: self.self_test_on_startup,
+            stats_refresh_hz: self.stats_throttle.rate_hz(),
+            lead_off_alert: self.lead_off_alert,
+            lead_off_beep: self.lead_off_beep,
+            lead_off_debounce_secs: self.lead_off.debounce_secs(),
+            montage: self.montage.clone(),
+            show_correlation: self.show_correlation,
+            impedance_limits_kohms: self.impedance_limits_kohms.clone(),
+            cyton_lead_off: self.cyton_lead_off,
+            sample_clamp_uv: self.sample_clamp_uv,
+            auto_reconnect: self.auto_reconnect,
+            osc_addr: self.osc_addr.clone(),
+            ws_addr: self.ws_addr.clone(),
+            sim_key_bindings: self.sim_key_bindings.clone(),
+            sim_sample_rate_hz: self.sim_sample_rate_hz,
+            calibration_blend: self.calibration_blend,
+            controller_style: self.controller_style,
+            link_filters: self.link_filters,
+            mains_hz: self.mains_hz,
+            report_filter_response: self.report_filter_response,
+            vjoy_rate_limit: self.vjoy_rate_limit,
+            vjoy_rate_hz: self.vjoy_rate_hz,
+            axis_gains: self.axis_gains,
+            stick_response: self.stick_response,
+            trigger_debounce: self.trigger_debounce,
+            reload_calibration: self.reload_calibration,
+            board: self.board,
+            decode_feature: self.decode_feature,
+            decode_feature_window: self.decode_feature_window,
+            live_spectrum: self.live_spectrum,
+            live_spectrum_hz: self.live_spectrum_hz,
+            spectrum_history_len: self.spectrum_history_len,
+            focus_band_hz: self.focus_band_hz,
+            show_band_snr: self.show_band_snr,
+            snr_band: self.snr_band,
+            window: self.window_geometry.clone(),
+        };
+        let _ = config.save();
     }
-    fn persist_language(&self) {
-        let path = Self::language_store_path();
-        if let Some(parent) = path.parent() {
-            let _ = fs::create_dir_all(parent);
+    fn run_self_test(&mut self) {
+        let checks = selftest::run();
+        let failed = checks.iter().filter(|c| !c.passed).count();
+        for check in &checks {
+            let mark = if check.passed { "✅" } else { "❌" };
+            self.log(&format!("{} {}: {}", mark, check.name, check.detail));
         }
-        let code = match self.language {
-            Language::English => "en",
-            Language::Chinese => "zh",
+        let summary = match (self.language, failed) {
+            (Language::English, 0) => "Self-test passed".to_owned(),
+            (Language::English, n) => format!("Self-test FAILED ({n} checks)"),
+            (Language::Chinese, 0) => "自检通过".to_owned(),
+            (Language::Chinese, n) => format!("自检失败（{n} 项）"),
         };
-        let _ = fs::write(path, code);
+        self.log(&summary);
     }
     fn set_language(&mut self, lang: Language) {
         if self.language != lang {
             self.language = lang;
             self.record_label = self.language.default_record_label().to_owned();
-            self.persist_language();
+            self.save_config();
+        }
+    }
+    /// Records the window geometry for the next launch. On the first frame that
+    /// reports a monitor, pulls a window restored off-screen back onto it.
+    fn track_window_geometry(&mut self, ctx: &egui::Context) {
+        let (monitor, outer, inner, maximized) = ctx.input(|i| {
+            let vp = i.viewport();
+            (vp.monitor_size, vp.outer_rect, vp.inner_rect, vp.maximized)
+        });
+        let geometry = &mut self.window_geometry;
+        if let Some(maximized) = maximized {
+            geometry.maximized = maximized;
+        }
+        if !geometry.maximized {
+            if let Some(inner) = inner {
+                geometry.size = [inner.width(), inner.height()];
+            }
+            if let Some(outer) = outer {
+                geometry.position = Some([outer.min.x, outer.min.y]);
+            }
+        }
+        let Some(monitor) = monitor else {
+            return;
+        };
+        let monitor = [monitor.x, monitor.y];
+        let origin = outer.and_then(|outer| monitor_origin(outer.center(), ctx.pixels_per_point()));
+        if !self.window_clamped {
+            self.window_clamped = true;
+            let clamped = geometry.clamped_to(origin, monitor);
+            if !geometry.maximized && clamped.position != geometry.position {
+                if let Some([x, y]) = clamped.position {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(egui::pos2(x, y)));
+                }
+            }
+            if !geometry.maximized && clamped.size != geometry.size {
+                let [w, h] = clamped.size;
+                ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(w, h)));
+            }
+            *geometry = clamped;
         }
+        geometry.monitor_size = Some(monitor);
+        geometry.monitor_origin = origin;
     }
     fn ensure_icon_texture(&mut self, ctx: &egui::Context) {
         if self.icon_tex.is_some() {
@@ -450,27 +912,565 @@ impl QnmdSolApp {
         ));
     }
     fn apply_waveform_pipeline_config(&mut self) {
-        if let Some(pipe) = &mut self.waveform_pipeline {
-            let y_scale = if self.wave_auto_scale {
-                YScale::Auto
-            } else {
-                YScale::FixedMicrovolts(self.wave_fixed_range_uv.max(10.0))
+        if let Some(mut pipe) = self.waveform_pipeline.take() {
+            self.configure_pipeline(&mut pipe);
+            self.waveform_pipeline = Some(pipe);
+        }
+    }
+    fn configure_pipeline(&self, pipe: &mut WaveformPipeline) {
+        let y_scale = if self.wave_auto_scale && self.wave_robust_scale {
+            YScale::AutoQuantile {
+                low: 0.01,
+                high: 0.99,
+            }
+        } else if self.wave_auto_scale {
+            YScale::Auto
+        } else {
+            YScale::FixedMicrovolts(self.wave_fixed_range_uv.max(10.0))
+        };
+        pipe.set_global_y_scale(y_scale);
+        for idx in 0..pipe.channel_count() {
+            pipe.set_channel_filters(idx, self.lane_filter_kinds(idx));
+        }
+        self.montage.apply_to_view(pipe);
+    }
+    /// Everything the live pipeline runs on one lane: its display filters, then smoothing.
+    fn lane_filter_kinds(&self, idx: usize) -> Vec<FilterKind> {
+        let mut kinds = channel_filters(
+            &self.wave_notch_channels,
+            idx,
+            self.mains_hz,
+            self.wave_band,
+        );
+        kinds.extend(smoothing_filter(self.smooth_alpha));
+        kinds
+    }
+    /// Simulation key bindings: one key picker per input slot, plus a reset to defaults.
+    fn show_sim_key_editor(&mut self, ui: &mut egui::Ui) {
+        let (title, hint, reset) = (
+            self.text(UiText::SimKeyBindings),
+            self.text(UiText::SimKeyBindingsHint),
+            self.text(UiText::ResetKeyBindings),
+        );
+        egui::CollapsingHeader::new(title)
+            .id_source("sim_key_bindings")
+            .show(ui, |ui| {
+                ui.label(hint);
+                egui::Grid::new("sim_key_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for slot in SimKey::ALL {
+                            ui.label(slot.default_key());
+                            let current = keymap::bound_key(&self.sim_key_bindings, slot);
+                            let mut key = current;
+                            egui::ComboBox::from_id_source(("sim_key", slot))
+                                .selected_text(key.name())
+                                .show_ui(ui, |ui| {
+                                    for option in keymap::BINDABLE_KEYS {
+                                        ui.selectable_value(&mut key, option, option.name());
+                                    }
+                                });
+                            if key != current {
+                                if key.name() == slot.default_key() {
+                                    self.sim_key_bindings.remove(&slot);
+                                } else {
+                                    self.sim_key_bindings.insert(slot, key.name().to_owned());
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if ui.button(reset).clicked() {
+                    self.sim_key_bindings.clear();
+                }
+            });
+    }
+    /// Starts OSC output at the typed address, or stops it when unticked.
+    fn apply_osc_output(&mut self) {
+        if !self.osc_output {
+            self.tx_cmd.send(GuiCommand::DisableOsc).ok();
+            return;
+        }
+        let text = self.osc_addr.clone();
+        match self.parse_output_addr("OSC", &text) {
+            Some(addr) => {
+                self.tx_cmd.send(GuiCommand::EnableOsc(addr)).ok();
+            }
+            None => self.osc_output = false,
+        }
+    }
+    /// Starts the WebSocket server on the typed address, or stops it when unticked.
+    fn apply_ws_output(&mut self) {
+        if !self.ws_output {
+            self.tx_cmd.send(GuiCommand::DisableWebSocket).ok();
+            return;
+        }
+        let text = self.ws_addr.clone();
+        match self.parse_output_addr("WebSocket", &text) {
+            Some(addr) => {
+                self.tx_cmd.send(GuiCommand::EnableWebSocket(addr)).ok();
+            }
+            None => self.ws_output = false,
+        }
+    }
+    /// Drops a marker into the active recording, labelled `event_label` or else `record_label`.
+    fn mark_event(&mut self) {
+        let label = match self.event_label.trim() {
+            "" => self.record_label.clone(),
+            typed => typed.to_owned(),
+        };
+        self.tx_cmd.send(GuiCommand::MarkEvent(label)).ok();
+    }
+    /// `host:port` typed for an output; logs and returns `None` when it does not parse.
+    fn parse_output_addr(&mut self, what: &str, text: &str) -> Option<SocketAddr> {
+        let parsed = text.trim().parse::<SocketAddr>().ok();
+        if parsed.is_none() {
+            let msg = match self.language {
+                Language::English => format!("Invalid {what} address: {text}"),
+                Language::Chinese => format!("{what} 地址无效: {text}"),
             };
-            pipe.set_global_y_scale(y_scale);
-            let filters = if self.wave_notch_50hz {
-                vec![FilterKind::Notch {
-                    freq_hz: 50.0,
-                    q: 35.0,
-                }]
-            } else {
-                Vec::new()
+            self.log(&msg);
+        }
+        parsed
+    }
+    /// Switches board layout in one step; the engine rebuilds its buffers and
+    /// mapping, so the local waveform state starts over too.
+    fn set_channel_layout(&mut self, layout: QuickMontage) {
+        self.montage.layout = layout;
+        self.tx_cmd
+            .send(GuiCommand::SetMontage(self.montage.clone()))
+            .ok();
+        self.clear_history();
+        self.resistance_values = None;
+        self.impedance_history.clear();
+        let msg = match self.language {
+            Language::English => format!(
+                "Layout: {} channels ({})",
+                layout.channel_count(),
+                layout.label()
+            ),
+            Language::Chinese => format!(
+                "通道布局：{} 通道（{}）",
+                layout.channel_count(),
+                layout.label()
+            ),
+        };
+        self.log(&msg);
+    }
+    /// Clean slate without disconnecting: engine buffers plus every local
+    /// buffer, drop stats and impedance result.
+    fn clear_history(&mut self) {
+        self.tx_cmd.send(GuiCommand::ClearHistory).ok();
+        self.waveform_pipeline = None;
+        self.waveform_view = None;
+        self.wave_history = None;
+        self.set_frozen(false);
+        self.paused = false;
+        self.resume_catch_up = false;
+        self.last_frame = None;
+        self.last_spectrum = None;
+        self.spectrum_history.clear();
+        self.waveform_last_len = 0;
+        self.waveform_clock = 0.0;
+        self.wave_time_origin = None;
+        self.wave_last_ts = None;
+        self.drop_meter.clear();
+        self.last_data_at = None;
+        self.stream_start = self.is_streaming.then(Instant::now);
+        self.resistance_values = None;
+        self.resistance_window_seconds = None;
+        self.resistance_last_measured = None;
+        self.impedance_highlight_idx = 0;
+        self.impedance_last_cycle = None;
+        self.lead_off.reset();
+    }
+    /// Watches the live window for channels that become railed and raises the alert.
+    fn check_lead_off(&mut self, now: f64) {
+        if !self.lead_off_alert || !self.is_streaming {
+            return;
+        }
+        let Some(frame) = self.last_frame.as_ref() else {
+            return;
+        };
+        let window = (frame.sample_rate_hz * 0.5).ceil() as usize;
+        let railed: Vec<bool> = frame
+            .samples
+            .iter()
+            .map(|ch| lead_off::is_railed(&ch[ch.len().saturating_sub(window)..]))
+            .collect();
+        let fired = self.lead_off.update(now, &railed);
+        if fired.is_empty() {
+            return;
+        }
+        let names: Vec<String> = fired.iter().map(|i| format!("Ch{}", i + 1)).collect();
+        let msg = match self.language {
+            Language::English => format!("⚠️ Lead-off: {}", names.join(", ")),
+            Language::Chinese => format!("⚠️ 电极脱落：{}", names.join(", ")),
+        };
+        self.log(&msg);
+        self.lead_off_flash_until = now + 3.0;
+        if self.lead_off_beep {
+            alert_beep();
+        }
+    }
+    /// Rebuilds the displayed view: the live pipeline, or the scrubbed window when frozen.
+    fn refresh_waveform_view(&mut self) {
+        if !self.wave_frozen {
+            if let Some(pipe) = &self.waveform_pipeline {
+                self.waveform_view = Some(pipe.view());
+            }
+            return;
+        }
+        let Some(history) = self.frozen_history.as_ref() else {
+            return;
+        };
+        let sr = history.sample_rate_hz;
+        let window_len = (self.wave_window_seconds * sr as f64).ceil() as usize;
+        let offset = (self.wave_scrub_seconds * sr as f64).round() as usize;
+        let window = history_slice(history, offset, window_len);
+        let mut pipe = WaveformPipeline::new(window.samples.len(), sr);
+        pipe.set_time_window(TimeWindow::new(self.wave_window_seconds as f32));
+        self.configure_pipeline(&mut pipe);
+        pipe.ingest_block(0.0, &window.samples);
+        self.waveform_view = Some(pipe.view());
+        if self.spectrum_follows_scrub {
+            self.last_spectrum = self.spectrum_source().map(|f| self.compute_spectrum(&f));
+        }
+    }
+    fn set_frozen(&mut self, frozen: bool) {
+        self.wave_frozen = frozen;
+        self.wave_scrub_seconds = 0.0;
+        self.frozen_history = if frozen {
+            self.wave_history.clone()
+        } else {
+            None
+        };
+        self.refresh_waveform_view();
+    }
+    /// Frame the spectrum is computed from: the on-screen scrub point when frozen
+    /// (and enabled), otherwise the latest engine snapshot.
+    fn spectrum_source(&self) -> Option<TimeSeriesFrame> {
+        match self.frozen_history.as_ref() {
+            Some(history) if self.wave_frozen && self.spectrum_follows_scrub => {
+                let sr = history.sample_rate_hz as f64;
+                let offset = (self.wave_scrub_seconds * sr).round() as usize;
+                Some(history_slice(history, offset, self.fft_size))
+            }
+            _ => self.last_frame.clone(),
+        }
+    }
+    /// Hands the display filter chains to the engine's decoder when linked.
+    fn send_decode_filters(&self) {
+        self.tx_cmd
+            .send(GuiCommand::SetDecodeFilters(self.decode_filter_kinds()))
+            .ok();
+    }
+    fn decode_filter_kinds(&self) -> Option<Vec<Vec<FilterKind>>> {
+        self.link_filters.then(|| {
+            (0..self.session_channel_count.max(16))
+                .map(|idx| {
+                    channel_filters(
+                        &self.wave_notch_channels,
+                        idx,
+                        self.mains_hz,
+                        self.wave_band,
+                    )
+                })
+                .collect()
+        })
+    }
+    /// Replays the recording in the path box through the current decode settings and
+    /// writes the decode timeline next to it as `<name>.decode.csv`, ready to diff.
+    fn replay_decode_timeline(&self) -> Result<(PathBuf, DecodeTimeline), ReplayError> {
+        let path = PathBuf::from(self.replay_path.trim());
+        let config = ReplayConfig {
+            thresholds: self.decode_thresholds(),
+            trigger_debounce: self.trigger_debounce,
+            mains_hz: self.mains_hz as f64,
+            notch_harmonics: self.notch_harmonics,
+            decode_filters: self.decode_filter_kinds(),
+            feature: self.decode_feature,
+            feature_window: self.decode_feature_window,
+            montage: self.montage.clone(),
+            sample_clamp_uv: self.sample_clamp_uv,
+            uv_per_unit: match self.connection_mode {
+                ConnectionMode::Hardware => self.board.uv_per_unit(),
+                ConnectionMode::Simulation => 1.0,
+            },
+            ..ReplayConfig::default()
+        };
+        let timeline = engine::replay_decode(&path, &config)?;
+        let out = path.with_extension("decode.csv");
+        fs::write(&out, timeline.to_csv()).map_err(ModelizeError::from)?;
+        Ok((out, timeline))
+    }
+    fn send_vjoy_rate(&self) {
+        let rate = self.vjoy_rate_limit.then_some(self.vjoy_rate_hz);
+        self.tx_cmd.send(GuiCommand::SetVJoyRate(rate)).ok();
+    }
+    fn send_decode_feature(&self) {
+        self.tx_cmd
+            .send(GuiCommand::SetDecodeFeature(
+                self.decode_feature,
+                self.decode_feature_window,
+            ))
+            .ok();
+    }
+    fn send_live_spectrum_config(&self) {
+        let cfg = self.live_spectrum.then_some(LiveSpectrumConfig {
+            fft_size: self.fft_size,
+            rate_hz: self.live_spectrum_hz,
+        });
+        self.tx_cmd.send(GuiCommand::SetLiveSpectrum(cfg)).ok();
+    }
+    /// `frame` in the selected export units.
+    fn export_frame(&self, frame: TimeSeriesFrame) -> TimeSeriesFrame {
+        match self.export_units {
+            ExportUnits::RawMicrovolts => frame,
+            ExportUnits::Display => {
+                let lanes: Vec<Vec<FilterKind>> = (0..frame.samples.len())
+                    .map(|idx| self.lane_filter_kinds(idx))
+                    .collect();
+                display_units_frame(
+                    &frame,
+                    &lanes,
+                    self.display_gain * self.signal_sensitivity,
+                    &self.channel_zoom,
+                )
+            }
+        }
+    }
+    /// Replays a recorded CSV through a `SignalPipeline` and keeps its last window
+    /// as the frame the PNG/FFT exports use. Returns the kept window length and the rate.
+    fn load_recording(&mut self) -> Result<(f32, f32), ModelizeError> {
+        let source = CsvFileSource::open(self.replay_path.trim(), 256)?;
+        let sample_rate_hz = source.sample_rate_hz();
+        let mut pipeline = SignalPipeline::new(source, self.wave_window_seconds as f32);
+        while pipeline.pump_once()?.is_some() {}
+        let frame = pipeline.latest_frame()?;
+        self.last_spectrum = Some(self.compute_spectrum(&frame));
+        let seconds = frame.duration_seconds();
+        self.last_frame = Some(frame);
+        Ok((seconds, sample_rate_hz))
+    }
+    fn render_overlay(&self) -> Result<Vec<u8>, ModelizeError> {
+        let frame = self
+            .last_frame
+            .as_ref()
+            .ok_or_else(|| ModelizeError::Plot("no frame to render".into()))?;
+        let style = OverlayStyle {
+            window_seconds: self.wave_window_seconds as f32,
+            ..OverlayStyle::default()
+        };
+        render_overlay_png(frame, self.overlay_channel, &style)
+    }
+    /// Saves one transparent overlay PNG of the selected channel to `data/`.
+    fn save_overlay_png(&self) -> Result<PathBuf, ModelizeError> {
+        let png = self.render_overlay()?;
+        let dir = PathBuf::from("data");
+        fs::create_dir_all(&dir)?;
+        let ts = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("overlay_{ts}_ch{}.png", self.overlay_channel + 1));
+        fs::write(&path, png)?;
+        Ok(path)
+    }
+    /// Starts or stops writing numbered overlay frames at `OVERLAY_SEQUENCE_FPS`.
+    fn toggle_overlay_sequence(&mut self) {
+        if let Some(seq) = self.overlay_sequence.take() {
+            let msg = match self.language {
+                Language::English => format!(
+                    "Overlay sequence: {} frames in {}",
+                    seq.frames,
+                    seq.dir.display()
+                ),
+                Language::Chinese => {
+                    format!("叠加序列：{} 帧，保存在 {}", seq.frames, seq.dir.display())
+                }
             };
-            for idx in 0..pipe.channel_count() {
-                pipe.set_channel_enabled(idx, true);
-                pipe.set_channel_filters(idx, filters.clone());
+            self.log(&msg);
+            return;
+        }
+        let ts = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let dir = PathBuf::from("data").join(format!("overlay_{ts}"));
+        match fs::create_dir_all(&dir) {
+            Ok(()) => {
+                self.overlay_sequence = Some(OverlaySequence {
+                    dir,
+                    frames: 0,
+                    throttle: RefreshThrottle::new(OVERLAY_SEQUENCE_FPS),
+                })
+            }
+            Err(e) => self.log(&format!("{}: {e}", self.text(UiText::OverlaySequence))),
+        }
+    }
+    fn capture_overlay_frame(&mut self, now: f64) {
+        let Some(mut seq) = self.overlay_sequence.take() else {
+            return;
+        };
+        if !seq.throttle.ready(now) {
+            self.overlay_sequence = Some(seq);
+            return;
+        }
+        let written = self.render_overlay().and_then(|png| {
+            let path = seq.dir.join(format!("frame_{:05}.png", seq.frames));
+            fs::write(path, png).map_err(ModelizeError::from)
+        });
+        match written {
+            Ok(()) => {
+                seq.frames += 1;
+                self.overlay_sequence = Some(seq);
+            }
+            // 还没有数据时继续等待；写盘失败则停止，避免每帧刷错误
+            Err(ModelizeError::Plot(_)) => self.overlay_sequence = Some(seq),
+            Err(e) => self.log(&format!("{}: {e}", self.text(UiText::OverlaySequence))),
+        }
+    }
+    /// Writes the visible window to `data/` in the selected export units.
+    fn export_window_csv(&self) -> std::io::Result<PathBuf> {
+        let frame = self.last_frame.as_ref().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no frame to export")
+        })?;
+        let len = (self.wave_window_seconds * frame.sample_rate_hz as f64).ceil() as usize;
+        let frame = self.export_frame(history_slice(frame, 0, len));
+        let dir = PathBuf::from("data");
+        fs::create_dir_all(&dir)?;
+        let ts = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("window_{ts}_{}.csv", self.export_units.file_tag()));
+        fs::write(&path, window_csv(&frame, self.export_units))?;
+        Ok(path)
+    }
+    /// Writes the whole buffered history (or the latest frame without one) to `data/` as EDF+.
+    fn export_edf(&self) -> std::io::Result<PathBuf> {
+        let frame = self
+            .wave_history
+            .as_ref()
+            .or(self.last_frame.as_ref())
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "no frame to export")
+            })?;
+        let dir = PathBuf::from("data");
+        fs::create_dir_all(&dir)?;
+        let ts = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("recording_{ts}.edf"));
+        recorder::write_edf(
+            &path,
+            frame.sample_rate_hz,
+            &frame.channel_labels,
+            &frame.samples,
+        )?;
+        Ok(path)
+    }
+    /// Spectrogram of the buffered history (or the latest frame) with the Spectrum
+    /// tab's FFT size and a quarter-window hop.
+    fn refresh_spectrogram(&mut self, ctx: &egui::Context) -> Result<(), ModelizeError> {
+        let frame = self
+            .wave_history
+            .as_ref()
+            .or(self.last_frame.as_ref())
+            .ok_or_else(|| ModelizeError::Plot("no frame to analyse".into()))?;
+        let hop = (self.fft_size / 4).max(1);
+        let spectrogram = Spectrogram::from_frame(frame, self.fft_size, hop);
+        let png =
+            render_spectrogram_png(&spectrogram, self.spectrogram_channel, PlotStyle::default())?;
+        let rgba = image::load_from_memory(&png)?.to_rgba8();
+        let size = [rgba.width() as usize, rgba.height() as usize];
+        let color_image = ColorImage::from_rgba_unmultiplied(size, rgba.as_raw());
+        self.spectrogram_tex =
+            Some(ctx.load_texture("spectrogram", color_image, TextureOptions::LINEAR));
+        self.spectrogram_png = Some(png);
+        Ok(())
+    }
+    fn save_spectrogram_png(&self) -> std::io::Result<PathBuf> {
+        let png = self.spectrogram_png.as_ref().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no spectrogram rendered")
+        })?;
+        let dir = PathBuf::from("data");
+        fs::create_dir_all(&dir)?;
+        let ts = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("spectrogram_{ts}.png"));
+        fs::write(&path, png)?;
+        Ok(path)
+    }
+    fn show_spectrogram(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let max_channel = self
+                .last_frame
+                .as_ref()
+                .map(|f| f.samples.len())
+                .unwrap_or(16)
+                .max(1);
+            let mut shown = self.spectrogram_channel.min(max_channel - 1) + 1;
+            ui.add(
+                egui::DragValue::new(&mut shown)
+                    .clamp_range(1..=max_channel)
+                    .prefix(self.text(UiText::SpectrogramChannelPrefix)),
+            );
+            self.spectrogram_channel = shown - 1;
+            ui.label(format!("{} {}", self.text(UiText::FftSize), self.fft_size));
+            if ui
+                .button(self.text(UiText::Update))
+                .on_hover_text(self.text(UiText::SpectrogramHint))
+                .clicked()
+            {
+                if let Err(e) = self.refresh_spectrogram(ui.ctx()) {
+                    let msg = match self.language {
+                        Language::English => format!("Spectrogram failed: {e}"),
+                        Language::Chinese => format!("时频图生成失败: {e}"),
+                    };
+                    self.log(&msg);
+                }
+            }
+            if ui
+                .add_enabled(
+                    self.spectrogram_png.is_some(),
+                    egui::Button::new(self.text(UiText::SaveSpectrogramPng)),
+                )
+                .clicked()
+            {
+                let msg = match (self.save_spectrogram_png(), self.language) {
+                    (Ok(path), Language::English) => {
+                        format!("Spectrogram saved: {}", path.display())
+                    }
+                    (Ok(path), Language::Chinese) => format!("时频图已保存: {}", path.display()),
+                    (Err(e), Language::English) => format!("Spectrogram save failed: {e}"),
+                    (Err(e), Language::Chinese) => format!("时频图保存失败: {e}"),
+                };
+                self.log(&msg);
+            }
+        });
+        ui.separator();
+        match &self.spectrogram_tex {
+            Some(tex) => {
+                ui.add(egui::Image::new(tex).shrink_to_fit());
+            }
+            None => {
+                ui.label(self.text(UiText::SpectrogramHint));
             }
         }
     }
+    fn compute_spectrum(&self, frame: &TimeSeriesFrame) -> FrequencySpectrum {
+        self.spectrum_builder.compute(frame)
+    }
+    fn rebuild_spectrum_builder(&mut self) {
+        self.spectrum_builder = SpectrumBuilder::with_size(self.fft_size)
+            .with_window(self.fft_window)
+            .with_detrend(self.fft_detrend);
+    }
     fn show_waveform(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
         // 顶部提示
         ui.horizontal_wrapped(|ui| {
@@ -489,6 +1489,36 @@ impl QnmdSolApp {
             } else {
                 ui.label(self.text(UiText::ConnectFirst));
             }
+            if let Some((attempt, max)) = self.reconnecting {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "🔌 {} ({attempt}/{max})...",
+                        self.text(UiText::Reconnecting)
+                    ))
+                    .strong()
+                    .color(Color32::from_rgb(243, 156, 18)),
+                );
+            }
+            let railed = self.lead_off.railed_channels();
+            if self.lead_off_alert && !railed.is_empty() {
+                let now = ui.input(|i| i.time);
+                // 刚触发时闪烁 3 秒，之后保持常亮红色
+                let dim = now < self.lead_off_flash_until && (now * 4.0) as i64 % 2 == 0;
+                let names: Vec<String> = railed.iter().map(|i| format!("Ch{}", i + 1)).collect();
+                ui.label(
+                    egui::RichText::new(format!(
+                        "⚠ {} {}",
+                        self.text(UiText::LeadOffBanner),
+                        names.join(", ")
+                    ))
+                    .strong()
+                    .color(if dim {
+                        Color32::from_rgb(120, 30, 30)
+                    } else {
+                        Color32::from_rgb(231, 76, 60)
+                    }),
+                );
+            }
         });
         // 行1：灵敏度 / 平滑度 + 窗口长度
         ui.horizontal_wrapped(|ui| {
@@ -500,7 +1530,13 @@ impl QnmdSolApp {
             );
             ui.monospace(format!("{:.2}", self.signal_sensitivity));
             ui.label(self.text(UiText::Smoothness));
-            ui.add(egui::Slider::new(&mut self.smooth_alpha, 0.0..=0.8).show_value(false));
+            if ui
+                .add(egui::Slider::new(&mut self.smooth_alpha, 0.0..=0.8).show_value(false))
+                .changed()
+            {
+                self.apply_waveform_pipeline_config();
+                self.refresh_waveform_view();
+            }
             ui.monospace(format!("{:.2}", self.smooth_alpha));
             ui.separator();
             ui.label(self.text(UiText::Window));
@@ -512,11 +1548,10 @@ impl QnmdSolApp {
                 if ui.selectable_label(selected, label).clicked() {
                     self.wave_window_seconds = seconds;
                     self.view_seconds = seconds;
-                    self.wave_smooth_state.clear();
                     if let Some(pipe) = &mut self.waveform_pipeline {
                         pipe.set_time_window(TimeWindow::new(seconds as f32));
-                        self.waveform_view = Some(pipe.view());
                     }
+                    self.refresh_waveform_view();
                 }
             }
             ui.separator();
@@ -532,11 +1567,48 @@ impl QnmdSolApp {
             {
                 self.wave_window_seconds = range;
                 self.view_seconds = range;
-                self.wave_smooth_state.clear();
                 if let Some(pipe) = &mut self.waveform_pipeline {
                     pipe.set_time_window(TimeWindow::new(range as f32));
-                    self.waveform_view = Some(pipe.view());
                 }
+                self.refresh_waveform_view();
+            }
+            ui.separator();
+            let pause_label = if self.paused {
+                self.text(UiText::ResumeWaveform)
+            } else {
+                self.text(UiText::PauseWaveform)
+            };
+            if ui
+                .selectable_label(self.paused, pause_label)
+                .on_hover_text(self.text(UiText::PauseWaveformHint))
+                .clicked()
+            {
+                self.paused = !self.paused;
+                self.resume_catch_up = !self.paused;
+            }
+            let mut frozen = self.wave_frozen;
+            if ui
+                .checkbox(&mut frozen, self.text(UiText::Freeze))
+                .changed()
+            {
+                self.set_frozen(frozen);
+            }
+            let max_scrub = self
+                .frozen_history
+                .as_ref()
+                .map(|h| (h.duration_seconds() as f64 - self.wave_window_seconds).max(0.0))
+                .unwrap_or(0.0);
+            let scrub_label = self.text(UiText::Scrub);
+            if ui
+                .add_enabled(
+                    self.wave_frozen && max_scrub > 0.0,
+                    egui::Slider::new(&mut self.wave_scrub_seconds, 0.0..=max_scrub)
+                        .suffix(" s")
+                        .text(scrub_label),
+                )
+                .changed()
+            {
+                self.refresh_waveform_view();
             }
         });
         // 行2：分辨率 + 量程 / 滤波 + 阈值/丢包率
@@ -567,6 +1639,13 @@ impl QnmdSolApp {
             changed |= ui
                 .checkbox(&mut self.wave_auto_scale, auto_y_label)
                 .changed();
+            if self.wave_auto_scale {
+                let robust_label = self.text(UiText::RobustAutoY);
+                changed |= ui
+                    .checkbox(&mut self.wave_robust_scale, robust_label)
+                    .on_hover_text(self.text(UiText::RobustAutoYHint))
+                    .changed();
+            }
             let resp = ui.add_enabled(
                 !self.wave_auto_scale,
                 egui::Slider::new(&mut self.wave_fixed_range_uv, 50.0..=800.0)
@@ -574,42 +1653,159 @@ impl QnmdSolApp {
                     .text(fixed_uv_label),
             );
             changed |= resp.changed();
-            changed |= ui
+            let (broadband_label, band_hint) = (
+                self.text(UiText::Broadband),
+                self.text(UiText::DisplayBandHint),
+            );
+            let band_name = |band: Option<EegBand>| band.map_or(broadband_label, EegBand::label);
+            let mut band = self.wave_band;
+            egui::ComboBox::from_id_source("wave_band")
+                .selected_text(band_name(band))
+                .show_ui(ui, |ui| {
+                    for option in std::iter::once(None).chain(EegBand::ALL.map(Some)) {
+                        ui.selectable_value(&mut band, option, band_name(option));
+                    }
+                })
+                .response
+                .on_hover_text(band_hint);
+            if band != self.wave_band {
+                self.wave_band = band;
+                changed = true;
+            }
+            if ui
                 .checkbox(&mut self.wave_notch_50hz, notch_label)
-                .changed();
+                .changed()
+            {
+                self.wave_notch_channels = vec![self.wave_notch_50hz; self.session_channel_count];
+                changed = true;
+            }
+            for hz in [50.0, 60.0] {
+                if ui
+                    .selectable_label(self.mains_hz == hz, format!("{hz:.0} Hz"))
+                    .clicked()
+                    && self.mains_hz != hz
+                {
+                    self.mains_hz = hz;
+                    self.tx_cmd
+                        .send(GuiCommand::SetMainsFrequency(hz as f64))
+                        .ok();
+                    changed = true;
+                }
+            }
+            let per_channel_label = self.text(UiText::NotchPerChannel);
+            ui.menu_button(per_channel_label, |ui| {
+                let count = self.session_channel_count;
+                self.wave_notch_channels.resize(count, false);
+                egui::Grid::new("notch_channel_grid").show(ui, |ui| {
+                    for idx in 0..count {
+                        changed |= ui
+                            .checkbox(&mut self.wave_notch_channels[idx], format!("Ch{}", idx + 1))
+                            .changed();
+                        if idx % 4 == 3 {
+                            ui.end_row();
+                        }
+                    }
+                });
+                self.wave_notch_50hz = self.wave_notch_channels.iter().all(|&on| on);
+            });
             changed |= ui
                 .checkbox(&mut self.wave_show_stats, stats_label)
                 .changed();
+            if self.wave_show_stats {
+                let mut rate = self.stats_throttle.rate_hz();
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut rate)
+                            .clamp_range(0.5..=30.0)
+                            .speed(0.1)
+                            .suffix(" Hz"),
+                    )
+                    .on_hover_text(self.text(UiText::StatsRefreshHint))
+                    .changed()
+                {
+                    self.stats_throttle.set_rate_hz(rate);
+                }
+            }
+            let link_label = self.text(UiText::LinkFilters);
+            if ui
+                .checkbox(&mut self.link_filters, link_label)
+                .on_hover_text(self.text(UiText::LinkFiltersHint))
+                .changed()
+            {
+                changed = true;
+            }
             if changed {
                 self.apply_waveform_pipeline_config();
-                if let Some(pipe) = &mut self.waveform_pipeline {
-                    self.waveform_view = Some(pipe.view());
-                }
+                self.refresh_waveform_view();
+                self.send_decode_filters();
             }
             ui.separator();
-            ui.label(format!(
-                "{} {:.1}",
-                self.text(UiText::Threshold),
-                self.trigger_threshold
-            ));
-            if let Some(start) = self.stream_start {
-                let elapsed = start.elapsed().as_secs_f64();
-                let expected = elapsed * self.waveform_sample_rate_hz as f64;
-                ui.separator();
-                if let Some(last) = self.last_data_at {
-                    let since = last.elapsed().as_secs_f64();
-                    if expected > 1.0 {
-                        let actual = self.total_samples_ingested as f64;
-                        let rate = (1.0 - actual / expected).clamp(0.0, 1.0) * 100.0;
-                        ui.label(format!(
-                            "{} {:.2}%",
-                            if self.language == Language::Chinese {
+            let (index_label, rms_label, left_label, right_label) = (
+                self.text(UiText::LaneIndexLabel),
+                self.text(UiText::LaneRmsLabel),
+                self.text(UiText::LabelsLeft),
+                self.text(UiText::LabelsRight),
+            );
+            ui.checkbox(&mut self.wave_show_index_label, index_label);
+            ui.checkbox(&mut self.wave_show_rms_label, rms_label);
+            let band_bars_label = self.text(UiText::BandBars);
+            ui.checkbox(&mut self.wave_show_band_bars, band_bars_label)
+                .on_hover_text(self.text(UiText::BandBarsHint));
+            if self.wave_show_index_label || self.wave_show_rms_label {
+                ui.selectable_value(&mut self.wave_label_side, LabelSide::Left, left_label);
+                ui.selectable_value(&mut self.wave_label_side, LabelSide::Right, right_label);
+            }
+            ui.separator();
+            ui.add(
+                egui::Slider::new(&mut self.trace_width, 0.5..=4.0)
+                    .show_value(false)
+                    .text(self.language.text(UiText::TraceWidth)),
+            );
+            ui.add(
+                egui::Slider::new(&mut self.trace_alpha, 0.1..=1.0)
+                    .show_value(false)
+                    .text(self.language.text(UiText::TraceOpacity)),
+            );
+            if ui
+                .button(self.text(UiText::ResetLaneZoom))
+                .on_hover_text(self.text(UiText::LaneZoomHint))
+                .clicked()
+            {
+                self.channel_zoom.clear();
+            }
+            ui.separator();
+            ui.label(format!(
+                "{} {:.1}",
+                self.text(UiText::Threshold),
+                self.trigger_threshold
+            ));
+            if self.stream_start.is_some() {
+                ui.separator();
+                if let Some(last) = self.last_data_at {
+                    let since = last.elapsed().as_secs_f64();
+                    if let Some(rate) = self.drop_meter.drop_rate() {
+                        ui.label(format!(
+                            "{} {:.2}%",
+                            if self.language == Language::Chinese {
                                 "丢包率:"
                             } else {
                                 "Drop:"
                             },
                             rate
                         ));
+                        let mut window = self.drop_meter.window_secs();
+                        if ui
+                            .add(
+                                egui::DragValue::new(&mut window)
+                                    .clamp_range(1.0..=30.0)
+                                    .speed(0.2)
+                                    .suffix(" s"),
+                            )
+                            .on_hover_text(self.text(UiText::DropWindowHint))
+                            .changed()
+                        {
+                            self.drop_meter.set_window_secs(window);
+                        }
                         ui.label(format!(
                             "{} {:.1}s",
                             if self.language == Language::Chinese {
@@ -620,6 +1816,24 @@ impl QnmdSolApp {
                             since
                         ));
                     }
+                    let rate_label = self.text(UiText::EffectiveRate);
+                    ui.checkbox(&mut self.show_effective_rate, rate_label)
+                        .on_hover_text(self.text(UiText::EffectiveRateHint));
+                    if let Some(rate) = self.stream_rate.filter(|_| self.show_effective_rate) {
+                        let label = format!(
+                            "{:.1} / {:.0} Hz ({})",
+                            rate.effective_hz, rate.nominal_hz, rate.samples
+                        );
+                        let short = rate.effective_hz < rate.nominal_hz * 0.98;
+                        ui.colored_label(
+                            if short {
+                                Color32::from_rgb(220, 120, 40)
+                            } else {
+                                ui.visuals().text_color()
+                            },
+                            label,
+                        );
+                    }
                 } else {
                     ui.label(if self.language == Language::Chinese {
                         "未收到数据"
@@ -634,25 +1848,13 @@ impl QnmdSolApp {
         let view: &WaveformView = if let Some(v) = self.waveform_view.as_ref() {
             v
         } else {
-            _placeholder = Some(WaveformView {
-                window_secs: self.wave_window_seconds as f32,
-                channels: (0..16)
-                    .map(|i| ChannelView {
-                        index: i,
-                        y_range: (-200.0, 200.0),
-                        rms_u_v: 0.0,
-                        min: 0.0,
-                        max: 0.0,
-                        samples: Vec::<SamplePoint>::new(),
-                    })
-                    .collect(),
-            });
+            _placeholder = Some(placeholder_view(
+                self.wave_window_seconds as f32,
+                self.session_channel_count,
+            ));
             _placeholder.as_ref().unwrap()
         };
-        let channel_count = view.channels.len().max(16);
-        if self.wave_smooth_state.len() != channel_count {
-            self.wave_smooth_state = vec![0.0; channel_count];
-        }
+        let channel_count = view.channels.len().max(self.session_channel_count).max(1);
         let max_points_per_channel: usize = 1400;
         let colors = [
             Color32::from_rgb(118, 94, 186),
@@ -680,13 +1882,36 @@ impl QnmdSolApp {
         let plot_height = total_height.max(available_h as f64) as f32;
         let y_min = -((channel_count as f64 - 1.0) * lane_height + y_span * 1.3);
         let y_max = y_span * 1.3;
-        let smooth_alpha = self.smooth_alpha.clamp(0.0, 1.0);
-        let empty: &[crate::waveform::view::SamplePoint] = &[];
         let uv_to_height = if y_span.abs() < f64::EPSILON {
             1.0
         } else {
             y_span / 160.0
         };
+        // 统计文字按固定频率刷新，避免数字每帧闪动；波形本身仍实时绘制
+        let now = ui.input(|i| i.time);
+        if self.stats_throttle.ready(now) || self.wave_stats_cache.len() != channel_count {
+            self.wave_stats_cache = (0..channel_count)
+                .map(|idx| {
+                    view.channels.iter().find(|c| c.index == idx).map(|ch| {
+                        format!(
+                            "min {:.0} / max {:.0} | y [{:.0}, {:.0}]",
+                            ch.min, ch.max, ch.y_range.0, ch.y_range.1
+                        )
+                    })
+                })
+                .collect();
+        }
+        // 与波形同步刷新：取最新 fft_size 个采样做一次 FFT
+        let band_shares = if self.wave_show_band_bars {
+            self.last_frame
+                .as_ref()
+                .map(|f| {
+                    alpha_beta_shares(&self.compute_spectrum(&history_slice(f, 0, self.fft_size)))
+                })
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
         egui::ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(ui, |ui| {
@@ -699,6 +1924,8 @@ impl QnmdSolApp {
                     .allow_zoom(false)
                     .show_axes([false, false])
                     .show_grid(false)
+                    .show_x(false)
+                    .show_y(false)
                     .height(plot_height)
                     .show(ui, |plot_ui| {
                         plot_ui.set_plot_bounds(PlotBounds::from_min_max(
@@ -707,37 +1934,19 @@ impl QnmdSolApp {
                         ));
                         for idx in 0..channel_count {
                             let ch_opt = view.channels.iter().find(|c| c.index == idx);
-                            let samples = ch_opt.map(|c| c.samples.as_slice()).unwrap_or(empty);
                             let rms = ch_opt.map(|c| c.rms_u_v).unwrap_or(0.0);
                             let base = -(idx as f64) * lane_height;
                             let col = colors.get(idx).unwrap_or(&Color32::WHITE);
-                            let step = samples
-                                .len()
-                                .checked_div(max_points_per_channel)
-                                .unwrap_or(0)
-                                .max(1);
-                            let mut points: Vec<[f64; 2]> = Vec::new();
-                            for sample in samples.iter().step_by(step) {
-                                let scaled = sample.value as f64
-                                    * self.display_gain as f64
-                                    * self.signal_sensitivity as f64
-                                    * uv_to_height;
-                                let prev = self.wave_smooth_state.get(idx).copied().unwrap_or(0.0);
-                                let smoothed = if smooth_alpha <= 0.0 || smooth_alpha >= 1.0 {
-                                    scaled
-                                } else {
-                                    prev * (1.0 - smooth_alpha) + scaled * smooth_alpha
-                                };
-                                if let Some(state) = self.wave_smooth_state.get_mut(idx) {
-                                    *state = smoothed;
-                                }
-                                let clamped = smoothed.clamp(-y_span, y_span);
-                                points.push([sample.time as f64, base + clamped]);
-                            }
+                            let gain = self.display_gain * self.signal_sensitivity * uv_to_height;
+                            let mut points = ch_opt
+                                .map(|c| {
+                                    lane_points(c, max_points_per_channel, |v| {
+                                        let scaled = scale_sample(v, gain, &self.channel_zoom, idx);
+                                        base + scaled.clamp(-y_span, y_span)
+                                    })
+                                })
+                                .unwrap_or_default();
                             if points.is_empty() {
-                                if let Some(state) = self.wave_smooth_state.get_mut(idx) {
-                                    *state = 0.0;
-                                }
                                 points.push([x_min, base]);
                                 points.push([x_max, base]);
                             }
@@ -762,35 +1971,130 @@ impl QnmdSolApp {
                             );
                             plot_ui.line(
                                 Line::new(PlotPoints::new(points))
-                                    .color(*col)
+                                    .stroke(trace_stroke(*col, self.trace_width, self.trace_alpha))
                                     .name(format!("Ch{}", idx + 1)),
                             );
-                            let label_x = x_min + view.window_secs as f64 * 0.02;
-                            let rms_x = x_min + view.window_secs as f64 * 0.35;
-                            plot_ui.text(
-                                egui_plot::Text::new(
-                                    [label_x, base + y_span * 0.6].into(),
-                                    format!("{:02}", idx + 1),
-                                )
-                                .color(Color32::WHITE),
+                            let window = view.window_secs as f64;
+                            let label_x =
+                                lane_label_x(self.wave_label_side, x_min, x_max, window * 0.02);
+                            let rms_x =
+                                lane_label_x(self.wave_label_side, x_min, x_max, window * 0.35);
+                            if self.wave_show_index_label {
+                                plot_ui.text(
+                                    egui_plot::Text::new(
+                                        [label_x, base + y_span * 0.6].into(),
+                                        match lane_zoom(&self.channel_zoom, idx) {
+                                            z if (z - 1.0).abs() < 1e-3 => {
+                                                format!("{:02}", idx + 1)
+                                            }
+                                            z => format!("{:02} x{:.1}", idx + 1, z),
+                                        },
+                                    )
+                                    .color(Color32::WHITE),
+                                );
+                            }
+                            if self.wave_show_rms_label {
+                                plot_ui.text(
+                                    egui_plot::Text::new(
+                                        [rms_x, base + y_span * 0.2].into(),
+                                        format!("{:.1} uVrms", rms),
+                                    )
+                                    .color(*col),
+                                );
+                            }
+                            if let Some(&(alpha, beta)) = band_shares.get(idx) {
+                                let bar_len = window * 0.15;
+                                for (share, name, color, y) in [
+                                    (alpha, "α", BAND_BAR_ALPHA, base - y_span * 0.55),
+                                    (beta, "β", BAND_BAR_BETA, base - y_span * 0.85),
+                                ] {
+                                    let end = rms_x + bar_len * share as f64;
+                                    plot_ui.line(
+                                        Line::new(PlotPoints::new(vec![[rms_x, y], [end, y]]))
+                                            .width(4.0)
+                                            .color(color),
+                                    );
+                                    plot_ui.text(
+                                        Text::new(
+                                            [rms_x + bar_len + window * 0.03, y].into(),
+                                            format!("{name} {:.0}%", share * 100.0),
+                                        )
+                                        .color(color),
+                                    );
+                                }
+                            }
+                            if self.wave_show_stats {
+                                if let Some(Some(stats)) = self.wave_stats_cache.get(idx) {
+                                    plot_ui.text(
+                                        Text::new(
+                                            [label_x, base - y_span * 0.35].into(),
+                                            stats.clone(),
+                                        )
+                                        .color(Color32::from_gray(120)),
+                                    );
+                                }
+                            }
+                        }
+                        // 测量光标：十字线 + 鼠标所在通道最近采样的时间和 µV
+                        let readout = plot_ui
+                            .pointer_coordinate()
+                            .filter(|_| plot_ui.response().hovered())
+                            .and_then(|p| {
+                                wave_readout(view, [p.x, p.y], lane_height, view.window_secs as f64)
+                            });
+                        if let Some(r) = readout {
+                            let base = -(r.lane as f64) * lane_height;
+                            let gain = self.display_gain * self.signal_sensitivity * uv_to_height;
+                            let y = base
+                                + scale_sample(r.value_uv, gain, &self.channel_zoom, r.lane)
+                                    .clamp(-y_span, y_span);
+                            let x = r.time as f64;
+                            let cursor = Color32::from_gray(160);
+                            plot_ui.line(
+                                Line::new(PlotPoints::new(vec![[x, y_min], [x, y_max]]))
+                                    .color(cursor),
+                            );
+                            plot_ui.line(
+                                Line::new(PlotPoints::new(vec![[x_min, y], [x_max, y]]))
+                                    .color(cursor),
+                            );
+                            plot_ui.points(
+                                egui_plot::Points::new(vec![[x, y]])
+                                    .radius(3.5)
+                                    .color(*colors.get(r.lane).unwrap_or(&Color32::WHITE)),
                             );
+                            // 靠近右边缘时把读数放到十字线左侧
+                            let anchor = if x > x_min + (x_max - x_min) * 0.75 {
+                                egui::Align2::RIGHT_BOTTOM
+                            } else {
+                                egui::Align2::LEFT_BOTTOM
+                            };
                             plot_ui.text(
-                                egui_plot::Text::new(
-                                    [rms_x, base + y_span * 0.2].into(),
-                                    format!("{:.1} uVrms", rms),
+                                Text::new(
+                                    [x, y].into(),
+                                    format!(
+                                        " Ch{}  {:.3} s  {:.1} µV ",
+                                        r.lane + 1,
+                                        r.time,
+                                        r.value_uv
+                                    ),
                                 )
-                                .color(*col),
+                                .anchor(anchor)
+                                .color(plot_ui.ctx().style().visuals.text_color()),
                             );
-                            if self.wave_show_stats {
-                                if let Some(ch) = ch_opt {
-                                    let stats = format!(
-                                        "min {:.0} / max {:.0} | y [{:.0}, {:.0}]",
-                                        ch.min, ch.max, ch.y_range.0, ch.y_range.1
-                                    );
-                                    plot_ui.text(
-                                        Text::new([label_x, base - y_span * 0.35].into(), stats)
-                                            .color(Color32::from_gray(120)),
-                                    );
+                        }
+                        // Ctrl + 滚轮：缩放鼠标所在通道
+                        let zoom_delta = plot_ui.ctx().input(|i| i.zoom_delta());
+                        if plot_ui.response().hovered() && (zoom_delta - 1.0).abs() > f32::EPSILON {
+                            if let Some(pointer) = plot_ui.pointer_coordinate() {
+                                let lane = (-pointer.y / lane_height).round();
+                                if lane >= 0.0 && (lane as usize) < channel_count {
+                                    let lane = lane as usize;
+                                    if self.channel_zoom.len() <= lane {
+                                        self.channel_zoom.resize(lane + 1, 1.0);
+                                    }
+                                    self.channel_zoom[lane] =
+                                        (self.channel_zoom[lane] * zoom_delta).clamp(0.25, 16.0);
                                 }
                             }
                         }
@@ -806,20 +2110,171 @@ impl QnmdSolApp {
                     .selectable_value(&mut self.fft_size, *sz, format!("{sz}"))
                     .clicked()
                 {
-                    if let Some(frame) = self.last_frame.clone() {
-                        let builder = SpectrumBuilder::with_size(*sz);
-                        self.last_spectrum = Some(builder.compute(&frame));
+                    self.rebuild_spectrum_builder();
+                    if let Some(frame) = self.spectrum_source() {
+                        self.last_spectrum = Some(self.compute_spectrum(&frame));
+                    }
+                    if self.live_spectrum {
+                        self.send_live_spectrum_config();
+                    }
+                }
+            }
+            let mut window_changed = false;
+            egui::ComboBox::from_id_source("fft_window")
+                .selected_text(self.fft_window.label())
+                .show_ui(ui, |ui| {
+                    for window in WindowFunction::ALL {
+                        window_changed |= ui
+                            .selectable_value(&mut self.fft_window, window, window.label())
+                            .changed();
+                    }
+                })
+                .response
+                .on_hover_text(self.text(UiText::FftWindowHint));
+            egui::ComboBox::from_id_source("fft_detrend")
+                .selected_text(self.fft_detrend.label())
+                .show_ui(ui, |ui| {
+                    for detrend in Detrend::ALL {
+                        window_changed |= ui
+                            .selectable_value(&mut self.fft_detrend, detrend, detrend.label())
+                            .changed();
                     }
+                })
+                .response
+                .on_hover_text(self.text(UiText::FftDetrendHint));
+            if window_changed {
+                self.rebuild_spectrum_builder();
+                if let Some(frame) = self.spectrum_source() {
+                    self.last_spectrum = Some(self.compute_spectrum(&frame));
                 }
             }
+            let live_label = self.text(UiText::LiveSpectrum);
+            let mut live_changed = ui
+                .checkbox(&mut self.live_spectrum, live_label)
+                .on_hover_text(self.text(UiText::LiveSpectrumHint))
+                .changed();
+            live_changed |= ui
+                .add_enabled(
+                    self.live_spectrum,
+                    egui::DragValue::new(&mut self.live_spectrum_hz)
+                        .clamp_range(1.0..=30.0)
+                        .suffix(" Hz"),
+                )
+                .changed();
+            if live_changed {
+                self.send_live_spectrum_config();
+            }
             if ui.button(self.text(UiText::Update)).clicked() {
-                if let Some(frame) = self.last_frame.clone() {
-                    let builder = SpectrumBuilder::with_size(self.fft_size);
-                    self.last_spectrum = Some(builder.compute(&frame));
+                if let Some(frame) = self.spectrum_source() {
+                    let spec = self.compute_spectrum(&frame);
+                    self.spectrum_history.push(spec.clone());
+                    self.last_spectrum = Some(spec);
+                }
+            }
+            let follow_label = self.text(UiText::SpectrumFollowsScrub);
+            if ui
+                .checkbox(&mut self.spectrum_follows_scrub, follow_label)
+                .changed()
+                && self.wave_frozen
+            {
+                if let Some(frame) = self.spectrum_source() {
+                    self.last_spectrum = Some(self.compute_spectrum(&frame));
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(self.text(UiText::SpectrumHistoryLen));
+            let hint = self.text(UiText::SpectrumHistoryHint);
+            if ui
+                .add(egui::DragValue::new(&mut self.spectrum_history_len).clamp_range(2..=2000))
+                .on_hover_text(hint)
+                .changed()
+            {
+                self.spectrum_history.set_cap(self.spectrum_history_len);
+            }
+            if let Some(flux) = self.spectrum_history.spectral_flux() {
+                let mean = flux.iter().sum::<f32>() / flux.len().max(1) as f32;
+                ui.label(format!(
+                    "{}: {:.3} ({}/{})",
+                    self.text(UiText::SpectralFlux),
+                    mean,
+                    self.spectrum_history.len(),
+                    self.spectrum_history_len
+                ));
+            }
+        });
+        ui.horizontal(|ui| {
+            let focus_label = self.text(UiText::DecodeBandOnly);
+            ui.checkbox(&mut self.spectrum_focus, focus_label)
+                .on_hover_text(self.text(UiText::DecodeBandHint));
+            if self.spectrum_focus {
+                let mut channel = self.spectrum_focus_channel + 1;
+                let channel_count = self.session_channel_count.max(1);
+                ui.add(
+                    egui::DragValue::new(&mut channel)
+                        .clamp_range(1..=channel_count)
+                        .prefix("Ch"),
+                );
+                self.spectrum_focus_channel = channel - 1;
+                let (low, high) = &mut self.focus_band_hz;
+                ui.add(
+                    egui::DragValue::new(low)
+                        .clamp_range(0.0..=*high)
+                        .speed(0.5)
+                        .suffix(" Hz"),
+                );
+                ui.label("–");
+                ui.add(
+                    egui::DragValue::new(high)
+                        .clamp_range(*low..=125.0)
+                        .speed(0.5)
+                        .suffix(" Hz"),
+                );
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            let snr_label = self.text(UiText::BandSnr);
+            ui.checkbox(&mut self.show_band_snr, snr_label)
+                .on_hover_text(self.text(UiText::BandSnrHint));
+            if !self.show_band_snr {
+                return;
+            }
+            egui::ComboBox::from_id_source("snr_band")
+                .selected_text(self.snr_band.label())
+                .show_ui(ui, |ui| {
+                    for band in EegBand::ALL {
+                        ui.selectable_value(&mut self.snr_band, band, band.label());
+                    }
+                });
+            // 有足够历史时用 Welch 平均，SNR 读数比单次 FFT 稳定得多
+            let welch = self
+                .wave_history
+                .as_ref()
+                .filter(|h| h.samples.first().map_or(0, Vec::len) > self.fft_size)
+                .map(|h| {
+                    self.spectrum_builder
+                        .compute_welch(h, self.fft_size, self.fft_size / 2)
+                });
+            if let Some(spec) = welch.as_ref().or(self.last_spectrum.as_ref()) {
+                for (idx, snr) in spec.band_snr_db(self.snr_band).iter().enumerate() {
+                    let name = spec
+                        .channel_labels
+                        .get(idx)
+                        .cloned()
+                        .unwrap_or_else(|| format!("Ch{}", idx + 1));
+                    ui.monospace(format!("{name}: {snr:+.1} dB"));
                 }
             }
         });
         if let Some(spec) = self.last_spectrum.as_ref() {
+            let focused = self.spectrum_focus.then(|| {
+                spec.band(
+                    self.spectrum_focus_channel,
+                    self.focus_band_hz.0,
+                    self.focus_band_hz.1,
+                )
+            });
+            let spec = focused.as_ref().unwrap_or(spec);
             let summary = match self.language {
                 Language::English => format!(
                     "FFT @ {:.1} Hz, channels: {}",
@@ -834,6 +2289,7 @@ impl QnmdSolApp {
             };
             ui.label(summary);
             Plot::new("spectrum_plot")
+                .legend(Legend::default())
                 .view_aspect(2.0)
                 .allow_drag(true)
                 .allow_zoom(true)
@@ -845,16 +2301,24 @@ impl QnmdSolApp {
                             .zip(mags.iter())
                             .map(|(f, m)| [*f as f64, *m as f64])
                             .collect();
-                        plot_ui.line(
-                            Line::new(points)
-                                .name(
-                                    spec.channel_labels
-                                        .get(idx)
-                                        .cloned()
-                                        .unwrap_or_else(|| format!("Ch{}", idx + 1)),
-                                )
-                                .color(Color32::from_rgb(30 + (idx as u8 * 13), 200, 120)),
-                        );
+                        let label = spec
+                            .channel_labels
+                            .get(idx)
+                            .cloned()
+                            .unwrap_or_else(|| format!("Ch{}", idx + 1));
+                        // 图例里附上各通道峰值频率与主导频段，便于确认 alpha 节律或工频干扰
+                        let name = match (spec.peak_frequency(idx), spec.dominant_band(idx)) {
+                            (Some((hz, _)), Some(band)) => {
+                                format!("{label} · peak {hz:.1} Hz · {}", band.label())
+                            }
+                            (Some((hz, _)), None) => format!("{label} · peak {hz:.1} Hz"),
+                            (None, _) => label,
+                        };
+                        plot_ui.line(Line::new(points).name(name).color(Color32::from_rgb(
+                            30 + (idx as u8 * 13),
+                            200,
+                            120,
+                        )));
                     }
                 });
         } else {
@@ -863,6 +2327,106 @@ impl QnmdSolApp {
     }
     fn show_png(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
+            ui.label(self.text(UiText::ReplayRecording));
+            ui.text_edit_singleline(&mut self.replay_path)
+                .on_hover_text(self.text(UiText::ReplayRecordingHint));
+            if ui.button(self.text(UiText::ReplayLoad)).clicked() {
+                let msg = match (self.load_recording(), self.language) {
+                    (Ok((secs, hz)), Language::English) => {
+                        format!("Replayed recording @ {hz:.1} Hz, last {secs:.1} s kept for export")
+                    }
+                    (Ok((secs, hz)), Language::Chinese) => {
+                        format!("已回放录制 ({hz:.1} Hz)，保留最后 {secs:.1} 秒用于导出")
+                    }
+                    (Err(e), Language::English) => format!("Replay failed: {e}"),
+                    (Err(e), Language::Chinese) => format!("回放失败: {e}"),
+                };
+                self.log(&msg);
+            }
+            let replay_decode_hint = self.text(UiText::ReplayDecodeHint);
+            if ui
+                .button(self.text(UiText::ReplayDecode))
+                .on_hover_text(replay_decode_hint)
+                .clicked()
+            {
+                let msg = match (self.replay_decode_timeline(), self.language) {
+                    (Ok((path, t)), Language::English) => format!(
+                        "Decode timeline: {} events over {} samples -> {}",
+                        t.events.len(),
+                        t.samples,
+                        path.display()
+                    ),
+                    (Ok((path, t)), Language::Chinese) => format!(
+                        "解码时间线：{} 个事件 / {} 个采样 -> {}",
+                        t.events.len(),
+                        t.samples,
+                        path.display()
+                    ),
+                    (Err(e), Language::English) => format!("Decode replay failed: {e}"),
+                    (Err(e), Language::Chinese) => format!("解码回放失败: {e}"),
+                };
+                self.log(&msg);
+            }
+        });
+        ui.horizontal(|ui| {
+            let grid_prefix = self.text(UiText::PngGridPrefix);
+            ui.add(
+                egui::DragValue::new(&mut self.png_grid_secs)
+                    .clamp_range(0.1..=10.0)
+                    .speed(0.1)
+                    .prefix(grid_prefix)
+                    .suffix(" s"),
+            );
+            let (zero_phase_label, zero_phase_hint) = (
+                self.text(UiText::PngZeroPhase),
+                self.text(UiText::PngZeroPhaseHint),
+            );
+            ui.checkbox(&mut self.png_zero_phase, zero_phase_label)
+                .on_hover_text(zero_phase_hint);
+            let (raw_label, display_label, display_hint) = (
+                self.text(UiText::ExportRawUv),
+                self.text(UiText::ExportDisplayUnits),
+                self.text(UiText::ExportDisplayHint),
+            );
+            ui.selectable_value(
+                &mut self.export_units,
+                ExportUnits::RawMicrovolts,
+                raw_label,
+            );
+            ui.selectable_value(&mut self.export_units, ExportUnits::Display, display_label)
+                .on_hover_text(display_hint);
+            if ui.button(self.text(UiText::ExportWindowCsv)).clicked() {
+                match self.export_window_csv() {
+                    Ok(path) => {
+                        let msg = match self.language {
+                            Language::English => format!("CSV saved: {}", path.display()),
+                            Language::Chinese => format!("CSV 已保存: {}", path.display()),
+                        };
+                        self.log(&msg);
+                    }
+                    Err(e) => {
+                        let msg = match self.language {
+                            Language::English => format!("CSV export failed: {e}"),
+                            Language::Chinese => format!("CSV 导出失败: {e}"),
+                        };
+                        self.log(&msg);
+                    }
+                }
+            }
+            let edf_hint = self.text(UiText::ExportEdfHint);
+            if ui
+                .button(self.text(UiText::ExportEdf))
+                .on_hover_text(edf_hint)
+                .clicked()
+            {
+                let msg = match (self.export_edf(), self.language) {
+                    (Ok(path), Language::English) => format!("EDF saved: {}", path.display()),
+                    (Ok(path), Language::Chinese) => format!("EDF 已保存: {}", path.display()),
+                    (Err(e), Language::English) => format!("EDF export failed: {e}"),
+                    (Err(e), Language::Chinese) => format!("EDF 导出失败: {e}"),
+                };
+                self.log(&msg);
+            }
             if ui.button(self.text(UiText::GenerateWaveformPng)).clicked() {
                 if let Some(frame) = self.last_frame.clone() {
                     let batch = make_batch(
@@ -875,7 +2439,23 @@ impl QnmdSolApp {
                         SignalPipeline::new(manual_source, self.wave_window_seconds as f32);
                     match pipeline.pump_once() {
                         Ok(Some(wave_frame)) => {
-                            match render_waveform_png(&wave_frame, PlotStyle::default()) {
+                            let wave_frame = display_filtered_frame(
+                                wave_frame,
+                                &self.wave_notch_channels,
+                                self.wave_band,
+                                self.mains_hz,
+                                self.png_zero_phase,
+                            );
+                            let wave_frame = self.export_frame(wave_frame);
+                            let style = PlotStyle {
+                                time_grid: GridSpacing::new(
+                                    self.png_grid_secs,
+                                    self.png_grid_secs / 5.0,
+                                ),
+                                value_unit: self.export_units.label().to_owned(),
+                                ..PlotStyle::default()
+                            };
+                            match render_waveform_png(&wave_frame, style) {
                                 Ok(png) => self.wave_png = Some(png),
                                 Err(e) => {
                                     let msg = match self.language {
@@ -967,21 +2547,92 @@ impl QnmdSolApp {
                 }
             }
         });
+        ui.horizontal(|ui| {
+            let max_channel = self
+                .last_frame
+                .as_ref()
+                .map(|f| f.samples.len())
+                .unwrap_or(16)
+                .max(1);
+            let mut shown = self.overlay_channel.min(max_channel - 1) + 1;
+            ui.add(
+                egui::DragValue::new(&mut shown)
+                    .clamp_range(1..=max_channel)
+                    .prefix(self.text(UiText::OverlayChannelPrefix)),
+            )
+            .on_hover_text(self.text(UiText::OverlayHint));
+            self.overlay_channel = shown - 1;
+            if ui.button(self.text(UiText::SaveOverlayPng)).clicked() {
+                let msg = match (self.save_overlay_png(), self.language) {
+                    (Ok(path), Language::English) => format!("Overlay saved: {}", path.display()),
+                    (Ok(path), Language::Chinese) => format!("叠加图已保存: {}", path.display()),
+                    (Err(e), Language::English) => format!("Overlay PNG failed: {e}"),
+                    (Err(e), Language::Chinese) => format!("叠加图导出失败: {e}"),
+                };
+                self.log(&msg);
+            }
+            let mut recording = self.overlay_sequence.is_some();
+            let seq_label = self.text(UiText::OverlaySequence);
+            if ui.checkbox(&mut recording, seq_label).changed() {
+                self.toggle_overlay_sequence();
+            }
+            if let Some(seq) = &self.overlay_sequence {
+                ui.label(format!("{} ({OVERLAY_SEQUENCE_FPS} fps)", seq.frames));
+            }
+        });
         ui.separator();
+        let mut save_request = None;
         if let Some(png) = &self.wave_png {
-            ui.label(self.text(UiText::WaveformPngLabel));
+            ui.horizontal(|ui| {
+                ui.label(self.text(UiText::WaveformPngLabel));
+                if ui.button(self.text(UiText::SavePngAs)).clicked() {
+                    save_request = Some((png.clone(), "waveform"));
+                }
+            });
             ui.add(egui::Image::from_bytes("wave_png", png.clone()).max_width(600.0));
         }
         if let Some(png) = &self.spectrum_png {
-            ui.label(self.text(UiText::SpectrumPngLabel));
+            ui.horizontal(|ui| {
+                ui.label(self.text(UiText::SpectrumPngLabel));
+                if ui.button(self.text(UiText::SavePngAs)).clicked() {
+                    save_request = Some((png.clone(), "spectrum"));
+                }
+            });
             ui.add(egui::Image::from_bytes("spectrum_png", png.clone()).max_width(600.0));
         }
+        if let Some((png, stem)) = save_request {
+            let msg = match (self.save_png_as(&png, stem), self.language) {
+                (Ok(Some(path)), Language::English) => format!("PNG saved: {}", path.display()),
+                (Ok(Some(path)), Language::Chinese) => format!("PNG 已保存: {}", path.display()),
+                (Ok(None), Language::English) => "PNG save cancelled".to_owned(),
+                (Ok(None), Language::Chinese) => "已取消保存 PNG".to_owned(),
+                (Err(e), Language::English) => format!("PNG save failed: {e}"),
+                (Err(e), Language::Chinese) => format!("PNG 保存失败: {e}"),
+            };
+            self.log(&msg);
+        }
+    }
+    /// Writes `png` where the save dialog points, starting at `reports/<stem>_<ts>.png`.
+    /// `Ok(None)` when the dialog was cancelled.
+    fn save_png_as(&self, png: &[u8], stem: &str) -> std::io::Result<Option<PathBuf>> {
+        let dir = PathBuf::from("reports");
+        fs::create_dir_all(&dir)?;
+        let ts = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let Some(path) = save_dialog::save_png_path(&dir.join(format!("{stem}_{ts}.png"))) else {
+            return Ok(None);
+        };
+        fs::write(&path, png)?;
+        Ok(Some(path))
     }
     fn show_calibration(&mut self, ui: &mut egui::Ui) {
         ui.heading(self.text(UiText::Calibration));
         if self.is_connected && self.is_streaming {
             if ui.button(self.text(UiText::RecordRelax)).clicked() {
                 self.calib_rest_max = 0.0;
+                self.calib_action = false;
                 self.is_calibrating = true;
                 self.calib_timer = 3.0;
                 self.set_progress(self.text(UiText::Calibration), 0.0);
@@ -991,6 +2642,7 @@ impl QnmdSolApp {
             }
             if ui.button(self.text(UiText::RecordAction)).clicked() {
                 self.calib_act_max = 0.0;
+                self.calib_action = true;
                 self.is_calibrating = true;
                 self.calib_timer = 3.0;
                 self.set_progress(self.text(UiText::Calibration), 0.0);
@@ -998,7 +2650,20 @@ impl QnmdSolApp {
                     .send(GuiCommand::StartCalibration(true))
                     .unwrap();
             }
-            if self.is_calibrating {
+            let guided_label = self.text(UiText::GuidedCalibration);
+            if ui
+                .add_enabled(
+                    self.guided_phase.is_none() && !self.is_calibrating,
+                    egui::Button::new(guided_label),
+                )
+                .on_hover_text(self.text(UiText::GuidedCalibrationHint))
+                .clicked()
+            {
+                self.start_guided_calibration();
+            }
+            if let Some(phase) = self.guided_phase {
+                ui.strong(self.guided_instruction(phase));
+            } else if self.is_calibrating {
                 ui.label(self.text(UiText::Recording));
             }
             ui.label(format!("Rest µ-power: {:.3}", self.calib_rest_max));
@@ -1006,6 +2671,209 @@ impl QnmdSolApp {
         } else {
             ui.label(self.text(UiText::ConnectStreamFirst));
         }
+        ui.horizontal(|ui| {
+            ui.label(self.text(UiText::ThresholdBlend));
+            ui.add(
+                egui::DragValue::new(&mut self.calibration_blend)
+                    .clamp_range(0.0..=1.0)
+                    .speed(0.01)
+                    .fixed_decimals(2),
+            )
+            .on_hover_text(self.text(UiText::ThresholdBlendHint));
+        });
+        ui.separator();
+        let reload_label = self.text(UiText::ReloadCalibration);
+        ui.checkbox(&mut self.reload_calibration, reload_label)
+            .on_hover_text(self.text(UiText::ReloadCalibrationHint));
+        if let Some(profile) = self.saved_calibration.clone() {
+            let label = format!(
+                "{} ({:.1})",
+                self.text(UiText::ApplySavedCalibration),
+                profile.threshold
+            );
+            if ui.button(label).clicked() {
+                self.apply_calibration(profile);
+            }
+        }
+        if !self.calib_channel_thresholds.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                for (idx, t) in self.calib_channel_thresholds.iter().enumerate() {
+                    ui.monospace(format!("Ch{} {:.0}", idx + 1, t));
+                }
+            });
+        }
+    }
+    fn board_name(&self) -> &'static str {
+        match self.connection_mode {
+            ConnectionMode::Hardware => self.board.label(),
+            ConnectionMode::Simulation => "Simulation",
+        }
+    }
+    /// Rest then action windows back to back; the engine reports each step.
+    fn start_guided_calibration(&mut self) {
+        self.calib_rest_max = 0.0;
+        self.calib_act_max = 0.0;
+        self.guided_phase = Some(CalibrationPhase::Rest);
+        self.set_progress(self.guided_instruction(CalibrationPhase::Rest), 0.0);
+        self.tx_cmd.send(GuiCommand::StartGuidedCalibration).ok();
+    }
+    fn guided_instruction(&self, phase: CalibrationPhase) -> &'static str {
+        match phase {
+            CalibrationPhase::Rest => self.text(UiText::CalibRelax),
+            CalibrationPhase::Action => self.text(UiText::CalibContract),
+        }
+    }
+    /// Threshold `calibration_blend` of the way from the rest to the action peak, per
+    /// channel and overall; applied to the engine and saved for the next session.
+    fn finish_calibration(&mut self) {
+        let blend = self.calibration_blend;
+        let Some(threshold) =
+            calibration::blend_threshold(self.calib_rest_max, self.calib_act_max, blend)
+        else {
+            let msg = match self.language {
+                Language::English => "Action peak is not above rest; threshold unchanged.",
+                Language::Chinese => "动作峰值未高于静息，阈值保持不变。",
+            };
+            self.log(msg);
+            return;
+        };
+        let profile = CalibrationProfile {
+            board: self.board_name().to_owned(),
+            skip_channels: self.montage.skip_channels.clone(),
+            threshold,
+            channel_thresholds: self
+                .calib_rest_channels
+                .iter()
+                .zip(&self.calib_act_channels)
+                .map(|(&rest, &act)| {
+                    // 动作峰值没超过静息的通道静息时也会触发，改用总阈值
+                    calibration::blend_threshold(rest, act, blend).unwrap_or(threshold)
+                })
+                .collect(),
+        };
+        let saved = profile.save_to(&CalibrationProfile::store_path());
+        self.apply_calibration(profile);
+        if let Err(e) = saved {
+            let msg = match self.language {
+                Language::English => format!("Calibration not saved: {e}"),
+                Language::Chinese => format!("校准结果保存失败: {e}"),
+            };
+            self.log(&msg);
+        }
+    }
+    fn finish_guided_calibration(&mut self, capture: GuidedCapture) {
+        let msg = match self.language {
+            Language::English => format!(
+                "Rest peak: {:.1} |µV|, action peak: {:.1} |µV|",
+                capture.rest_max, capture.action_max
+            ),
+            Language::Chinese => format!(
+                "静息峰值：{:.1} |µV|，动作峰值：{:.1} |µV|",
+                capture.rest_max, capture.action_max
+            ),
+        };
+        self.log(&msg);
+        self.calib_rest_max = capture.rest_max;
+        self.calib_rest_channels = capture.rest_channels;
+        self.calib_act_max = capture.action_max;
+        self.calib_act_channels = capture.action_channels;
+        self.finish_calibration();
+    }
+    /// Sends the overall threshold to every channel, then each calibrated channel's own.
+    fn apply_calibration(&mut self, profile: CalibrationProfile) {
+        self.trigger_threshold = profile.threshold;
+        self.calib_channel_thresholds = profile.channel_thresholds;
+        self.saved_calibration = None;
+        self.tx_cmd
+            .send(GuiCommand::SetThreshold(self.trigger_threshold))
+            .ok();
+        for (idx, &t) in self.calib_channel_thresholds.iter().enumerate() {
+            self.tx_cmd
+                .send(GuiCommand::SetChannelThreshold(idx, t))
+                .ok();
+        }
+        let channels = self.calib_channel_thresholds.len();
+        let msg = match self.language {
+            Language::English => format!(
+                "Threshold set to {:.1} ({channels} per-channel)",
+                self.trigger_threshold
+            ),
+            Language::Chinese => format!(
+                "阈值已设为 {:.1} (含 {channels} 个通道阈值)",
+                self.trigger_threshold
+            ),
+        };
+        self.log(&msg);
+    }
+    /// What the engine decodes against: the calibrated per-channel thresholds, the
+    /// overall one for channels without them.
+    fn decode_thresholds(&self) -> [f64; 16] {
+        let mut thresholds = [self.trigger_threshold; 16];
+        for (slot, &t) in thresholds.iter_mut().zip(&self.calib_channel_thresholds) {
+            *slot = t;
+        }
+        thresholds
+    }
+    /// At startup: restores the last saved thresholds so a relaunch needs no
+    /// recalibration; connecting still checks the profile against the board and montage.
+    fn load_startup_calibration(&mut self) {
+        let path = CalibrationProfile::store_path();
+        let msg = match CalibrationProfile::load(&path) {
+            Ok(profile) => {
+                let msg = match self.language {
+                    Language::English => format!(
+                        "Saved calibration found ({}, {})",
+                        profile.board,
+                        path.display()
+                    ),
+                    Language::Chinese => {
+                        format!("已找到保存的校准 ({}，{})", profile.board, path.display())
+                    }
+                };
+                self.log(&msg);
+                self.apply_calibration(profile);
+                return;
+            }
+            Err(CalibrationError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                match self.language {
+                    Language::English => {
+                        "No saved calibration, using the default threshold".to_owned()
+                    }
+                    Language::Chinese => "没有保存的校准，使用默认阈值".to_owned(),
+                }
+            }
+            Err(e) => match self.language {
+                Language::English => {
+                    format!("Saved calibration unreadable, using the default threshold: {e}")
+                }
+                Language::Chinese => format!("保存的校准无法读取，使用默认阈值: {e}"),
+            },
+        };
+        self.log(&msg);
+    }
+    /// On connect: applies or offers the saved calibration if it matches this setup.
+    fn check_saved_calibration(&mut self) {
+        let path = CalibrationProfile::store_path();
+        if !path.exists() {
+            return;
+        }
+        let board = self.board_name();
+        match CalibrationProfile::load_matching(
+            &path,
+            board,
+            self.session_channel_count,
+            &self.montage,
+        ) {
+            Ok(profile) if self.reload_calibration => self.apply_calibration(profile),
+            Ok(profile) => self.saved_calibration = Some(profile),
+            Err(e) => {
+                let msg = match self.language {
+                    Language::English => format!("Saved calibration ignored: {e}"),
+                    Language::Chinese => format!("已忽略保存的校准: {e}"),
+                };
+                self.log(&msg);
+            }
+        }
     }
     fn run_resistance_check(&mut self) {
         if !self.is_connected || !self.is_streaming {
@@ -1021,10 +2889,22 @@ impl QnmdSolApp {
             return;
         }
         let channels: Vec<&[f32]> = frame.samples.iter().map(|c| c.as_slice()).collect();
-        let values = cyton_impedances_from_samples(&channels);
+        // 硬件模式按所选板卡计算（Ganglion 读电阻通道），否则按当前通道布局
+        let model = match self.connection_mode {
+            ConnectionMode::Hardware => self.board.layout().impedance_model(),
+            ConnectionMode::Simulation => self.montage.layout.impedance_model(),
+        };
+        let values = model.impedances_ohms(&channels, &self.cyton_lead_off);
+        // Ganglion 的数值来自电阻通道，不是 EEG 电压，不做贴轨判断
+        self.resistance_railed = match model {
+            ImpedanceModel::Cyton => channels.iter().map(|ch| channel_is_railed(ch)).collect(),
+            ImpedanceModel::Ganglion => Vec::new(),
+        };
         self.resistance_labels = frame.channel_labels.clone();
         self.resistance_window_seconds = Some(frame.duration_seconds());
-        self.resistance_last_measured = Some(SystemTime::now());
+        let now = SystemTime::now();
+        self.resistance_last_measured = Some(now);
+        record_impedance(&mut self.impedance_history, now, values.clone());
         self.resistance_values = Some(values);
         self.log(self.text(UiText::ImpedanceUpdated));
     }
@@ -1048,7 +2928,60 @@ impl QnmdSolApp {
         if !can_measure {
             ui.label(self.text(UiText::ConnectStreamFirst));
         }
+        ui.horizontal(|ui| {
+            let alert_label = self.text(UiText::LeadOffAlert);
+            let beep_label = self.text(UiText::LeadOffBeep);
+            if ui.checkbox(&mut self.lead_off_alert, alert_label).changed() {
+                self.lead_off.reset();
+            }
+            ui.add_enabled(
+                self.lead_off_alert,
+                egui::Checkbox::new(&mut self.lead_off_beep, beep_label),
+            );
+            let mut debounce = self.lead_off.debounce_secs();
+            if ui
+                .add_enabled(
+                    self.lead_off_alert,
+                    egui::DragValue::new(&mut debounce)
+                        .clamp_range(0.0..=10.0)
+                        .speed(0.1)
+                        .suffix(" s"),
+                )
+                .on_hover_text(self.text(UiText::LeadOffDebounceHint))
+                .changed()
+            {
+                self.lead_off.set_debounce_secs(debounce);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(self.text(UiText::LeadOffDrive));
+            egui::ComboBox::from_id_source("lead_off_drive")
+                .selected_text(self.cyton_lead_off.drive_label())
+                .show_ui(ui, |ui| {
+                    for amps in CytonLeadOff::STANDARD_DRIVE_AMPS {
+                        let option = CytonLeadOff {
+                            drive_amps: amps,
+                            ..self.cyton_lead_off
+                        };
+                        ui.selectable_value(&mut self.cyton_lead_off, option, option.drive_label());
+                    }
+                })
+                .response
+                .on_hover_text(self.text(UiText::LeadOffDriveHint));
+            ui.add(
+                egui::DragValue::new(&mut self.cyton_lead_off.series_ohms)
+                    .clamp_range(0.0..=100_000.0)
+                    .speed(100.0)
+                    .suffix(" Ω"),
+            );
+        });
+        let corr_label = self.text(UiText::ShowCorrelation);
+        ui.checkbox(&mut self.show_correlation, corr_label);
+        if self.show_correlation {
+            self.show_correlation_heatmap(ui);
+        }
         ui.separator();
+        let mut clear_trend = false;
         if let Some(values) = self.resistance_values.as_ref() {
             let labels: Vec<String> = if self.resistance_labels.is_empty() {
                 (1..=values.len()).map(|i| format!("Ch{i}")).collect()
@@ -1068,15 +3001,28 @@ impl QnmdSolApp {
                     self.impedance_last_cycle = Some(now);
                 }
             }
+            let mut limits = std::mem::take(&mut self.impedance_limits_kohms);
+            if limits.len() < values.len() {
+                limits.resize(values.len(), 0.0);
+            }
             egui::Grid::new("resistance_grid")
                 .striped(true)
                 .show(ui, |ui| {
                     ui.label(self.text(UiText::ImpedanceChannelHeader));
                     ui.label(self.text(UiText::ImpedanceValueHeader));
+                    ui.label(self.text(UiText::ImpedanceLimitHeader))
+                        .on_hover_text(self.text(UiText::ImpedanceLimitHint));
+                    ui.label(self.text(UiText::ImpedanceTrendHeader))
+                        .on_hover_text(self.text(UiText::ImpedanceTrendHint));
                     ui.end_row();
                     for (row, (label, value)) in labels.iter().zip(values.iter()).enumerate() {
                         let ohms = *value;
-                        let (color, status) = Self::impedance_status(ohms, self.language);
+                        let railed = self.resistance_railed.get(row).copied().unwrap_or(false);
+                        let (color, status) = if railed {
+                            Self::impedance_status(f32::NAN, self.language)
+                        } else {
+                            Self::channel_impedance_status(ohms, &limits, row, self.language)
+                        };
                         let marker = egui::RichText::new("⬤").color(color);
                         ui.horizontal(|ui| {
                             if row == self.impedance_highlight_idx {
@@ -1087,12 +3033,30 @@ impl QnmdSolApp {
                             ui.label(label);
                         });
                         ui.label(format!("{:.2} kΩ ({status})", ohms / 1000.0));
+                        ui.add(
+                            egui::DragValue::new(&mut limits[row])
+                                .clamp_range(0.0..=5000.0)
+                                .speed(10.0),
+                        );
+                        draw_sparkline(ui, &impedance_trend(&self.impedance_history, row), color);
                         ui.end_row();
                     }
                 });
-            if let Some(window) = self.resistance_window_seconds {
-                ui.label(format!("{} {:.1}s", self.text(UiText::Window), window));
-            }
+            self.impedance_limits_kohms = limits;
+            clear_trend = ui
+                .horizontal(|ui| {
+                    if let Some(window) = self.resistance_window_seconds {
+                        ui.label(format!("{} {:.1}s", self.text(UiText::Window), window));
+                    }
+                    ui.label(format!(
+                        "{} {}/{IMPEDANCE_HISTORY_LEN}",
+                        self.text(UiText::ImpedanceTrendHeader),
+                        self.impedance_history.len()
+                    ));
+                    ui.button(self.text(UiText::ClearImpedanceHistory))
+                        .clicked()
+                })
+                .inner;
             if let Some(first) = values.first() {
                 let ganglion_k = ganglion_display_impedance_kohms((*first as f32) / 1000.0);
                 ui.label(format!("Ganglion 显示(kΩ)：{:.2}", ganglion_k));
@@ -1109,7 +3073,7 @@ impl QnmdSolApp {
                         .sum::<f32>()
                         / ch.len().max(1) as f32;
                     let std = variance.sqrt();
-                    let imp = cyton_impedance_from_std(std);
+                    let imp = cyton_impedance_from_std_with(std, &self.cyton_lead_off);
                     ui.label(format!("Ch1 即时估算(Ω)：{:.0}", imp));
                 }
             }
@@ -1127,8 +3091,59 @@ impl QnmdSolApp {
         } else {
             ui.label(self.text(UiText::ImpedanceNoData));
         }
+        if clear_trend {
+            self.impedance_history.clear();
+        }
     }
-    fn show_start_screen(&mut self, ctx: &egui::Context) {
+    /// Channel-by-channel correlation over the live window; bright red cells
+    /// off the diagonal point at bridged electrodes or a shared artifact.
+    fn show_correlation_heatmap(&mut self, ui: &mut egui::Ui) {
+        let now = ui.input(|i| i.time);
+        if self.correlation_throttle.ready(now) || self.correlation.is_none() {
+            self.correlation = self.last_frame.as_ref().map(|f| f.correlation_matrix());
+        }
+        let Some(matrix) = self.correlation.as_ref().filter(|m| !m.is_empty()) else {
+            ui.label(self.text(UiText::ImpedanceNoData));
+            return;
+        };
+        let n = matrix.len();
+        let cell = 14.0;
+        let (rect, response) =
+            ui.allocate_exact_size(Vec2::splat(cell * n as f32), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        for (i, row) in matrix.iter().enumerate() {
+            for (j, r) in row.iter().enumerate() {
+                let min = rect.min + Vec2::new(j as f32 * cell, i as f32 * cell);
+                painter.rect_filled(
+                    egui::Rect::from_min_size(min, Vec2::splat(cell - 1.0)),
+                    0.0,
+                    correlation_color(*r),
+                );
+            }
+        }
+        if let Some(pos) = response.hover_pos() {
+            let j = ((pos.x - rect.min.x) / cell) as usize;
+            let i = ((pos.y - rect.min.y) / cell) as usize;
+            if let Some(r) = matrix.get(i).and_then(|row| row.get(j)) {
+                response.on_hover_text(format!("Ch{} ↔ Ch{}: r = {:.2}", i + 1, j + 1, r));
+            }
+        }
+        let bridged: Vec<String> = correlated_pairs(matrix, 0.95)
+            .into_iter()
+            .map(|(i, j)| format!("Ch{}-Ch{}", i + 1, j + 1))
+            .collect();
+        if !bridged.is_empty() {
+            ui.colored_label(
+                Color32::from_rgb(231, 76, 60),
+                format!(
+                    "{} {}",
+                    self.text(UiText::CorrelatedPairs),
+                    bridged.join(", ")
+                ),
+            );
+        }
+    }
+    fn show_start_screen(&mut self, ctx: &egui::Context) {
         let mut visuals = egui::Visuals::light();
         visuals.widgets.noninteractive.bg_fill = Color32::from_rgb(242, 245, 250);
         visuals.window_fill = Color32::from_rgb(246, 248, 252);
@@ -1216,7 +3231,12 @@ impl QnmdSolApp {
     }
 }
 impl eframe::App for QnmdSolApp {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_config();
+    }
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.track_window_geometry(ctx);
+        self.capture_overlay_frame(ctx.input(|i| i.time));
         self.ensure_icon_texture(ctx);
         if !self.has_started {
             self.show_start_screen(ctx);
@@ -1224,77 +3244,16 @@ impl eframe::App for QnmdSolApp {
         }
         // 主题应用（苹果白默认，可切换黑夜）
         self.apply_theme(ctx);
-        // 键盘输入 (Sim Mode) - 保持不变
+        // 键盘输入 (Sim Mode)：按用户键位表解码
         if self.connection_mode == ConnectionMode::Simulation {
-            let mut input = SimInputIntent::default();
-            if ctx.input(|i| i.key_down(egui::Key::W)) {
-                input.w = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::S)) {
-                input.s = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::A)) {
-                input.a = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::D)) {
-                input.d = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::Space)) {
-                input.space = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::Z)) {
-                input.key_z = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::X)) {
-                input.key_x = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::C)) {
-                input.key_c = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::I)) {
-                input.up = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::K)) {
-                input.down = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::J)) {
-                input.left = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::L)) {
-                input.right = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::Q)) {
-                input.q = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::E)) {
-                input.e = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::U)) {
-                input.u = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::O)) {
-                input.o = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::ArrowUp)) {
-                input.arrow_up = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::ArrowDown)) {
-                input.arrow_down = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::ArrowLeft)) {
-                input.arrow_left = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::ArrowRight)) {
-                input.arrow_right = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::Num1)) {
-                input.key_1 = true;
-            }
-            if ctx.input(|i| i.key_down(egui::Key::Num2)) {
-                input.key_2 = true;
-            }
+            let input =
+                keymap::sim_intent(&self.sim_key_bindings, |key| ctx.input(|i| i.key_down(key)));
             self.tx_cmd.send(GuiCommand::UpdateSimInput(input)).ok();
         }
+        // 录制中按 F2 打事件标记
+        if self.is_recording && ctx.input(|i| i.key_pressed(MARK_EVENT_KEY)) {
+            self.mark_event();
+        }
         // 消息处理
         let mut msg_count = 0;
         while let Ok(msg) = self.rx.try_recv() {
@@ -1315,14 +3274,23 @@ impl eframe::App for QnmdSolApp {
                     BciMessage::Log(s) => self.log(&s),
                     BciMessage::Status(b) => {
                         self.is_connected = b;
-                        if !b {
+                        if b {
+                            self.check_saved_calibration();
+                        } else {
+                            // 也可能是重连放弃，引擎已停止推流
+                            self.is_streaming = false;
+                            self.stream_start = None;
+                            self.reconnecting = None;
                             self.resistance_values = None;
                             self.resistance_window_seconds = None;
                             self.resistance_last_measured = None;
                             self.resistance_labels.clear();
+                            if self.guided_phase.take().is_some() {
+                                self.clear_progress();
+                            }
                         }
                     }
-                    BciMessage::VJoyStatus(b) => self.is_vjoy_active = b,
+                    BciMessage::VJoyStatus(backend) => self.gamepad_backend = backend,
                     BciMessage::GamepadUpdate(gp) => {
                         self.gamepad_target = gp;
                         self.last_gamepad_update = Some(Instant::now());
@@ -1330,9 +3298,33 @@ impl eframe::App for QnmdSolApp {
                     BciMessage::ModelPrediction(scores) => {
                         self.model_scores = Some(scores);
                     }
+                    BciMessage::ClassifierClasses(classes) => {
+                        self.classifier_classes = classes;
+                        self.model_scores = None;
+                    }
                     BciMessage::RecordingStatus(b) => self.is_recording = b,
+                    BciMessage::Reconnecting(state) => self.reconnecting = state,
+                    BciMessage::StreamRate(rate) => {
+                        if let Some(start) = self.stream_start {
+                            self.drop_meter.record(
+                                start.elapsed().as_secs_f64(),
+                                rate.samples,
+                                rate.buffered,
+                            );
+                        }
+                        self.stream_rate = Some(rate);
+                    }
+                    BciMessage::DecodeConfidence(confidence) => {
+                        if self.show_decode_confidence {
+                            self.decode_confidence = confidence;
+                        }
+                    }
                     BciMessage::Spectrum(spec) => {
-                        self.last_spectrum = Some(spec);
+                        // 冻结并跟随回放窗口时，以回放位置的频谱为准
+                        self.spectrum_history.push(spec.clone());
+                        if !(self.wave_frozen && self.spectrum_follows_scrub) {
+                            self.last_spectrum = Some(spec);
+                        }
                     }
                     BciMessage::DataFrame(frame) => {
                         let sr = frame.sample_rate_hz;
@@ -1341,6 +3333,7 @@ impl eframe::App for QnmdSolApp {
                         }
                         self.last_frame = Some(frame.clone());
                         let channel_count = frame.samples.len();
+                        self.session_channel_count = channel_count.max(1);
                         let needs_new_pipeline = self
                             .waveform_pipeline
                             .as_ref()
@@ -1349,14 +3342,17 @@ impl eframe::App for QnmdSolApp {
                             || (self.waveform_sample_rate_hz - sr).abs() > f32::EPSILON;
                         if needs_new_pipeline {
                             self.waveform_pipeline = Some(WaveformPipeline::new(channel_count, sr));
-                            self.wave_smooth_state = vec![0.0; channel_count];
                             self.waveform_view = None;
                             self.stream_start = None;
                             self.waveform_clock = 0.0;
-                            self.total_samples_ingested = 0;
+                            self.wave_time_origin = None;
+                            self.wave_last_ts = None;
+                            self.drop_meter.clear();
                             self.waveform_last_len = 0;
+                            self.wave_history = None;
                             self.vertical_spacing = 240.0_f64.max(self.vertical_spacing);
                             self.stream_start = Some(Instant::now());
+                            self.stream_rate = None;
                             self.apply_waveform_pipeline_config();
                             if let Some(pipe) = &mut self.waveform_pipeline {
                                 let zeros = vec![0.0; channel_count];
@@ -1369,37 +3365,89 @@ impl eframe::App for QnmdSolApp {
                             if total_samples == 0 {
                                 continue;
                             }
-                            // 初次填充：填满当前窗口长度的尾巴
-                            let window_cap = (self.wave_window_seconds * sr as f64).ceil() as usize;
-                            let chunk_size =
-                                if self.waveform_clock == 0.0 && self.waveform_last_len == 0 {
-                                    total_samples.min(window_cap)
-                                } else {
-                                    // 后续每帧仅摄入约 1/8 秒的新数据，确保持续刷新又不积压
-                                    let target = (sr / 8.0).ceil() as usize;
-                                    target.clamp(1, total_samples.min(window_cap))
-                                };
-                            let start_idx = total_samples.saturating_sub(chunk_size);
+                            self.last_data_at = Some(Instant::now());
+                            if self.paused {
+                                continue;
+                            }
+                            // 初次填充或暂停恢复：填满当前窗口长度的尾巴，直接跳到最新数据
+                            let mut refill = (self.waveform_clock == 0.0
+                                && self.waveform_last_len == 0)
+                                || std::mem::take(&mut self.resume_catch_up);
+                            // 有设备时间戳时只送入上次之后的新采样，横轴用设备时间
+                            let device_ts = (frame.timestamps.len() == total_samples)
+                                .then_some(frame.timestamps.as_slice());
+                            let mut unseen = None;
+                            if let (Some(ts), Some(last), false) =
+                                (device_ts, self.wave_last_ts, refill)
+                            {
+                                unseen = unseen_start(ts, last);
+                                if unseen.is_none() {
+                                    refill = true;
+                                    self.wave_time_origin = None;
+                                }
+                            }
+                            let chunk_size = ingest_chunk_len(
+                                sr,
+                                total_samples,
+                                self.wave_window_seconds,
+                                refill,
+                            );
+                            let start_idx = match unseen {
+                                Some(idx) => idx.max(
+                                    total_samples
+                                        - ingest_chunk_len(
+                                            sr,
+                                            total_samples,
+                                            self.wave_window_seconds,
+                                            true,
+                                        ),
+                                ),
+                                None => total_samples.saturating_sub(chunk_size),
+                            };
+                            if start_idx >= total_samples {
+                                continue;
+                            }
+                            let chunk_size = total_samples - start_idx;
                             let mut tails: Vec<Vec<f32>> = Vec::with_capacity(frame.samples.len());
                             for ch in &frame.samples {
                                 tails.push(ch.iter().skip(start_idx).cloned().collect());
                             }
-                            let start_time = self.waveform_clock;
+                            let start_time = match device_ts {
+                                Some(ts) => {
+                                    let origin =
+                                        *self.wave_time_origin.get_or_insert(ts[start_idx]);
+                                    (ts[start_idx] - origin) as f32
+                                }
+                                None => self.waveform_clock,
+                            };
                             pipe.ingest_block(start_time, &tails);
-                            self.waveform_clock += chunk_size as f32 / sr;
+                            self.waveform_clock = start_time + chunk_size as f32 / sr;
+                            self.wave_last_ts = device_ts.and_then(|ts| ts.last().copied());
                             self.waveform_last_len = total_samples;
-                            self.total_samples_ingested =
-                                self.total_samples_ingested.saturating_add(chunk_size);
-                            self.waveform_view = Some(pipe.view());
+                            if !self.wave_frozen {
+                                self.waveform_view = Some(pipe.view());
+                            }
+                            let history =
+                                self.wave_history.get_or_insert_with(|| TimeSeriesFrame {
+                                    sample_rate_hz: sr,
+                                    channel_labels: frame.channel_labels.clone(),
+                                    samples: vec![Vec::new(); channel_count],
+                                    timestamps: Vec::new(),
+                                });
+                            append_history(
+                                history,
+                                &tails,
+                                (WAVE_HISTORY_SECONDS * sr as f64) as usize,
+                            );
                             self.waveform_sample_rate_hz = sr;
-                            self.last_data_at = Some(Instant::now());
                         }
                     }
-                    BciMessage::CalibrationResult(_, max) => {
+                    BciMessage::CalibrationResult(channels, max) => {
                         self.is_calibrating = false;
                         self.clear_progress();
-                        if self.calib_rest_max == 0.0 {
+                        if !self.calib_action {
                             self.calib_rest_max = max;
+                            self.calib_rest_channels = channels;
                             let msg = match self.language {
                                 Language::English => format!("Rest µ-power: {:.3}", max),
                                 Language::Chinese => format!("基线：{:.1}", max),
@@ -1407,14 +3455,25 @@ impl eframe::App for QnmdSolApp {
                             self.log(&msg);
                         } else {
                             self.calib_act_max = max;
+                            self.calib_act_channels = channels;
                             let msg = match self.language {
                                 Language::English => format!("Imagery µ-power: {:.3}", max),
                                 Language::Chinese => format!("动作：{:.1}", max),
                             };
                             self.log(&msg);
+                            self.finish_calibration();
                             // Hardware mode now uses pure EEG µ-band power mapping for forward axis.
                         }
                     }
+                    BciMessage::CalibrationProgress(phase, progress) => {
+                        self.guided_phase = Some(phase);
+                        self.set_progress(self.guided_instruction(phase), progress);
+                    }
+                    BciMessage::GuidedCalibrationResult(capture) => {
+                        self.guided_phase = None;
+                        self.clear_progress();
+                        self.finish_guided_calibration(capture);
+                    }
                 }
             }
         }
@@ -1439,12 +3498,13 @@ impl eframe::App for QnmdSolApp {
         self.gamepad_visual.y = self.gamepad_target.y;
         self.gamepad_visual.lb = self.gamepad_target.lb;
         self.gamepad_visual.rb = self.gamepad_target.rb;
-        self.gamepad_visual.lt = self.gamepad_target.lt;
-        self.gamepad_visual.rt = self.gamepad_target.rt;
+        self.gamepad_visual.lt = Self::lerp(self.gamepad_visual.lt, self.gamepad_target.lt, speed);
+        self.gamepad_visual.rt = Self::lerp(self.gamepad_visual.rt, self.gamepad_target.rt, speed);
         self.gamepad_visual.dpad_up = self.gamepad_target.dpad_up;
         self.gamepad_visual.dpad_down = self.gamepad_target.dpad_down;
         self.gamepad_visual.dpad_left = self.gamepad_target.dpad_left;
         self.gamepad_visual.dpad_right = self.gamepad_target.dpad_right;
+        self.check_lead_off(ctx.input(|i| i.time));
         if self.is_streaming {
             ctx.request_repaint();
         }
@@ -1519,6 +3579,11 @@ impl eframe::App for QnmdSolApp {
                                 self.theme_dark = true;
                                 self.apply_theme(ctx);
                             }
+                            if ui.button(self.text(UiText::RunSelfTest)).clicked() {
+                                self.run_self_test();
+                            }
+                            let startup_label = self.text(UiText::SelfTestOnStartup);
+                            ui.checkbox(&mut self.self_test_on_startup, startup_label);
                         });
                         ui.separator();
                         ui.label(self.text(UiText::LanguageSwitch));
@@ -1543,6 +3608,8 @@ impl eframe::App for QnmdSolApp {
                         if selected_language != self.language {
                             self.set_language(selected_language);
                         }
+                        let response_label = self.text(UiText::ReportFilterResponse);
+                        ui.checkbox(&mut self.report_filter_response, response_label);
                         if ui.button(self.text(UiText::ReportFeedback)).clicked() {
                             match self.generate_report() {
                                 Ok(path) => {
@@ -1563,17 +3630,131 @@ impl eframe::App for QnmdSolApp {
                         }
                         ui.separator();
                         if self.connection_mode == ConnectionMode::Hardware {
-                            ui.label(self.text(UiText::PortLabel));
-                            egui::ComboBox::from_id_source("port_selector_side")
-                                .selected_text(&self.selected_port)
-                                .show_ui(ui, |ui| {
-                                    for p in &self.available_ports {
-                                        ui.selectable_value(&mut self.selected_port, p.clone(), p);
-                                    }
-                                });
-                            if ui.button(self.text(UiText::RefreshPorts)).clicked() {
-                                self.refresh_ports();
+                            ui.label(self.text(UiText::BoardLabel));
+                            let mut board = self.board;
+                            ui.add_enabled_ui(!self.is_connected, |ui| {
+                                egui::ComboBox::from_id_source("board_selector_side")
+                                    .selected_text(board.label())
+                                    .show_ui(ui, |ui| {
+                                        for option in BoardKind::ALL {
+                                            ui.selectable_value(&mut board, option, option.label());
+                                        }
+                                    })
+                            })
+                            .response
+                            .on_hover_text(self.text(UiText::BoardHint));
+                            if board != self.board {
+                                self.board = board;
+                                if board.layout() != self.montage.layout {
+                                    self.set_channel_layout(board.layout());
+                                }
+                            }
+                            ui.add_enabled_ui(self.board.needs_serial_port(), |ui| {
+                                ui.label(self.text(UiText::PortLabel));
+                                egui::ComboBox::from_id_source("port_selector_side")
+                                    .selected_text(&self.selected_port)
+                                    .show_ui(ui, |ui| {
+                                        for p in &self.available_ports {
+                                            ui.selectable_value(
+                                                &mut self.selected_port,
+                                                p.clone(),
+                                                p,
+                                            );
+                                        }
+                                    });
+                                if ui.button(self.text(UiText::RefreshPorts)).clicked() {
+                                    self.refresh_ports();
+                                }
+                            });
+                            let clamp_prefix = self.text(UiText::SampleClampPrefix);
+                            if ui
+                                .add(
+                                    egui::DragValue::new(&mut self.sample_clamp_uv)
+                                        .clamp_range(1e3..=1e9)
+                                        .speed(1e4)
+                                        .prefix(clamp_prefix)
+                                        .suffix(" µV"),
+                                )
+                                .on_hover_text(self.text(UiText::SampleClampHint))
+                                .changed()
+                            {
+                                self.tx_cmd
+                                    .send(GuiCommand::SetSampleClamp(self.sample_clamp_uv))
+                                    .ok();
+                            }
+                            let reconnect_label = self.text(UiText::AutoReconnect);
+                            if ui
+                                .checkbox(&mut self.auto_reconnect, reconnect_label)
+                                .on_hover_text(self.text(UiText::AutoReconnectHint))
+                                .changed()
+                            {
+                                self.tx_cmd
+                                    .send(GuiCommand::SetAutoReconnect(self.auto_reconnect))
+                                    .ok();
                             }
+                            let lsl_label = self.text(UiText::LslOutput);
+                            if ui
+                                .checkbox(&mut self.lsl_output, lsl_label)
+                                .on_hover_text(self.text(UiText::LslOutputHint))
+                                .changed()
+                            {
+                                self.tx_cmd
+                                    .send(GuiCommand::EnableLsl(self.lsl_output))
+                                    .ok();
+                            }
+                            let (osc_label, osc_hint) = (
+                                self.text(UiText::OscOutput),
+                                self.text(UiText::OscOutputHint),
+                            );
+                            let osc_toggled = ui
+                                .checkbox(&mut self.osc_output, osc_label)
+                                .on_hover_text(osc_hint)
+                                .changed();
+                            let addr_edited = ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut self.osc_addr)
+                                        .desired_width(120.0),
+                                )
+                                .lost_focus();
+                            if osc_toggled || (self.osc_output && addr_edited) {
+                                self.apply_osc_output();
+                            }
+                            let (ws_label, ws_hint) = (
+                                self.text(UiText::WebSocketOutput),
+                                self.text(UiText::WebSocketOutputHint),
+                            );
+                            let ws_toggled = ui
+                                .checkbox(&mut self.ws_output, ws_label)
+                                .on_hover_text(ws_hint)
+                                .changed();
+                            let addr_edited = ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut self.ws_addr)
+                                        .desired_width(120.0),
+                                )
+                                .lost_focus();
+                            if ws_toggled || (self.ws_output && addr_edited) {
+                                self.apply_ws_output();
+                            }
+                        } else {
+                            ui.horizontal(|ui| {
+                                ui.label(self.text(UiText::SimSampleRate))
+                                    .on_hover_text(self.text(UiText::SimSampleRateHint));
+                                for hz in SIM_SAMPLE_RATES {
+                                    if ui
+                                        .selectable_label(
+                                            self.sim_sample_rate_hz == hz,
+                                            format!("{hz:.0} Hz"),
+                                        )
+                                        .clicked()
+                                        && self.sim_sample_rate_hz != hz
+                                    {
+                                        self.sim_sample_rate_hz = hz;
+                                        self.tx_cmd.send(GuiCommand::SetSimSampleRate(hz)).ok();
+                                    }
+                                }
+                            });
+                            self.show_sim_key_editor(ui);
                         }
                         ui.separator();
                         ui.heading(self.text(UiText::ModelSection));
@@ -1592,6 +3773,25 @@ impl eframe::App for QnmdSolApp {
                                 self.log(&msg);
                             }
                         }
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button(self.text(UiText::LoadClassifier))
+                                .on_hover_text(self.text(UiText::LoadClassifierHint))
+                                .clicked()
+                            {
+                                self.tx_cmd.send(GuiCommand::LoadClassifier(None)).ok();
+                            }
+                            let drive_label = self.text(UiText::ClassifierDrive);
+                            if ui
+                                .checkbox(&mut self.classifier_drive, drive_label)
+                                .on_hover_text(self.text(UiText::ClassifierDriveHint))
+                                .changed()
+                            {
+                                self.tx_cmd
+                                    .send(GuiCommand::SetClassifierDrive(self.classifier_drive))
+                                    .ok();
+                            }
+                        });
                         if let Some(status) = &self.model_status {
                             ui.label(format!(
                                 "{}: {}",
@@ -1632,6 +3832,7 @@ impl eframe::App for QnmdSolApp {
                                     .send(GuiCommand::Connect(
                                         self.connection_mode,
                                         self.selected_port.clone(),
+                                        self.board,
                                     ))
                                     .ok();
                             }
@@ -1651,18 +3852,31 @@ impl eframe::App for QnmdSolApp {
                                     self.tx_cmd.send(GuiCommand::StartStream).ok();
                                     self.is_streaming = true;
                                     self.stream_start = Some(Instant::now());
+                                    self.stream_rate = None;
+                                    // 暂停期间的滤波器状态已过时，避免恢复时出现瞬态
+                                    if let Some(pipe) = self.waveform_pipeline.as_mut() {
+                                        pipe.reset_filters();
+                                    }
                                 }
+                                self.drop_meter.clear();
                             }
                             if ui.button(self.text(UiText::ResetView)).clicked() {
                                 self.waveform_pipeline = None;
                                 self.waveform_view = None;
+                                self.wave_history = None;
+                                self.set_frozen(false);
                                 self.waveform_last_len = 0;
                                 self.waveform_clock = 0.0;
-                                self.wave_smooth_state.clear();
+                                self.wave_time_origin = None;
+                                self.wave_last_ts = None;
                                 self.stream_start = None;
+                                self.drop_meter.clear();
                                 self.gamepad_target = GamepadState::default();
                                 self.last_gamepad_update = None;
                             }
+                            if ui.button(self.text(UiText::ClearHistory)).clicked() {
+                                self.clear_history();
+                            }
                             let follow_label = if self.follow_latest {
                                 self.text(UiText::FollowOn)
                             } else {
@@ -1671,6 +3885,53 @@ impl eframe::App for QnmdSolApp {
                             if ui.button(follow_label).clicked() {
                                 self.follow_latest = !self.follow_latest;
                             }
+                            let harmonics_label = self.text(UiText::NotchHarmonics);
+                            if ui
+                                .checkbox(&mut self.notch_harmonics, harmonics_label)
+                                .changed()
+                            {
+                                self.tx_cmd
+                                    .send(GuiCommand::SetNotchHarmonics(self.notch_harmonics))
+                                    .ok();
+                            }
+                            let mut layout = self.montage.layout;
+                            egui::ComboBox::from_id_source("channel_layout")
+                                .selected_text(format!(
+                                    "{} {}",
+                                    self.text(UiText::ChannelLayout),
+                                    layout.label()
+                                ))
+                                .show_ui(ui, |ui| {
+                                    for option in QuickMontage::ALL {
+                                        ui.selectable_value(&mut layout, option, option.label());
+                                    }
+                                })
+                                .response
+                                .on_hover_text(self.text(UiText::ChannelLayoutHint));
+                            if layout != self.montage.layout {
+                                self.set_channel_layout(layout);
+                            }
+                            let mut skip_first = self.montage.leading_skipped();
+                            let skip_changed = ui
+                                .add(
+                                    egui::DragValue::new(&mut skip_first)
+                                        .clamp_range(0..=4)
+                                        .prefix(self.text(UiText::SkipFirstChannels)),
+                                )
+                                .changed();
+                            if skip_changed {
+                                self.montage.set_skip_first(skip_first);
+                            }
+                            let hide_label = self.text(UiText::HideSkippedChannels);
+                            let hide_changed = ui
+                                .checkbox(&mut self.montage.hide_skipped, hide_label)
+                                .changed();
+                            if skip_changed || hide_changed {
+                                self.tx_cmd
+                                    .send(GuiCommand::SetMontage(self.montage.clone()))
+                                    .ok();
+                                self.apply_waveform_pipeline_config();
+                            }
                             if self.connection_mode == ConnectionMode::Simulation
                                 && self.is_streaming
                             {
@@ -1706,6 +3967,10 @@ impl eframe::App for QnmdSolApp {
                                     };
                                     self.tx_cmd.send(GuiCommand::SetMappingHelper(cmd)).ok();
                                 }
+                                let reload_label = self.text(UiText::ReloadMapping);
+                                if ui.button(reload_label).clicked() {
+                                    self.tx_cmd.send(GuiCommand::LoadMapping(None)).ok();
+                                }
                                 ui.horizontal_wrapped(|ui| {
                                     if ui.button("Pulse A").clicked() {
                                         self.tx_cmd
@@ -1821,19 +4086,157 @@ impl eframe::App for QnmdSolApp {
                                     .ok();
                             }
                         }
+                        if self.is_recording {
+                            let hint = self.text(UiText::EventLabelHint);
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.event_label)
+                                        .desired_width(110.0)
+                                        .hint_text(self.record_label.as_str()),
+                                )
+                                .on_hover_text(hint);
+                                let mark_label = format!("{} (F2)", self.text(UiText::MarkEvent));
+                                if ui.button(mark_label).on_hover_text(hint).clicked() {
+                                    self.mark_event();
+                                }
+                            });
+                        }
+                        let snap_label = self.text(UiText::SnapEvents);
+                        if ui
+                            .checkbox(&mut self.snap_events_to_samples, snap_label)
+                            .changed()
+                        {
+                            let alignment = if self.snap_events_to_samples {
+                                EventAlignment::NearestSample
+                            } else {
+                                EventAlignment::WallClock
+                            };
+                            self.tx_cmd
+                                .send(GuiCommand::SetEventAlignment(alignment))
+                                .ok();
+                        }
+                        ui.add_enabled_ui(!self.is_recording, |ui| {
+                            let index_label = self.text(UiText::RecordSampleIndex);
+                            if ui
+                                .checkbox(&mut self.record_sample_index, index_label)
+                                .changed()
+                            {
+                                self.tx_cmd
+                                    .send(GuiCommand::SetRecordSampleIndex(
+                                        self.record_sample_index,
+                                    ))
+                                    .ok();
+                            }
+                            let mne_label = self.text(UiText::RecordMneExport);
+                            if ui
+                                .checkbox(&mut self.record_mne_export, mne_label)
+                                .on_hover_text(self.text(UiText::RecordMneExportHint))
+                                .changed()
+                            {
+                                self.tx_cmd
+                                    .send(GuiCommand::SetRecordMneExport(self.record_mne_export))
+                                    .ok();
+                            }
+                            let labels_label = self.text(UiText::RecordLabels);
+                            if ui
+                                .checkbox(&mut self.record_labels, labels_label)
+                                .on_hover_text(self.text(UiText::RecordLabelsHint))
+                                .changed()
+                            {
+                                self.tx_cmd
+                                    .send(GuiCommand::SetRecordLabels(self.record_labels))
+                                    .ok();
+                            }
+                            let dual_label = self.text(UiText::RecordDual);
+                            if ui
+                                .checkbox(&mut self.record_dual, dual_label)
+                                .on_hover_text(self.text(UiText::RecordDualHint))
+                                .changed()
+                            {
+                                let mode = if self.record_dual {
+                                    RecordMode::Dual
+                                } else {
+                                    RecordMode::RawOnly
+                                };
+                                self.tx_cmd.send(GuiCommand::SetRecordMode(mode)).ok();
+                            }
+                            ui.horizontal(|ui| {
+                                ui.label(self.text(UiText::RotateFiles));
+                                let mut rotation = self.record_rotation;
+                                if ui
+                                    .selectable_label(
+                                        rotation == Rotation::Off,
+                                        self.text(UiText::Off),
+                                    )
+                                    .clicked()
+                                {
+                                    rotation = Rotation::Off;
+                                }
+                                let by_time = matches!(rotation, Rotation::EverySeconds(_));
+                                if ui.selectable_label(by_time, "min").clicked() && !by_time {
+                                    rotation = Rotation::EverySeconds(600.0);
+                                }
+                                let by_size = matches!(rotation, Rotation::EveryBytes(_));
+                                if ui.selectable_label(by_size, "MB").clicked() && !by_size {
+                                    rotation = Rotation::EveryBytes(100 * 1024 * 1024);
+                                }
+                                match &mut rotation {
+                                    Rotation::EverySeconds(secs) => {
+                                        let mut minutes = *secs / 60.0;
+                                        ui.add(
+                                            egui::DragValue::new(&mut minutes)
+                                                .clamp_range(1.0..=240.0)
+                                                .suffix(" min"),
+                                        );
+                                        *secs = minutes * 60.0;
+                                    }
+                                    Rotation::EveryBytes(bytes) => {
+                                        let mut mb = *bytes / (1024 * 1024);
+                                        ui.add(
+                                            egui::DragValue::new(&mut mb)
+                                                .clamp_range(1..=4096)
+                                                .suffix(" MB"),
+                                        );
+                                        *bytes = mb * 1024 * 1024;
+                                    }
+                                    Rotation::Off => {}
+                                }
+                                if rotation != self.record_rotation {
+                                    self.record_rotation = rotation;
+                                    self.tx_cmd
+                                        .send(GuiCommand::SetRecordingRotation(rotation))
+                                        .ok();
+                                }
+                            });
+                        });
                         if self.is_connected && self.is_streaming {
                             if ui.button(self.text(UiText::RecordRelax)).clicked() {
                                 self.calib_rest_max = 0.0;
+                                self.calib_action = false;
                                 self.is_calibrating = true;
                                 self.calib_timer = 3.0;
                                 self.tx_cmd.send(GuiCommand::StartCalibration(false)).ok();
                             }
                             if ui.button(self.text(UiText::RecordAction)).clicked() {
                                 self.calib_act_max = 0.0;
+                                self.calib_action = true;
                                 self.is_calibrating = true;
                                 self.calib_timer = 3.0;
                                 self.tx_cmd.send(GuiCommand::StartCalibration(true)).ok();
                             }
+                            let guided_label = self.text(UiText::GuidedCalibration);
+                            if ui
+                                .add_enabled(
+                                    self.guided_phase.is_none() && !self.is_calibrating,
+                                    egui::Button::new(guided_label),
+                                )
+                                .clicked()
+                            {
+                                self.start_guided_calibration();
+                            }
+                            if let Some(phase) = self.guided_phase {
+                                ui.strong(self.guided_instruction(phase));
+                            }
                             ui.label(format!(
                                 "{} {:.1}",
                                 self.text(UiText::Threshold),
@@ -1867,12 +4270,178 @@ impl eframe::App for QnmdSolApp {
                     );
                     ui.separator();
                 }
-                ui.label(self.text(UiText::Controller));
-                visualizer::draw_xbox_controller(ui, &self.gamepad_visual);
+                ui.horizontal(|ui| {
+                    ui.label(self.text(UiText::Controller));
+                    ui.weak(
+                        self.gamepad_backend
+                            .unwrap_or(self.text(UiText::NoVirtualGamepad)),
+                    );
+                    let confidence_label = self.text(UiText::DecodeConfidence);
+                    if ui
+                        .checkbox(&mut self.show_decode_confidence, confidence_label)
+                        .on_hover_text(self.text(UiText::DecodeConfidenceHint))
+                        .changed()
+                    {
+                        self.decode_confidence.clear();
+                        self.tx_cmd
+                            .send(GuiCommand::SetDecodeConfidence(self.show_decode_confidence))
+                            .ok();
+                    }
+                    egui::ComboBox::from_id_source("controller_style")
+                        .selected_text(self.controller_style.label())
+                        .show_ui(ui, |ui| {
+                            for style in visualizer::ControllerStyle::ALL {
+                                ui.selectable_value(
+                                    &mut self.controller_style,
+                                    style,
+                                    style.label(),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text(self.text(UiText::ControllerStyleHint));
+                });
+                let confidence = self
+                    .show_decode_confidence
+                    .then_some(self.decode_confidence.as_slice());
+                visualizer::draw_controller(
+                    ui,
+                    self.controller_style,
+                    &self.gamepad_visual,
+                    confidence,
+                );
+                if self.show_decode_confidence && !self.decode_confidence.is_empty() {
+                    ui.horizontal_wrapped(|ui| {
+                        for (action, c) in &self.decode_confidence {
+                            ui.monospace(format!("{} {:.0}%", action.label(), c * 100.0));
+                        }
+                    });
+                }
+                ui.horizontal(|ui| {
+                    let limit_label = self.text(UiText::VJoyRateLimit);
+                    let mut rate_changed = ui
+                        .checkbox(&mut self.vjoy_rate_limit, limit_label)
+                        .on_hover_text(self.text(UiText::VJoyRateHint))
+                        .changed();
+                    rate_changed |= ui
+                        .add_enabled(
+                            self.vjoy_rate_limit,
+                            egui::DragValue::new(&mut self.vjoy_rate_hz)
+                                .clamp_range(10.0..=1000.0)
+                                .suffix(" Hz"),
+                        )
+                        .changed();
+                    if rate_changed {
+                        self.send_vjoy_rate();
+                    }
+                });
+                let dead_zone_label = self.text(UiText::StickDeadZone);
+                let dead_zone_hint = self.text(UiText::StickDeadZoneHint);
+                ui.collapsing(self.text(UiText::AxisGains), |ui| {
+                    let gains = &mut self.axis_gains;
+                    let mut gains_changed = false;
+                    for (name, gain) in [
+                        ("LX", &mut gains.lx_gain),
+                        ("LY", &mut gains.ly_gain),
+                        ("RX", &mut gains.rx_gain),
+                        ("RY", &mut gains.ry_gain),
+                    ] {
+                        gains_changed |= ui
+                            .add(egui::Slider::new(gain, 0.0..=2.0).text(name))
+                            .changed();
+                    }
+                    if gains_changed {
+                        self.tx_cmd
+                            .send(GuiCommand::SetAxisGains(self.axis_gains))
+                            .ok();
+                    }
+                    let response = &mut self.stick_response;
+                    let mut response_changed = ui
+                        .add(
+                            egui::Slider::new(&mut response.dead_zone, 0.0..=0.5)
+                                .text(dead_zone_label),
+                        )
+                        .on_hover_text(dead_zone_hint)
+                        .changed();
+                    egui::ComboBox::from_id_source("stick_curve")
+                        .selected_text(response.curve.label())
+                        .show_ui(ui, |ui| {
+                            for curve in ResponseCurve::ALL {
+                                response_changed |= ui
+                                    .selectable_value(&mut response.curve, curve, curve.label())
+                                    .changed();
+                            }
+                        });
+                    if response_changed {
+                        self.tx_cmd
+                            .send(GuiCommand::SetStickResponse(self.stick_response))
+                            .ok();
+                    }
+                })
+                .header_response
+                .on_hover_text(self.text(UiText::AxisGainsHint));
+                ui.horizontal(|ui| {
+                    ui.label(self.text(UiText::TriggerDebounce))
+                        .on_hover_text(self.text(UiText::TriggerDebounceHint));
+                    let debounce = &mut self.trigger_debounce;
+                    let mut gap_pct = debounce.hysteresis * 100.0;
+                    let mut debounce_changed = ui
+                        .add(
+                            egui::DragValue::new(&mut gap_pct)
+                                .clamp_range(0.0..=90.0)
+                                .suffix(" %"),
+                        )
+                        .changed();
+                    debounce.hysteresis = gap_pct / 100.0;
+                    debounce_changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut debounce.hold_ms)
+                                .clamp_range(0.0..=1000.0)
+                                .suffix(" ms"),
+                        )
+                        .changed();
+                    if debounce_changed {
+                        self.tx_cmd
+                            .send(GuiCommand::SetTriggerDebounce(self.trigger_debounce))
+                            .ok();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label(self.text(UiText::DecodeFeature))
+                        .on_hover_text(self.text(UiText::DecodeFeatureHint));
+                    let mut feature_changed = false;
+                    egui::ComboBox::from_id_source("decode_feature")
+                        .selected_text(self.decode_feature.label())
+                        .show_ui(ui, |ui| {
+                            for kind in FeatureKind::ALL {
+                                feature_changed |= ui
+                                    .selectable_value(&mut self.decode_feature, kind, kind.label())
+                                    .changed();
+                            }
+                        });
+                    feature_changed |= ui
+                        .add_enabled(
+                            self.decode_feature != FeatureKind::Instant,
+                            egui::DragValue::new(&mut self.decode_feature_window)
+                                .clamp_range(2..=1000)
+                                .suffix(" samples"),
+                        )
+                        .changed();
+                    if feature_changed {
+                        self.send_decode_feature();
+                    }
+                });
                 ui.separator();
                 ui.label(self.text(UiText::ModelOutput));
-                if let Some(status) = &self.model_status {
-                    let classes = &status.info.classes;
+                // 引擎分类器的类别优先，其次是 GUI 读取的模型文件
+                let classes = if !self.classifier_classes.is_empty() {
+                    Some(&self.classifier_classes)
+                } else {
+                    self.model_status
+                        .as_ref()
+                        .map(|status| &status.info.classes)
+                };
+                if let Some(classes) = classes {
                     let scores = self
                         .model_scores
                         .clone()
@@ -1911,6 +4480,7 @@ impl eframe::App for QnmdSolApp {
                 for (label, tab) in [
                     (self.text(UiText::TabWaveform), ViewTab::Waveform),
                     (self.text(UiText::TabSpectrum), ViewTab::Spectrum),
+                    (self.text(UiText::TabSpectrogram), ViewTab::Spectrogram),
                     (self.text(UiText::TabPng), ViewTab::Png),
                     (self.text(UiText::TabCalibration), ViewTab::Calibration),
                     (self.text(UiText::TabImpedance), ViewTab::Impedance),
@@ -1925,6 +4495,7 @@ impl eframe::App for QnmdSolApp {
             match self.selected_tab {
                 ViewTab::Waveform => self.show_waveform(ui, frame),
                 ViewTab::Spectrum => self.show_spectrum(ui),
+                ViewTab::Spectrogram => self.show_spectrogram(ui),
                 ViewTab::Png => self.show_png(ui),
                 ViewTab::Calibration => self.show_calibration(ui),
                 ViewTab::Impedance => self.show_impedance(ui),
@@ -1932,284 +4503,936 @@ impl eframe::App for QnmdSolApp {
         });
     }
 }
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum Language {
-    English,
-    Chinese,
+/// System alert sound: `MessageBeep` from user32 on Windows, the terminal bell elsewhere.
+fn alert_beep() {
+    #[cfg(windows)]
+    unsafe {
+        type FnMessageBeep = unsafe extern "system" fn(u32) -> i32;
+        if let Ok(lib) = libloading::Library::new("user32.dll") {
+            if let Ok(beep) = lib.get::<FnMessageBeep>(b"MessageBeep") {
+                beep(0xFFFF_FFFF);
+            }
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        print!("\x07");
+        let _ = std::io::stdout().flush();
+    }
 }
-impl Language {
-    fn text(&self, key: UiText) -> &'static str {
-        match (self, key) {
-            (Language::English, UiText::Title) => "Neurostick demo v0.1",
-            (Language::English, UiText::Subtitle) => "Neural Interface Control",
-            (Language::English, UiText::Sim) => "Simulation",
-            (Language::English, UiText::Real) => "Hardware",
-            (Language::English, UiText::Connect) => "Connect",
-            (Language::English, UiText::Disconnect) => "Disconnect",
-            (Language::English, UiText::StartStream) => "Start Stream",
-            (Language::English, UiText::StopStream) => "Stop Stream",
-            (Language::English, UiText::ResetView) => "Reset View",
-            (Language::English, UiText::Controller) => "Xbox Controller Visualizer",
-            (Language::English, UiText::Data) => "AI Data Collection",
-            (Language::English, UiText::Recording) => "Recording...",
-            (Language::English, UiText::HardwareRequired) => "Hardware required",
-            (Language::English, UiText::KeyHint) => "Try Keys: WASD / Space / ZXC / QEUO / Arrows",
-            (Language::English, UiText::ConnectFirst) => "Connect first.",
-            (Language::English, UiText::Threshold) => "Trigger Threshold:",
-            (Language::English, UiText::Calibration) => "Calibration",
-            (Language::English, UiText::FollowOn) => "Follow Latest: ON",
-            (Language::English, UiText::FollowOff) => "Follow Latest: OFF",
-            (Language::English, UiText::Ready) => "Neurostick Demo v0.1 Ready.",
-            (Language::English, UiText::LanguagePrompt) => "Choose your language",
-            (Language::English, UiText::StartSubtitle) => "Pick a language to start",
-            (Language::English, UiText::StartHeading) => "Welcome to Neurostick",
-            (Language::English, UiText::StartRecording) => "Record",
-            (Language::English, UiText::StopRecording) => "Stop",
-            (Language::English, UiText::FftSize) => "FFT Size:",
-            (Language::English, UiText::Update) => "Update",
-            (Language::English, UiText::GenerateWaveformPng) => "Generate Waveform PNG",
-            (Language::English, UiText::GenerateSpectrumPng) => "Generate Spectrum PNG",
-            (Language::English, UiText::WaveformPngLabel) => "Waveform PNG:",
-            (Language::English, UiText::SpectrumPngLabel) => "Spectrum PNG:",
-            (Language::English, UiText::NoSpectrumYet) => {
-                "No spectrum yet. Start streaming to populate."
-            }
-            (Language::English, UiText::RecordRelax) => "1. Record Relax (3s)",
-            (Language::English, UiText::RecordAction) => "2. Record Action (3s)",
-            (Language::English, UiText::ConnectStreamFirst) => "Connect & Stream first.",
-            (Language::English, UiText::Loading) => "Working...",
-            (Language::English, UiText::Sensitivity) => "Sensitivity",
-            (Language::English, UiText::Smoothness) => "Smoothing",
-            (Language::English, UiText::Window) => "Window",
-            (Language::English, UiText::Window30) => "30s",
-            (Language::English, UiText::Window60) => "60s",
-            (Language::English, UiText::TabWaveform) => "Waveform",
-            (Language::English, UiText::TabSpectrum) => "Spectrum",
-            (Language::English, UiText::TabPng) => "PNG Export",
-            (Language::English, UiText::TabCalibration) => "Calibration",
-            (Language::English, UiText::TabImpedance) => "Resistance Check",
-            (Language::English, UiText::ImpedanceDesc) => {
-                "Estimate electrode impedance from the latest buffer (Cyton math)."
-            }
-            (Language::English, UiText::ImpedanceAction) => "Run check",
-            (Language::English, UiText::ImpedanceNoData) => "No impedance result yet.",
-            (Language::English, UiText::ImpedanceUpdated) => "Impedance results updated.",
-            (Language::English, UiText::ImpedanceChannelHeader) => "Channel",
-            (Language::English, UiText::ImpedanceValueHeader) => "Impedance (kOhm)",
-            (Language::English, UiText::PortLabel) => "Port:",
-            (Language::English, UiText::RefreshPorts) => "Refresh",
-            (Language::English, UiText::PortsScanned) => "Ports scanned:",
-            (Language::English, UiText::InjectArtifact) => "Inject Artifact",
-            (Language::English, UiText::ReportFeedback) => "Report Feedback",
-            (Language::English, UiText::ThemeLight) => "Light",
-            (Language::English, UiText::ThemeDark) => "Dark",
-            (Language::English, UiText::LanguageSwitch) => "Language",
-            (Language::English, UiText::Logs) => "Logs",
-            (Language::English, UiText::ReportLogs) => "Last Logs:",
-            (Language::English, UiText::Resolution) => "Resolution",
-            (Language::English, UiText::Maximize) => "Maximize",
-            (Language::English, UiText::AutoY) => "Auto Y",
-            (Language::English, UiText::FixedUv) => "Fixed uV",
-            (Language::English, UiText::Notch50) => "50Hz Notch",
-            (Language::English, UiText::Stats) => "Stats",
-            (Language::English, UiText::TimeAxis) => "Time span (s)",
-            (Language::English, UiText::ShowPanel) => "Show Panel",
-            (Language::English, UiText::HidePanel) => "Hide Panel",
-            (Language::English, UiText::ImpedanceLegend) => {
-                "Good <500k | Acceptable 0.5-2.5M | Poor >2.5M | Railed = no contact"
-            }
-            (Language::English, UiText::ModelSection) => "AI Model",
-            (Language::English, UiText::ModelPath) => "Path",
-            (Language::English, UiText::ModelReload) => "Load / Reload",
-            (Language::English, UiText::ModelLoaded) => "Model",
-            (Language::English, UiText::ModelNone) => "No model loaded.",
-            (Language::English, UiText::ModelError) => "Model error",
-            (Language::English, UiText::ModelClasses) => "Classes",
-            (Language::English, UiText::ModelChannels) => "Channels",
-            (Language::English, UiText::ModelOutput) => "Model Output",
-            (Language::Chinese, UiText::Title) => "Neurostick 演示 v0.1",
-            (Language::Chinese, UiText::Subtitle) => "神经接口控制",
-            (Language::Chinese, UiText::Sim) => "模拟模式",
-            (Language::Chinese, UiText::Real) => "实机模式",
-            (Language::Chinese, UiText::Connect) => "连接",
-            (Language::Chinese, UiText::Disconnect) => "断开",
-            (Language::Chinese, UiText::StartStream) => "开始采集",
-            (Language::Chinese, UiText::StopStream) => "停止采集",
-            (Language::Chinese, UiText::ResetView) => "重置视图",
-            (Language::Chinese, UiText::Controller) => "手柄可视化",
-            (Language::Chinese, UiText::Data) => "AI数据采集",
-            (Language::Chinese, UiText::Recording) => "录制中...",
-            (Language::Chinese, UiText::HardwareRequired) => "需要硬件设备",
-            (Language::Chinese, UiText::KeyHint) => "键盘提示：WASD / 空格 / ZXC / QEUO / 方向键",
-            (Language::Chinese, UiText::ConnectFirst) => "请先连接设备。",
-            (Language::Chinese, UiText::Threshold) => "触发阈值：",
-            (Language::Chinese, UiText::Calibration) => "校准",
-            (Language::Chinese, UiText::FollowOn) => "跟随最新：开",
-            (Language::Chinese, UiText::FollowOff) => "跟随最新：关",
-            (Language::Chinese, UiText::Ready) => "Neurostick 演示 v0.1 就绪。",
-            (Language::Chinese, UiText::LanguagePrompt) => "选择语言",
-            (Language::Chinese, UiText::StartSubtitle) => "选择语言开始",
-            (Language::Chinese, UiText::StartHeading) => "欢迎使用 Neurostick",
-            (Language::Chinese, UiText::StartRecording) => "开始录制",
-            (Language::Chinese, UiText::StopRecording) => "停止录制",
-            (Language::Chinese, UiText::FftSize) => "FFT 大小:",
-            (Language::Chinese, UiText::Update) => "更新",
-            (Language::Chinese, UiText::GenerateWaveformPng) => "导出波形PNG",
-            (Language::Chinese, UiText::GenerateSpectrumPng) => "导出频谱PNG",
-            (Language::Chinese, UiText::WaveformPngLabel) => "波形PNG:",
-            (Language::Chinese, UiText::SpectrumPngLabel) => "频谱PNG:",
-            (Language::Chinese, UiText::NoSpectrumYet) => "暂无频谱，开始采集后生成。",
-            (Language::Chinese, UiText::RecordRelax) => "1. 录制静息 (3s)",
-            (Language::Chinese, UiText::RecordAction) => "2. 录制动作 (3s)",
-            (Language::Chinese, UiText::ConnectStreamFirst) => "请先连接并开始采集。",
-            (Language::Chinese, UiText::Loading) => "处理中...",
-            (Language::Chinese, UiText::Sensitivity) => "敏感度",
-            (Language::Chinese, UiText::Smoothness) => "平滑度",
-            (Language::Chinese, UiText::Window) => "窗口长度",
-            (Language::Chinese, UiText::Window30) => "30秒",
-            (Language::Chinese, UiText::Window60) => "60秒",
-            (Language::Chinese, UiText::TabWaveform) => "波形",
-            (Language::Chinese, UiText::TabSpectrum) => "频谱",
-            (Language::Chinese, UiText::TabPng) => "导出PNG",
-            (Language::Chinese, UiText::TabCalibration) => "校准",
-            (Language::Chinese, UiText::TabImpedance) => "阻抗检测",
-            (Language::Chinese, UiText::ImpedanceDesc) => {
-                "基于最新缓冲区估算电极阻抗（Cyton 计算）。"
-            }
-            (Language::Chinese, UiText::ImpedanceAction) => "执行检测",
-            (Language::Chinese, UiText::ImpedanceNoData) => "暂无阻抗结果。",
-            (Language::Chinese, UiText::ImpedanceUpdated) => "阻抗结果已更新。",
-            (Language::Chinese, UiText::ImpedanceChannelHeader) => "通道",
-            (Language::Chinese, UiText::ImpedanceValueHeader) => "阻抗 (kOhm)",
-            (Language::Chinese, UiText::PortLabel) => "串口:",
-            (Language::Chinese, UiText::RefreshPorts) => "刷新",
-            (Language::Chinese, UiText::PortsScanned) => "已扫描串口:",
-            (Language::Chinese, UiText::InjectArtifact) => "注入伪迹",
-            (Language::Chinese, UiText::ReportFeedback) => "报告反馈",
-            (Language::Chinese, UiText::ThemeLight) => "浅色",
-            (Language::Chinese, UiText::ThemeDark) => "深色",
-            (Language::Chinese, UiText::LanguageSwitch) => "语言",
-            (Language::Chinese, UiText::Logs) => "日志",
-            (Language::Chinese, UiText::ReportLogs) => "最近日志：",
-            (Language::Chinese, UiText::Resolution) => "分辨率",
-            (Language::Chinese, UiText::Maximize) => "最大化",
-            (Language::Chinese, UiText::AutoY) => "自动Y轴",
-            (Language::Chinese, UiText::FixedUv) => "固定范围(uV)",
-            (Language::Chinese, UiText::Notch50) => "50Hz 陷波",
-            (Language::Chinese, UiText::Stats) => "统计",
-            (Language::Chinese, UiText::TimeAxis) => "时间轴长度(秒)",
-            (Language::Chinese, UiText::ShowPanel) => "展开面板",
-            (Language::Chinese, UiText::HidePanel) => "收起面板",
-            (Language::Chinese, UiText::ImpedanceLegend) => {
-                "良好 <500k | 可用 0.5-2.5M | 较差 >2.5M | Railed = 无接触"
-            }
-            (Language::Chinese, UiText::ModelSection) => "AI 模型",
-            (Language::Chinese, UiText::ModelPath) => "路径",
-            (Language::Chinese, UiText::ModelReload) => "加载 / 重载",
-            (Language::Chinese, UiText::ModelLoaded) => "模型",
-            (Language::Chinese, UiText::ModelNone) => "未加载模型。",
-            (Language::Chinese, UiText::ModelError) => "模型错误",
-            (Language::Chinese, UiText::ModelClasses) => "类别",
-            (Language::Chinese, UiText::ModelChannels) => "通道数",
-            (Language::Chinese, UiText::ModelOutput) => "模型输出",
-        }
-    }
-    fn default_record_label(&self) -> &'static str {
+/// Top-left corner, in egui points, of the monitor nearest `point`: `MonitorFromPoint`
+/// from user32 on Windows; other platforms do not report it through egui.
+fn monitor_origin(point: egui::Pos2, pixels_per_point: f32) -> Option<[f32; 2]> {
+    #[cfg(windows)]
+    unsafe {
+        use winapi::shared::windef::POINT;
+        use winapi::um::winuser::{
+            GetMonitorInfoW, MonitorFromPoint, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+        };
+        let pt = POINT {
+            x: (point.x * pixels_per_point) as i32,
+            y: (point.y * pixels_per_point) as i32,
+        };
+        let monitor = MonitorFromPoint(pt, MONITOR_DEFAULTTONEAREST);
+        let mut info: MONITORINFO = std::mem::zeroed();
+        info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+        if GetMonitorInfoW(monitor, &mut info) == 0 {
+            return None;
+        }
+        Some([
+            info.rcMonitor.left as f32 / pixels_per_point,
+            info.rcMonitor.top as f32 / pixels_per_point,
+        ])
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = (point, pixels_per_point);
+        None
+    }
+}
+/// Diverging map: blue for anti-correlated, dark at 0, red for correlated.
+fn correlation_color(r: f32) -> Color32 {
+    let level = (r.clamp(-1.0, 1.0).abs() * 255.0) as u8;
+    if r >= 0.0 {
+        Color32::from_rgb(level, 30, 30)
+    } else {
+        Color32::from_rgb(30, 30, level)
+    }
+}
+/// Channel pairs (upper triangle) whose correlation is at least `threshold`.
+fn correlated_pairs(matrix: &[Vec<f32>], threshold: f32) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for (i, row) in matrix.iter().enumerate() {
+        for (j, r) in row.iter().enumerate().skip(i + 1) {
+            if *r >= threshold {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+/// Index of the first sample in a device-timestamped snapshot newer than `last_seen`,
+/// or `None` when the device clock went backwards (a new session) and the window
+/// should be refilled.
+fn unseen_start(timestamps: &[f64], last_seen: f64) -> Option<usize> {
+    if timestamps.last().is_some_and(|&t| t < last_seen) {
+        return None;
+    }
+    Some(timestamps.partition_point(|&t| t <= last_seen))
+}
+/// Samples of a `total_samples` snapshot to feed the waveform pipeline this frame:
+/// the whole window on `refill`, otherwise about 1/8 s so it keeps moving without backlog.
+fn ingest_chunk_len(sr: f32, total_samples: usize, window_secs: f64, refill: bool) -> usize {
+    let window_cap = (window_secs * sr as f64).ceil() as usize;
+    if refill {
+        total_samples.min(window_cap)
+    } else {
+        let target = (sr / 8.0).ceil() as usize;
+        target.clamp(1, total_samples.min(window_cap).max(1))
+    }
+}
+/// Rates the simulator can emulate: Cyton + Daisy (125 Hz), Cyton (250 Hz) and faster boards.
+const SIM_SAMPLE_RATES: [f32; 3] = [125.0, 250.0, 500.0];
+/// Marks an event while recording, from anywhere in the window.
+const MARK_EVENT_KEY: egui::Key = egui::Key::F2;
+const BAND_BAR_ALPHA: Color32 = Color32::from_rgb(46, 204, 113);
+const BAND_BAR_BETA: Color32 = Color32::from_rgb(243, 156, 18);
+/// Per channel, the share of 0.5–45 Hz power in the alpha and beta bands (0..=1 each).
+fn alpha_beta_shares(spec: &FrequencySpectrum) -> Vec<(f32, f32)> {
+    let band = |band: EegBand| {
+        let (low, high) = band.range_hz();
+        spec.band_power(low, high)
+    };
+    let (alpha, beta) = (band(EegBand::Alpha), band(EegBand::Beta));
+    let total = spec.band_power(EegBand::Delta.range_hz().0, EegBand::Gamma.range_hz().1);
+    alpha
+        .iter()
+        .zip(&beta)
+        .zip(&total)
+        .map(
+            |((a, b), t)| {
+                if *t > 0.0 {
+                    (a / t, b / t)
+                } else {
+                    (0.0, 0.0)
+                }
+            },
+        )
+        .collect()
+}
+/// How many impedance measurements the trend sparklines keep.
+const IMPEDANCE_HISTORY_LEN: usize = 30;
+/// Appends one measurement, dropping the oldest beyond `IMPEDANCE_HISTORY_LEN`.
+fn record_impedance(
+    history: &mut VecDeque<(SystemTime, Vec<f32>)>,
+    at: SystemTime,
+    values: Vec<f32>,
+) {
+    history.push_back((at, values));
+    while history.len() > IMPEDANCE_HISTORY_LEN {
+        history.pop_front();
+    }
+}
+/// Ohms of one channel across the history, oldest first; measurements
+/// taken with fewer channels are skipped.
+fn impedance_trend(history: &VecDeque<(SystemTime, Vec<f32>)>, channel: usize) -> Vec<f32> {
+    history
+        .iter()
+        .filter_map(|(_, values)| values.get(channel).copied())
+        .filter(|v| v.is_finite())
+        .collect()
+}
+/// Small line of `values` scaled to their own min..max; nothing is drawn below two points.
+fn draw_sparkline(ui: &mut egui::Ui, values: &[f32], color: Color32) {
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(90.0, 16.0), egui::Sense::hover());
+    if values.len() < 2 {
+        return;
+    }
+    let lo = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let hi = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let span = (hi - lo).max(f32::EPSILON);
+    let step = rect.width() / (values.len() - 1) as f32;
+    let points = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            egui::pos2(
+                rect.left() + i as f32 * step,
+                rect.bottom() - (v - lo) / span * rect.height(),
+            )
+        })
+        .collect();
+    ui.painter_at(rect)
+        .add(egui::Shape::line(points, egui::Stroke::new(1.5, color)));
+}
+/// Lets slow-changing overlay text update at a fixed cadence.
+struct RefreshThrottle {
+    interval_secs: f64,
+    last: Option<f64>,
+}
+impl RefreshThrottle {
+    fn new(rate_hz: f64) -> Self {
+        let mut throttle = Self {
+            interval_secs: 0.5,
+            last: None,
+        };
+        throttle.set_rate_hz(rate_hz);
+        throttle
+    }
+    fn rate_hz(&self) -> f64 {
+        1.0 / self.interval_secs
+    }
+    fn set_rate_hz(&mut self, rate_hz: f64) {
+        self.interval_secs = 1.0 / rate_hz.clamp(0.1, 120.0);
+    }
+    /// True (and re-arms) once `interval_secs` has passed since the last refresh.
+    fn ready(&mut self, now_secs: f64) -> bool {
+        match self.last {
+            Some(last) if now_secs - last < self.interval_secs => false,
+            _ => {
+                self.last = Some(now_secs);
+                true
+            }
+        }
+    }
+}
+/// Frames per second written while an overlay sequence is being captured.
+const OVERLAY_SEQUENCE_FPS: f64 = 10.0;
+/// Numbered transparent PNGs for compositing over gameplay video.
+struct OverlaySequence {
+    dir: PathBuf,
+    frames: usize,
+    throttle: RefreshThrottle,
+}
+/// Device samples vs samples buffered by the engine over a trailing window, so the
+/// drop-rate label reflects the last few seconds instead of jumping per frame. The
+/// waveform's own per-frame ingest cap plays no part, so a healthy stream reads 0%.
+struct DropRateWindow {
+    window_secs: f64,
+    /// `(t_secs, available, buffered)` running totals from the engine's rate reports.
+    counts: VecDeque<(f64, u64, u64)>,
+}
+impl DropRateWindow {
+    fn new(window_secs: f64) -> Self {
+        Self {
+            window_secs: window_secs.max(0.5),
+            counts: VecDeque::new(),
+        }
+    }
+    fn window_secs(&self) -> f64 {
+        self.window_secs
+    }
+    fn set_window_secs(&mut self, secs: f64) {
+        self.window_secs = secs.max(0.5);
+    }
+    fn clear(&mut self) {
+        self.counts.clear();
+    }
+    /// Running totals at stream-relative time `t_secs`: samples the device reported
+    /// and how many of them were buffered.
+    fn record(&mut self, t_secs: f64, available: u64, buffered: u64) {
+        // 计数随每次推流重新从零开始
+        if self.counts.back().is_some_and(|&(_, a, _)| available < a) {
+            self.counts.clear();
+        }
+        self.counts.push_back((t_secs, available, buffered));
+        // Keep one extra window so growing the window still has data.
+        while self.counts.len() > 1 && self.counts[0].0 < t_secs - self.window_secs * 2.0 {
+            self.counts.pop_front();
+        }
+    }
+    /// Percentage of device samples that never reached the buffer over the trailing
+    /// window, ending at the latest report.
+    fn drop_rate(&self) -> Option<f64> {
+        let &(now, available, buffered) = self.counts.back()?;
+        let start = now - self.window_secs;
+        let &(_, base_available, base_buffered) = self
+            .counts
+            .iter()
+            .rev()
+            .find(|(t, _, _)| *t <= start)
+            .or(self.counts.front())?;
+        drop_rate(
+            available.saturating_sub(base_available),
+            buffered.saturating_sub(base_buffered),
+        )
+    }
+}
+/// Share of `available` samples missing from `buffered`, in percent; `None` until any
+/// samples have arrived (e.g. no time has elapsed since the first report).
+fn drop_rate(available: u64, buffered: u64) -> Option<f64> {
+    (available > 0).then(|| (1.0 - buffered as f64 / available as f64).clamp(0.0, 1.0) * 100.0)
+}
+/// How much raw history is kept for scrubbing a frozen waveform.
+const WAVE_HISTORY_SECONDS: f64 = 120.0;
+fn append_history(history: &mut TimeSeriesFrame, tails: &[Vec<f32>], max_len: usize) {
+    for (ch, tail) in history.samples.iter_mut().zip(tails) {
+        ch.extend_from_slice(tail);
+        let excess = ch.len().saturating_sub(max_len);
+        ch.drain(..excess);
+    }
+}
+/// `len` samples ending `end_offset` samples before the newest one.
+fn history_slice(history: &TimeSeriesFrame, end_offset: usize, len: usize) -> TimeSeriesFrame {
+    TimeSeriesFrame {
+        sample_rate_hz: history.sample_rate_hz,
+        channel_labels: history.channel_labels.clone(),
+        samples: history
+            .samples
+            .iter()
+            .map(|ch| {
+                let end = ch.len().saturating_sub(end_offset);
+                ch[end.saturating_sub(len)..end].to_vec()
+            })
+            .collect(),
+        timestamps: Vec::new(),
+    }
+}
+/// Empty lanes shown before any data arrives, sized to the last known session.
+fn placeholder_view(window_secs: f32, channel_count: usize) -> WaveformView {
+    WaveformView {
+        window_secs,
+        channels: (0..channel_count.max(1))
+            .map(|i| ChannelView {
+                index: i,
+                y_range: (-200.0, 200.0),
+                rms_u_v: 0.0,
+                min: 0.0,
+                max: 0.0,
+                samples: Vec::<SamplePoint>::new(),
+            })
+            .collect(),
+    }
+}
+/// Which edge of the waveform plot the per-lane labels hug.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LabelSide {
+    Left,
+    Right,
+}
+/// X coordinate for a lane label `inset` seconds in from the chosen edge.
+fn lane_label_x(side: LabelSide, x_min: f64, x_max: f64, inset: f64) -> f64 {
+    match side {
+        LabelSide::Left => x_min + inset,
+        LabelSide::Right => x_max - inset,
+    }
+}
+/// Units for the CSV/PNG export of the visible window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ExportUnits {
+    /// Filtered signal as delivered by the engine.
+    RawMicrovolts,
+    /// Exactly what the waveform shows: µV x display gain x sensitivity x lane zoom, smoothed.
+    Display,
+}
+impl ExportUnits {
+    fn label(self) -> &'static str {
         match self {
-            Language::English => "Attack",
-            Language::Chinese => "攻击",
+            ExportUnits::RawMicrovolts => "µV",
+            ExportUnits::Display => "display units",
+        }
+    }
+    fn file_tag(self) -> &'static str {
+        match self {
+            ExportUnits::RawMicrovolts => "uv",
+            ExportUnits::Display => "display",
         }
     }
 }
-#[derive(Clone, Copy)]
-enum UiText {
-    Title,
-    Subtitle,
-    Sim,
-    Real,
-    Connect,
-    Disconnect,
-    StartStream,
-    StopStream,
-    ResetView,
-    Controller,
-    Data,
-    Recording,
-    HardwareRequired,
-    KeyHint,
-    ConnectFirst,
-    Threshold,
-    Calibration,
-    FollowOn,
-    FollowOff,
-    Ready,
-    LanguagePrompt,
-    StartSubtitle,
-    StartHeading,
-    StartRecording,
-    StopRecording,
-    FftSize,
-    Update,
-    GenerateWaveformPng,
-    GenerateSpectrumPng,
-    WaveformPngLabel,
-    SpectrumPngLabel,
-    NoSpectrumYet,
-    RecordRelax,
-    RecordAction,
-    ConnectStreamFirst,
-    Loading,
-    Sensitivity,
-    Smoothness,
-    Window,
-    Window30,
-    Window60,
-    TabWaveform,
-    TabSpectrum,
-    TabPng,
-    TabCalibration,
-    TabImpedance,
-    ImpedanceDesc,
-    ImpedanceAction,
-    ImpedanceNoData,
-    ImpedanceUpdated,
-    ImpedanceChannelHeader,
-    ImpedanceValueHeader,
-    PortLabel,
-    RefreshPorts,
-    PortsScanned,
-    InjectArtifact,
-    ReportFeedback,
-    ThemeLight,
-    ThemeDark,
-    LanguageSwitch,
-    Logs,
-    ReportLogs,
-    Resolution,
-    Maximize,
-    AutoY,
-    FixedUv,
-    Notch50,
-    Stats,
-    TimeAxis,
-    ShowPanel,
-    HidePanel,
-    ImpedanceLegend,
-    ModelSection,
-    ModelPath,
-    ModelReload,
-    ModelLoaded,
-    ModelNone,
-    ModelError,
-    ModelClasses,
-    ModelChannels,
-    ModelOutput,
+/// Runs each lane's filter chain (`lanes[idx]`, as the live pipeline does) causally over
+/// the frame, then applies the on-screen gain and lane zoom. The filters start from rest
+/// at the first sample rather than from the live lane's state.
+fn display_units_frame(
+    frame: &TimeSeriesFrame,
+    lanes: &[Vec<FilterKind>],
+    gain: f64,
+    zooms: &[f32],
+) -> TimeSeriesFrame {
+    let samples = frame
+        .samples
+        .iter()
+        .enumerate()
+        .map(|(idx, ch)| {
+            let kinds = lanes.get(idx).map_or(&[][..], Vec::as_slice);
+            let mut chain = FilterChain::from_kinds(frame.sample_rate_hz, kinds);
+            ch.iter()
+                .map(|&v| scale_sample(chain.process_sample(v), gain, zooms, idx) as f32)
+                .collect()
+        })
+        .collect();
+    TimeSeriesFrame {
+        sample_rate_hz: frame.sample_rate_hz,
+        channel_labels: frame.channel_labels.clone(),
+        samples,
+        timestamps: Vec::new(),
+    }
+}
+/// CSV of a window, one row per sample; the unit is part of every channel column name.
+fn window_csv(frame: &TimeSeriesFrame, units: ExportUnits) -> String {
+    let unit = match units {
+        ExportUnits::RawMicrovolts => "uV",
+        ExportUnits::Display => "display units",
+    };
+    let mut out = String::from("Time");
+    for (idx, _) in frame.samples.iter().enumerate() {
+        let name = frame
+            .channel_labels
+            .get(idx)
+            .cloned()
+            .unwrap_or_else(|| format!("Ch{}", idx + 1));
+        out.push_str(&format!(",{name} ({unit})"));
+    }
+    out.push('\n');
+    let len = frame.samples.iter().map(|c| c.len()).max().unwrap_or(0);
+    let dt = 1.0 / frame.sample_rate_hz.max(f32::EPSILON);
+    for i in 0..len {
+        out.push_str(&format!("{:.4}", i as f32 * dt));
+        for ch in &frame.samples {
+            match ch.get(i) {
+                Some(v) => out.push_str(&format!(",{v:.3}")),
+                None => out.push(','),
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+/// Frequencies tabulated in the report's filter response section.
+const REPORT_RESPONSE_HZ: [f32; 8] = [1.0, 5.0, 10.0, 25.0, 45.0, 50.0, 55.0, 100.0];
+/// Markdown section documenting the display filters: one table of gain per
+/// distinct chain, listing the channels it applies to.
+fn filter_response_report(
+    notch_channels: &[bool],
+    band: Option<EegBand>,
+    channel_count: usize,
+    sample_rate_hz: f32,
+    mains_hz: f32,
+) -> String {
+    let mut groups: Vec<(Vec<FilterKind>, Vec<usize>)> = Vec::new();
+    for idx in 0..channel_count {
+        let kinds = channel_filters(notch_channels, idx, mains_hz, band);
+        match groups.iter_mut().find(|(k, _)| *k == kinds) {
+            Some((_, channels)) => channels.push(idx),
+            None => groups.push((kinds, vec![idx])),
+        }
+    }
+    let mut out = String::from("\n## Filter response (display)\n");
+    for (kinds, channels) in groups {
+        let names: Vec<String> = channels.iter().map(|i| format!("Ch{}", i + 1)).collect();
+        if kinds.is_empty() {
+            out.push_str(&format!("\n{}: no filters\n", names.join(", ")));
+            continue;
+        }
+        let chain = FilterChain::from_kinds(sample_rate_hz, &kinds);
+        out.push_str(&format!(
+            "\n{}: {:?}\n\n| Hz | dB |\n|---:|---:|\n",
+            names.join(", "),
+            kinds
+        ));
+        let freqs: Vec<f32> = REPORT_RESPONSE_HZ
+            .into_iter()
+            .filter(|&hz| hz < sample_rate_hz / 2.0)
+            .collect();
+        for (hz, db) in freqs
+            .iter()
+            .zip(chain.magnitude_response(sample_rate_hz, &freqs))
+        {
+            out.push_str(&format!("| {hz:.0} | {db:.1} |\n"));
+        }
+    }
+    out.push('\n');
+    out
+}
+/// Runs each lane's display filters over the whole frame, zero-phase (filtfilt) or
+/// causally like the live view.
+fn display_filtered_frame(
+    mut frame: TimeSeriesFrame,
+    notch_channels: &[bool],
+    band: Option<EegBand>,
+    mains_hz: f32,
+    zero_phase: bool,
+) -> TimeSeriesFrame {
+    let sample_rate_hz = frame.sample_rate_hz;
+    for (idx, samples) in frame.samples.iter_mut().enumerate() {
+        let kinds = channel_filters(notch_channels, idx, mains_hz, band);
+        if kinds.is_empty() {
+            continue;
+        }
+        let mut chain = FilterChain::from_kinds(sample_rate_hz, &kinds);
+        *samples = if zero_phase {
+            chain.process_filtfilt(samples)
+        } else {
+            samples.iter().map(|&v| chain.process_sample(v)).collect()
+        };
+    }
+    frame
+}
+/// Running mean standing in for the smoothing factor: an N-point mean lags as much as
+/// an exponential smoother with `alpha = 2 / (N + 1)`. `None` when smoothing is off.
+fn smoothing_filter(smooth_alpha: f64) -> Option<FilterKind> {
+    if smooth_alpha <= 0.0 || smooth_alpha >= 1.0 {
+        return None;
+    }
+    let window_samples = (2.0 / smooth_alpha - 1.0).round() as usize;
+    (window_samples > 1).then_some(FilterKind::MovingAverage { window_samples })
+}
+/// Display filter chain for one lane: the band-pass selected for every lane, then
+/// the mains notch only where it is enabled.
+fn channel_filters(
+    notch_channels: &[bool],
+    idx: usize,
+    mains_hz: f32,
+    band: Option<EegBand>,
+) -> Vec<FilterKind> {
+    let mut kinds = Vec::new();
+    if let Some(band) = band {
+        let (low_hz, high_hz) = band.range_hz();
+        kinds.push(FilterKind::ButterworthBandpass {
+            low_hz,
+            high_hz,
+            order: 4,
+        });
+    }
+    if notch_channels.get(idx).copied().unwrap_or(false) {
+        kinds.push(FilterKind::Notch {
+            freq_hz: mains_hz,
+            q: 35.0,
+        });
+    }
+    kinds
+}
+/// Plot points for one lane. The pipeline has already filtered and smoothed every
+/// sample, so thinning to `max_points` only picks which points are drawn: past the
+/// budget each bucket keeps its min and max, so a single spike is never skipped.
+fn lane_points(
+    channel: &ChannelView,
+    max_points: usize,
+    to_lane: impl Fn(f32) -> f64,
+) -> Vec<[f64; 2]> {
+    let envelope =
+        (channel.samples.len() > max_points).then(|| channel.downsample_envelope(max_points));
+    envelope
+        .as_deref()
+        .unwrap_or(&channel.samples)
+        .iter()
+        .map(|s| [s.time as f64, to_lane(s.value)])
+        .collect()
+}
+/// Sample under the waveform cursor: lane, time relative to the newest sample (s) and µV.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct WaveReadout {
+    lane: usize,
+    time: f32,
+    value_uv: f32,
+}
+/// Maps a plot coordinate back to a sample: the lane whose baseline is nearest to
+/// `y`, then that lane's sample nearest in time to `x`. The value is read from the
+/// view, so it is the filtered µV before lane offset, display gain and clipping.
+/// `None` outside the time window or the lanes.
+fn wave_readout(
+    view: &WaveformView,
+    pointer: [f64; 2],
+    lane_height: f64,
+    window_secs: f64,
+) -> Option<WaveReadout> {
+    let [x, y] = pointer;
+    if !(-window_secs..=0.0).contains(&x) || lane_height <= 0.0 {
+        return None;
+    }
+    let lane = (-y / lane_height).round();
+    if lane < 0.0 {
+        return None;
+    }
+    let channel = view.channels.iter().find(|c| c.index == lane as usize)?;
+    let visible = channel
+        .samples
+        .partition_point(|s| (s.time as f64) < -window_secs);
+    let samples = &channel.samples[visible..];
+    let idx = samples.partition_point(|s| (s.time as f64) < x);
+    let nearest = [idx.checked_sub(1), Some(idx)]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| samples.get(i))
+        .min_by(|a, b| {
+            (a.time as f64 - x)
+                .abs()
+                .total_cmp(&(b.time as f64 - x).abs())
+        })?;
+    Some(WaveReadout {
+        lane: channel.index,
+        time: nearest.time,
+        value_uv: nearest.value,
+    })
+}
+/// Per-lane display multiplier; lanes without an entry render at 1x.
+fn lane_zoom(zooms: &[f32], idx: usize) -> f64 {
+    zooms.get(idx).copied().unwrap_or(1.0) as f64
+}
+/// Converts a sample to lane height units, applying that lane's zoom.
+fn scale_sample(value_uv: f32, base_scale: f64, zooms: &[f32], idx: usize) -> f64 {
+    value_uv as f64 * base_scale * lane_zoom(zooms, idx)
+}
+/// Stroke for a live waveform trace: lane colour with the configured width and opacity.
+fn trace_stroke(base: Color32, width: f32, alpha: f32) -> egui::Stroke {
+    let a = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+    egui::Stroke::new(
+        width.max(0.1),
+        Color32::from_rgba_unmultiplied(base.r(), base.g(), base.b(), a),
+    )
 }
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum ViewTab {
     Waveform,
     Spectrum,
+    Spectrogram,
     Png,
     Calibration,
     Impedance,
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn alpha_rhythm_fills_the_alpha_bar_and_leaves_beta_short() {
+        let sr = 250.0;
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: sr,
+            channel_labels: vec!["Ch1".into(), "Ch2".into()],
+            samples: vec![
+                (0..256)
+                    .map(|i| (2.0 * std::f32::consts::PI * 10.0 * i as f32 / sr).sin() * 20.0)
+                    .collect(),
+                vec![0.0; 256],
+            ],
+            timestamps: Vec::new(),
+        };
+        let shares = alpha_beta_shares(&SpectrumBuilder::with_size(256).compute(&frame));
+        assert_eq!(shares.len(), 2);
+        let (alpha, beta) = shares[0];
+        assert!(alpha > 0.9, "{alpha}");
+        assert!(beta < 0.05, "{beta}");
+        assert_eq!(shares[1], (0.0, 0.0));
+    }
+    #[test]
+    fn resume_refills_the_window_while_live_frames_take_an_eighth_second() {
+        // 5 s 快照，4 s 窗口，250 Hz
+        assert_eq!(ingest_chunk_len(250.0, 1250, 4.0, true), 1000);
+        assert_eq!(ingest_chunk_len(250.0, 1250, 4.0, false), 32);
+        assert_eq!(ingest_chunk_len(250.0, 10, 4.0, false), 10);
+        assert_eq!(ingest_chunk_len(250.0, 10, 4.0, true), 10);
+    }
+    #[test]
+    fn only_samples_after_the_last_device_time_are_ingested() {
+        let ts = [100.000, 100.004, 100.008, 100.012];
+        assert_eq!(unseen_start(&ts, 100.004), Some(2));
+        assert_eq!(unseen_start(&ts, 100.012), Some(4));
+        assert_eq!(unseen_start(&ts, 99.0), Some(0));
+        // 设备时钟倒退 (重新开始推流) 时整窗重填
+        assert_eq!(unseen_start(&ts, 200.0), None);
+    }
+    #[test]
+    fn impedance_history_keeps_the_latest_measurements_per_channel() {
+        let mut history = VecDeque::new();
+        let t0 = SystemTime::UNIX_EPOCH;
+        for i in 0..IMPEDANCE_HISTORY_LEN + 5 {
+            let at = t0 + std::time::Duration::from_secs(i as u64);
+            record_impedance(&mut history, at, vec![1000.0 * i as f32, 5.0]);
+        }
+        assert_eq!(history.len(), IMPEDANCE_HISTORY_LEN);
+        let trend = impedance_trend(&history, 0);
+        assert_eq!(trend.len(), IMPEDANCE_HISTORY_LEN);
+        assert_eq!(trend.first(), Some(&5000.0));
+        assert_eq!(
+            trend.last(),
+            Some(&(1000.0 * (IMPEDANCE_HISTORY_LEN + 4) as f32))
+        );
+        // 少通道的测量与非有限值不进入曲线
+        record_impedance(&mut history, t0, vec![f32::NAN]);
+        assert_eq!(
+            impedance_trend(&history, 1).len(),
+            IMPEDANCE_HISTORY_LEN - 1
+        );
+        assert_eq!(
+            impedance_trend(&history, 0).len(),
+            IMPEDANCE_HISTORY_LEN - 1
+        );
+    }
+    #[test]
+    fn report_documents_notch_attenuation_at_50hz() {
+        let report = filter_response_report(&[true, false], None, 2, 250.0, 50.0);
+        assert!(report.contains("Ch1: [Notch"), "{report}");
+        assert!(report.contains("Ch2: no filters"), "{report}");
+        let db_at = |hz: &str| -> f32 {
+            let row = report
+                .lines()
+                .find(|l| l.starts_with(&format!("| {hz} |")))
+                .unwrap_or_else(|| panic!("no {hz} Hz row in {report}"));
+            row.split('|').nth(2).unwrap().trim().parse().unwrap()
+        };
+        assert!(db_at("50") < -20.0);
+        assert!(db_at("10").abs() < 0.5);
+    }
+    #[test]
+    fn channel_impedance_limit_overrides_only_that_row() {
+        let limits = [0.0, 3000.0];
+        let lang = Language::English;
+        // 2.8 MΩ is "Poor" on the global bands...
+        let global = QnmdSolApp::impedance_status(2_800_000.0, lang);
+        assert_eq!(
+            QnmdSolApp::channel_impedance_status(2_800_000.0, &limits, 0, lang),
+            global
+        );
+        // ...but within the 3 MΩ limit configured for the ear reference on row 1.
+        let (color, status) = QnmdSolApp::channel_impedance_status(2_800_000.0, &limits, 1, lang);
+        assert_eq!(status, "Within channel limit");
+        assert_eq!(color, QnmdSolApp::impedance_status(0.0, lang).0);
+        assert_ne!(color, global.0);
+        let (_, over) = QnmdSolApp::channel_impedance_status(3_500_000.0, &limits, 1, lang);
+        assert_eq!(over, "Over channel limit");
+    }
+    #[test]
+    fn lane_labels_mirror_between_left_and_right() {
+        assert_eq!(lane_label_x(LabelSide::Left, 10.0, 40.0, 0.6), 10.6);
+        assert_eq!(lane_label_x(LabelSide::Right, 10.0, 40.0, 0.6), 39.4);
+        // The RMS text sits further in than the index label on either side.
+        assert!(lane_label_x(LabelSide::Right, 10.0, 40.0, 10.5) < 39.4);
+    }
+    #[test]
+    fn trace_stroke_applies_width_and_alpha() {
+        let base = Color32::from_rgb(200, 100, 50);
+        let stroke = trace_stroke(base, 2.5, 0.5);
+        assert_eq!(stroke.width, 2.5);
+        assert_eq!(stroke.color.a(), 128);
+        let opaque = trace_stroke(base, 1.0, 1.0);
+        assert_eq!(opaque.color, base);
+        // Out-of-range opacity is clamped rather than wrapping.
+        assert_eq!(trace_stroke(base, 1.0, 3.0).color, base);
+    }
+    #[test]
+    fn placeholder_matches_remembered_channel_count() {
+        let config: AppConfig = toml::from_str("last_channel_count = 8").unwrap();
+        let view = placeholder_view(30.0, config.last_channel_count);
+        assert_eq!(view.channels.len(), 8);
+        assert!(view.channels.iter().all(|c| c.samples.is_empty()));
+    }
+    #[test]
+    fn notch_on_one_channel_leaves_others_unfiltered() {
+        let notch = [false, false, true];
+        assert!(channel_filters(&notch, 0, 50.0, None).is_empty());
+        assert!(matches!(
+            channel_filters(&notch, 2, 50.0, None).as_slice(),
+            [FilterKind::Notch { freq_hz, .. }] if *freq_hz == 50.0
+        ));
+        assert!(channel_filters(&notch, 5, 50.0, None).is_empty());
+        assert!(matches!(
+            channel_filters(&notch, 2, 60.0, None).as_slice(),
+            [FilterKind::Notch { freq_hz, .. }] if *freq_hz == 60.0
+        ));
+    }
+    #[test]
+    fn smoothed_lane_values_do_not_depend_on_the_point_budget() {
+        let mut pipe = WaveformPipeline::new(1, 250.0);
+        pipe.set_time_window(TimeWindow::new(10.0));
+        pipe.set_channel_filters(0, smoothing_filter(0.18).into_iter().collect());
+        let raw: Vec<f32> = (0..2000)
+            .map(|i| 40.0 * (i as f32 * 0.05).sin() + 15.0 * (i as f32 * 2.3).sin())
+            .collect();
+        pipe.ingest_block(0.0, std::slice::from_ref(&raw));
+        let view = pipe.view();
+        let channel = &view.channels[0];
+        let full = lane_points(channel, usize::MAX, f64::from);
+        assert_eq!(full.len(), raw.len());
+        assert!(full
+            .iter()
+            .zip(&raw)
+            .any(|(p, &r)| (p[1] - r as f64).abs() > 1.0));
+        for budget in [1400, 300] {
+            let drawn = lane_points(channel, budget, f64::from);
+            assert!(drawn.len() <= budget, "{budget}: {} points", drawn.len());
+            for point in drawn {
+                assert!(
+                    full.contains(&point),
+                    "{budget}: {point:?} not in the full trace"
+                );
+            }
+        }
+    }
+    #[test]
+    fn cursor_reads_the_nearest_sample_of_the_hovered_lane_in_microvolts() {
+        let mut pipe = WaveformPipeline::new(2, 250.0);
+        pipe.set_time_window(TimeWindow::new(2.0));
+        let lanes: Vec<Vec<f32>> = (0..2)
+            .map(|ch| (0..1000).map(|i| (ch * 1000 + i) as f32).collect())
+            .collect();
+        pipe.ingest_block(0.0, &lanes);
+        let view = pipe.view();
+        let lane_height = 30.0;
+        // 第二条通道 (基线 y = -30)，光标在 -0.5 s 附近且偏离基线
+        let r = wave_readout(&view, [-0.501, -30.0 + 9.0], lane_height, 2.0).unwrap();
+        assert_eq!(r.lane, 1);
+        assert!((r.time + 0.5).abs() < 1e-3, "{}", r.time);
+        assert_eq!(r.value_uv, 1999.0 - 125.0);
+        assert!(wave_readout(&view, [-2.5, 0.0], lane_height, 2.0).is_none());
+        assert!(wave_readout(&view, [-0.5, 20.0], lane_height, 2.0).is_none());
+        assert!(wave_readout(&view, [-0.5, -90.0], lane_height, 2.0).is_none());
+    }
+    #[test]
+    fn smoothing_factor_maps_to_an_equal_lag_running_mean() {
+        assert_eq!(smoothing_filter(0.0), None);
+        assert_eq!(
+            smoothing_filter(0.18),
+            Some(FilterKind::MovingAverage { window_samples: 10 })
+        );
+        assert_eq!(
+            smoothing_filter(0.8),
+            Some(FilterKind::MovingAverage { window_samples: 2 })
+        );
+        assert_eq!(smoothing_filter(1.0), None);
+    }
+    #[test]
+    fn band_preset_applies_to_every_lane_alongside_the_notch() {
+        let notch = [false, true];
+        assert!(matches!(
+            channel_filters(&notch, 0, 50.0, Some(EegBand::Alpha)).as_slice(),
+            [FilterKind::ButterworthBandpass { low_hz, high_hz, .. }]
+                if (*low_hz, *high_hz) == (8.0, 13.0)
+        ));
+        assert!(matches!(
+            channel_filters(&notch, 1, 50.0, Some(EegBand::Beta)).as_slice(),
+            [
+                FilterKind::ButterworthBandpass { low_hz, .. },
+                FilterKind::Notch { freq_hz, .. }
+            ] if *low_hz == 13.0 && *freq_hz == 50.0
+        ));
+        let chain = FilterChain::from_kinds(
+            250.0,
+            &channel_filters(&notch, 0, 50.0, Some(EegBand::Alpha)),
+        );
+        let db = chain.magnitude_response(250.0, &[10.0, 25.0]);
+        assert!(db[0] > -3.0 && db[1] < -20.0, "{db:?}");
+    }
+    #[test]
+    fn display_units_export_applies_gain() {
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: vec!["Ch1".into(), "Ch2".into()],
+            samples: vec![vec![10.0, -20.0], vec![1.0, 2.0]],
+            timestamps: Vec::new(),
+        };
+        // Lane 2 zoomed 2x on top of the 0.5 global gain; no filters.
+        let display = display_units_frame(&frame, &[], 0.5, &[1.0, 2.0]);
+        assert_eq!(display.samples, vec![vec![5.0, -10.0], vec![1.0, 2.0]]);
+        let csv = window_csv(&display, ExportUnits::Display);
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("Time,Ch1 (display units),Ch2 (display units)")
+        );
+        assert_eq!(lines.next(), Some("0.0000,5.000,1.000"));
+        let raw = window_csv(&frame, ExportUnits::RawMicrovolts);
+        assert!(raw.starts_with("Time,Ch1 (uV),Ch2 (uV)\n0.0000,10.000,1.000"));
+
+        // With notch, band-pass and smoothing, the export matches the drawn lane values.
+        let raw: Vec<f32> = (0..500)
+            .map(|i| 40.0 * (i as f32 * 0.25).sin() + 20.0 * (i as f32 * 1.26).sin())
+            .collect();
+        let frame = TimeSeriesFrame {
+            sample_rate_hz: 250.0,
+            channel_labels: vec!["Ch1".into()],
+            samples: vec![raw],
+            timestamps: Vec::new(),
+        };
+        let mut kinds = channel_filters(&[true], 0, 50.0, Some(EegBand::Alpha));
+        kinds.extend(smoothing_filter(0.18));
+        let mut pipe = WaveformPipeline::new(1, 250.0);
+        pipe.set_time_window(TimeWindow::new(10.0));
+        pipe.set_channel_filters(0, kinds.clone());
+        pipe.ingest_block(0.0, &frame.samples);
+        let drawn = lane_points(&pipe.view().channels[0], usize::MAX, |v| {
+            scale_sample(v, 0.5, &[2.0], 0)
+        });
+        let display = display_units_frame(&frame, &[kinds], 0.5, &[2.0]);
+        assert_eq!(drawn.len(), display.samples[0].len());
+        for (point, &exported) in drawn.iter().zip(&display.samples[0]) {
+            assert!(
+                (point[1] - exported as f64).abs() < 1e-3,
+                "{point:?} vs {exported}"
+            );
+        }
+    }
+    #[test]
+    fn stats_throttle_waits_for_interval() {
+        let mut throttle = RefreshThrottle::new(2.0);
+        assert!(throttle.ready(10.0));
+        assert!(!throttle.ready(10.2));
+        assert!(!throttle.ready(10.49));
+        assert!(throttle.ready(10.5));
+        assert!(!throttle.ready(10.6));
+    }
+    #[test]
+    fn windowed_drop_rate_converges_to_steady_state() {
+        // 250 Hz stream buffering 20 of every 25 device samples (20% drop).
+        let mut meter = DropRateWindow::new(5.0);
+        for i in 1..=100 {
+            meter.record(i as f64 * 0.1, i * 25, i * 20);
+        }
+        let rate = meter.drop_rate().unwrap();
+        assert!((rate - 20.0).abs() < 0.5, "rate {rate}");
+    }
+    #[test]
+    fn drop_rate_is_zero_when_everything_is_buffered_and_unknown_before_data() {
+        assert_eq!(drop_rate(0, 0), None);
+        assert_eq!(drop_rate(250, 250), Some(0.0));
+        assert_eq!(drop_rate(200, 150), Some(25.0));
+        // A single report spans no time yet.
+        let mut meter = DropRateWindow::new(5.0);
+        assert_eq!(meter.drop_rate(), None);
+        meter.record(1.0, 250, 250);
+        assert_eq!(meter.drop_rate(), None);
+        // A healthy stream reads 0%.
+        for s in 2..=10u64 {
+            meter.record(s as f64, s * 250, s * 250);
+        }
+        assert_eq!(meter.drop_rate(), Some(0.0));
+        // A new stream restarts the counters.
+        meter.record(11.0, 250, 200);
+        assert_eq!(meter.drop_rate(), None);
+    }
+    #[test]
+    fn lane_zoom_only_scales_its_own_channel() {
+        let zooms = [1.0, 2.0];
+        assert_eq!(scale_sample(10.0, 0.5, &zooms, 0), 5.0);
+        assert_eq!(scale_sample(10.0, 0.5, &zooms, 1), 10.0);
+        assert_eq!(scale_sample(10.0, 0.5, &zooms, 2), 5.0);
+    }
+    #[test]
+    fn scrubbed_spectrum_differs_from_live_tail() {
+        let sr = 250.0_f32;
+        // 2 s of 10 Hz followed by 2 s of 40 Hz.
+        let samples: Vec<f32> = (0..1000)
+            .map(|i| {
+                let t = i as f32 / sr;
+                let f = if i < 500 { 10.0 } else { 40.0 };
+                (2.0 * std::f32::consts::PI * f * t).sin()
+            })
+            .collect();
+        let history = TimeSeriesFrame {
+            sample_rate_hz: sr,
+            channel_labels: vec!["Ch1".into()],
+            samples: vec![samples],
+            timestamps: Vec::new(),
+        };
+        let builder = SpectrumBuilder::with_size(256);
+        let peak = |spec: &FrequencySpectrum| {
+            let mags = &spec.magnitudes[0];
+            let idx = (0..mags.len())
+                .max_by(|a, b| mags[*a].total_cmp(&mags[*b]))
+                .unwrap();
+            spec.frequencies_hz[idx]
+        };
+        let live = builder.compute(&history_slice(&history, 0, 256));
+        let scrubbed = builder.compute(&history_slice(&history, 500, 256));
+        assert!((peak(&live) - 40.0).abs() < 2.0);
+        assert!((peak(&scrubbed) - 10.0).abs() < 2.0);
+    }
+}
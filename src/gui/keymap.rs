@@ -0,0 +1,105 @@
+// src/gui/keymap.rs
+// 模拟模式键位：SimInputIntent 的每个字段绑定到哪个 egui::Key；配置里按键名保存，缺省或无效时回到默认键位
+use crate::types::{SimInputIntent, SimKey};
+use eframe::egui::Key;
+use std::collections::BTreeMap;
+
+/// Keys offered by the binding editor; a saved name outside this list falls back to the default.
+pub const BINDABLE_KEYS: [Key; 48] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::ArrowUp,
+    Key::ArrowDown,
+    Key::ArrowLeft,
+    Key::ArrowRight,
+    Key::Space,
+    Key::Minus,
+    Key::PlusEquals,
+    Key::Insert,
+    Key::Delete,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+];
+
+/// Bindable key with this `Key::name`.
+pub fn key_from_name(name: &str) -> Option<Key> {
+    BINDABLE_KEYS.into_iter().find(|k| k.name() == name)
+}
+
+/// Key currently driving `slot`: its saved binding, or the default layout's key.
+pub fn bound_key(bindings: &BTreeMap<SimKey, String>, slot: SimKey) -> Key {
+    bindings
+        .get(&slot)
+        .and_then(|name| key_from_name(name))
+        .or_else(|| key_from_name(slot.default_key()))
+        .unwrap_or(Key::Space)
+}
+
+/// This frame's simulated input from the keys `is_down` reports as held.
+pub fn sim_intent(
+    bindings: &BTreeMap<SimKey, String>,
+    is_down: impl Fn(Key) -> bool,
+) -> SimInputIntent {
+    let mut intent = SimInputIntent::default();
+    for slot in SimKey::ALL {
+        intent.set(slot, is_down(bound_key(bindings, slot)));
+    }
+    intent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_binding_replaces_the_default_key_for_its_slot_only() {
+        // AZERTY：前进改到 Z，原来 Z 对应的 B 键改到 W
+        let bindings = BTreeMap::from([
+            (SimKey::W, "Z".to_owned()),
+            (SimKey::KeyZ, "W".to_owned()),
+            (SimKey::A, "not a key".to_owned()),
+        ]);
+        let intent = sim_intent(&bindings, |k| k == Key::Z);
+        assert!(intent.w && !intent.key_z);
+        let intent = sim_intent(&bindings, |k| k == Key::W);
+        assert!(intent.key_z && !intent.w);
+        // 无效的键名回到默认键位
+        assert!(sim_intent(&bindings, |k| k == Key::A).a);
+        assert!(sim_intent(&BTreeMap::new(), |k| k == Key::ArrowLeft).arrow_left);
+    }
+}
@@ -0,0 +1,898 @@
+// src/gui/locale.rs
+// 界面文案：每种语言一张 (键, 文本) 表，首次使用时建成 HashMap，缺失的键回退到英文。
+// 新增语言只需新增一个 Language 变体和一张表。
+use std::collections::HashMap;
+use std::sync::OnceLock;
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    Chinese,
+}
+/// Declares `UiText` together with the list of every key, so each locale
+/// table can be checked for completeness.
+macro_rules! ui_text {
+    ($($name:ident,)*) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum UiText {
+            $($name,)*
+        }
+        impl UiText {
+            #[cfg(test)]
+            pub const ALL: &'static [UiText] = &[$(UiText::$name,)*];
+        }
+    };
+}
+ui_text! {
+    Title,
+    Subtitle,
+    Sim,
+    Real,
+    Connect,
+    Disconnect,
+    StartStream,
+    StopStream,
+    ResetView,
+    Controller,
+    Data,
+    Recording,
+    HardwareRequired,
+    KeyHint,
+    ConnectFirst,
+    Threshold,
+    Calibration,
+    FollowOn,
+    FollowOff,
+    Ready,
+    LanguagePrompt,
+    StartSubtitle,
+    StartHeading,
+    StartRecording,
+    StopRecording,
+    FftSize,
+    Update,
+    GenerateWaveformPng,
+    GenerateSpectrumPng,
+    WaveformPngLabel,
+    SpectrumPngLabel,
+    NoSpectrumYet,
+    RecordRelax,
+    RecordAction,
+    ConnectStreamFirst,
+    Loading,
+    Sensitivity,
+    Smoothness,
+    Window,
+    Window30,
+    Window60,
+    TabWaveform,
+    TabSpectrum,
+    TabPng,
+    TabCalibration,
+    TabImpedance,
+    ImpedanceDesc,
+    ImpedanceAction,
+    ImpedanceNoData,
+    ImpedanceUpdated,
+    ImpedanceChannelHeader,
+    ImpedanceValueHeader,
+    ImpedanceLimitHeader,
+    ImpedanceLimitHint,
+    PortLabel,
+    RefreshPorts,
+    PortsScanned,
+    InjectArtifact,
+    ReportFeedback,
+    ThemeLight,
+    ThemeDark,
+    LanguageSwitch,
+    Logs,
+    ReportLogs,
+    Resolution,
+    Maximize,
+    AutoY,
+    FixedUv,
+    Notch50,
+    Stats,
+    TimeAxis,
+    ShowPanel,
+    HidePanel,
+    ImpedanceLegend,
+    ModelSection,
+    ModelPath,
+    ModelReload,
+    ModelLoaded,
+    ModelNone,
+    ModelError,
+    ModelClasses,
+    ModelChannels,
+    ModelOutput,
+    MarkEvent,
+    SnapEvents,
+    TraceWidth,
+    TraceOpacity,
+    Freeze,
+    Scrub,
+    SpectrumFollowsScrub,
+    ResetLaneZoom,
+    LaneZoomHint,
+    NotchHarmonics,
+    ClearHistory,
+    RotateFiles,
+    Off,
+    DropWindowHint,
+    RunSelfTest,
+    SelfTestOnStartup,
+    StatsRefreshHint,
+    LeadOffBanner,
+    LeadOffAlert,
+    LeadOffBeep,
+    LeadOffDebounceHint,
+    RecordSampleIndex,
+    ReloadMapping,
+    SkipFirstChannels,
+    HideSkippedChannels,
+    PngGridPrefix,
+    ShowCorrelation,
+    CorrelatedPairs,
+    SampleClampPrefix,
+    SampleClampHint,
+    LiveSpectrum,
+    LiveSpectrumHint,
+    NotchPerChannel,
+    ExportRawUv,
+    ExportDisplayUnits,
+    ExportDisplayHint,
+    ExportWindowCsv,
+    AutoReconnect,
+    AutoReconnectHint,
+    SpectrumHistoryLen,
+    SpectrumHistoryHint,
+    SpectralFlux,
+    LaneIndexLabel,
+    LaneRmsLabel,
+    LabelsLeft,
+    LabelsRight,
+    LinkFilters,
+    LinkFiltersHint,
+    RobustAutoY,
+    RobustAutoYHint,
+    RecordMneExport,
+    RecordMneExportHint,
+    VJoyRateLimit,
+    VJoyRateHint,
+    DecodeBandOnly,
+    DecodeBandHint,
+    DecodeFeature,
+    DecodeFeatureHint,
+    ReportFilterResponse,
+    BandSnr,
+    BandSnrHint,
+    FftWindowHint,
+    AxisGains,
+    AxisGainsHint,
+    LslOutput,
+    LslOutputHint,
+    ReplayRecording,
+    ReplayRecordingHint,
+    ReplayLoad,
+    ReloadCalibration,
+    ReloadCalibrationHint,
+    ApplySavedCalibration,
+    EffectiveRate,
+    EffectiveRateHint,
+    ChannelLayout,
+    ChannelLayoutHint,
+    BoardLabel,
+    BoardHint,
+    OverlayChannelPrefix,
+    OverlayHint,
+    SaveOverlayPng,
+    OverlaySequence,
+    RecordDual,
+    RecordDualHint,
+    DecodeConfidence,
+    DecodeConfidenceHint,
+    ExportEdf,
+    ExportEdfHint,
+    ReplayDecode,
+    ReplayDecodeHint,
+    TriggerDebounce,
+    TriggerDebounceHint,
+    NoVirtualGamepad,
+    StickDeadZone,
+    StickDeadZoneHint,
+    RecordLabels,
+    RecordLabelsHint,
+    TabSpectrogram,
+    SpectrogramChannelPrefix,
+    SpectrogramHint,
+    SaveSpectrogramPng,
+    LeadOffDrive,
+    LeadOffDriveHint,
+    ImpedanceTrendHeader,
+    ImpedanceTrendHint,
+    ClearImpedanceHistory,
+    PauseWaveform,
+    ResumeWaveform,
+    PauseWaveformHint,
+    SavePngAs,
+    BandBars,
+    BandBarsHint,
+    PngZeroPhase,
+    PngZeroPhaseHint,
+    Broadband,
+    DisplayBandHint,
+    OscOutput,
+    OscOutputHint,
+    WebSocketOutput,
+    WebSocketOutputHint,
+    Reconnecting,
+    SimKeyBindings,
+    SimKeyBindingsHint,
+    ResetKeyBindings,
+    ControllerStyleHint,
+    EventLabelHint,
+    LoadClassifier,
+    LoadClassifierHint,
+    ClassifierDrive,
+    ClassifierDriveHint,
+    FftDetrendHint,
+    SimSampleRate,
+    SimSampleRateHint,
+    GuidedCalibration,
+    GuidedCalibrationHint,
+    CalibRelax,
+    CalibContract,
+    ThresholdBlend,
+    ThresholdBlendHint,
+}
+impl Language {
+    fn table(&self) -> &'static HashMap<UiText, &'static str> {
+        static ENGLISH: OnceLock<HashMap<UiText, &'static str>> = OnceLock::new();
+        static CHINESE: OnceLock<HashMap<UiText, &'static str>> = OnceLock::new();
+        match self {
+            Language::English => ENGLISH.get_or_init(|| EN.iter().copied().collect()),
+            Language::Chinese => CHINESE.get_or_init(|| ZH.iter().copied().collect()),
+        }
+    }
+    /// Text for `key` in this language, or the English text if the locale lacks it.
+    pub fn text(&self, key: UiText) -> &'static str {
+        self.table()
+            .get(&key)
+            .or_else(|| Language::English.table().get(&key))
+            .copied()
+            .unwrap_or_default()
+    }
+    pub fn default_record_label(&self) -> &'static str {
+        match self {
+            Language::English => "Attack",
+            Language::Chinese => "攻击",
+        }
+    }
+    /// Short code stored in the config ("en" / "zh").
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Chinese => "zh",
+        }
+    }
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim() {
+            "zh" | "cn" => Some(Language::Chinese),
+            "en" => Some(Language::English),
+            _ => None,
+        }
+    }
+}
+const EN: &[(UiText, &str)] = &[
+    (UiText::Title, "Neurostick demo v0.1"),
+    (UiText::Subtitle, "Neural Interface Control"),
+    (UiText::Sim, "Simulation"),
+    (UiText::Real, "Hardware"),
+    (UiText::Connect, "Connect"),
+    (UiText::Disconnect, "Disconnect"),
+    (UiText::StartStream, "Start Stream"),
+    (UiText::StopStream, "Stop Stream"),
+    (UiText::ResetView, "Reset View"),
+    (UiText::Controller, "Xbox Controller Visualizer"),
+    (UiText::Data, "AI Data Collection"),
+    (UiText::Recording, "Recording..."),
+    (UiText::HardwareRequired, "Hardware required"),
+    (UiText::KeyHint, "Try Keys: WASD / Space / ZXC / QEUO / Arrows"),
+    (UiText::ConnectFirst, "Connect first."),
+    (UiText::Threshold, "Trigger Threshold:"),
+    (UiText::Calibration, "Calibration"),
+    (UiText::FollowOn, "Follow Latest: ON"),
+    (UiText::FollowOff, "Follow Latest: OFF"),
+    (UiText::Ready, "Neurostick Demo v0.1 Ready."),
+    (UiText::LanguagePrompt, "Choose your language"),
+    (UiText::StartSubtitle, "Pick a language to start"),
+    (UiText::StartHeading, "Welcome to Neurostick"),
+    (UiText::StartRecording, "Record"),
+    (UiText::StopRecording, "Stop"),
+    (UiText::FftSize, "FFT Size:"),
+    (UiText::Update, "Update"),
+    (UiText::GenerateWaveformPng, "Generate Waveform PNG"),
+    (UiText::GenerateSpectrumPng, "Generate Spectrum PNG"),
+    (UiText::WaveformPngLabel, "Waveform PNG:"),
+    (UiText::SpectrumPngLabel, "Spectrum PNG:"),
+    (UiText::NoSpectrumYet, "No spectrum yet. Start streaming to populate."),
+    (UiText::RecordRelax, "1. Record Relax (3s)"),
+    (UiText::RecordAction, "2. Record Action (3s)"),
+    (UiText::ConnectStreamFirst, "Connect & Stream first."),
+    (UiText::Loading, "Working..."),
+    (UiText::Sensitivity, "Sensitivity"),
+    (UiText::Smoothness, "Smoothing"),
+    (UiText::Window, "Window"),
+    (UiText::Window30, "30s"),
+    (UiText::Window60, "60s"),
+    (UiText::TabWaveform, "Waveform"),
+    (UiText::TabSpectrum, "Spectrum"),
+    (UiText::TabPng, "PNG Export"),
+    (UiText::TabCalibration, "Calibration"),
+    (UiText::TabImpedance, "Resistance Check"),
+    (UiText::ImpedanceDesc, "Estimate electrode impedance from the latest buffer (Cyton math)."),
+    (UiText::ImpedanceAction, "Run check"),
+    (UiText::ImpedanceNoData, "No impedance result yet."),
+    (UiText::ImpedanceUpdated, "Impedance results updated."),
+    (UiText::ImpedanceChannelHeader, "Channel"),
+    (UiText::ImpedanceValueHeader, "Impedance (kOhm)"),
+    (UiText::ImpedanceLimitHeader, "Limit (kOhm)"),
+    (UiText::ImpedanceLimitHint, "Acceptable impedance for this channel; 0 uses the global bands"),
+    (UiText::PortLabel, "Port:"),
+    (UiText::RefreshPorts, "Refresh"),
+    (UiText::PortsScanned, "Ports scanned:"),
+    (UiText::InjectArtifact, "Inject Artifact"),
+    (UiText::ReportFeedback, "Report Feedback"),
+    (UiText::ThemeLight, "Light"),
+    (UiText::ThemeDark, "Dark"),
+    (UiText::LanguageSwitch, "Language"),
+    (UiText::Logs, "Logs"),
+    (UiText::ReportLogs, "Last Logs:"),
+    (UiText::Resolution, "Resolution"),
+    (UiText::Maximize, "Maximize"),
+    (UiText::AutoY, "Auto Y"),
+    (UiText::FixedUv, "Fixed uV"),
+    (UiText::Notch50, "Mains notch"),
+    (UiText::Stats, "Stats"),
+    (UiText::TimeAxis, "Time span (s)"),
+    (UiText::ShowPanel, "Show Panel"),
+    (UiText::HidePanel, "Hide Panel"),
+    (UiText::ImpedanceLegend, "Good <500k | Acceptable 0.5-2.5M | Poor >2.5M | Railed = no contact"),
+    (UiText::ModelSection, "AI Model"),
+    (UiText::ModelPath, "Path"),
+    (UiText::ModelReload, "Load / Reload"),
+    (UiText::ModelLoaded, "Model"),
+    (UiText::ModelNone, "No model loaded."),
+    (UiText::ModelError, "Model error"),
+    (UiText::ModelClasses, "Classes"),
+    (UiText::ModelChannels, "Channels"),
+    (UiText::ModelOutput, "Model Output"),
+    (UiText::MarkEvent, "Mark Event"),
+    (UiText::SnapEvents, "Snap events to samples"),
+    (UiText::TraceWidth, "Line width"),
+    (UiText::TraceOpacity, "Opacity"),
+    (UiText::Freeze, "Freeze"),
+    (UiText::Scrub, "Scrub"),
+    (UiText::SpectrumFollowsScrub, "Follow frozen window"),
+    (UiText::ResetLaneZoom, "Reset lane zoom"),
+    (UiText::LaneZoomHint, "Ctrl + scroll over a lane to zoom it"),
+    (UiText::NotchHarmonics, "Notch harmonics (100/150 Hz)"),
+    (UiText::ClearHistory, "Clear History"),
+    (UiText::RotateFiles, "Split files every"),
+    (UiText::Off, "Off"),
+    (UiText::DropWindowHint, "Drop rate averaging window"),
+    (UiText::RunSelfTest, "Run self-test"),
+    (UiText::SelfTestOnStartup, "at startup"),
+    (UiText::StatsRefreshHint, "Stats text refresh rate"),
+    (UiText::LeadOffBanner, "Lead-off (railed):"),
+    (UiText::LeadOffAlert, "Lead-off alert"),
+    (UiText::LeadOffBeep, "Beep"),
+    (UiText::RecordSampleIndex, "SampleIndex column"),
+    (UiText::ReloadMapping, "Reload mapping.toml"),
+    (UiText::SkipFirstChannels, "Reference channels: "),
+    (UiText::HideSkippedChannels, "Hide reference"),
+    (UiText::PngGridPrefix, "Grid every "),
+    (UiText::ShowCorrelation, "Channel correlation"),
+    (UiText::CorrelatedPairs, "Possibly bridged:"),
+    (UiText::SampleClampPrefix, "Reject beyond ±"),
+    (UiText::LiveSpectrum, "Live"),
+    (UiText::NotchPerChannel, "Per channel"),
+    (UiText::ExportRawUv, "Raw µV"),
+    (UiText::ExportDisplayUnits, "Display units"),
+    (UiText::ExportDisplayHint, "Export what the waveform shows: display gain, sensitivity, lane zoom and smoothing applied"),
+    (UiText::ExportWindowCsv, "Export window CSV"),
+    (UiText::AutoReconnect, "Auto-reconnect"),
+    (UiText::SpectrumHistoryLen, "History"),
+    (UiText::SpectrumHistoryHint, "Spectra kept for the novelty readout; the oldest are dropped past this cap"),
+    (UiText::SpectralFlux, "Spectral flux"),
+    (UiText::LaneIndexLabel, "Lane numbers"),
+    (UiText::LaneRmsLabel, "RMS text"),
+    (UiText::LabelsLeft, "Labels left"),
+    (UiText::LabelsRight, "Labels right"),
+    (UiText::LinkFilters, "Decode what I see"),
+    (UiText::RobustAutoY, "Ignore outliers"),
+    (UiText::RecordMneExport, "Export for MNE"),
+    (UiText::VJoyRateLimit, "vJoy output rate"),
+    (UiText::DecodeBandOnly, "Decode band only"),
+    (UiText::DecodeFeature, "Decode feature"),
+    (UiText::ReportFilterResponse, "Include filter response"),
+    (UiText::BandSnr, "Band SNR"),
+    (UiText::AxisGains, "Stick gain"),
+    (UiText::ChannelLayout, "Layout"),
+    (UiText::BoardLabel, "Board"),
+    (UiText::OverlayChannelPrefix, "Overlay Ch"),
+    (UiText::OverlayHint, "Channel drawn alone on a transparent strip for overlaying on gameplay video; the newest data scrolls in from the right"),
+    (UiText::SaveOverlayPng, "Save overlay PNG"),
+    (UiText::OverlaySequence, "Record overlay sequence"),
+    (UiText::RecordDual, "Record filtered too"),
+    (UiText::DecodeConfidence, "Confidence"),
+    (UiText::DecodeConfidenceHint, "Shade each fired action by its weakest channel's margin over the threshold: faint just above it, full at twice it"),
+    (UiText::ExportEdf, "Export EDF"),
+    (UiText::ReplayDecode, "Replay decode"),
+    (UiText::TriggerDebounce, "Button debounce"),
+    (UiText::TriggerDebounceHint, "Release gap: a pressed action lets go only below this fraction under its threshold. Hold: how long it must stay there first. Both 0 turns debouncing off"),
+    (UiText::NoVirtualGamepad, "no virtual pad (install vJoy or ViGEmBus)"),
+    (UiText::StickDeadZone, "Dead zone"),
+    (UiText::RecordLabels, "Record gamepad labels"),
+    (UiText::TabSpectrogram, "Spectrogram"),
+    (UiText::SpectrogramChannelPrefix, "Ch "),
+    (UiText::SpectrogramHint, "Press Update to draw how the buffered history's spectrum evolves over time (Spectrum tab FFT size, 75% overlap)"),
+    (UiText::SaveSpectrogramPng, "Save PNG"),
+    (UiText::LeadOffDrive, "Cyton lead-off current"),
+    (UiText::ImpedanceTrendHeader, "Trend"),
+    (UiText::ImpedanceTrendHint, "This channel over the last measurements, oldest on the left, scaled to its own range; re-measure while applying gel to watch it drop"),
+    (UiText::ClearImpedanceHistory, "Clear history"),
+    (UiText::PauseWaveform, "⏸ Pause"),
+    (UiText::ResumeWaveform, "▶ Resume"),
+    (UiText::SavePngAs, "Save..."),
+    (UiText::BandBars, "α/β bars"),
+    (UiText::BandBarsHint, "Bar per lane with the share of 0.5-45 Hz power in alpha (green) and beta (orange), from an FFT of the newest samples (Spectrum tab size)"),
+    (UiText::PngZeroPhase, "Zero-phase"),
+    (UiText::Broadband, "Broadband"),
+    (UiText::DisplayBandHint, "Band-pass every waveform lane to one EEG band (4th-order Butterworth); the mains notch still applies on top"),
+    (UiText::OscOutput, "OSC output"),
+    (UiText::OscOutputHint, "Send gamepad states (/qnmd/stick/*, /qnmd/button/*, /qnmd/dpad/*) and live band powers (/qnmd/band/*) over UDP to host:port; needs the osc build feature"),
+    (UiText::WebSocketOutput, "WebSocket"),
+    (UiText::WebSocketOutputHint, "Serve ws://host:port and push clean data to every browser client as JSON {sample_rate, channels} about 10 times a second (at most 32 points per channel); slow clients skip frames; needs the ws build feature"),
+    (UiText::Reconnecting, "Reconnecting to the board"),
+    (UiText::SimKeyBindings, "Key bindings"),
+    (UiText::SimKeyBindingsHint, "Left: the input's default key. Right: the key that drives it now."),
+    (UiText::ResetKeyBindings, "Reset to defaults"),
+    (UiText::ControllerStyleHint, "Pad drawn below: Xbox (A/B/X/Y, LB/LT) or PlayStation (✕/○/□/△, L1/L2); the decoded output is the same"),
+    (UiText::LoadClassifier, "Load classifier"),
+    (UiText::LoadClassifierHint, "Load data/model.json (classes, weights, bias, optional actions): logistic regression over ln(1 + band power), delta..gamma per channel; scores show under Model output"),
+    (UiText::ClassifierDrive, "Drive gamepad"),
+    (UiText::ClassifierDriveHint, "Press the action mapped to the top-scoring class while its score is at least 0.5, on top of the threshold decode"),
+    (UiText::GuidedCalibration, "Guided calibration (3s + 3s)"),
+    (UiText::GuidedCalibrationHint, "Records 3 s of rest, then 3 s of action, and applies the threshold between the two peaks in one go"),
+    (UiText::CalibRelax, "Relax..."),
+    (UiText::CalibContract, "Contract..."),
+    (UiText::ThresholdBlend, "Threshold blend"),
+    (UiText::ThresholdBlendHint, "Where the calibrated threshold sits between the rest peak (0) and the action peak (1); 0.5 is the midpoint"),
+    (UiText::SimSampleRate, "Sim rate"),
+    (UiText::SimSampleRateHint, "Sample rate the simulator emulates; the buffer, filters, waveform time axis and FFT frequencies follow it"),
+    (UiText::FftDetrendHint, "Subtract each channel's mean (default) or least-squares line before windowing, so a DC offset or slow drift does not swamp the low bins"),
+    (UiText::EventLabelHint, "Marker written to events_<name>.csv at the current recording time (same clock as the data rows); F2 marks too. Empty uses the recording label"),
+    (UiText::PngZeroPhaseHint, "Apply the waveform notch filters forward and backward (filtfilt) so the PNG has no filter delay; off renders them causally like the live view"),
+    (UiText::PauseWaveformHint, "Hold the waveform on screen while acquisition, recording and decoding continue; resuming jumps to the latest data"),
+    (UiText::LeadOffDriveHint, "Match the board's lead-off register (firmware default 6 nA) and series resistor, or Cyton impedance readings are off by the current ratio"),
+    (UiText::RecordLabelsHint, "Also write the decoded stick/button state of every sample to labels_<name>.csv, row-aligned with the data file, for training intent classifiers"),
+    (UiText::StickDeadZoneHint, "Stick deflection below this radius is sent as centred, so resting noise does not nudge the sticks; the curve below shapes the rest"),
+    (UiText::ReplayDecodeHint, "Run the recording through the current threshold, mapping, filters and decode feature, and write every action on/off to <name>.decode.csv; the same inputs always give the same file"),
+    (UiText::ExportEdfHint, "Write the whole buffered history (raw µV) as an EDF+ file under data/ for EDFbrowser, MNE or EEGLAB"),
+    (UiText::RecordDualHint, "Write RawCh0..15 and the filtered CleanCh0..15 side by side in each row"),
+    (UiText::BoardHint, "BrainFlow board to open; Synthetic generates test EEG through the same path without hardware or a serial port"),
+    (UiText::ChannelLayoutHint, "Board channel layout: sets channel count, labels, impedance math and the default mapping in one step"),
+    (UiText::EffectiveRate, "Rate"),
+    (UiText::EffectiveRateHint, "Measured samples per second since START STREAM vs the nominal rate, with the sample count; orange means the board is under-delivering"),
+    (UiText::ReloadCalibration, "Reload calibration on connect"),
+    (UiText::ReloadCalibrationHint, "Apply data/calibration.json automatically when its board, montage and channel count match"),
+    (UiText::ApplySavedCalibration, "Apply saved calibration"),
+    (UiText::ReplayRecording, "Recording CSV"),
+    (UiText::ReplayRecordingHint, "Path to a training_data_*.csv; its last window replaces the live frame for PNG/FFT export"),
+    (UiText::ReplayLoad, "Replay"),
+    (UiText::LslOutput, "LSL output"),
+    (UiText::LslOutputHint, "Publish clean samples as an LSL \"EEG\" stream while streaming (needs the lsl build feature and liblsl)"),
+    (UiText::AxisGainsHint, "Scale each decoded stick axis before it is sent to vJoy; results are clamped to full deflection"),
+    (UiText::FftWindowHint, "Taper applied before the FFT; Hann keeps a tone in a few bins instead of smearing it"),
+    (UiText::BandSnrHint, "Mean power in the band over the median 0.5-45 Hz noise floor, per channel; averaged over the buffered history with Welch's method (50% overlap) when available"),
+    (UiText::DecodeFeatureHint, "Threshold a sliding-window feature instead of each sample; steadier for EMG. Recalibrate after switching to log power"),
+    (UiText::DecodeBandHint, "Show one channel limited to the band you decode, to check it carries energy"),
+    (UiText::VJoyRateHint, "Write the latest decoded state to vJoy at this rate instead of on every sample; match the game's polling rate"),
+    (UiText::RecordMneExportHint, "When recording stops, also write channels x samples CSV plus a JSON with sfreq and ch_names"),
+    (UiText::RobustAutoYHint, "Auto Y spans the 1st-99th percentile of the window instead of its min/max"),
+    (UiText::LinkFiltersHint, "Apply the display filters to the decoder input too, so mapping thresholds act on the shown signal"),
+    (UiText::AutoReconnectHint, "Reconnect when the stream stalls or reads keep failing, waiting 1, 2, 4... s (up to 30 s) between tries and giving up after 6; recordings get a gap marker with the estimated missing samples"),
+    (UiText::LiveSpectrumHint, "Engine streams a Hann-windowed sliding FFT at this rate"),
+    (UiText::SampleClampHint, "Raw values outside this range are glitches and get replaced by the last good value"),
+    (UiText::LeadOffDebounceHint, "How long a channel must stay railed before alerting"),
+];
+const ZH: &[(UiText, &str)] = &[
+    (UiText::Title, "Neurostick 演示 v0.1"),
+    (UiText::Subtitle, "神经接口控制"),
+    (UiText::Sim, "模拟模式"),
+    (UiText::Real, "实机模式"),
+    (UiText::Connect, "连接"),
+    (UiText::Disconnect, "断开"),
+    (UiText::StartStream, "开始采集"),
+    (UiText::StopStream, "停止采集"),
+    (UiText::ResetView, "重置视图"),
+    (UiText::Controller, "手柄可视化"),
+    (UiText::Data, "AI数据采集"),
+    (UiText::Recording, "录制中..."),
+    (UiText::HardwareRequired, "需要硬件设备"),
+    (
+        UiText::KeyHint,
+        "键盘提示：WASD / 空格 / ZXC / QEUO / 方向键",
+    ),
+    (UiText::ConnectFirst, "请先连接设备。"),
+    (UiText::Threshold, "触发阈值："),
+    (UiText::Calibration, "校准"),
+    (UiText::FollowOn, "跟随最新：开"),
+    (UiText::FollowOff, "跟随最新：关"),
+    (UiText::Ready, "Neurostick 演示 v0.1 就绪。"),
+    (UiText::LanguagePrompt, "选择语言"),
+    (UiText::StartSubtitle, "选择语言开始"),
+    (UiText::StartHeading, "欢迎使用 Neurostick"),
+    (UiText::StartRecording, "开始录制"),
+    (UiText::StopRecording, "停止录制"),
+    (UiText::FftSize, "FFT 大小:"),
+    (UiText::Update, "更新"),
+    (UiText::GenerateWaveformPng, "导出波形PNG"),
+    (UiText::GenerateSpectrumPng, "导出频谱PNG"),
+    (UiText::WaveformPngLabel, "波形PNG:"),
+    (UiText::SpectrumPngLabel, "频谱PNG:"),
+    (UiText::NoSpectrumYet, "暂无频谱，开始采集后生成。"),
+    (UiText::RecordRelax, "1. 录制静息 (3s)"),
+    (UiText::RecordAction, "2. 录制动作 (3s)"),
+    (UiText::ConnectStreamFirst, "请先连接并开始采集。"),
+    (UiText::Loading, "处理中..."),
+    (UiText::Sensitivity, "敏感度"),
+    (UiText::Smoothness, "平滑度"),
+    (UiText::Window, "窗口长度"),
+    (UiText::Window30, "30秒"),
+    (UiText::Window60, "60秒"),
+    (UiText::TabWaveform, "波形"),
+    (UiText::TabSpectrum, "频谱"),
+    (UiText::TabPng, "导出PNG"),
+    (UiText::TabCalibration, "校准"),
+    (UiText::TabImpedance, "阻抗检测"),
+    (
+        UiText::ImpedanceDesc,
+        "基于最新缓冲区估算电极阻抗（Cyton 计算）。",
+    ),
+    (UiText::ImpedanceAction, "执行检测"),
+    (UiText::ImpedanceNoData, "暂无阻抗结果。"),
+    (UiText::ImpedanceUpdated, "阻抗结果已更新。"),
+    (UiText::ImpedanceChannelHeader, "通道"),
+    (UiText::ImpedanceValueHeader, "阻抗 (kOhm)"),
+    (UiText::ImpedanceLimitHeader, "阈值 (kOhm)"),
+    (
+        UiText::ImpedanceLimitHint,
+        "该通道可接受的阻抗；0 表示使用全局分级",
+    ),
+    (UiText::PortLabel, "串口:"),
+    (UiText::RefreshPorts, "刷新"),
+    (UiText::PortsScanned, "已扫描串口:"),
+    (UiText::InjectArtifact, "注入伪迹"),
+    (UiText::ReportFeedback, "报告反馈"),
+    (UiText::ThemeLight, "浅色"),
+    (UiText::ThemeDark, "深色"),
+    (UiText::LanguageSwitch, "语言"),
+    (UiText::Logs, "日志"),
+    (UiText::ReportLogs, "最近日志："),
+    (UiText::Resolution, "分辨率"),
+    (UiText::Maximize, "最大化"),
+    (UiText::AutoY, "自动Y轴"),
+    (UiText::FixedUv, "固定范围(uV)"),
+    (UiText::Notch50, "工频陷波"),
+    (UiText::Stats, "统计"),
+    (UiText::TimeAxis, "时间轴长度(秒)"),
+    (UiText::ShowPanel, "展开面板"),
+    (UiText::HidePanel, "收起面板"),
+    (
+        UiText::ImpedanceLegend,
+        "良好 <500k | 可用 0.5-2.5M | 较差 >2.5M | Railed = 无接触",
+    ),
+    (UiText::ModelSection, "AI 模型"),
+    (UiText::ModelPath, "路径"),
+    (UiText::ModelReload, "加载 / 重载"),
+    (UiText::ModelLoaded, "模型"),
+    (UiText::ModelNone, "未加载模型。"),
+    (UiText::ModelError, "模型错误"),
+    (UiText::ModelClasses, "类别"),
+    (UiText::ModelChannels, "通道数"),
+    (UiText::ModelOutput, "模型输出"),
+    (UiText::MarkEvent, "标记事件"),
+    (UiText::SnapEvents, "事件对齐到采样点"),
+    (UiText::TraceWidth, "线宽"),
+    (UiText::TraceOpacity, "不透明度"),
+    (UiText::Freeze, "冻结"),
+    (UiText::Scrub, "回看"),
+    (UiText::SpectrumFollowsScrub, "跟随冻结窗口"),
+    (UiText::ResetLaneZoom, "重置通道缩放"),
+    (UiText::LaneZoomHint, "在通道上按住 Ctrl 滚动滚轮可单独缩放"),
+    (UiText::NotchHarmonics, "陷波谐波 (100/150 Hz)"),
+    (UiText::ClearHistory, "清空历史"),
+    (UiText::RotateFiles, "分段保存"),
+    (UiText::Off, "关闭"),
+    (UiText::DropWindowHint, "丢包率统计窗口"),
+    (UiText::RunSelfTest, "运行自检"),
+    (UiText::SelfTestOnStartup, "启动时自检"),
+    (UiText::StatsRefreshHint, "统计文字刷新频率"),
+    (UiText::LeadOffBanner, "电极脱落："),
+    (UiText::LeadOffAlert, "脱落报警"),
+    (UiText::LeadOffBeep, "提示音"),
+    (UiText::RecordSampleIndex, "写入采样序号列"),
+    (UiText::ReloadMapping, "重新加载 mapping.toml"),
+    (UiText::SkipFirstChannels, "参考通道数: "),
+    (UiText::HideSkippedChannels, "隐藏参考通道"),
+    (UiText::PngGridPrefix, "网格间隔 "),
+    (UiText::ShowCorrelation, "通道相关性"),
+    (UiText::CorrelatedPairs, "疑似短接:"),
+    (UiText::SampleClampPrefix, "超出即剔除 ±"),
+    (UiText::LiveSpectrum, "实时"),
+    (UiText::NotchPerChannel, "按通道"),
+    (UiText::ExportRawUv, "原始 µV"),
+    (UiText::ExportDisplayUnits, "显示单位"),
+    (
+        UiText::ExportDisplayHint,
+        "按屏幕所见导出：已应用显示增益、灵敏度、通道缩放和平滑",
+    ),
+    (UiText::ExportWindowCsv, "导出窗口 CSV"),
+    (UiText::AutoReconnect, "断线自动重连"),
+    (UiText::SpectrumHistoryLen, "历史"),
+    (
+        UiText::SpectrumHistoryHint,
+        "新颖度指标保留的频谱数量，超出上限时丢弃最旧的",
+    ),
+    (UiText::SpectralFlux, "频谱通量"),
+    (UiText::LaneIndexLabel, "通道序号"),
+    (UiText::LaneRmsLabel, "RMS 文字"),
+    (UiText::LabelsLeft, "标签居左"),
+    (UiText::LabelsRight, "标签居右"),
+    (UiText::LinkFilters, "解码与显示同步"),
+    (UiText::RobustAutoY, "忽略离群值"),
+    (UiText::RecordMneExport, "导出 MNE 格式"),
+    (UiText::VJoyRateLimit, "vJoy 输出频率"),
+    (UiText::DecodeBandOnly, "仅显示解码频段"),
+    (UiText::DecodeFeature, "解码特征"),
+    (UiText::ReportFilterResponse, "附带滤波器响应"),
+    (UiText::BandSnr, "频段信噪比"),
+    (UiText::AxisGains, "摇杆增益"),
+    (UiText::ChannelLayout, "通道布局"),
+    (UiText::BoardLabel, "板卡"),
+    (UiText::OverlayChannelPrefix, "叠加通道 "),
+    (
+        UiText::OverlayHint,
+        "单独在透明背景条上绘制该通道，用于叠加到游戏视频上；最新数据从右侧滚入",
+    ),
+    (UiText::SaveOverlayPng, "保存叠加 PNG"),
+    (UiText::OverlaySequence, "录制叠加序列"),
+    (UiText::RecordDual, "同时录制滤波数据"),
+    (UiText::DecodeConfidence, "置信度"),
+    (
+        UiText::DecodeConfidenceHint,
+        "按最弱通道超出阈值的余量给已触发的动作着色：刚过阈值时很淡，达到两倍阈值时最亮",
+    ),
+    (UiText::ExportEdf, "导出 EDF"),
+    (UiText::ReplayDecode, "回放解码"),
+    (UiText::TriggerDebounce, "按键去抖"),
+    (UiText::NoVirtualGamepad, "无虚拟手柄 (请安装 vJoy 或 ViGEmBus)"),
+    (UiText::StickDeadZone, "死区"),
+    (UiText::RecordLabels, "录制手柄标签"),
+    (UiText::TabSpectrogram, "时频图"),
+    (UiText::SpectrogramChannelPrefix, "通道 "),
+    (
+        UiText::SpectrogramHint,
+        "点击更新，绘制缓冲历史的频谱随时间的变化 (使用频谱页的 FFT 点数，75% 重叠)",
+    ),
+    (UiText::SaveSpectrogramPng, "保存 PNG"),
+    (UiText::LeadOffDrive, "Cyton 导联脱落电流"),
+    (UiText::ImpedanceTrendHeader, "趋势"),
+    (
+        UiText::ImpedanceTrendHint,
+        "该通道最近几次测量的变化，左旧右新，按自身范围缩放；涂导电膏时反复测量可看到阻抗下降",
+    ),
+    (UiText::ClearImpedanceHistory, "清除历史"),
+    (UiText::PauseWaveform, "⏸ 暂停"),
+    (UiText::ResumeWaveform, "▶ 继续"),
+    (UiText::SavePngAs, "另存为..."),
+    (UiText::BandBars, "α/β 条"),
+    (
+        UiText::BandBarsHint,
+        "每个通道一组条形：alpha (绿) 与 beta (橙) 占 0.5-45 Hz 总功率的比例，取最新采样做 FFT (点数同频谱页)",
+    ),
+    (UiText::PngZeroPhase, "零相位"),
+    (UiText::Broadband, "全频带"),
+    (UiText::OscOutput, "OSC 输出"),
+    (UiText::WebSocketOutput, "WebSocket"),
+    (UiText::Reconnecting, "正在重连设备"),
+    (UiText::SimKeyBindings, "键位设置"),
+    (UiText::SimKeyBindingsHint, "左列：该输入的默认按键；右列：当前使用的按键。"),
+    (UiText::ResetKeyBindings, "恢复默认"),
+    (
+        UiText::ControllerStyleHint,
+        "下方手柄样式：Xbox (A/B/X/Y、LB/LT) 或 PlayStation (✕/○/□/△、L1/L2)；解码输出相同",
+    ),
+    (UiText::LoadClassifier, "加载分类器"),
+    (
+        UiText::LoadClassifierHint,
+        "读取 data/model.json (classes、weights、bias，可选 actions)：对每通道 delta..gamma 的 ln(1 + 频段功率) 做逻辑回归，得分显示在模型输出中",
+    ),
+    (UiText::ClassifierDrive, "驱动手柄"),
+    (
+        UiText::ClassifierDriveHint,
+        "得分最高的类别得分不低于 0.5 时按下其对应动作，与阈值解码叠加",
+    ),
+    (UiText::GuidedCalibration, "引导校准 (3s + 3s)"),
+    (
+        UiText::GuidedCalibrationHint,
+        "先录 3 秒静息、再录 3 秒动作，一次完成并应用两峰之间的阈值",
+    ),
+    (UiText::CalibRelax, "放松..."),
+    (UiText::CalibContract, "用力..."),
+    (UiText::ThresholdBlend, "阈值比例"),
+    (
+        UiText::ThresholdBlendHint,
+        "校准阈值在静息峰值 (0) 与动作峰值 (1) 之间的位置；0.5 为中点",
+    ),
+    (UiText::SimSampleRate, "模拟采样率"),
+    (
+        UiText::SimSampleRateHint,
+        "模拟器模仿的采样率；缓冲、滤波器、波形时间轴和 FFT 频率都随之改变",
+    ),
+    (
+        UiText::FftDetrendHint,
+        "加窗前减去每通道均值 (默认) 或最小二乘直线，避免直流偏置或慢漂移淹没低频桶",
+    ),
+    (
+        UiText::EventLabelHint,
+        "按当前录制时间 (与数据行同一时钟) 写入 events_<名称>.csv 的标记；F2 也可标记，留空则用录制标签",
+    ),
+    (
+        UiText::WebSocketOutputHint,
+        "在 ws://host:port 提供服务，每秒约 10 次把干净数据以 JSON {sample_rate, channels} 推给浏览器 (每通道最多 32 点)；客户端跟不上时跳帧；需启用 ws 编译特性",
+    ),
+    (
+        UiText::OscOutputHint,
+        "经 UDP 向 host:port 发送手柄状态 (/qnmd/stick/*、/qnmd/button/*、/qnmd/dpad/*) 和实时频段功率 (/qnmd/band/*)；需启用 osc 编译特性",
+    ),
+    (
+        UiText::DisplayBandHint,
+        "所有波形通道按所选 EEG 频段带通滤波 (4 阶 Butterworth)；工频陷波仍可同时开启",
+    ),
+    (
+        UiText::PngZeroPhaseHint,
+        "波形陷波滤波器正反各跑一遍 (filtfilt)，导出的 PNG 没有滤波延迟；关闭时与实时画面一样按因果方式滤波",
+    ),
+    (
+        UiText::PauseWaveformHint,
+        "保持波形画面不动，采集、录制与解码照常进行；继续后直接跳到最新数据",
+    ),
+    (
+        UiText::LeadOffDriveHint,
+        "需与板卡导联脱落寄存器 (固件默认 6 nA) 及串联电阻一致，否则 Cyton 阻抗读数会按电流比例偏差",
+    ),
+    (
+        UiText::RecordLabelsHint,
+        "同时把每个采样解码出的摇杆/按键状态写入 labels_<name>.csv，与数据文件逐行对齐，用于训练意图分类器",
+    ),
+    (
+        UiText::StickDeadZoneHint,
+        "摇杆偏移小于此半径时按回中发送，静息噪声不会推动摇杆；下方曲线决定其余部分的响应",
+    ),
+    (
+        UiText::TriggerDebounceHint,
+        "释放间隔：已按下的动作要低于阈值这么多比例才松开；保持：需要持续低于释放阈值的时间。两者都为 0 时关闭去抖",
+    ),
+    (
+        UiText::ReplayDecodeHint,
+        "用当前阈值、映射、滤波和解码特征处理录制，把每个动作的开/关写入 <name>.decode.csv；输入相同则文件完全相同",
+    ),
+    (
+        UiText::ExportEdfHint,
+        "把缓冲的全部历史 (原始 µV) 写成 data/ 下的 EDF+ 文件，可用 EDFbrowser、MNE 或 EEGLAB 打开",
+    ),
+    (
+        UiText::RecordDualHint,
+        "每行并排写入原始 RawCh0..15 和滤波后的 CleanCh0..15",
+    ),
+    (
+        UiText::BoardHint,
+        "要打开的 BrainFlow 板卡；Synthetic 无需硬件和串口，经同一路径生成测试脑电数据",
+    ),
+    (
+        UiText::ChannelLayoutHint,
+        "板卡通道布局：一次性设置通道数、通道名、阻抗算法和默认映射",
+    ),
+    (UiText::EffectiveRate, "采样率"),
+    (
+        UiText::EffectiveRateHint,
+        "开始推流以来实测的每秒采样数与标称采样率及采样总数；橙色表示板子供数不足",
+    ),
+    (UiText::ReloadCalibration, "连接时载入校准"),
+    (
+        UiText::ReloadCalibrationHint,
+        "板卡、导联和通道数一致时自动应用 data/calibration.json",
+    ),
+    (UiText::ApplySavedCalibration, "应用已保存的校准"),
+    (UiText::ReplayRecording, "录制 CSV"),
+    (
+        UiText::ReplayRecordingHint,
+        "training_data_*.csv 的路径；用其最后一个窗口代替实时数据导出 PNG/FFT",
+    ),
+    (UiText::ReplayLoad, "回放"),
+    (UiText::LslOutput, "LSL 输出"),
+    (
+        UiText::LslOutputHint,
+        "推流时把干净数据发布为 LSL \"EEG\" 流（需启用 lsl 编译特性并安装 liblsl）",
+    ),
+    (
+        UiText::AxisGainsHint,
+        "发送到 vJoy 前按轴缩放解码出的摇杆值，超出满偏会被截断",
+    ),
+    (
+        UiText::FftWindowHint,
+        "FFT 前施加的窗函数；Hann 窗让单频信号集中在少数几个频点，不再泄漏到整个频谱",
+    ),
+    (
+        UiText::BandSnrHint,
+        "各通道频段内平均功率相对 0.5-45 Hz 噪声底（中位数）的比值；有缓存历史时按 Welch 法（50% 重叠）平均",
+    ),
+    (
+        UiText::DecodeFeatureHint,
+        "对滑动窗口特征而不是单个采样做阈值判定，EMG 更稳定；切换到对数功率后需重新校准",
+    ),
+    (
+        UiText::DecodeBandHint,
+        "只显示单个通道在解码频段内的频谱，确认该频段确实有能量",
+    ),
+    (
+        UiText::VJoyRateHint,
+        "按此频率把最新的解码状态写入 vJoy，而不是每个采样都写；与游戏的轮询频率一致即可",
+    ),
+    (
+        UiText::RecordMneExportHint,
+        "停止录制时另存一份“通道 x 采样”CSV，并附带含 sfreq 和 ch_names 的 JSON",
+    ),
+    (
+        UiText::RobustAutoYHint,
+        "自动 Y 轴取窗口内第 1 到第 99 百分位，而不是最小/最大值",
+    ),
+    (
+        UiText::LinkFiltersHint,
+        "解码输入也经过显示滤波器，映射阈值作用于屏幕上看到的信号",
+    ),
+    (
+        UiText::AutoReconnectHint,
+        "推流中断或连续读取失败时自动重连，每次间隔 1、2、4... 秒 (最长 30 秒)，6 次后放弃；录制文件中会写入缺口标记及估算的丢失采样数",
+    ),
+    (
+        UiText::LiveSpectrumHint,
+        "由引擎按此频率持续计算加汉宁窗的滑动 FFT",
+    ),
+    (
+        UiText::SampleClampHint,
+        "超出此范围的原始值视为异常，用上一个正常值替代",
+    ),
+    (UiText::LeadOffDebounceHint, "持续多久才触发报警"),
+];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn every_key_resolves_in_every_language() {
+        for lang in [Language::English, Language::Chinese] {
+            let missing: Vec<&UiText> = UiText::ALL
+                .iter()
+                .filter(|k| !lang.table().contains_key(k))
+                .collect();
+            assert!(missing.is_empty(), "missing keys: {missing:?}");
+        }
+        // No key listed twice (a duplicate would silently shadow the first entry).
+        assert_eq!(EN.len(), UiText::ALL.len());
+        assert_eq!(ZH.len(), UiText::ALL.len());
+    }
+}
@@ -0,0 +1,17 @@
+// src/gui/save_dialog.rs
+// 原生“另存为”对话框 (rfd)：Windows/macOS 用系统对话框，Linux 走 XDG 桌面门户
+use std::path::{Path, PathBuf};
+
+/// Asks where to save a PNG, starting at `default_path`. `None` when the user cancels.
+pub fn save_png_path(default_path: &Path) -> Option<PathBuf> {
+    let mut dialog = rfd::FileDialog::new().add_filter("PNG", &["png"]);
+    if let Some(dir) = default_path.parent() {
+        // 对话框需要绝对路径；不用 canonicalize，它在 Windows 上会加 \\?\ 前缀
+        let dir = std::env::current_dir().map_or_else(|_| dir.to_path_buf(), |cwd| cwd.join(dir));
+        dialog = dialog.set_directory(dir);
+    }
+    if let Some(name) = default_path.file_name() {
+        dialog = dialog.set_file_name(name.to_string_lossy());
+    }
+    dialog.save_file()
+}
@@ -2,11 +2,15 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 mod assets;
 mod brain_utils;
+mod calibration;
+mod config;
 mod drivers;
 mod engine;
 mod gui;
+mod montage;
 mod openbci;
 mod recorder;
+mod selftest;
 mod types;
 mod visualizer;
 mod vjoy;
@@ -47,11 +51,17 @@ fn load_app_icon() -> Option<IconData> {
 fn main() -> eframe::Result<()> {
     env_logger::init();
     let icon_data = load_app_icon();
+    // 恢复上次的窗口大小/位置，并限制在上次所在显示器范围内
+    let geometry = config::AppConfig::load().window.restored();
     let viewport = egui::ViewportBuilder::default()
-        .with_inner_size([1463.0, 915.0])
-        .with_min_inner_size([1200.0, 760.0])
-        .with_maximized(true)
+        .with_inner_size(geometry.size)
+        .with_min_inner_size(config::WindowGeometry::MIN_SIZE)
+        .with_maximized(geometry.maximized)
         .with_title("Neurostick demo v0.1");
+    let viewport = match geometry.position {
+        Some(pos) => viewport.with_position(pos),
+        None => viewport,
+    };
     let viewport = if let Some(icon) = icon_data {
         viewport.with_icon(icon)
     } else {
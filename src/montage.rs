@@ -0,0 +1,108 @@
+// src/montage.rs
+// 导联配置：有些电极帽把参考/偏置电极放在最前面的通道，这些通道不参与解码，默认也不显示
+use crate::drivers::ImpedanceModel;
+use crate::waveform::WaveformPipeline;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Montage {
+    /// Zero-based channels carrying reference/bias electrodes.
+    pub skip_channels: Vec<usize>,
+    /// Leave skipped channels out of the default waveform view.
+    pub hide_skipped: bool,
+    /// Board channel layout; sets channel count, labels, impedance math and default mapping.
+    pub layout: QuickMontage,
+}
+
+/// One-click board layouts so switching Cyton / Cyton+Daisy / Ganglion is one action.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickMontage {
+    Cyton8,
+    #[default]
+    CytonDaisy16,
+    Ganglion4,
+}
+
+impl QuickMontage {
+    pub const ALL: [QuickMontage; 3] = [
+        QuickMontage::Cyton8,
+        QuickMontage::CytonDaisy16,
+        QuickMontage::Ganglion4,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            QuickMontage::Cyton8 => "Cyton (8)",
+            QuickMontage::CytonDaisy16 => "Cyton+Daisy (16)",
+            QuickMontage::Ganglion4 => "Ganglion (4)",
+        }
+    }
+
+    pub fn channel_count(self) -> usize {
+        match self {
+            QuickMontage::Cyton8 => 8,
+            QuickMontage::CytonDaisy16 => 16,
+            QuickMontage::Ganglion4 => 4,
+        }
+    }
+
+    pub fn default_labels(self) -> Vec<String> {
+        (1..=self.channel_count())
+            .map(|i| format!("Ch{i}"))
+            .collect()
+    }
+
+    pub fn impedance_model(self) -> ImpedanceModel {
+        match self {
+            QuickMontage::Cyton8 | QuickMontage::CytonDaisy16 => ImpedanceModel::Cyton,
+            QuickMontage::Ganglion4 => ImpedanceModel::Ganglion,
+        }
+    }
+}
+
+impl Default for Montage {
+    fn default() -> Self {
+        Self {
+            skip_channels: Vec::new(),
+            hide_skipped: true,
+            layout: QuickMontage::default(),
+        }
+    }
+}
+
+impl Montage {
+    pub fn is_skipped(&self, idx: usize) -> bool {
+        self.skip_channels.contains(&idx)
+    }
+
+    /// Number of channels skipped contiguously from channel 0.
+    pub fn leading_skipped(&self) -> usize {
+        (0..).take_while(|&i| self.is_skipped(i)).count()
+    }
+
+    /// Replaces the skip list with the first `n` channels.
+    pub fn set_skip_first(&mut self, n: usize) {
+        self.skip_channels = (0..n).collect();
+    }
+
+    /// Zeroes skipped channels so no mapping pattern can trigger on them.
+    pub fn mask_decode(&self, data: &mut [f64]) {
+        for &idx in &self.skip_channels {
+            if let Some(v) = data.get_mut(idx) {
+                *v = 0.0;
+            }
+        }
+    }
+
+    pub fn shown_by_default(&self, idx: usize) -> bool {
+        !(self.hide_skipped && self.is_skipped(idx))
+    }
+
+    pub fn apply_to_view(&self, pipe: &mut WaveformPipeline) {
+        for idx in 0..pipe.channel_count() {
+            pipe.set_channel_enabled(idx, self.shown_by_default(idx));
+        }
+    }
+}
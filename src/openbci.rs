@@ -1,12 +1,66 @@
 use anyhow::{anyhow, Context, Result};
 use libloading::Library;
 use once_cell::sync::OnceCell;
-use serde::Serialize;
+use crate::montage::QuickMontage;
+use serde::{Deserialize, Serialize};
 use std::ffi::CString;
 use std::os::raw::{c_char, c_double, c_int};
-const BOARD_ID_CYTON_DAISY: c_int = 2; // matches python trainer script
 const PRESET_DEFAULT: c_int = 0;
 const STREAM_RINGBUF_PACKETS: c_int = 450_000;
+/// Boards the BrainFlow session can open; ids follow BrainFlow's `BoardIds`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoardKind {
+    #[default]
+    CytonDaisy,
+    Cyton,
+    Ganglion,
+    /// BrainFlow's generated test signal; needs no serial port or hardware.
+    Synthetic,
+}
+impl BoardKind {
+    pub const ALL: [BoardKind; 4] = [
+        BoardKind::CytonDaisy,
+        BoardKind::Cyton,
+        BoardKind::Ganglion,
+        BoardKind::Synthetic,
+    ];
+    fn board_id(self) -> c_int {
+        match self {
+            BoardKind::CytonDaisy => 2, // matches python trainer script
+            BoardKind::Cyton => 0,
+            BoardKind::Ganglion => 1,
+            BoardKind::Synthetic => -1,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            BoardKind::CytonDaisy => "Cyton+Daisy",
+            BoardKind::Cyton => "Cyton",
+            BoardKind::Ganglion => "Ganglion",
+            BoardKind::Synthetic => "Synthetic",
+        }
+    }
+    pub fn needs_serial_port(self) -> bool {
+        self != BoardKind::Synthetic
+    }
+    /// Channel layout matching the board's EEG channels.
+    pub fn layout(self) -> QuickMontage {
+        match self {
+            BoardKind::CytonDaisy | BoardKind::Synthetic => QuickMontage::CytonDaisy16,
+            BoardKind::Cyton => QuickMontage::Cyton8,
+            BoardKind::Ganglion => QuickMontage::Ganglion4,
+        }
+    }
+    /// Factor from BrainFlow's EEG rows to µV: the OpenBCI boards are read as
+    /// volts, the synthetic board already generates microvolts.
+    pub fn uv_per_unit(self) -> f64 {
+        match self {
+            BoardKind::Synthetic => 1.0,
+            _ => 1e6,
+        }
+    }
+}
 #[derive(Serialize)]
 struct BrainFlowInputParams {
     serial_port: String,
@@ -27,9 +81,13 @@ struct BrainFlowInputParams {
     master_board: i32,
 }
 impl BrainFlowInputParams {
-    fn for_serial(port: &str) -> Self {
+    fn for_board(board: BoardKind, port: &str) -> Self {
         Self {
-            serial_port: port.to_string(),
+            serial_port: if board.needs_serial_port() {
+                port.to_string()
+            } else {
+                String::new()
+            },
             mac_address: String::new(),
             ip_address: String::new(),
             ip_address_aux: String::new(),
@@ -58,14 +116,10 @@ struct BrainFlowApi {
     get_sampling_rate: unsafe extern "C" fn(c_int, c_int, *mut c_int) -> c_int,
     get_num_rows: unsafe extern "C" fn(c_int, c_int, *mut c_int) -> c_int,
     get_eeg_channels: unsafe extern "C" fn(c_int, c_int, *mut c_int, *mut c_int) -> c_int,
-    get_current_board_data: unsafe extern "C" fn(
-        c_int,
-        c_int,
-        *mut c_double,
-        *mut c_int,
-        c_int,
-        *const c_char,
-    ) -> c_int,
+    /// Missing from very old `BoardController.dll` builds; samples are then left without device time.
+    get_timestamp_channel: Option<unsafe extern "C" fn(c_int, c_int, *mut c_int) -> c_int>,
+    get_board_data_count: unsafe extern "C" fn(c_int, *mut c_int, c_int, *const c_char) -> c_int,
+    get_board_data: unsafe extern "C" fn(c_int, c_int, *mut c_double, c_int, *const c_char) -> c_int,
 }
 impl BrainFlowApi {
     fn load() -> Result<Self> {
@@ -82,7 +136,9 @@ impl BrainFlowApi {
                 get_sampling_rate: *lib.get(b"get_sampling_rate\0")?,
                 get_num_rows: *lib.get(b"get_num_rows\0")?,
                 get_eeg_channels: *lib.get(b"get_eeg_channels\0")?,
-                get_current_board_data: *lib.get(b"get_current_board_data\0")?,
+                get_timestamp_channel: lib.get(b"get_timestamp_channel\0").ok().map(|f| *f),
+                get_board_data_count: *lib.get(b"get_board_data_count\0")?,
+                get_board_data: *lib.get(b"get_board_data\0")?,
                 lib,
             })
         }
@@ -162,72 +218,116 @@ impl BrainFlowApi {
         buf.truncate(out_len as usize);
         Ok(buf)
     }
-    fn current_board_data(
+    /// Row holding BrainFlow's per-sample UNIX timestamp (seconds), if the board has one.
+    fn timestamp_channel(&self, board_id: c_int) -> Option<usize> {
+        let get = self.get_timestamp_channel?;
+        let mut row: c_int = -1;
+        let code = unsafe { get(board_id, PRESET_DEFAULT, &mut row as *mut c_int) };
+        (code == 0 && row >= 0).then_some(row as usize)
+    }
+    fn board_data_count(&self, board_id: c_int, input: &CString) -> Result<usize> {
+        let mut count: c_int = 0;
+        Self::check(
+            unsafe {
+                (self.get_board_data_count)(
+                    PRESET_DEFAULT,
+                    &mut count as *mut c_int,
+                    board_id,
+                    input.as_ptr(),
+                )
+            },
+            "get_board_data_count",
+        )?;
+        Ok(count.max(0) as usize)
+    }
+    /// Moves `num_samples` samples out of BrainFlow's ring buffer, so each
+    /// sample is returned exactly once.
+    fn board_data(
         &self,
         board_id: c_int,
         num_rows: usize,
         input: &CString,
         num_samples: usize,
-        buffer: &mut [f64],
-    ) -> Result<usize> {
-        let mut current_size: c_int = 0;
+    ) -> Result<Vec<f64>> {
+        let mut buffer = vec![0.0f64; num_rows * num_samples];
         Self::check(
             unsafe {
-                (self.get_current_board_data)(
+                (self.get_board_data)(
                     num_samples as c_int,
                     PRESET_DEFAULT,
                     buffer.as_mut_ptr(),
-                    &mut current_size as *mut c_int,
                     board_id,
                     input.as_ptr(),
                 )
             },
-            "get_current_board_data",
+            "get_board_data",
         )?;
-        let samples = current_size.max(0) as usize;
-        let expected = num_rows * num_samples;
-        if buffer.len() < expected {
-            return Err(anyhow::anyhow!(
-                "buffer too small: {} < {}",
-                buffer.len(),
-                expected
-            ));
-        }
-        Ok(samples)
+        Ok(buffer)
     }
 }
-/// BrainFlow-backed session for OpenBCI Cyton + Daisy via USB dongle.
+/// Picks the EEG rows out of a BrainFlow row-major block (`row * samples + idx`).
+fn eeg_rows(buf: &[f64], num_rows: usize, samples: usize, eeg_channels: &[c_int]) -> Vec<Vec<f64>> {
+    eeg_channels
+        .iter()
+        .map(|&ch| ch as usize)
+        .filter(|&row| row < num_rows && (row + 1) * samples <= buf.len())
+        .map(|row| buf[row * samples..(row + 1) * samples].to_vec())
+        .collect()
+}
+/// The timestamp row of the same block; empty when the board reports none.
+fn timestamp_row(buf: &[f64], num_rows: usize, samples: usize, row: Option<usize>) -> Vec<f64> {
+    row.filter(|&row| row < num_rows && (row + 1) * samples <= buf.len())
+        .map(|row| buf[row * samples..(row + 1) * samples].to_vec())
+        .unwrap_or_default()
+}
+/// Samples drained from BrainFlow in one call.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SampleBlock {
+    /// EEG channels x samples.
+    pub channels: Vec<Vec<f64>>,
+    /// Device time of each sample in UNIX seconds; empty when the board has no timestamp row.
+    pub timestamps: Vec<f64>,
+}
+/// BrainFlow-backed session for an OpenBCI board via USB dongle (or BrainFlow's
+/// synthetic board).
 ///
 /// Compared to the previous raw-serial approach, this uses BrainFlow's
 /// `BoardController.dll` so we decode the binary dongle stream reliably and
 /// get properly scaled EEG samples.
 pub struct OpenBciSession {
+    board: BoardKind,
     port_name: String,
     api: &'static BrainFlowApi,
     input_json: CString,
     eeg_channels: Vec<c_int>,
+    timestamp_row: Option<usize>,
     num_rows: usize,
     sample_rate_hz: f32,
     is_streaming: bool,
     released: bool,
 }
 impl OpenBciSession {
-    /// Connects and prepares a BrainFlow session for Cyton+Daisy (board id 2).
-    pub fn connect(port_name: &str) -> Result<Self> {
+    /// Connects and prepares a BrainFlow session for `board`. Rate, row count and
+    /// EEG rows are queried from BrainFlow for that board, not assumed.
+    pub fn connect_board(board: BoardKind, port_name: &str) -> Result<Self> {
         let api = BrainFlowApi::instance()?;
-        let params = BrainFlowInputParams::for_serial(port_name);
+        let board_id = board.board_id();
+        let params = BrainFlowInputParams::for_board(board, port_name);
         let json = serde_json::to_string(&params)?;
         let input_json =
             CString::new(json).context("failed to encode BrainFlow input params to C string")?;
-        api.prepare(BOARD_ID_CYTON_DAISY, &input_json)?;
-        let sample_rate_hz = api.sampling_rate(BOARD_ID_CYTON_DAISY)? as f32;
-        let num_rows = api.num_rows(BOARD_ID_CYTON_DAISY)? as usize;
-        let eeg_channels = api.eeg_channels(BOARD_ID_CYTON_DAISY, num_rows)?;
+        api.prepare(board_id, &input_json)?;
+        let sample_rate_hz = api.sampling_rate(board_id)? as f32;
+        let num_rows = api.num_rows(board_id)? as usize;
+        let eeg_channels = api.eeg_channels(board_id, num_rows)?;
+        let timestamp_row = api.timestamp_channel(board_id);
         Ok(Self {
+            board,
             port_name: port_name.to_string(),
             api,
             input_json,
             eeg_channels,
+            timestamp_row,
             num_rows,
             sample_rate_hz,
             is_streaming: false,
@@ -240,10 +340,14 @@ impl OpenBciSession {
     pub fn sample_rate_hz(&self) -> f32 {
         self.sample_rate_hz
     }
+    /// EEG channels BrainFlow reports for the connected board.
+    pub fn channel_count(&self) -> usize {
+        self.eeg_channels.len()
+    }
     pub fn start_stream(&mut self) -> Result<()> {
         if !self.is_streaming {
             self.api
-                .start_stream(BOARD_ID_CYTON_DAISY, &self.input_json)?;
+                .start_stream(self.board.board_id(), &self.input_json)?;
             self.is_streaming = true;
         }
         Ok(())
@@ -252,45 +356,34 @@ impl OpenBciSession {
         if !self.released {
             if self.is_streaming {
                 self.api
-                    .stop_stream(BOARD_ID_CYTON_DAISY, &self.input_json)?;
+                    .stop_stream(self.board.board_id(), &self.input_json)?;
                 self.is_streaming = false;
             }
-            self.api.release(BOARD_ID_CYTON_DAISY, &self.input_json)?;
+            self.api.release(self.board.board_id(), &self.input_json)?;
             self.released = true;
         }
         Ok(())
     }
-    /// Pulls the most recent sample for all EEG channels (if any).
-    pub fn next_sample(&mut self) -> Result<Option<Vec<f64>>> {
-        // We request up to 5 samples to reduce FFI overhead; only the latest is used.
-        let max_samples = 5;
-        let mut buf = vec![0.0f64; self.num_rows * max_samples];
-        let available = self.api.current_board_data(
-            BOARD_ID_CYTON_DAISY,
+    /// Drains every sample BrainFlow has buffered since the last call, as
+    /// EEG channels x samples plus their device timestamps. Empty when nothing
+    /// new has arrived.
+    pub fn next_samples(&mut self) -> Result<SampleBlock> {
+        let available = self
+            .api
+            .board_data_count(self.board.board_id(), &self.input_json)?;
+        if available == 0 {
+            return Ok(SampleBlock::default());
+        }
+        let buf = self.api.board_data(
+            self.board.board_id(),
             self.num_rows,
             &self.input_json,
-            max_samples,
-            &mut buf,
+            available,
         )?;
-        if available == 0 {
-            return Ok(None);
-        }
-        let last_idx = available - 1;
-        let mut sample = Vec::with_capacity(self.eeg_channels.len());
-        for ch in &self.eeg_channels {
-            let ch_idx = *ch as usize;
-            if ch_idx < self.num_rows {
-                let offset = ch_idx * available + last_idx;
-                if offset < buf.len() {
-                    sample.push(buf[offset]);
-                }
-            }
-        }
-        if sample.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(sample))
-        }
+        Ok(SampleBlock {
+            channels: eeg_rows(&buf, self.num_rows, available, &self.eeg_channels),
+            timestamps: timestamp_row(&buf, self.num_rows, available, self.timestamp_row),
+        })
     }
 }
 impl Drop for OpenBciSession {
@@ -298,3 +391,34 @@ impl Drop for OpenBciSession {
         let _ = self.stop_stream();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synthetic_board_uses_id_minus_one_without_a_serial_port() {
+        assert_eq!(BoardKind::Synthetic.board_id(), -1);
+        assert_eq!(BoardKind::CytonDaisy.board_id(), 2);
+        let params = BrainFlowInputParams::for_board(BoardKind::Synthetic, "COM3");
+        assert!(params.serial_port.is_empty());
+        let params = BrainFlowInputParams::for_board(BoardKind::Cyton, "COM3");
+        assert_eq!(params.serial_port, "COM3");
+    }
+
+    #[test]
+    fn eeg_rows_keep_every_sample_of_a_block() {
+        // 4 rows (package num, 2 EEG, timestamp) x 3 samples, row-major like BrainFlow
+        let buf = vec![
+            0.0, 1.0, 2.0, // package num
+            10.0, 11.0, 12.0, // EEG 1
+            20.0, 21.0, 22.0, // EEG 2
+            9.0, 9.1, 9.2, // timestamp
+        ];
+        let rows = eeg_rows(&buf, 4, 3, &[1, 2, 7]);
+        assert_eq!(rows, vec![vec![10.0, 11.0, 12.0], vec![20.0, 21.0, 22.0]]);
+        assert_eq!(timestamp_row(&buf, 4, 3, Some(3)), vec![9.0, 9.1, 9.2]);
+        assert!(timestamp_row(&buf, 4, 3, Some(4)).is_empty());
+        assert!(timestamp_row(&buf, 4, 3, None).is_empty());
+    }
+}
@@ -1,54 +1,876 @@
+mod edf;
+
+use crate::types::GamepadState;
+pub use edf::write_edf;
+use serde::Serialize;
 use std::fs::File;
 use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
+/// How `MarkEvent` timestamps are placed in the events file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventAlignment {
+    /// Snap each event to the nearest recorded sample row (unambiguous ML labels).
+    NearestSample,
+    /// Keep the raw wall-clock time; the index is the next row to be written.
+    WallClock,
+}
+/// Which channel columns each recorded row carries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecordMode {
+    /// `Ch0..ChN`: the unfiltered samples as they came from the board.
+    #[default]
+    RawOnly,
+    /// `RawCh0..RawChN` followed by the filtered `CleanCh0..CleanChN`.
+    Dual,
+}
+/// When a long recording rolls over to a new CSV part.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+    Off,
+    /// Start a new part after this many seconds of samples.
+    EverySeconds(f64),
+    /// Start a new part once the current one reaches this many bytes.
+    EveryBytes(u64),
+}
+#[derive(Serialize)]
+struct PartInfo {
+    file: String,
+    first_row: u64,
+    rows: u64,
+}
+/// `session_<id>.json`: describes every part of a rotated recording.
+#[derive(Serialize)]
+struct SessionManifest<'a> {
+    session_id: &'a str,
+    label: &'a str,
+    rotation: String,
+    columns: Vec<String>,
+    events_file: String,
+    parts: &'a [PartInfo],
+}
+/// `mne_<id>.json`: what `mne.create_info` needs to load `mne_<id>.csv`
+/// (one row per channel, one column per sample) as a `RawArray`.
+#[derive(Serialize)]
+struct MneInfo {
+    sfreq: f64,
+    ch_names: Vec<String>,
+    ch_types: Vec<&'static str>,
+    n_times: usize,
+    data_file: String,
+    events_file: String,
+    layout: &'static str,
+}
+struct Session {
+    dir: PathBuf,
+    id: String,
+    label: String,
+    part_started_at: Option<f64>,
+    part_bytes: u64,
+    parts: Vec<PartInfo>,
+}
+struct PendingEvent {
+    time: f64,
+    label: String,
+}
 pub struct DataRecorder {
     writer: Option<BufWriter<File>>,
+    events_writer: Option<BufWriter<File>>,
+    /// Decoded gamepad state per data row, for supervised training.
+    labels_writer: Option<BufWriter<File>>,
     start_time: SystemTime,
+    /// Latest board timestamp written and the wall time it arrived; once a board
+    /// reports one, rows carry device time and markers extrapolate from it.
+    device_clock: Option<(f64, SystemTime)>,
+    event_alignment: EventAlignment,
+    rotation: Rotation,
+    sample_index_column: bool,
+    mne_export: bool,
+    record_mode: RecordMode,
+    /// Board channels per row side, fixed for the length of a recording.
+    channel_count: usize,
+    sample_rate_hz: f64,
+    session: Option<Session>,
+    rows_written: u64,
+    last_row_time: Option<f64>,
+    pending_events: Vec<PendingEvent>,
 }
 impl DataRecorder {
     pub fn new() -> Self {
         Self {
             writer: None,
+            events_writer: None,
+            labels_writer: None,
             start_time: SystemTime::now(),
+            device_clock: None,
+            event_alignment: EventAlignment::NearestSample,
+            rotation: Rotation::Off,
+            sample_index_column: false,
+            mne_export: false,
+            record_mode: RecordMode::RawOnly,
+            channel_count: 16,
+            sample_rate_hz: 250.0,
+            session: None,
+            rows_written: 0,
+            last_row_time: None,
+            pending_events: Vec::new(),
         }
     }
-    pub fn start(&mut self, label: &str) {
+    /// Starts a recording whose header and rows carry `channel_count` channels.
+    pub fn start(&mut self, label: &str, channel_count: usize) {
+        self.start_in(Path::new("."), label, channel_count);
+    }
+    /// Like `start`, plus a `labels_<id>.csv` with the decoded gamepad state of
+    /// every data row (see `write_label`).
+    pub fn start_with_labels(&mut self, label: &str, channel_count: usize) {
+        self.start_with_labels_in(Path::new("."), label, channel_count);
+    }
+    pub fn start_with_labels_in(&mut self, dir: &Path, label: &str, channel_count: usize) {
+        self.start_in(dir, label, channel_count);
+        let Some(session) = self.session.as_ref() else {
+            return;
+        };
+        let labels_name = dir.join(format!("labels_{}.csv", session.id));
+        self.labels_writer = File::create(labels_name).ok().map(|f| {
+            let mut w = BufWriter::new(f);
+            writeln!(
+                w,
+                "SampleIndex,Timestamp,LX,LY,RX,RY,A,B,X,Y,LB,RB,LT,RT,DpadUp,DpadDown,DpadLeft,DpadRight"
+            )
+            .ok();
+            w
+        });
+    }
+    pub fn start_in(&mut self, dir: &Path, label: &str, channel_count: usize) {
+        self.channel_count = channel_count;
         // 文件名带时间戳和标签，方便后续 AI 识别
         let timestamp = SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs();
-        let filename = format!("training_data_{}_{}.csv", label, timestamp);
-        if let Ok(file) = File::create(&filename) {
-            let mut w = BufWriter::new(file);
-            // 写入 CSV 表头: Timestamp, Ch0 ... Ch15
-            writeln!(
-                w,
-                "Timestamp,Ch0,Ch1,Ch2,Ch3,Ch4,Ch5,Ch6,Ch7,Ch8,Ch9,Ch10,Ch11,Ch12,Ch13,Ch14,Ch15"
+        let id = format!("{}_{}", label, timestamp);
+        self.session = Some(Session {
+            dir: dir.to_path_buf(),
+            id: id.clone(),
+            label: label.to_owned(),
+            part_started_at: None,
+            part_bytes: 0,
+            parts: Vec::new(),
+        });
+        if !self.open_part() {
+            self.session = None;
+            return;
+        }
+        // 数据行与事件都相对这一时刻计时，二者的 Timestamp 列可直接对齐
+        self.start_time = SystemTime::now();
+        self.device_clock = None;
+        self.rows_written = 0;
+        self.last_row_time = None;
+        self.pending_events.clear();
+        // 事件标记单独存放，SampleIndex 对应数据文件中的行号（从 0 开始，不含表头；分片时跨文件连续）
+        let events_name = dir.join(format!("events_{}.csv", id));
+        self.events_writer = File::create(events_name).ok().map(|f| {
+            let mut w = BufWriter::new(f);
+            writeln!(w, "SampleIndex,Timestamp,Label").ok();
+            w
+        });
+    }
+    /// Opens the next data file of the current session and writes its header.
+    fn open_part(&mut self) -> bool {
+        let header = self.header();
+        let Some(session) = self.session.as_mut() else {
+            return false;
+        };
+        let name = if self.rotation == Rotation::Off {
+            format!("training_data_{}.csv", session.id)
+        } else {
+            format!(
+                "training_data_{}_part{:03}.csv",
+                session.id,
+                session.parts.len()
             )
-            .ok();
-            self.writer = Some(w);
-            println!("💾 Recording started: {}", filename);
+        };
+        let filename = session.dir.join(&name);
+        let Ok(file) = File::create(&filename) else {
+            return false;
+        };
+        let mut w = BufWriter::new(file);
+        // 写入 CSV 表头: [SampleIndex,] Timestamp, Ch0 ... ChN
+        writeln!(w, "{}", header).ok();
+        if let Some(mut old) = self.writer.replace(w) {
+            old.flush().ok();
+        }
+        session.part_started_at = None;
+        session.part_bytes = header.len() as u64 + 1;
+        session.parts.push(PartInfo {
+            file: name,
+            first_row: self.rows_written,
+            rows: 0,
+        });
+        println!("💾 Recording started: {}", filename.display());
+        true
+    }
+    fn should_rotate(&self, t: f64) -> bool {
+        let Some(session) = self.session.as_ref() else {
+            return false;
+        };
+        let part_rows = session.parts.last().map(|p| p.rows).unwrap_or(0);
+        if part_rows == 0 {
+            return false;
+        }
+        match self.rotation {
+            Rotation::Off => false,
+            Rotation::EverySeconds(secs) => session
+                .part_started_at
+                .map(|start| t - start >= secs)
+                .unwrap_or(false),
+            Rotation::EveryBytes(bytes) => session.part_bytes >= bytes,
+        }
+    }
+    fn write_manifest(&self) {
+        let Some(session) = self.session.as_ref() else {
+            return;
+        };
+        if self.rotation == Rotation::Off {
+            return;
+        }
+        let manifest = SessionManifest {
+            session_id: &session.id,
+            label: &session.label,
+            rotation: format!("{:?}", self.rotation),
+            columns: self.header().split(',').map(str::to_owned).collect(),
+            events_file: format!("events_{}.csv", session.id),
+            parts: &session.parts,
+        };
+        if let Ok(json) = serde_json::to_string_pretty(&manifest) {
+            let path = session.dir.join(format!("session_{}.json", session.id));
+            std::fs::write(path, json).ok();
         }
     }
-    pub fn stop(&mut self) {
+    /// Flushes the session; an MNE export failure is returned once the CSVs are closed.
+    pub fn stop(&mut self) -> std::io::Result<()> {
+        // Events marked after the last sample belong to the last row.
+        let last_index = self.rows_written.saturating_sub(1);
+        for event in std::mem::take(&mut self.pending_events) {
+            self.write_event(last_index, &event);
+        }
+        if let Some(mut w) = self.events_writer.take() {
+            w.flush().ok();
+        }
+        if let Some(mut w) = self.labels_writer.take() {
+            w.flush().ok();
+        }
         if let Some(mut w) = self.writer.take() {
             w.flush().ok();
             println!("💾 Recording saved.");
         }
+        self.write_manifest();
+        let exported = if self.mne_export {
+            self.write_mne_export()
+        } else {
+            Ok(())
+        };
+        self.session = None;
+        exported
+    }
+    /// Re-reads every part of the finished session and writes it channels x samples
+    /// next to a metadata JSON, so MNE-Python can load it without reshaping.
+    fn write_mne_export(&self) -> std::io::Result<()> {
+        let Some(session) = self.session.as_ref() else {
+            return Ok(());
+        };
+        let header = self.header();
+        let skip = header
+            .split(',')
+            .take_while(|c| matches!(*c, "SampleIndex" | "Timestamp"))
+            .count();
+        let ch_names: Vec<String> = header.split(',').skip(skip).map(str::to_owned).collect();
+        let mut channels: Vec<Vec<&str>> = vec![Vec::new(); ch_names.len()];
+        let parts: Vec<String> = session
+            .parts
+            .iter()
+            .map(|part| std::fs::read_to_string(session.dir.join(&part.file)))
+            .collect::<std::io::Result<_>>()?;
+        for row in parts.iter().flat_map(|data| data.lines().skip(1)) {
+            for (ch, value) in channels.iter_mut().zip(row.split(',').skip(skip)) {
+                ch.push(value);
+            }
+        }
+        let data_file = format!("mne_{}.csv", session.id);
+        let mut w = BufWriter::new(File::create(session.dir.join(&data_file))?);
+        for ch in &channels {
+            writeln!(w, "{}", ch.join(","))?;
+        }
+        w.flush()?;
+        let info = MneInfo {
+            sfreq: self.sample_rate_hz,
+            ch_types: vec!["eeg"; ch_names.len()],
+            ch_names,
+            n_times: channels.first().map(Vec::len).unwrap_or(0),
+            data_file,
+            events_file: format!("events_{}.csv", session.id),
+            layout: "channels x samples",
+        };
+        let json = serde_json::to_string_pretty(&info).map_err(std::io::Error::other)?;
+        std::fs::write(session.dir.join(format!("mne_{}.json", session.id)), json)
+    }
+    fn header(&self) -> String {
+        let n = self.channel_count;
+        let names = |prefix: &'static str| (0..n).map(move |i| format!("{prefix}Ch{i}"));
+        let columns = std::iter::once("Timestamp".to_owned());
+        let columns = match self.record_mode {
+            RecordMode::RawOnly => columns.chain(names("")).collect::<Vec<_>>(),
+            RecordMode::Dual => columns
+                .chain(names("Raw"))
+                .chain(names("Clean"))
+                .collect::<Vec<_>>(),
+        }
+        .join(",");
+        if self.sample_index_column {
+            format!("SampleIndex,{}", columns)
+        } else {
+            columns
+        }
+    }
+    fn channel_columns(&self) -> usize {
+        match self.record_mode {
+            RecordMode::RawOnly => self.channel_count,
+            RecordMode::Dual => self.channel_count * 2,
+        }
+    }
+    pub fn record_mode(&self) -> RecordMode {
+        self.record_mode
+    }
+    /// Applies to the next recording, like the SampleIndex column.
+    pub fn set_record_mode(&mut self, mode: RecordMode) {
+        if self.session.is_none() {
+            self.record_mode = mode;
+        }
+    }
+    /// Prepends an integer `SampleIndex` column (0 at record start) to the next recording.
+    pub fn set_sample_index_column(&mut self, enabled: bool) {
+        if self.session.is_none() {
+            self.sample_index_column = enabled;
+        }
+    }
+    /// Also write an MNE-Python friendly copy (`mne_<id>.csv` + `.json`) when the recording stops.
+    pub fn set_mne_export(&mut self, enabled: bool) {
+        self.mne_export = enabled;
+    }
+    /// Sampling rate recorded in the MNE metadata as `sfreq`.
+    pub fn set_sample_rate(&mut self, sample_rate_hz: f64) {
+        self.sample_rate_hz = sample_rate_hz;
+    }
+    /// Applies to the next recording; a running one keeps its scheme.
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        if self.session.is_none() {
+            self.rotation = rotation;
+        }
+    }
+    pub fn set_event_alignment(&mut self, alignment: EventAlignment) {
+        self.event_alignment = alignment;
+    }
+    /// Current recording time: the device clock advanced by the wall time since its
+    /// latest timestamp, or seconds since the recording started without one.
+    fn now(&self) -> f64 {
+        match self.device_clock {
+            Some((device_time, seen_at)) => {
+                device_time + seen_at.elapsed().unwrap_or_default().as_secs_f64()
+            }
+            None => self.start_time.elapsed().unwrap_or_default().as_secs_f64(),
+        }
+    }
+    /// Timestamp of a row: the board's `device_time` when it reported one.
+    fn row_time(&mut self, device_time: Option<f64>) -> f64 {
+        match device_time {
+            Some(t) => {
+                self.device_clock = Some((t, SystemTime::now()));
+                t
+            }
+            None => self.now(),
+        }
+    }
+    /// One row of exactly the recording's channel count, padding short input with zeros.
+    pub fn write_record(&mut self, data: &[f64], device_time: Option<f64>) {
+        let t = self.row_time(device_time);
+        let row = self.fit(data);
+        self.write_record_at(t, &row);
+    }
+    /// One row of raw channels followed by the filtered ones; each side is padded
+    /// or cut to the channel count so rows always match the `Dual` header.
+    pub fn write_record_dual(&mut self, raw: &[f64], clean: &[f64], device_time: Option<f64>) {
+        let t = self.row_time(device_time);
+        let row = [self.fit(raw), self.fit(clean)].concat();
+        self.write_record_at(t, &row);
     }
-    pub fn write_record(&mut self, data: &[f64]) {
+    fn fit(&self, data: &[f64]) -> Vec<f64> {
+        (0..self.channel_count)
+            .map(|i| data.get(i).copied().unwrap_or(0.0))
+            .collect()
+    }
+    fn write_record_at(&mut self, t: f64, data: &[f64]) {
+        if self.writer.is_none() {
+            return;
+        }
+        self.resolve_pending_events(t);
+        if self.should_rotate(t) && self.open_part() {
+            self.write_manifest();
+        }
+        // 写入一行数据
+        let mut line = if self.sample_index_column {
+            format!("{},{:.4}", self.rows_written, t)
+        } else {
+            format!("{:.4}", t)
+        };
+        for val in data.iter().take(self.channel_columns()) {
+            line.push_str(&format!(",{:.2}", val));
+        }
         if let Some(w) = &mut self.writer {
-            // 写入一行数据
-            let t = self.start_time.elapsed().unwrap_or_default().as_secs_f64();
-            write!(w, "{:.4}", t).ok();
-            for val in data.iter().take(16) {
-                write!(w, ",{:.2}", val).ok();
+            writeln!(w, "{}", line).ok();
+        }
+        if let Some(session) = self.session.as_mut() {
+            session.part_started_at.get_or_insert(t);
+            session.part_bytes += line.len() as u64 + 1;
+            if let Some(part) = session.parts.last_mut() {
+                part.rows += 1;
             }
-            writeln!(w).ok();
+        }
+        self.rows_written += 1;
+        self.last_row_time = Some(t);
+    }
+    /// Appends `gp` to the labels file against the data row just written, with that
+    /// row's index and timestamp; does nothing before the first row or without labels.
+    pub fn write_label(&mut self, gp: &GamepadState) {
+        let (Some(w), Some(t)) = (self.labels_writer.as_mut(), self.last_row_time) else {
+            return;
+        };
+        let mut line = format!(
+            "{},{:.4},{:.3},{:.3},{:.3},{:.3}",
+            self.rows_written - 1,
+            t,
+            gp.lx,
+            gp.ly,
+            gp.rx,
+            gp.ry
+        );
+        for pressed in [
+            gp.a,
+            gp.b,
+            gp.x,
+            gp.y,
+            gp.lb,
+            gp.rb,
+            gp.lt_pressed(),
+            gp.rt_pressed(),
+            gp.dpad_up,
+            gp.dpad_down,
+            gp.dpad_left,
+            gp.dpad_right,
+        ] {
+            line.push_str(if pressed { ",1" } else { ",0" });
+        }
+        writeln!(w, "{}", line).ok();
+    }
+    /// Marks an event at the current recording time.
+    pub fn mark_event(&mut self, label: &str) {
+        let t = self.now();
+        self.mark_event_at(t, label);
+    }
+    fn mark_event_at(&mut self, t: f64, label: &str) {
+        if self.events_writer.is_none() {
+            return;
+        }
+        // Keep the events CSV single-column-safe.
+        let label: String = label
+            .chars()
+            .map(|c| {
+                if c == ',' || c == '\n' || c == '\r' {
+                    ' '
+                } else {
+                    c
+                }
+            })
+            .collect();
+        let event = PendingEvent { time: t, label };
+        match self.event_alignment {
+            // Wait for the next row so we know both neighbouring sample times.
+            EventAlignment::NearestSample => self.pending_events.push(event),
+            EventAlignment::WallClock => {
+                let index = self.rows_written;
+                self.write_event(index, &event);
+            }
+        }
+    }
+    /// Writes every pending event that happened before a row at `next_row_time`,
+    /// choosing whichever of the previous row or the upcoming row is closer.
+    fn resolve_pending_events(&mut self, next_row_time: f64) {
+        if self.pending_events.is_empty() {
+            return;
+        }
+        let next_index = self.rows_written;
+        let (ready, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending_events)
+            .into_iter()
+            .partition(|e| e.time <= next_row_time);
+        self.pending_events = waiting;
+        for event in ready {
+            let index = match self.last_row_time {
+                Some(prev) if (event.time - prev) < (next_row_time - event.time) => next_index - 1,
+                _ => next_index,
+            };
+            self.write_event(index, &event);
         }
     }
+    fn write_event(&mut self, index: u64, event: &PendingEvent) {
+        if let Some(w) = &mut self.events_writer {
+            writeln!(w, "{},{:.4},{}", index, event.time, event.label).ok();
+        }
+    }
+    /// Records a reconnect: writes a `gap_missing_samples=N` event at the next row
+    /// so the dropped span is visible instead of silently concatenated.
+    /// Returns the estimate (samples between the last row and now, at `sample_rate_hz`).
+    pub fn mark_gap(&mut self, sample_rate_hz: f64) -> u64 {
+        let t = self.now();
+        self.mark_gap_at(t, sample_rate_hz)
+    }
+    fn mark_gap_at(&mut self, t: f64, sample_rate_hz: f64) -> u64 {
+        if self.events_writer.is_none() || sample_rate_hz <= 0.0 {
+            return 0;
+        }
+        let missing = match self.last_row_time {
+            Some(prev) => ((t - prev) * sample_rate_hz).round().max(1.0) as u64 - 1,
+            None => 0,
+        };
+        let event = PendingEvent {
+            time: t,
+            label: format!("gap_missing_samples={}", missing),
+        };
+        let index = self.rows_written;
+        self.write_event(index, &event);
+        missing
+    }
     pub fn is_recording(&self) -> bool {
         self.writer.is_some()
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::Duration;
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "neurostick_recorder_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+    fn read_prefixed(dir: &Path, prefix: &str) -> String {
+        let entry = std::fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .find(|e| e.file_name().to_string_lossy().starts_with(prefix))
+            .expect("file with prefix");
+        std::fs::read_to_string(entry.path()).unwrap()
+    }
+    #[test]
+    fn events_snap_to_nearest_sample_row() {
+        let dir = temp_dir("events_snap");
+        let mut rec = DataRecorder::new();
+        rec.start_in(&dir, "snap", 16);
+        rec.write_record_at(0.000, &[0.0; 16]);
+        rec.write_record_at(0.004, &[0.0; 16]);
+        // Closer to row 1 (t=0.004) than to row 2 (t=0.008).
+        rec.mark_event_at(0.0049, "blink");
+        // Closer to row 3 (t=0.012) than to row 2.
+        rec.mark_event_at(0.0111, "jaw");
+        rec.write_record_at(0.008, &[0.0; 16]);
+        rec.write_record_at(0.012, &[0.0; 16]);
+        rec.stop().unwrap();
+        let events = read_prefixed(&dir, "events_");
+        let rows: Vec<&str> = events.lines().skip(1).collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].starts_with("1,") && rows[0].ends_with(",blink"));
+        assert!(rows[1].starts_with("3,") && rows[1].ends_with(",jaw"));
+    }
+    #[test]
+    fn marker_timestamp_is_elapsed_time_since_the_recording_started() {
+        let dir = temp_dir("events_elapsed");
+        let mut rec = DataRecorder::new();
+        rec.set_event_alignment(EventAlignment::WallClock);
+        // The recorder was created long before this recording.
+        rec.start_time -= Duration::from_secs(100);
+        rec.start_in(&dir, "elapsed", 16);
+        // Two seconds into the recording.
+        rec.start_time -= Duration::from_secs(2);
+        rec.write_record(&[0.0; 16], None);
+        rec.mark_event("eyes closed");
+        rec.stop().unwrap();
+        let column = |text: &str, col: usize| -> f64 {
+            let row = text.lines().nth(1).expect("one row");
+            row.split(',').nth(col).unwrap().parse().unwrap()
+        };
+        let events = read_prefixed(&dir, "events_");
+        assert!(events.lines().nth(1).unwrap().ends_with(",eyes closed"));
+        let marked = column(&events, 1);
+        let row_time = column(&read_prefixed(&dir, "training_data_"), 0);
+        assert!((2.0..2.5).contains(&marked), "marker at {marked} s");
+        assert!(row_time <= marked && marked - row_time < 0.5);
+    }
+    #[test]
+    fn rows_and_markers_carry_the_device_timestamp() {
+        let dir = temp_dir("device_time");
+        let mut rec = DataRecorder::new();
+        rec.set_event_alignment(EventAlignment::WallClock);
+        rec.start_in(&dir, "device", 16);
+        rec.write_record(&[0.0; 16], Some(1_700_000_000.25));
+        rec.write_record(&[0.0; 16], Some(1_700_000_000.254));
+        rec.mark_event("blink");
+        rec.stop().unwrap();
+        let data = read_prefixed(&dir, "training_data_");
+        let times: Vec<&str> = data
+            .lines()
+            .skip(1)
+            .map(|row| row.split(',').next().unwrap())
+            .collect();
+        assert_eq!(times, ["1700000000.2500", "1700000000.2540"]);
+        let events = read_prefixed(&dir, "events_");
+        let marked: f64 = events
+            .lines()
+            .nth(1)
+            .unwrap()
+            .split(',')
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(
+            (1_700_000_000.254..1_700_000_000.754).contains(&marked),
+            "{marked}"
+        );
+    }
+    #[test]
+    fn rotation_splits_into_parts_of_one_session() {
+        let dir = temp_dir("rotation");
+        let mut rec = DataRecorder::new();
+        rec.set_rotation(Rotation::EverySeconds(1.0));
+        rec.start_in(&dir, "rot", 16);
+        for i in 0..8 {
+            rec.write_record_at(i as f64 * 0.25, &[1.0; 16]);
+        }
+        rec.stop().unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_str(&read_prefixed(&dir, "session_")).unwrap();
+        let session_id = manifest["session_id"].as_str().unwrap();
+        let parts = manifest["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1]["first_row"], 4);
+        for part in parts {
+            let file = part["file"].as_str().unwrap();
+            assert!(file.contains(session_id), "{file} vs {session_id}");
+            let data = std::fs::read_to_string(dir.join(file)).unwrap();
+            assert_eq!(data.lines().count(), 5);
+        }
+    }
+    #[test]
+    fn reconnect_gap_is_annotated_with_missing_samples() {
+        let dir = temp_dir("gap");
+        let mut rec = DataRecorder::new();
+        rec.start_in(&dir, "gap", 16);
+        for i in 0..10 {
+            rec.write_record_at(i as f64 * 0.004, &[0.0; 16]);
+        }
+        // Board drops after t=0.036 and is back at t=1.040: ~1 s (250 samples) lost.
+        assert_eq!(rec.mark_gap_at(1.040, 250.0), 250);
+        rec.write_record_at(1.040, &[0.0; 16]);
+        rec.stop().unwrap();
+        let events = read_prefixed(&dir, "events_");
+        let rows: Vec<&str> = events.lines().skip(1).collect();
+        assert_eq!(rows, vec!["10,1.0400,gap_missing_samples=250"]);
+    }
+    #[test]
+    fn labels_file_has_one_row_per_data_row() {
+        let dir = temp_dir("labels");
+        let mut rec = DataRecorder::new();
+        rec.start_with_labels_in(&dir, "labels", 8);
+        // 还没有数据行时不写标签
+        rec.write_label(&GamepadState::default());
+        for i in 0..20 {
+            rec.write_record_at(i as f64 * 0.004, &[0.0; 8]);
+            let gp = GamepadState {
+                a: i % 2 == 0,
+                ly: 1.0,
+                ..GamepadState::default()
+            };
+            rec.write_label(&gp);
+        }
+        rec.stop().unwrap();
+        let data = read_prefixed(&dir, "training_data_");
+        let labels = read_prefixed(&dir, "labels_");
+        assert_eq!(data.lines().count(), 21);
+        assert_eq!(labels.lines().count(), data.lines().count());
+        // 每行标签与对应数据行同一时间戳
+        for (d, l) in data.lines().zip(labels.lines()).skip(1) {
+            assert_eq!(d.split(',').next(), l.split(',').nth(1));
+        }
+        assert!(labels
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("0,0.0000,0.000,1.000,"));
+    }
+    #[test]
+    fn recorded_buffer_replays_through_csv_source() {
+        use crate::drivers::{CsvFileSource, SignalSource};
+        let dir = temp_dir("replay");
+        let mut rec = DataRecorder::new();
+        rec.set_sample_index_column(true);
+        rec.start_in(&dir, "replay", 16);
+        let rows: Vec<Vec<f64>> = (0..100)
+            .map(|i| {
+                (0..16)
+                    .map(|ch| ch as f64 * 1.5 - i as f64 * 0.25)
+                    .collect()
+            })
+            .collect();
+        for (i, row) in rows.iter().enumerate() {
+            rec.write_record_at(i as f64 / 250.0, row);
+        }
+        rec.stop().unwrap();
+        let path = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .find(|p| {
+                p.file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .starts_with("training_data_")
+            })
+            .expect("data file");
+        let mut source = CsvFileSource::open(&path, 32).unwrap();
+        assert!((source.sample_rate_hz() - 250.0).abs() < 0.01);
+        let mut replayed = vec![Vec::new(); 16];
+        let mut batch_lens = Vec::new();
+        while let Some(batch) = source.next_batch().unwrap() {
+            batch.validate().unwrap();
+            assert_eq!(batch.channel_labels[0], "Ch0");
+            batch_lens.push(batch.samples_per_channel().unwrap());
+            for (ch, samples) in replayed.iter_mut().zip(batch.samples) {
+                ch.extend(samples);
+            }
+        }
+        assert_eq!(batch_lens, vec![32, 32, 32, 4]);
+        for (i, row) in rows.iter().enumerate() {
+            for (ch, &v) in row.iter().enumerate() {
+                assert!((replayed[ch][i] as f64 - v).abs() < 0.01);
+            }
+        }
+    }
+    #[test]
+    fn mne_export_metadata_matches_session() {
+        let dir = temp_dir("mne");
+        let mut rec = DataRecorder::new();
+        rec.set_sample_index_column(true);
+        rec.set_mne_export(true);
+        rec.set_sample_rate(125.0);
+        rec.start_in(&dir, "mne", 16);
+        for i in 0..5 {
+            let row: Vec<f64> = (0..16).map(|ch| (ch * 10 + i) as f64).collect();
+            rec.write_record_at(i as f64 / 125.0, &row);
+        }
+        rec.stop().unwrap();
+        let json = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .find(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .expect("mne metadata");
+        let info: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(json).unwrap()).unwrap();
+        assert_eq!(info["sfreq"], 125.0);
+        let names: Vec<&str> = info["ch_names"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_str().unwrap())
+            .collect();
+        assert_eq!(names.len(), 16);
+        assert_eq!(names[0], "Ch0");
+        assert_eq!(names[15], "Ch15");
+        assert_eq!(info["n_times"], 5);
+        let data = std::fs::read_to_string(dir.join(info["data_file"].as_str().unwrap())).unwrap();
+        let rows: Vec<&str> = data.lines().collect();
+        assert_eq!(rows.len(), 16);
+        assert_eq!(rows[1], "10.00,11.00,12.00,13.00,14.00");
+    }
+    #[test]
+    fn sample_index_column_counts_rows_from_zero() {
+        let dir = temp_dir("sample_index");
+        let mut rec = DataRecorder::new();
+        rec.set_sample_index_column(true);
+        rec.start_in(&dir, "idx", 16);
+        for i in 0..5 {
+            rec.write_record_at(i as f64 * 0.004, &[0.0; 16]);
+        }
+        rec.stop().unwrap();
+        let data = read_prefixed(&dir, "training_data_");
+        let mut lines = data.lines();
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with("SampleIndex,Timestamp,Ch0"));
+        let indices: Vec<u64> = lines
+            .map(|l| l.split(',').next().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(indices, vec![0, 1, 2, 3, 4]);
+    }
+    #[test]
+    fn dual_rows_carry_as_many_columns_as_the_header() {
+        let dir = temp_dir("dual");
+        let mut rec = DataRecorder::new();
+        rec.set_record_mode(RecordMode::Dual);
+        rec.start_in(&dir, "dual", 16);
+        let raw: Vec<f64> = (0..16).map(|ch| ch as f64).collect();
+        rec.write_record_dual(&raw, &[0.5; 16], None);
+        // 短的一侧补零，列数仍与表头一致
+        rec.write_record_dual(&raw[..8], &[0.5; 16], None);
+        rec.stop().unwrap();
+        let data = read_prefixed(&dir, "training_data_");
+        let mut lines = data.lines();
+        let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(header.len(), 33);
+        assert_eq!(header[1], "RawCh0");
+        assert_eq!(header[17], "CleanCh0");
+        for row in lines {
+            let cells: Vec<&str> = row.split(',').collect();
+            assert_eq!(cells.len(), header.len(), "{row}");
+            assert_eq!(cells[16], if cells[9] == "0.00" { "0.00" } else { "15.00" });
+            assert_eq!(cells[17], "0.50");
+        }
+    }
+    #[test]
+    fn header_and_rows_match_the_channel_count() {
+        for channels in [4, 8, 16] {
+            let dir = temp_dir(&format!("width_{channels}"));
+            let mut rec = DataRecorder::new();
+            rec.start_in(&dir, "width", channels);
+            // 输入比通道数多或少都按通道数写出
+            rec.write_record(&[1.0; 16], None);
+            rec.write_record(&[1.0; 2], None);
+            rec.stop().unwrap();
+            let data = read_prefixed(&dir, "training_data_");
+            let mut lines = data.lines();
+            let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+            assert_eq!(header.len(), channels + 1);
+            assert_eq!(header[channels], format!("Ch{}", channels - 1));
+            let rows: Vec<&str> = lines.collect();
+            assert_eq!(rows.len(), 2);
+            for row in rows {
+                assert_eq!(row.split(',').count(), header.len(), "{row}");
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+// src/recorder/edf.rs
+// EDF+ 导出：临床/科研工具 (EDFbrowser、MNE、EEGLAB) 普遍读 EDF+ 而不是我们的 CSV。
+// 每个数据块约 1 秒，另附 EDF+ 规定的 "EDF Annotations" 通道记录每块的起始时间。
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+const DIGITAL_MIN: i32 = -32768;
+const DIGITAL_MAX: i32 = 32767;
+/// 16-bit slots per record for the time-keeping annotation (60 bytes, plenty for "+onset").
+const ANNOTATION_SAMPLES: usize = 30;
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+struct Signal<'a> {
+    label: &'a str,
+    transducer: &'a str,
+    dimension: &'a str,
+    physical: (f64, f64),
+    samples_per_record: usize,
+}
+
+/// Writes `samples` (channels x samples, in µV) as a continuous EDF+ file.
+/// Shorter channels are padded with zeros up to the longest one, and the last
+/// data record is padded the same way.
+pub fn write_edf(
+    path: &Path,
+    sample_rate_hz: f32,
+    channel_labels: &[String],
+    samples: &[Vec<f32>],
+) -> io::Result<()> {
+    if !sample_rate_hz.is_finite() || sample_rate_hz <= 0.0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "sample rate must be positive",
+        ));
+    }
+    if channel_labels.len() != samples.len() || samples.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} labels for {} channels",
+                channel_labels.len(),
+                samples.len()
+            ),
+        ));
+    }
+    // 非整数采样率时按四舍五入的每块采样数折算块时长
+    let per_record = (sample_rate_hz.round() as usize).max(1);
+    let duration = per_record as f64 / sample_rate_hz as f64;
+    let len = samples.iter().map(Vec::len).max().unwrap_or(0);
+    let records = len.div_ceil(per_record).max(1);
+
+    let mut signals: Vec<Signal> = channel_labels
+        .iter()
+        .zip(samples)
+        .map(|(label, channel)| Signal {
+            label,
+            transducer: "AgAgCl electrode",
+            dimension: "uV",
+            physical: physical_range(channel),
+            samples_per_record: per_record,
+        })
+        .collect();
+    signals.push(Signal {
+        label: "EDF Annotations",
+        transducer: "",
+        dimension: "",
+        physical: (-1.0, 1.0),
+        samples_per_record: ANNOTATION_SAMPLES,
+    });
+
+    let mut w = BufWriter::new(File::create(path)?);
+    w.write_all(&header(&signals, records, duration, SystemTime::now()))?;
+    for record in 0..records {
+        for (channel, signal) in samples.iter().zip(&signals) {
+            for i in record * per_record..(record + 1) * per_record {
+                let v = channel.get(i).copied().unwrap_or(0.0);
+                w.write_all(&to_digital(v, signal.physical).to_le_bytes())?;
+            }
+        }
+        let mut tal = format!("+{}\x14\x14\0", number(record as f64 * duration)).into_bytes();
+        tal.resize(ANNOTATION_SAMPLES * 2, 0);
+        w.write_all(&tal)?;
+    }
+    w.flush()
+}
+
+fn header(signals: &[Signal], records: usize, duration: f64, start: SystemTime) -> Vec<u8> {
+    let secs = start
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    let mut h = Vec::with_capacity(256 * (signals.len() + 1));
+    push(&mut h, "0", 8);
+    push(&mut h, "X X X X", 80);
+    let startdate = format!("{day:02}-{}-{year}", MONTHS[month as usize - 1]);
+    push(&mut h, &format!("Startdate {startdate} X X X"), 80);
+    push(&mut h, &format!("{day:02}.{month:02}.{:02}", year % 100), 8);
+    let (hh, mm, ss) = (time / 3600, time / 60 % 60, time % 60);
+    push(&mut h, &format!("{hh:02}.{mm:02}.{ss:02}"), 8);
+    push(&mut h, &(256 * (signals.len() + 1)).to_string(), 8);
+    push(&mut h, "EDF+C", 44);
+    push(&mut h, &records.to_string(), 8);
+    push(&mut h, &number(duration), 8);
+    push(&mut h, &signals.len().to_string(), 4);
+    // 各字段按信号依次排列，一个字段写完全部信号再写下一个字段
+    for s in signals {
+        push(&mut h, s.label, 16);
+    }
+    for s in signals {
+        push(&mut h, s.transducer, 80);
+    }
+    for s in signals {
+        push(&mut h, s.dimension, 8);
+    }
+    for s in signals {
+        push(&mut h, &number(s.physical.0), 8);
+    }
+    for s in signals {
+        push(&mut h, &number(s.physical.1), 8);
+    }
+    for _ in signals {
+        push(&mut h, &DIGITAL_MIN.to_string(), 8);
+    }
+    for _ in signals {
+        push(&mut h, &DIGITAL_MAX.to_string(), 8);
+    }
+    for _ in signals {
+        push(&mut h, "", 80);
+    }
+    for s in signals {
+        push(&mut h, &s.samples_per_record.to_string(), 8);
+    }
+    for _ in signals {
+        push(&mut h, "", 32);
+    }
+    h
+}
+
+/// Appends `text` as a space-padded printable-ASCII field of exactly `width` bytes.
+fn push(h: &mut Vec<u8>, text: &str, width: usize) {
+    let mut field: Vec<u8> = text
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() || c == ' ' {
+                c as u8
+            } else {
+                b'_'
+            }
+        })
+        .take(width)
+        .collect();
+    field.resize(width, b' ');
+    h.extend(field);
+}
+
+/// Whole µV bounds around the finite samples, always at least 1 µV wide.
+fn physical_range(channel: &[f32]) -> (f64, f64) {
+    let (min, max) = channel
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((0.0f64, 0.0f64), |(lo, hi), &v| {
+            (lo.min(v as f64), hi.max(v as f64))
+        });
+    let min = min.floor().max(-9_999_999.0);
+    let max = max.ceil().min(99_999_999.0);
+    (min, if max > min { max } else { min + 1.0 })
+}
+
+fn to_digital(v: f32, (pmin, pmax): (f64, f64)) -> i16 {
+    let scale = (DIGITAL_MAX - DIGITAL_MIN) as f64 / (pmax - pmin);
+    ((v as f64 - pmin) * scale + DIGITAL_MIN as f64)
+        .round()
+        .clamp(DIGITAL_MIN as f64, DIGITAL_MAX as f64) as i16
+}
+
+/// Shortest decimal form that fits an 8-byte header field.
+fn number(v: f64) -> String {
+    let s = format!("{v:.6}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    s.chars().take(8).collect()
+}
+
+/// Days since 1970-01-01 to (year, month, day), proleptic Gregorian.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_fields_sit_at_their_edf_byte_offsets() {
+        let dir = std::env::temp_dir().join(format!("neurostick_edf_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("export.edf");
+        let labels = vec!["Fp1".to_owned(), "Fp2".to_owned()];
+        // 300 个采样 @ 250 Hz -> 2 个数据块，第二块补零
+        let samples = vec![
+            (0..300).map(|i| (i % 50) as f32 - 25.0).collect::<Vec<_>>(),
+            vec![10.0; 300],
+        ];
+        write_edf(&path, 250.0, &labels, &samples).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let field = |start: usize, len: usize| {
+            String::from_utf8(bytes[start..start + len].to_vec())
+                .unwrap()
+                .trim_end()
+                .to_owned()
+        };
+        let ns = 3;
+        assert_eq!(&bytes[0..8], b"0       ");
+        assert_eq!(field(8, 80), "X X X X");
+        assert!(field(88, 80).starts_with("Startdate "));
+        assert_eq!(&bytes[170..171], b".");
+        assert_eq!(&bytes[178..179], b".");
+        assert_eq!(field(184, 8), (256 * (ns + 1)).to_string());
+        assert_eq!(field(192, 44), "EDF+C");
+        assert_eq!(field(236, 8), "2");
+        assert_eq!(field(244, 8), "1");
+        assert_eq!(field(252, 4), ns.to_string());
+        assert_eq!(field(256, 16), "Fp1");
+        assert_eq!(field(256 + 2 * 16, 16), "EDF Annotations");
+        let dims = 256 + ns * (16 + 80);
+        assert_eq!(field(dims, 8), "uV");
+        assert_eq!(field(dims + ns * 8, 8), "-25");
+        assert_eq!(field(dims + ns * 16, 8), "24");
+        assert_eq!(field(dims + ns * 24, 8), "-32768");
+        let per_record = 256 + ns * (16 + 80 + 8 * 5 + 80);
+        assert_eq!(field(per_record, 8), "250");
+        assert_eq!(field(per_record + 2 * 8, 8), ANNOTATION_SAMPLES.to_string());
+        let header_len = 256 * (ns + 1);
+        assert_eq!(
+            bytes.len(),
+            header_len + 2 * (250 + 250 + ANNOTATION_SAMPLES) * 2
+        );
+        // 第一个采样 -25 µV 即物理最小值，对应数字最小值
+        assert_eq!(
+            i16::from_le_bytes([bytes[header_len], bytes[header_len + 1]]),
+            i16::MIN
+        );
+        let tal = header_len + (250 + 250) * 2;
+        assert_eq!(&bytes[tal..tal + 5], b"+0\x14\x14\0");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
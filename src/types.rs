@@ -1,4 +1,13 @@
+use crate::calibration::GuidedCapture;
 use crate::drivers::{FrequencySpectrum, TimeSeriesFrame};
+use crate::engine::{FeatureKind, GamepadAction};
+use crate::montage::Montage;
+use crate::openbci::BoardKind;
+use crate::recorder::{EventAlignment, RecordMode, Rotation};
+use crate::waveform::FilterKind;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
 // src/types.rs
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum ConnectionMode {
@@ -7,21 +16,100 @@ pub enum ConnectionMode {
 }
 #[derive(Clone, Debug)]
 pub enum GuiCommand {
-    // === 修改：Connect 现在接收 (模式, 端口名) ===
-    Connect(ConnectionMode, String),
+    // === 修改：Connect 现在接收 (模式, 端口名, 板卡) ===
+    Connect(ConnectionMode, String, BoardKind),
     Disconnect,
     StartStream,
     StopStream,
+    /// Same decode threshold (µV) on every channel.
     SetThreshold(f64),
+    /// Decode threshold of one channel, e.g. from its calibrated activation peak.
+    SetChannelThreshold(usize, f64),
     StartCalibration(bool),
+    /// Capture rest, then action, in one go; progress arrives as `CalibrationProgress`.
+    StartGuidedCalibration,
     UpdateSimInput(SimInputIntent),
     StartRecording(String),
     StopRecording,
+    /// Drops a labelled marker into the active recording's events file.
+    MarkEvent(String),
+    SetEventAlignment(EventAlignment),
+    /// Split the next recording into parts by duration or size.
+    SetRecordingRotation(Rotation),
+    /// Prepend an integer SampleIndex column to the next recording.
+    SetRecordSampleIndex(bool),
+    /// On stop, also export the recording channels x samples with an MNE metadata JSON.
+    SetRecordMneExport(bool),
+    /// Also write the decoded gamepad state per row to `labels_<id>.csv`.
+    SetRecordLabels(bool),
+    /// Record only raw channels, or raw and filtered side by side, in the next recording.
+    SetRecordMode(RecordMode),
+    /// Also notch 100/150 Hz (below Nyquist) in the decode filter, not just 50 Hz.
+    SetNotchHarmonics(bool),
+    /// Mains frequency the decode notch (and its harmonics) targets: 50 or 60 Hz.
+    SetMainsFrequency(f64),
+    /// Sample rate the simulator emulates, e.g. 125/250/500 Hz; values ≤ 0 are ignored.
+    SetSimSampleRate(f32),
+    /// Drops buffered samples, filter state and stats without disconnecting.
+    ClearHistory,
+    /// Re-read the mapping table (default `data/mapping.toml`) and swap it in live.
+    /// An invalid file leaves the active table untouched.
+    LoadMapping(Option<PathBuf>),
+    /// Load a logistic-regression classifier (default `data/model.json`) over per-channel
+    /// band powers; its scores arrive as `ModelPrediction`.
+    LoadClassifier(Option<PathBuf>),
+    /// Let the top-scoring class (score ≥ 0.5) press its mapped gamepad action.
+    SetClassifierDrive(bool),
+    /// Reference/bias channels to exclude from decode.
+    SetMontage(Montage),
+    /// Replace raw values beyond ±this many µV (or non-finite) with the channel's last good value.
+    SetSampleClamp(f64),
+    /// Reconnect the board when a hardware stream stalls; recordings get a gap marker.
+    SetAutoReconnect(bool),
+    /// Have the engine stream Hann-windowed spectra (`None` turns it off).
+    SetLiveSpectrum(Option<LiveSpectrumConfig>),
+    /// Write decoded states to vJoy at most this often (`None` = every decode).
+    SetVJoyRate(Option<f32>),
+    /// Push clean samples to a Lab Streaming Layer outlet while streaming.
+    EnableLsl(bool),
+    /// Send gamepad states and live band powers as OSC to this UDP address.
+    EnableOsc(SocketAddr),
+    DisableOsc,
+    /// Serve WebSocket clients on this address and push clean data to them as JSON.
+    EnableWebSocket(SocketAddr),
+    DisableWebSocket,
+    /// Per-axis stick sensitivity applied to decoded states before vJoy scaling.
+    SetAxisGains(AxisGains),
+    /// Send `DecodeConfidence` alongside each gamepad update.
+    SetDecodeConfidence(bool),
+    /// Feature computed over the last `usize` samples per channel before thresholding.
+    SetDecodeFeature(FeatureKind, usize),
+    /// Run decode input through the display filter chain of each channel, so the
+    /// decoder sees what the waveform shows (`None` unlinks).
+    SetDecodeFilters(Option<Vec<Vec<FilterKind>>>),
+    SetTriggerDebounce(TriggerDebounce),
+    SetStickResponse(StickResponse),
     InjectArtifact,
     /// Helper to generate vJoy input for Steam mapping without keyboard focus.
     SetMappingHelper(MappingHelperCommand),
 }
 
+/// Step of a guided calibration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CalibrationPhase {
+    /// Relaxed baseline window.
+    Rest,
+    /// Window with the action held.
+    Action,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LiveSpectrumConfig {
+    pub fft_size: usize,
+    /// Spectra emitted per second; consecutive windows overlap when this exceeds rate/fft_size.
+    pub rate_hz: f32,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MappingHelperCommand {
     Off,
@@ -43,13 +131,37 @@ pub enum MappingHelperCommand {
 pub enum BciMessage {
     Log(String),
     Status(bool),
-    VJoyStatus(bool),
+    /// Virtual gamepad backend in use ("vJoy", ViGEm), `None` when neither is available.
+    VJoyStatus(Option<&'static str>),
     DataFrame(TimeSeriesFrame),
     Spectrum(FrequencySpectrum),
     GamepadUpdate(GamepadState),
     RecordingStatus(bool),
-    CalibrationResult((), f64),
+    /// Per-channel peak |µV| and the overall peak seen during a calibration window.
+    CalibrationResult(Vec<f64>, f64),
+    /// Guided calibration phase now capturing and how far through it, 0..=1.
+    CalibrationProgress(CalibrationPhase, f32),
+    /// Both windows of a guided calibration, sent once the action window is full.
+    GuidedCalibrationResult(GuidedCapture),
     ModelPrediction(Vec<f32>),
+    /// Measured vs nominal sample rate since the stream started, about once a second.
+    StreamRate(StreamRate),
+    /// Auto-reconnect attempt and the attempt cap while the board is being re-opened;
+    /// `None` once the board is open again or reconnecting was given up.
+    Reconnecting(Option<(u32, u32)>),
+    /// Fired actions with how far they cleared their threshold, 0..=1.
+    DecodeConfidence(Vec<(GamepadAction, f32)>),
+    /// Class names of a newly loaded classifier, in `ModelPrediction` order.
+    ClassifierClasses(Vec<String>),
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StreamRate {
+    /// Samples received since START STREAM.
+    pub samples: u64,
+    /// Of those, samples that made it into the display buffer.
+    pub buffered: u64,
+    pub nominal_hz: f32,
+    pub effective_hz: f32,
 }
 #[derive(Clone, Copy, Debug, Default)]
 pub struct GamepadState {
@@ -63,13 +175,112 @@ pub struct GamepadState {
     pub y: bool,
     pub lb: bool,
     pub rb: bool,
-    pub lt: bool,
-    pub rt: bool,
+    /// Trigger pull, 0 (released) ..= 1 (fully pressed).
+    pub lt: f32,
+    pub rt: f32,
     pub dpad_up: bool,
     pub dpad_down: bool,
     pub dpad_left: bool,
     pub dpad_right: bool,
 }
+impl GamepadState {
+    /// Triggers as buttons, for outputs that only know pressed / released.
+    pub fn lt_pressed(&self) -> bool {
+        self.lt > 0.0
+    }
+    pub fn rt_pressed(&self) -> bool {
+        self.rt > 0.0
+    }
+}
+/// Output gain per stick axis; the scaled deflection is clamped back to -1..=1.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisGains {
+    pub lx_gain: f32,
+    pub ly_gain: f32,
+    pub rx_gain: f32,
+    pub ry_gain: f32,
+}
+impl Default for AxisGains {
+    fn default() -> Self {
+        Self {
+            lx_gain: 1.0,
+            ly_gain: 1.0,
+            rx_gain: 1.0,
+            ry_gain: 1.0,
+        }
+    }
+}
+impl AxisGains {
+    pub fn apply(&self, gp: &mut GamepadState) {
+        gp.lx = (gp.lx * self.lx_gain).clamp(-1.0, 1.0);
+        gp.ly = (gp.ly * self.ly_gain).clamp(-1.0, 1.0);
+        gp.rx = (gp.rx * self.rx_gain).clamp(-1.0, 1.0);
+        gp.ry = (gp.ry * self.ry_gain).clamp(-1.0, 1.0);
+    }
+}
+/// How stick deflection maps to output once it leaves the dead zone.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    Quadratic,
+    Cubic,
+}
+impl ResponseCurve {
+    pub const ALL: [ResponseCurve; 3] = [
+        ResponseCurve::Linear,
+        ResponseCurve::Quadratic,
+        ResponseCurve::Cubic,
+    ];
+    pub fn label(self) -> &'static str {
+        match self {
+            ResponseCurve::Linear => "Linear",
+            ResponseCurve::Quadratic => "Quadratic",
+            ResponseCurve::Cubic => "Cubic",
+        }
+    }
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            ResponseCurve::Linear => t,
+            ResponseCurve::Quadratic => t * t,
+            ResponseCurve::Cubic => t * t * t,
+        }
+    }
+}
+/// Radial dead zone and response curve per stick, applied before vJoy scaling.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StickResponse {
+    /// Deflection radius (0..1) treated as centred.
+    pub dead_zone: f32,
+    pub curve: ResponseCurve,
+}
+impl StickResponse {
+    /// Zeroes deflections inside the dead zone and rescales the rest so output
+    /// still starts at 0 and reaches 1 at full deflection.
+    pub fn shape(&self, x: f32, y: f32) -> (f32, f32) {
+        let m = x.hypot(y);
+        let dead_zone = self.dead_zone.clamp(0.0, 0.99);
+        if m <= dead_zone || m == 0.0 {
+            return (0.0, 0.0);
+        }
+        let within = m.min(1.0);
+        let t = ((within - dead_zone) / (1.0 - dead_zone)).clamp(0.0, 1.0);
+        let scale = self.curve.apply(t) / within;
+        (x * scale, y * scale)
+    }
+}
+/// Button release hysteresis: a held action lets go only after its channels stay
+/// below `(1 - hysteresis) * threshold` for `hold_ms`. Both 0 disables debouncing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TriggerDebounce {
+    /// Release threshold below the trigger threshold, as a fraction of it (0..1).
+    pub hysteresis: f64,
+    pub hold_ms: f64,
+}
 #[derive(Default, Clone, Copy, Debug)]
 pub struct SimInputIntent {
     pub w: bool,
@@ -95,3 +306,111 @@ pub struct SimInputIntent {
     pub arrow_left: bool,
     pub arrow_right: bool,
 }
+impl SimInputIntent {
+    pub fn set(&mut self, key: SimKey, down: bool) {
+        *match key {
+            SimKey::W => &mut self.w,
+            SimKey::A => &mut self.a,
+            SimKey::S => &mut self.s,
+            SimKey::D => &mut self.d,
+            SimKey::Up => &mut self.up,
+            SimKey::Down => &mut self.down,
+            SimKey::Left => &mut self.left,
+            SimKey::Right => &mut self.right,
+            SimKey::Space => &mut self.space,
+            SimKey::KeyZ => &mut self.key_z,
+            SimKey::KeyX => &mut self.key_x,
+            SimKey::KeyC => &mut self.key_c,
+            SimKey::Key1 => &mut self.key_1,
+            SimKey::Key2 => &mut self.key_2,
+            SimKey::Q => &mut self.q,
+            SimKey::E => &mut self.e,
+            SimKey::U => &mut self.u,
+            SimKey::O => &mut self.o,
+            SimKey::ArrowUp => &mut self.arrow_up,
+            SimKey::ArrowDown => &mut self.arrow_down,
+            SimKey::ArrowLeft => &mut self.arrow_left,
+            SimKey::ArrowRight => &mut self.arrow_right,
+        } = down;
+    }
+}
+/// One `SimInputIntent` field, as a slot the simulation key bindings map a key to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimKey {
+    W,
+    A,
+    S,
+    D,
+    Up,
+    Down,
+    Left,
+    Right,
+    Space,
+    KeyZ,
+    KeyX,
+    KeyC,
+    Key1,
+    Key2,
+    Q,
+    E,
+    U,
+    O,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+}
+impl SimKey {
+    pub const ALL: [SimKey; 22] = [
+        SimKey::W,
+        SimKey::A,
+        SimKey::S,
+        SimKey::D,
+        SimKey::Up,
+        SimKey::Down,
+        SimKey::Left,
+        SimKey::Right,
+        SimKey::Space,
+        SimKey::KeyZ,
+        SimKey::KeyX,
+        SimKey::KeyC,
+        SimKey::Key1,
+        SimKey::Key2,
+        SimKey::Q,
+        SimKey::E,
+        SimKey::U,
+        SimKey::O,
+        SimKey::ArrowUp,
+        SimKey::ArrowDown,
+        SimKey::ArrowLeft,
+        SimKey::ArrowRight,
+    ];
+    /// `egui::Key::name` of the key bound in the default layout.
+    pub fn default_key(self) -> &'static str {
+        match self {
+            SimKey::W => "W",
+            SimKey::A => "A",
+            SimKey::S => "S",
+            SimKey::D => "D",
+            SimKey::Up => "I",
+            SimKey::Down => "K",
+            SimKey::Left => "J",
+            SimKey::Right => "L",
+            SimKey::Space => "Space",
+            SimKey::KeyZ => "Z",
+            SimKey::KeyX => "X",
+            SimKey::KeyC => "C",
+            SimKey::Key1 => "1",
+            SimKey::Key2 => "2",
+            SimKey::Q => "Q",
+            SimKey::E => "E",
+            SimKey::U => "U",
+            SimKey::O => "O",
+            SimKey::ArrowUp => "Up",
+            SimKey::ArrowDown => "Down",
+            SimKey::ArrowLeft => "Left",
+            SimKey::ArrowRight => "Right",
+        }
+    }
+}
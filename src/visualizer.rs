@@ -1,8 +1,158 @@
 // src/visualizer.rs
+use crate::engine::GamepadAction;
 use crate::types::GamepadState;
 use eframe::egui;
 use egui::{Color32, Pos2, Rect, Rounding, Shape, Stroke, Vec2};
-pub fn draw_xbox_controller(ui: &mut egui::Ui, gamepad: &GamepadState) {
+use serde::{Deserialize, Serialize};
+/// Brightness of an active control: full without confidence data, otherwise
+/// 35-100% by the strongest confidence among `actions`.
+fn confidence_level(confidence: Option<&[(GamepadAction, f32)]>, actions: &[GamepadAction]) -> f32 {
+    let Some(confidence) = confidence else {
+        return 1.0;
+    };
+    let best = confidence
+        .iter()
+        .filter(|(a, _)| actions.contains(a))
+        .map(|&(_, c)| c)
+        .fold(0.0f32, f32::max);
+    0.35 + 0.65 * best
+}
+fn shaded(color: Color32, level: f32) -> Color32 {
+    let k = level.clamp(0.0, 1.0);
+    Color32::from_rgb(
+        (color.r() as f32 * k) as u8,
+        (color.g() as f32 * k) as u8,
+        (color.b() as f32 * k) as u8,
+    )
+}
+/// Part of a trigger's rect lit for `pull` (0..=1), rising from its bottom edge.
+fn trigger_fill(rect: Rect, pull: f32) -> Rect {
+    let height = rect.height() * pull.clamp(0.0, 1.0);
+    Rect::from_min_max(Pos2::new(rect.min.x, rect.max.y - height), rect.max)
+}
+/// Stick deflection from centre, 0..=1.
+fn stick_magnitude(x: f32, y: f32) -> f32 {
+    x.hypot(y).min(1.0)
+}
+/// Pad drawn by the visualizer; the decoded state is the same for both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControllerStyle {
+    #[default]
+    Xbox,
+    DualShock,
+}
+impl ControllerStyle {
+    pub const ALL: [ControllerStyle; 2] = [ControllerStyle::Xbox, ControllerStyle::DualShock];
+    pub fn label(self) -> &'static str {
+        match self {
+            ControllerStyle::Xbox => "Xbox",
+            ControllerStyle::DualShock => "DualShock",
+        }
+    }
+}
+/// Symbol printed on a face button.
+#[derive(Clone, Copy)]
+enum FaceGlyph {
+    Letter(&'static str),
+    Triangle,
+    Circle,
+    Cross,
+    Square,
+}
+/// Where one pad style puts its controls (offsets from the face view centre) and how it labels them.
+struct PadLayout {
+    triggers: [&'static str; 2],
+    bumpers: [&'static str; 2],
+    body: [Vec2; 6],
+    left_stick: Vec2,
+    right_stick: Vec2,
+    dpad: Vec2,
+    face: Vec2,
+    /// South, east, west, north: the buttons `GamepadState` calls A, B, X and Y.
+    face_buttons: [(FaceGlyph, Color32); 4],
+}
+const XBOX_LAYOUT: PadLayout = PadLayout {
+    triggers: ["LT", "RT"],
+    bumpers: ["LB", "RB"],
+    body: [
+        Vec2::new(-70.0, -40.0),
+        Vec2::new(70.0, -40.0),
+        Vec2::new(110.0, 20.0),
+        Vec2::new(70.0, 60.0),
+        Vec2::new(-70.0, 60.0),
+        Vec2::new(-110.0, 20.0),
+    ],
+    left_stick: Vec2::new(-65.0, -10.0),
+    right_stick: Vec2::new(35.0, 30.0),
+    dpad: Vec2::new(-35.0, 30.0),
+    face: Vec2::new(65.0, -30.0),
+    face_buttons: [
+        (FaceGlyph::Letter("A"), Color32::GREEN),
+        (FaceGlyph::Letter("B"), Color32::RED),
+        (FaceGlyph::Letter("X"), Color32::BLUE),
+        (FaceGlyph::Letter("Y"), Color32::YELLOW),
+    ],
+};
+// 手柄两翼更长，两个摇杆并排在下方，十字键在左上
+const DUALSHOCK_LAYOUT: PadLayout = PadLayout {
+    triggers: ["L2", "R2"],
+    bumpers: ["L1", "R1"],
+    body: [
+        Vec2::new(-80.0, -45.0),
+        Vec2::new(80.0, -45.0),
+        Vec2::new(120.0, 45.0),
+        Vec2::new(95.0, 65.0),
+        Vec2::new(-95.0, 65.0),
+        Vec2::new(-120.0, 45.0),
+    ],
+    left_stick: Vec2::new(-38.0, 25.0),
+    right_stick: Vec2::new(38.0, 25.0),
+    dpad: Vec2::new(-75.0, -15.0),
+    face: Vec2::new(75.0, -15.0),
+    face_buttons: [
+        (FaceGlyph::Cross, Color32::from_rgb(124, 178, 232)),
+        (FaceGlyph::Circle, Color32::from_rgb(255, 102, 102)),
+        (FaceGlyph::Square, Color32::from_rgb(255, 105, 248)),
+        (FaceGlyph::Triangle, Color32::from_rgb(64, 226, 160)),
+    ],
+};
+/// Draws the pad in `style`; see `draw_xbox_controller` for `confidence`.
+pub fn draw_controller(
+    ui: &mut egui::Ui,
+    style: ControllerStyle,
+    gamepad: &GamepadState,
+    confidence: Option<&[(GamepadAction, f32)]>,
+) {
+    match style {
+        ControllerStyle::Xbox => draw_xbox_controller(ui, gamepad, confidence),
+        ControllerStyle::DualShock => draw_dualshock_controller(ui, gamepad, confidence),
+    }
+}
+/// `confidence` (per fired action, 0..=1) shades active controls; `None` draws them at full color.
+pub fn draw_xbox_controller(
+    ui: &mut egui::Ui,
+    gamepad: &GamepadState,
+    confidence: Option<&[(GamepadAction, f32)]>,
+) {
+    draw_pad(ui, gamepad, confidence, &XBOX_LAYOUT);
+}
+/// PlayStation-style pad (L1/L2, △○✕□) showing the same `GamepadState` as the Xbox view.
+pub fn draw_dualshock_controller(
+    ui: &mut egui::Ui,
+    gamepad: &GamepadState,
+    confidence: Option<&[(GamepadAction, f32)]>,
+) {
+    draw_pad(ui, gamepad, confidence, &DUALSHOCK_LAYOUT);
+}
+fn draw_pad(
+    ui: &mut egui::Ui,
+    gamepad: &GamepadState,
+    confidence: Option<&[(GamepadAction, f32)]>,
+    layout: &PadLayout,
+) {
+    use GamepadAction::*;
+    let level = |actions: &[GamepadAction]| confidence_level(confidence, actions);
     let body_color = Color32::from_rgb(50, 50, 55);
     let outline_color = Color32::from_rgb(80, 80, 85);
     let btn_base_color = Color32::from_rgb(70, 70, 75);
@@ -37,32 +187,39 @@ pub fn draw_xbox_controller(ui: &mut egui::Ui, gamepad: &GamepadState) {
     let trigger_size = Vec2::new(45.0, 20.0);
     let lt_pos = top_body_rect.left_center() + Vec2::new(trigger_size.x / 2.0 - 5.0, 0.0);
     let rt_pos = top_body_rect.right_center() - Vec2::new(trigger_size.x / 2.0 - 5.0, 0.0);
-    let draw_trigger = |center: Pos2, active: bool, label: &str| {
+    // 扳机按拉动量从下往上填充
+    let draw_trigger = |center: Pos2, pull: f32, label: &str, level: f32| {
         let r = Rect::from_center_size(center, trigger_size);
-        let fill = if active {
-            Color32::from_rgb(200, 50, 50)
-        } else {
-            btn_base_color
-        };
-        painter.rect_filled(r, Rounding::same(4.0), fill);
+        painter.rect_filled(r, Rounding::same(4.0), btn_base_color);
+        if pull > 0.0 {
+            painter.rect_filled(
+                trigger_fill(r, pull),
+                Rounding::same(4.0),
+                shaded(Color32::from_rgb(200, 50, 50), level),
+            );
+        }
         painter.rect_stroke(r, Rounding::same(4.0), Stroke::new(1.0, outline_color));
         painter.text(
             center,
             egui::Align2::CENTER_CENTER,
-            label,
-            egui::FontId::proportional(12.0),
-            if active { Color32::WHITE } else { text_color },
+            format!("{label} {:.0}%", pull.clamp(0.0, 1.0) * 100.0),
+            egui::FontId::proportional(11.0),
+            if pull > 0.5 {
+                Color32::WHITE
+            } else {
+                text_color
+            },
         );
     };
-    draw_trigger(lt_pos, gamepad.lt, "LT");
-    draw_trigger(rt_pos, gamepad.rt, "RT");
+    draw_trigger(lt_pos, gamepad.lt, layout.triggers[0], level(&[Lt]));
+    draw_trigger(rt_pos, gamepad.rt, layout.triggers[1], level(&[Rt]));
     let bumper_size = Vec2::new(40.0, 14.0);
     let lb_pos = lt_pos + Vec2::new(trigger_size.x / 2.0 + bumper_size.x / 2.0 + 2.0, 0.0);
     let rb_pos = rt_pos - Vec2::new(trigger_size.x / 2.0 + bumper_size.x / 2.0 + 2.0, 0.0);
-    let draw_bumper = |center: Pos2, active: bool, label: &str| {
+    let draw_bumper = |center: Pos2, active: bool, label: &str, level: f32| {
         let r = Rect::from_center_size(center, bumper_size);
         let fill = if active {
-            Color32::from_rgb(50, 200, 200)
+            shaded(Color32::from_rgb(50, 200, 200), level)
         } else {
             btn_base_color
         };
@@ -76,8 +233,8 @@ pub fn draw_xbox_controller(ui: &mut egui::Ui, gamepad: &GamepadState) {
             if active { Color32::BLACK } else { text_color },
         );
     };
-    draw_bumper(lb_pos, gamepad.lb, "LB");
-    draw_bumper(rb_pos, gamepad.rb, "RB");
+    draw_bumper(lb_pos, gamepad.lb, layout.bumpers[0], level(&[Lb]));
+    draw_bumper(rb_pos, gamepad.rb, layout.bumpers[1], level(&[Rb]));
     // 2. Face View
     let face_rect = Rect::from_min_size(
         top_left + Vec2::new(0.0, height_back + spacing),
@@ -91,26 +248,19 @@ pub fn draw_xbox_controller(ui: &mut egui::Ui, gamepad: &GamepadState) {
         text_color,
     );
     let fc = face_rect.center();
-    let body_points = vec![
-        fc + Vec2::new(-70.0, -40.0),
-        fc + Vec2::new(70.0, -40.0),
-        fc + Vec2::new(110.0, 20.0),
-        fc + Vec2::new(70.0, 60.0),
-        fc + Vec2::new(-70.0, 60.0),
-        fc + Vec2::new(-110.0, 20.0),
-    ];
+    let body_points = layout.body.iter().map(|&v| fc + v).collect();
     painter.add(Shape::convex_polygon(
-        body_points.clone(),
+        body_points,
         body_color,
         Stroke::new(1.5, outline_color),
     ));
-    let draw_stick = |c: Pos2, x: f32, y: f32, lbl: &str| {
+    let draw_stick = |c: Pos2, x: f32, y: f32, lbl: &str, level: f32| {
         painter.circle_filled(c, 22.0, btn_base_color);
         painter.circle_stroke(c, 22.0, Stroke::new(1.0, outline_color));
         let head = c + Vec2::new(x, -y) * 12.0;
         let act = x.abs() > 0.1 || y.abs() > 0.1;
         let col = if act {
-            Color32::from_rgb(0, 255, 255)
+            shaded(Color32::from_rgb(0, 255, 255), level)
         } else {
             Color32::from_rgb(60, 60, 65)
         };
@@ -119,14 +269,31 @@ pub fn draw_xbox_controller(ui: &mut egui::Ui, gamepad: &GamepadState) {
         painter.text(
             c + Vec2::new(0.0, 35.0),
             egui::Align2::CENTER_TOP,
-            lbl,
+            format!("{lbl} {:.2}", stick_magnitude(x, y)),
             egui::FontId::proportional(12.0),
             text_color,
         );
     };
-    draw_stick(fc + Vec2::new(-65.0, -10.0), gamepad.lx, gamepad.ly, "LS");
-    draw_stick(fc + Vec2::new(35.0, 30.0), gamepad.rx, gamepad.ry, "RS");
-    let dpad_c = fc + Vec2::new(-35.0, 30.0);
+    draw_stick(
+        fc + layout.left_stick,
+        gamepad.lx,
+        gamepad.ly,
+        "LS",
+        level(&[LeftStickUp, LeftStickDown, LeftStickLeft, LeftStickRight]),
+    );
+    draw_stick(
+        fc + layout.right_stick,
+        gamepad.rx,
+        gamepad.ry,
+        "RS",
+        level(&[
+            RightStickUp,
+            RightStickDown,
+            RightStickLeft,
+            RightStickRight,
+        ]),
+    );
+    let dpad_c = fc + layout.dpad;
     let d_sz = 10.0;
     let draw_dpad_arm = |offset: Vec2, active: bool| {
         let r = Rect::from_center_size(dpad_c + offset, Vec2::splat(d_sz));
@@ -143,24 +310,100 @@ pub fn draw_xbox_controller(ui: &mut egui::Ui, gamepad: &GamepadState) {
     draw_dpad_arm(Vec2::new(0.0, d_sz), gamepad.dpad_down);
     draw_dpad_arm(Vec2::new(-d_sz, 0.0), gamepad.dpad_left);
     draw_dpad_arm(Vec2::new(d_sz, 0.0), gamepad.dpad_right);
-    let btn_c = fc + Vec2::new(65.0, -30.0);
+    let btn_c = fc + layout.face;
     let b_rad = 11.0;
     let b_gap = 20.0;
-    let draw_face_btn = |offset: Vec2, active: bool, label: &str, color: Color32| {
-        let pos = btn_c + offset;
-        let fill = if active { color } else { btn_base_color };
-        painter.circle_filled(pos, b_rad, fill);
-        painter.circle_stroke(pos, b_rad, Stroke::new(1.0, outline_color));
-        painter.text(
-            pos,
-            egui::Align2::CENTER_CENTER,
-            label,
-            egui::FontId::proportional(14.0),
-            if active { Color32::BLACK } else { color },
-        );
-    };
-    draw_face_btn(Vec2::new(0.0, b_gap), gamepad.a, "A", Color32::GREEN);
-    draw_face_btn(Vec2::new(b_gap, 0.0), gamepad.b, "B", Color32::RED);
-    draw_face_btn(Vec2::new(-b_gap, 0.0), gamepad.x, "X", Color32::BLUE);
-    draw_face_btn(Vec2::new(0.0, -b_gap), gamepad.y, "Y", Color32::YELLOW);
+    let draw_face_btn =
+        |offset: Vec2, active: bool, glyph: FaceGlyph, color: Color32, level: f32| {
+            let pos = btn_c + offset;
+            let fill = if active {
+                shaded(color, level)
+            } else {
+                btn_base_color
+            };
+            painter.circle_filled(pos, b_rad, fill);
+            painter.circle_stroke(pos, b_rad, Stroke::new(1.0, outline_color));
+            let ink = if active { Color32::BLACK } else { color };
+            // PS 符号直接用线条画，不依赖字体里有没有这些字形
+            let stroke = Stroke::new(1.5, ink);
+            let r = b_rad * 0.5;
+            match glyph {
+                FaceGlyph::Letter(label) => {
+                    painter.text(
+                        pos,
+                        egui::Align2::CENTER_CENTER,
+                        label,
+                        egui::FontId::proportional(14.0),
+                        ink,
+                    );
+                }
+                FaceGlyph::Triangle => {
+                    let corners = [
+                        pos + Vec2::new(0.0, -r * 1.1),
+                        pos + Vec2::new(r, r * 0.7),
+                        pos + Vec2::new(-r, r * 0.7),
+                    ];
+                    painter.add(Shape::closed_line(corners.to_vec(), stroke));
+                }
+                FaceGlyph::Circle => {
+                    painter.circle_stroke(pos, r, stroke);
+                }
+                FaceGlyph::Cross => {
+                    painter.line_segment([pos + Vec2::splat(-r), pos + Vec2::splat(r)], stroke);
+                    painter.line_segment([pos + Vec2::new(-r, r), pos + Vec2::new(r, -r)], stroke);
+                }
+                FaceGlyph::Square => {
+                    painter.rect_stroke(
+                        Rect::from_center_size(pos, Vec2::splat(r * 1.7)),
+                        Rounding::ZERO,
+                        stroke,
+                    );
+                }
+            }
+        };
+    let [south, east, west, north] = layout.face_buttons;
+    draw_face_btn(
+        Vec2::new(0.0, b_gap),
+        gamepad.a,
+        south.0,
+        south.1,
+        level(&[A]),
+    );
+    draw_face_btn(
+        Vec2::new(b_gap, 0.0),
+        gamepad.b,
+        east.0,
+        east.1,
+        level(&[B]),
+    );
+    draw_face_btn(
+        Vec2::new(-b_gap, 0.0),
+        gamepad.x,
+        west.0,
+        west.1,
+        level(&[X]),
+    );
+    draw_face_btn(
+        Vec2::new(0.0, -b_gap),
+        gamepad.y,
+        north.0,
+        north.1,
+        level(&[Y]),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_pulled_trigger_fills_the_lower_half_of_its_rect() {
+        let r = Rect::from_min_size(Pos2::new(10.0, 20.0), Vec2::new(45.0, 20.0));
+        let half = trigger_fill(r, 0.5);
+        assert_eq!(half.height(), r.height() / 2.0);
+        assert_eq!((half.max, half.min.x), (r.max, r.min.x));
+        assert_eq!(trigger_fill(r, 1.5), r);
+        assert_eq!(trigger_fill(r, 0.0).height(), 0.0);
+        assert_eq!(stick_magnitude(0.6, -0.8), 1.0);
+    }
 }
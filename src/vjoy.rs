@@ -2,12 +2,28 @@
 use anyhow::{anyhow, Result};
 use libloading::{Library, Symbol};
 use std::sync::Arc;
+mod vigem;
+pub use vigem::VigemPad;
+/// 虚拟手柄输出后端 (vJoy 或 ViGEm)；按键/轴编号统一用 vJoy 的：按键 1-12，轴 0x30/0x31/0x33/0x34，
+/// 扳机为滑块 0x36/0x37；轴值 0..=32768 (摇杆中心 16384)
+pub trait VirtualGamepad {
+    fn name(&self) -> &'static str;
+    fn set_button(&self, btn_id: u8, down: bool);
+    fn set_axis(&self, axis_id: u32, value: i32);
+    fn reset(&self);
+    /// 只有 vJoy 有 POV 帽；其他后端从按键 9-12 读方向键
+    fn set_disc_pov(&self, _pov_id: u32, _direction: i32) {}
+    fn set_cont_pov(&self, _pov_id: u32, _value: i32) {}
+    /// 整帧状态设置完后调用；按整份报告更新的后端 (ViGEm) 在这里才真正发送
+    fn flush(&self) {}
+}
 // 定义函数签名
 type FnAcquire = unsafe extern "C" fn(u32) -> i32;
 type FnRelinquish = unsafe extern "C" fn(u32) -> i32;
 type FnSetBtn = unsafe extern "C" fn(i32, u32, u8) -> i32;
 type FnSetAxis = unsafe extern "C" fn(i32, u32, u32) -> i32;
 type FnReset = unsafe extern "C" fn(u32) -> i32;
+type FnSetPov = unsafe extern "C" fn(i32, u32, u8) -> i32;
 pub struct VJoyClient {
     lib: Arc<Library>,
     device_id: u32,
@@ -37,27 +53,48 @@ impl VJoyClient {
             Ok(())
         }
     }
-    pub fn reset(&self) {
+}
+impl VirtualGamepad for VJoyClient {
+    fn name(&self) -> &'static str {
+        "vJoy"
+    }
+    fn reset(&self) {
         unsafe {
             if let Ok(f) = self.lib.get::<FnReset>(b"ResetVJD") {
                 f(self.device_id);
             }
         }
     }
-    pub fn set_button(&self, btn_id: u8, down: bool) {
+    fn set_button(&self, btn_id: u8, down: bool) {
         unsafe {
             if let Ok(f) = self.lib.get::<FnSetBtn>(b"SetBtn") {
                 f(if down { 1 } else { 0 }, self.device_id, btn_id);
             }
         }
     }
-    pub fn set_axis(&self, axis_id: u32, value: i32) {
+    fn set_axis(&self, axis_id: u32, value: i32) {
         unsafe {
             if let Ok(f) = self.lib.get::<FnSetAxis>(b"SetAxis") {
                 f(value, self.device_id, axis_id);
             }
         }
     }
+    /// 4 向 POV：0 上、1 右、2 下、3 左，-1 回中
+    fn set_disc_pov(&self, pov_id: u32, direction: i32) {
+        unsafe {
+            if let Ok(f) = self.lib.get::<FnSetPov>(b"SetDiscPov") {
+                f(direction, self.device_id, pov_id as u8);
+            }
+        }
+    }
+    /// 连续 POV：0..=35999，单位 1/100 度，上为 0、顺时针；-1 回中
+    fn set_cont_pov(&self, pov_id: u32, value: i32) {
+        unsafe {
+            if let Ok(f) = self.lib.get::<FnSetPov>(b"SetContPov") {
+                f(value, self.device_id, pov_id as u8);
+            }
+        }
+    }
 }
 impl Drop for VJoyClient {
     fn drop(&mut self) {
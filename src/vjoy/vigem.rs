@@ -0,0 +1,191 @@
+// src/vjoy/vigem.rs
+// 没装 vJoy 时的备选：通过 ViGEmBus (ViGEmClient.dll) 模拟一个 Xbox 360 手柄，
+// 游戏直接按 XInput 识别，无需 Steam 映射。按键/轴编号沿用 vJoy 的，由这里换算成 XUSB 报告
+use super::VirtualGamepad;
+use anyhow::{anyhow, Result};
+use libloading::Library;
+use std::cell::Cell;
+use std::ffi::c_void;
+
+const VIGEM_ERROR_NONE: u32 = 0x2000_0000;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct XusbReport {
+    buttons: u16,
+    left_trigger: u8,
+    right_trigger: u8,
+    thumb_lx: i16,
+    thumb_ly: i16,
+    thumb_rx: i16,
+    thumb_ry: i16,
+}
+
+impl XusbReport {
+    /// vJoy button ids as `engine::vjoy_buttons` assigns them.
+    fn set_button(&mut self, btn_id: u8, down: bool) {
+        let bit = match btn_id {
+            1 => 0x1000,  // A
+            2 => 0x2000,  // B
+            3 => 0x4000,  // X
+            4 => 0x8000,  // Y
+            5 => 0x0100,  // LB
+            6 => 0x0200,  // RB
+            9 => 0x0001,  // 方向键上
+            10 => 0x0002, // 下
+            11 => 0x0004, // 左
+            12 => 0x0008, // 右
+            // LT/RT (7/8) 用滑块轴的模拟量，不按开关处理
+            _ => return,
+        };
+        if down {
+            self.buttons |= bit;
+        } else {
+            self.buttons &= !bit;
+        }
+    }
+
+    /// vJoy axis values (0..=32768, centre 16384) to signed XInput thumb values; the
+    /// trigger sliders become the 0..=255 trigger bytes.
+    fn set_axis(&mut self, axis_id: u32, value: i32) {
+        let thumb = ((value - 16384) * 2).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        let trigger = (value.clamp(0, 32768) * 255 / 32768) as u8;
+        match axis_id {
+            0x30 => self.thumb_lx = thumb,
+            0x31 => self.thumb_ly = thumb,
+            0x33 => self.thumb_rx = thumb,
+            0x34 => self.thumb_ry = thumb,
+            0x36 => self.left_trigger = trigger,
+            0x37 => self.right_trigger = trigger,
+            _ => {}
+        }
+    }
+}
+
+type FnAlloc = unsafe extern "C" fn() -> *mut c_void;
+type FnConnect = unsafe extern "C" fn(*mut c_void) -> u32;
+type FnTargetAdd = unsafe extern "C" fn(*mut c_void, *mut c_void) -> u32;
+type FnUpdate = unsafe extern "C" fn(*mut c_void, *mut c_void, XusbReport) -> u32;
+type FnFree = unsafe extern "C" fn(*mut c_void);
+
+/// Virtual Xbox 360 pad on ViGEmBus; the report is sent on `flush`.
+pub struct VigemPad {
+    lib: Library,
+    client: *mut c_void,
+    target: *mut c_void,
+    report: Cell<XusbReport>,
+}
+
+impl VigemPad {
+    pub fn new() -> Result<Self> {
+        unsafe {
+            let lib = Library::new("ViGEmClient.dll")
+                .map_err(|_| anyhow!("Failed to load ViGEmClient DLL"))?;
+            let client = lib.get::<FnAlloc>(b"vigem_alloc")?();
+            if client.is_null() {
+                return Err(anyhow!("ViGEm client alloc failed"));
+            }
+            let mut pad = Self {
+                lib,
+                client,
+                target: std::ptr::null_mut(),
+                report: Cell::new(XusbReport::default()),
+            };
+            // 之后任何一步失败都由 Drop 释放已分配的部分
+            if pad.lib.get::<FnConnect>(b"vigem_connect")?(client) != VIGEM_ERROR_NONE {
+                return Err(anyhow!("ViGEmBus not installed"));
+            }
+            pad.target = pad.lib.get::<FnAlloc>(b"vigem_target_x360_alloc")?();
+            if pad.target.is_null() {
+                return Err(anyhow!("ViGEm target alloc failed"));
+            }
+            let add = pad.lib.get::<FnTargetAdd>(b"vigem_target_add")?;
+            if add(client, pad.target) != VIGEM_ERROR_NONE {
+                pad.lib.get::<FnFree>(b"vigem_target_free")?(pad.target);
+                pad.target = std::ptr::null_mut();
+                return Err(anyhow!("Failed to plug in the virtual Xbox 360 pad"));
+            }
+            Ok(pad)
+        }
+    }
+}
+
+impl VirtualGamepad for VigemPad {
+    fn name(&self) -> &'static str {
+        "ViGEm (Xbox 360)"
+    }
+
+    fn set_button(&self, btn_id: u8, down: bool) {
+        let mut report = self.report.get();
+        report.set_button(btn_id, down);
+        self.report.set(report);
+    }
+
+    fn set_axis(&self, axis_id: u32, value: i32) {
+        let mut report = self.report.get();
+        report.set_axis(axis_id, value);
+        self.report.set(report);
+    }
+
+    fn reset(&self) {
+        self.report.set(XusbReport::default());
+        self.flush();
+    }
+
+    fn flush(&self) {
+        unsafe {
+            if let Ok(f) = self.lib.get::<FnUpdate>(b"vigem_target_x360_update") {
+                f(self.client, self.target, self.report.get());
+            }
+        }
+    }
+}
+
+impl Drop for VigemPad {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.target.is_null() {
+                if let Ok(f) = self.lib.get::<FnTargetAdd>(b"vigem_target_remove") {
+                    f(self.client, self.target);
+                }
+                if let Ok(f) = self.lib.get::<FnFree>(b"vigem_target_free") {
+                    f(self.target);
+                }
+            }
+            if let Ok(f) = self.lib.get::<FnFree>(b"vigem_disconnect") {
+                f(self.client);
+            }
+            if let Ok(f) = self.lib.get::<FnFree>(b"vigem_free") {
+                f(self.client);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vjoy_ids_land_on_the_matching_xinput_controls() {
+        let mut report = XusbReport::default();
+        for id in [1, 4, 5, 9] {
+            report.set_button(id, true);
+        }
+        report.set_button(8, true);
+        report.set_axis(0x37, 32768);
+        report.set_axis(0x36, 8192);
+        report.set_axis(0x30, 384);
+        report.set_axis(0x31, 32384);
+        report.set_axis(0x34, 16384);
+        assert_eq!(report.buttons, 0x1000 | 0x8000 | 0x0100 | 0x0001);
+        assert_eq!((report.left_trigger, report.right_trigger), (63, u8::MAX));
+        assert_eq!((report.thumb_lx, report.thumb_ly), (-32000, 32000));
+        assert_eq!(report.thumb_ry, 0);
+
+        report.set_button(1, false);
+        report.set_axis(0x37, 0);
+        assert_eq!(report.buttons, 0x8000 | 0x0100 | 0x0001);
+        assert_eq!(report.right_trigger, 0);
+    }
+}
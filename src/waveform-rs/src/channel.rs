@@ -77,6 +77,14 @@ impl ChannelState {
                 let pad = ((max - min) * 0.1).max(1.0);
                 (min - pad, max + pad)
             }
+            YScale::AutoQuantile { low, high } => {
+                let mut values: Vec<f32> = samples.iter().map(|s| s.value).collect();
+                values.sort_by(|a, b| a.total_cmp(b));
+                let lo = quantile(&values, low.min(high));
+                let hi = quantile(&values, high.max(low));
+                let pad = ((hi - lo) * 0.1).max(1.0);
+                (lo - pad, hi + pad)
+            }
             YScale::FixedMicrovolts(mag) => (-mag, mag),
         };
         // Shift timestamps so callers can draw relative to the newest point if they want.
@@ -105,6 +113,11 @@ impl ChannelState {
         self.config.y_scale = y_scale;
     }
     fn set_filters(&mut self, sample_rate_hz: f32, filters: Vec<FilterKind>) {
+        if filters == self.config.filters {
+            // Same design: keep the sections, only drop their history.
+            self.filters.reset();
+            return;
+        }
         self.config.filters = filters;
         self.filters = FilterChain::from_kinds(sample_rate_hz, &self.config.filters);
     }
@@ -112,6 +125,14 @@ impl ChannelState {
         self.config.enabled = enabled;
     }
 }
+/// Linearly interpolated quantile of already sorted, non-empty `values`.
+fn quantile(values: &[f32], q: f32) -> f32 {
+    let pos = q.clamp(0.0, 1.0) * (values.len() - 1) as f32;
+    let below = pos.floor() as usize;
+    let above = (below + 1).min(values.len() - 1);
+    let frac = pos - below as f32;
+    values[below] + (values[above] - values[below]) * frac
+}
 pub struct WaveformPipeline {
     sample_rate_hz: f32,
     time_window: TimeWindow,
@@ -155,6 +176,12 @@ impl WaveformPipeline {
             ch.set_filters(self.sample_rate_hz, filters);
         }
     }
+    /// Clears every channel's filter history, e.g. when a stream resumes after a gap.
+    pub fn reset_filters(&mut self) {
+        for channel in &mut self.channels {
+            channel.filters.reset();
+        }
+    }
     /// Ingest a single multi-channel frame. `timestamp_secs` should be monotonic.
     pub fn ingest_frame(&mut self, timestamp_secs: f32, microvolts_by_channel: &[f32]) {
         for (idx, value) in microvolts_by_channel.iter().enumerate() {
@@ -195,3 +222,22 @@ impl WaveformPipeline {
         }
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn quantile_range_ignores_a_single_outlier() {
+        let mut pipe = WaveformPipeline::new(1, 250.0);
+        for i in 0..500 {
+            let value = if i == 250 { 5000.0 } else { (i % 20) as f32 - 10.0 };
+            pipe.ingest_frame(i as f32 / 250.0, &[value]);
+        }
+        pipe.set_global_y_scale(YScale::Auto);
+        let (auto_lo, auto_hi) = pipe.view().channels[0].y_range;
+        pipe.set_global_y_scale(YScale::AutoQuantile { low: 0.01, high: 0.99 });
+        let (lo, hi) = pipe.view().channels[0].y_range;
+        assert!(auto_hi > 5000.0);
+        assert!(hi < 20.0 && lo > auto_lo - 1.0, "quantile range ({lo}, {hi})");
+        assert!(hi - lo < (auto_hi - auto_lo) / 10.0);
+    }
+}
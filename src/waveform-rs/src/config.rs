@@ -3,6 +3,9 @@ pub enum YScale {
     /// Match the Processing implementation: compute the min and max of the visible window
     /// once per refresh and use that as the Y axis range.
     Auto,
+    /// Like `Auto`, but spans the `low`..`high` quantiles (0.0-1.0) of the visible window,
+    /// so a few extreme samples don't flatten the trace.
+    AutoQuantile { low: f32, high: f32 },
     /// Fix the Y axis to +/- the provided microvolt value.
     FixedMicrovolts(f32),
 }
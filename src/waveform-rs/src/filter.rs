@@ -1,11 +1,18 @@
 use std::f32::consts::PI;
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FilterKind {
     Notch { freq_hz: f32, q: f32 },
     Highpass { cutoff_hz: f32, q: f32 },
     Lowpass { cutoff_hz: f32, q: f32 },
     Bandpass { low_hz: f32, high_hz: f32, q: f32 },
     Bandstop { low_hz: f32, high_hz: f32, q: f32 },
+    /// Maximally flat cascade; `order` (2, 4, 6 or 8) sets the roll-off at 6 dB/octave per order.
+    ButterworthHighpass { cutoff_hz: f32, order: usize },
+    ButterworthLowpass { cutoff_hz: f32, order: usize },
+    /// Butterworth highpass at `low_hz` followed by a lowpass at `high_hz`, each of `order`.
+    ButterworthBandpass { low_hz: f32, high_hz: f32, order: usize },
+    /// Mean of the last `window_samples` inputs (at least 1), updated recursively.
+    MovingAverage { window_samples: usize },
 }
 #[derive(Clone, Copy, Debug)]
 struct BiquadCoeffs {
@@ -40,9 +47,67 @@ impl BiquadFilter {
         y
     }
 }
+/// Running mean: each sample adds the newest input to the sum and drops the oldest,
+/// so the cost per sample does not grow with the window. The sum is kept in f64 to
+/// avoid drift over long streams.
+#[derive(Clone, Debug)]
+struct RunningMean {
+    history: Vec<f32>,
+    next: usize,
+    sum: f64,
+}
+impl RunningMean {
+    fn new(window_samples: usize) -> Self {
+        Self {
+            history: vec![0.0; window_samples.max(1)],
+            next: 0,
+            sum: 0.0,
+        }
+    }
+    fn process(&mut self, input: f32) -> f32 {
+        self.sum += input as f64 - self.history[self.next] as f64;
+        self.history[self.next] = input;
+        self.next = (self.next + 1) % self.history.len();
+        (self.sum / self.history.len() as f64) as f32
+    }
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.next = 0;
+        self.sum = 0.0;
+    }
+    /// |sin(Nω/2) / (N sin(ω/2))|, the Dirichlet kernel of an N-point mean.
+    fn gain(&self, w: f64) -> f64 {
+        let n = self.history.len() as f64;
+        let den = n * (w / 2.0).sin();
+        if den.abs() < 1e-12 {
+            1.0
+        } else {
+            ((n * w / 2.0).sin() / den).abs()
+        }
+    }
+}
+#[derive(Clone, Debug)]
+enum Stage {
+    Biquad(BiquadFilter),
+    MovingAverage(RunningMean),
+}
+impl Stage {
+    fn process(&mut self, input: f32) -> f32 {
+        match self {
+            Stage::Biquad(section) => section.process(input),
+            Stage::MovingAverage(mean) => mean.process(input),
+        }
+    }
+    fn reset(&mut self) {
+        match self {
+            Stage::Biquad(section) => section.state = BiquadState::default(),
+            Stage::MovingAverage(mean) => mean.reset(),
+        }
+    }
+}
 #[derive(Default, Debug)]
 pub struct FilterChain {
-    sections: Vec<BiquadFilter>,
+    sections: Vec<Stage>,
 }
 impl FilterChain {
     pub fn empty() -> Self {
@@ -58,44 +123,139 @@ impl FilterChain {
     pub fn is_empty(&self) -> bool {
         self.sections.is_empty()
     }
+    /// Steady-state gain in dB at each of `freqs_hz`, evaluated from the section coefficients.
+    pub fn magnitude_response(&self, sample_rate_hz: f32, freqs_hz: &[f32]) -> Vec<f32> {
+        freqs_hz
+            .iter()
+            .map(|&freq| {
+                let w = 2.0 * std::f64::consts::PI * freq as f64 / sample_rate_hz as f64;
+                let (c1, s1, c2, s2) = (w.cos(), w.sin(), (2.0 * w).cos(), (2.0 * w).sin());
+                let gain: f64 = self
+                    .sections
+                    .iter()
+                    .map(|stage| {
+                        let section = match stage {
+                            Stage::Biquad(section) => section,
+                            Stage::MovingAverage(mean) => return mean.gain(w),
+                        };
+                        let c = section.coeffs;
+                        let (b0, b1, b2) = (c.b0 as f64, c.b1 as f64, c.b2 as f64);
+                        let (a1, a2) = (c.a1 as f64, c.a2 as f64);
+                        let num = (b0 + b1 * c1 + b2 * c2).hypot(b1 * s1 + b2 * s2);
+                        let den = (1.0 + a1 * c1 + a2 * c2).hypot(a1 * s1 + a2 * s2);
+                        num / den
+                    })
+                    .product();
+                (20.0 * gain.log10()) as f32
+            })
+            .collect()
+    }
+    /// Zeroes every section's history so the next sample is filtered as if the chain were new.
+    pub fn reset(&mut self) {
+        for section in &mut self.sections {
+            section.reset();
+        }
+    }
     pub fn process_sample(&mut self, mut value: f32) -> f32 {
         for section in &mut self.sections {
             value = section.process(value);
         }
         value
     }
+    /// Zero-phase filtering of a whole buffer (filtfilt): the chain runs forward, then
+    /// backward over the reversed result, so the magnitude response is squared and the
+    /// phase shift cancels. Both ends get an odd reflection of `3 * (2 * sections + 1)`
+    /// samples to keep start-up transients out of the output. History is cleared before
+    /// and after, so no live state leaks in or out.
+    pub fn process_filtfilt(&mut self, samples: &[f32]) -> Vec<f32> {
+        if samples.len() < 2 || self.sections.is_empty() {
+            return samples.to_vec();
+        }
+        let n = samples.len();
+        let pad = (3 * (2 * self.sections.len() + 1)).min(n - 1);
+        let (first, last) = (samples[0], samples[n - 1]);
+        let mut padded = Vec::with_capacity(n + 2 * pad);
+        padded.extend((1..=pad).rev().map(|i| 2.0 * first - samples[i]));
+        padded.extend_from_slice(samples);
+        padded.extend((1..=pad).map(|i| 2.0 * last - samples[n - 1 - i]));
+        self.reset();
+        let mut forward: Vec<f32> = padded.iter().map(|&v| self.process_sample(v)).collect();
+        forward.reverse();
+        self.reset();
+        let mut out: Vec<f32> = forward.iter().map(|&v| self.process_sample(v)).collect();
+        out.reverse();
+        self.reset();
+        out.drain(pad..pad + n).collect()
+    }
 }
-fn design_sections(sample_rate_hz: f32, kind: FilterKind) -> Vec<BiquadFilter> {
+fn design_sections(sample_rate_hz: f32, kind: FilterKind) -> Vec<Stage> {
     let nyquist = sample_rate_hz * 0.5;
     match kind {
+        FilterKind::MovingAverage { window_samples } => {
+            vec![Stage::MovingAverage(RunningMean::new(window_samples))]
+        }
         FilterKind::Notch { freq_hz, q } => {
             let coeffs = notch(nyquist_clamp(freq_hz, nyquist), sample_rate_hz, q);
-            vec![BiquadFilter::new(coeffs)]
+            vec![Stage::Biquad(BiquadFilter::new(coeffs))]
         }
         FilterKind::Highpass { cutoff_hz, q } => {
             let coeffs = highpass(nyquist_clamp(cutoff_hz, nyquist), sample_rate_hz, q);
-            vec![BiquadFilter::new(coeffs)]
+            vec![Stage::Biquad(BiquadFilter::new(coeffs))]
         }
         FilterKind::Lowpass { cutoff_hz, q } => {
             let coeffs = lowpass(nyquist_clamp(cutoff_hz, nyquist), sample_rate_hz, q);
-            vec![BiquadFilter::new(coeffs)]
+            vec![Stage::Biquad(BiquadFilter::new(coeffs))]
         }
         FilterKind::Bandpass { low_hz, high_hz, q } => {
             let (low, high) = band_edges(low_hz, high_hz, nyquist);
             let center = (low * high).sqrt();
             let q_val = q.max(0.1).min(100.0).min(center / (high - low));
             let coeffs = bandpass(center, sample_rate_hz, q_val);
-            vec![BiquadFilter::new(coeffs)]
+            vec![Stage::Biquad(BiquadFilter::new(coeffs))]
         }
         FilterKind::Bandstop { low_hz, high_hz, q } => {
             let (low, high) = band_edges(low_hz, high_hz, nyquist);
             let center = (low * high).sqrt();
             let q_val = q.max(0.1).min(100.0).min(center / (high - low));
             let coeffs = notch(center, sample_rate_hz, q_val);
-            vec![BiquadFilter::new(coeffs)]
+            vec![Stage::Biquad(BiquadFilter::new(coeffs))]
+        }
+        FilterKind::ButterworthHighpass { cutoff_hz, order } => {
+            let freq = nyquist_clamp(cutoff_hz, nyquist);
+            butterworth_qs(order)
+                .map(|q| Stage::Biquad(BiquadFilter::new(highpass_exact(freq, sample_rate_hz, q))))
+                .collect()
+        }
+        FilterKind::ButterworthLowpass { cutoff_hz, order } => {
+            let freq = nyquist_clamp(cutoff_hz, nyquist);
+            butterworth_qs(order)
+                .map(|q| Stage::Biquad(BiquadFilter::new(lowpass_exact(freq, sample_rate_hz, q))))
+                .collect()
+        }
+        FilterKind::ButterworthBandpass {
+            low_hz,
+            high_hz,
+            order,
+        } => {
+            let (low, high) = band_edges(low_hz, high_hz, nyquist);
+            let highpass = FilterKind::ButterworthHighpass { cutoff_hz: low, order };
+            let lowpass = FilterKind::ButterworthLowpass { cutoff_hz: high, order };
+            let mut stages = design_sections(sample_rate_hz, highpass);
+            stages.extend(design_sections(sample_rate_hz, lowpass));
+            stages
         }
     }
 }
+/// Q of each biquad section of an even-order Butterworth filter, one per conjugate
+/// pole pair: Q_k = 1 / (2 cos θ_k) with pole angles θ_k = (2k - 1)π / 2N.
+/// Odd or out-of-range orders are rounded down into 2..=8.
+fn butterworth_qs(order: usize) -> impl Iterator<Item = f32> {
+    let order = order.clamp(2, 8) / 2 * 2;
+    (1..=order / 2).map(move |k| {
+        let theta = (2 * k - 1) as f32 * PI / (2 * order) as f32;
+        1.0 / (2.0 * theta.cos())
+    })
+}
 fn nyquist_clamp(freq_hz: f32, nyquist: f32) -> f32 {
     freq_hz.clamp(0.01, nyquist - 0.01)
 }
@@ -106,7 +266,14 @@ fn band_edges(low_hz: f32, high_hz: f32, nyquist: f32) -> (f32, f32) {
 }
 fn lowpass(freq_hz: f32, sample_rate_hz: f32, q: f32) -> BiquadCoeffs {
     let w0 = 2.0 * PI * freq_hz / sample_rate_hz;
-    let alpha = (w0 / 2.0).sin() / (2.0 * q);
+    lowpass_alpha(w0, (w0 / 2.0).sin() / (2.0 * q))
+}
+/// RBJ lowpass with the exact bandwidth term, so cascaded sections keep -3 dB at `freq_hz`.
+fn lowpass_exact(freq_hz: f32, sample_rate_hz: f32, q: f32) -> BiquadCoeffs {
+    let w0 = 2.0 * PI * freq_hz / sample_rate_hz;
+    lowpass_alpha(w0, w0.sin() / (2.0 * q))
+}
+fn lowpass_alpha(w0: f32, alpha: f32) -> BiquadCoeffs {
     let cos_w0 = w0.cos();
     let b0 = (1.0 - cos_w0) * 0.5;
     let b1 = 1.0 - cos_w0;
@@ -118,7 +285,13 @@ fn lowpass(freq_hz: f32, sample_rate_hz: f32, q: f32) -> BiquadCoeffs {
 }
 fn highpass(freq_hz: f32, sample_rate_hz: f32, q: f32) -> BiquadCoeffs {
     let w0 = 2.0 * PI * freq_hz / sample_rate_hz;
-    let alpha = (w0 / 2.0).sin() / (2.0 * q);
+    highpass_alpha(w0, (w0 / 2.0).sin() / (2.0 * q))
+}
+fn highpass_exact(freq_hz: f32, sample_rate_hz: f32, q: f32) -> BiquadCoeffs {
+    let w0 = 2.0 * PI * freq_hz / sample_rate_hz;
+    highpass_alpha(w0, w0.sin() / (2.0 * q))
+}
+fn highpass_alpha(w0: f32, alpha: f32) -> BiquadCoeffs {
     let cos_w0 = w0.cos();
     let b0 = (1.0 + cos_w0) * 0.5;
     let b1 = -(1.0 + cos_w0);
@@ -163,3 +336,106 @@ fn normalize(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> BiquadCoef
         a2: a2 * a0_inv,
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn response_db(kind: FilterKind, freq_hz: f32, sample_rate_hz: f32) -> f64 {
+        FilterChain::from_kinds(sample_rate_hz, &[kind])
+            .magnitude_response(sample_rate_hz, &[freq_hz])[0] as f64
+    }
+    #[test]
+    fn reset_chain_matches_fresh_chain() {
+        let kinds = [
+            FilterKind::Notch { freq_hz: 50.0, q: 35.0 },
+            FilterKind::ButterworthHighpass { cutoff_hz: 1.0, order: 4 },
+        ];
+        let mut used = FilterChain::from_kinds(250.0, &kinds);
+        for _ in 0..100 {
+            used.process_sample(100.0);
+        }
+        used.reset();
+        let mut fresh = FilterChain::from_kinds(250.0, &kinds);
+        for _ in 0..50 {
+            assert_eq!(used.process_sample(100.0), fresh.process_sample(100.0));
+        }
+    }
+    #[test]
+    fn filtfilt_keeps_a_symmetric_pulse_centred() {
+        let centre = 200;
+        let pulse: Vec<f32> = (0..400)
+            .map(|i| {
+                let t = (i as f32 - centre as f32) / 6.0;
+                100.0 * (-0.5 * t * t).exp()
+            })
+            .collect();
+        let peak_at = |x: &[f32]| (0..x.len()).max_by(|&a, &b| x[a].total_cmp(&x[b])).unwrap();
+        let kind = FilterKind::ButterworthLowpass { cutoff_hz: 15.0, order: 4 };
+        let mut chain = FilterChain::from_kinds(250.0, &[kind]);
+        let causal: Vec<f32> = pulse.iter().map(|&v| chain.process_sample(v)).collect();
+        let zero_phase = chain.process_filtfilt(&pulse);
+        assert_eq!(zero_phase.len(), pulse.len());
+        // The causal pass lags by its group delay; forward-backward cancels it.
+        assert!(peak_at(&causal) > centre + 2, "causal peak at {}", peak_at(&causal));
+        assert_eq!(peak_at(&zero_phase), centre);
+        for k in 1..50 {
+            let (l, r) = (zero_phase[centre - k], zero_phase[centre + k]);
+            assert!((l - r).abs() < 1e-2, "asymmetric at ±{k}: {l} vs {r}");
+        }
+        // The chain is left reset for live use.
+        let mut fresh = FilterChain::from_kinds(250.0, &[kind]);
+        assert_eq!(chain.process_sample(1.0), fresh.process_sample(1.0));
+    }
+    #[test]
+    fn moving_average_ramps_a_step_over_its_window() {
+        let window = 10;
+        let kind = FilterKind::MovingAverage { window_samples: window };
+        let mut chain = FilterChain::from_kinds(250.0, &[kind]);
+        for _ in 0..25 {
+            assert_eq!(chain.process_sample(0.0), 0.0);
+        }
+        let ramp: Vec<f32> = (0..3 * window).map(|_| chain.process_sample(5.0)).collect();
+        for (k, &v) in ramp.iter().enumerate().take(window) {
+            let expected = 5.0 * (k + 1) as f32 / window as f32;
+            assert!((v - expected).abs() < 1e-5, "sample {k}: {v} vs {expected}");
+        }
+        assert!(ramp[window..].iter().all(|&v| (v - 5.0).abs() < 1e-5));
+        // An N-point mean nulls fs/N and passes DC untouched.
+        let db = chain.magnitude_response(250.0, &[0.0, 25.0]);
+        assert!(db[0].abs() < 1e-3 && db[1] < -60.0, "{db:?}");
+    }
+    #[test]
+    fn butterworth_is_3db_down_at_cutoff() {
+        for order in [2, 4, 6, 8] {
+            for kind in [
+                FilterKind::ButterworthHighpass { cutoff_hz: 10.0, order },
+                FilterKind::ButterworthLowpass { cutoff_hz: 30.0, order },
+            ] {
+                assert_eq!(design_sections(250.0, kind).len(), order / 2);
+                let cutoff = match kind {
+                    FilterKind::ButterworthHighpass { cutoff_hz, .. }
+                    | FilterKind::ButterworthLowpass { cutoff_hz, .. } => cutoff_hz,
+                    _ => unreachable!(),
+                };
+                let db = response_db(kind, cutoff, 250.0);
+                assert!((db + 3.01).abs() < 0.05, "{kind:?}: {db:.3} dB at cutoff");
+            }
+        }
+    }
+    #[test]
+    fn order_8_highpass_attenuates_1hz_by_40db() {
+        let kind = FilterKind::ButterworthHighpass { cutoff_hz: 10.0, order: 8 };
+        assert!(response_db(kind, 1.0, 250.0) <= -40.0);
+        // Passband stays flat well above the cutoff.
+        assert!(response_db(kind, 40.0, 250.0).abs() < 0.1);
+        let band = FilterKind::ButterworthBandpass { low_hz: 8.0, high_hz: 30.0, order: 8 };
+        assert!(response_db(band, 1.0, 250.0) <= -40.0);
+        assert!(response_db(band, 15.0, 250.0).abs() < 0.5);
+        // The same through the streaming path.
+        let mut chain = FilterChain::from_kinds(250.0, &[kind]);
+        let out: Vec<f32> = (0..2500)
+            .map(|i| chain.process_sample((2.0 * PI * i as f32 / 250.0).sin()))
+            .collect();
+        let tail_peak = out[1250..].iter().fold(0.0f32, |m, v| m.max(v.abs()));
+        assert!(tail_peak < 0.01, "1 Hz leaked through at {tail_peak}");
+    }
+}
@@ -14,8 +14,57 @@ pub struct ChannelView {
     pub max: f32,
     pub samples: Vec<SamplePoint>,
 }
+impl ChannelView {
+    /// Min/max envelope of `samples` in at most `target_points` points: each bucket of
+    /// consecutive samples keeps its lowest and highest one, in time order, so the
+    /// polyline zig-zags through every peak instead of skipping it like `step_by` would.
+    pub fn downsample_envelope(&self, target_points: usize) -> Vec<SamplePoint> {
+        let buckets = target_points / 2;
+        if buckets == 0 || self.samples.len() <= target_points {
+            return self.samples.clone();
+        }
+        let bucket_len = self.samples.len().div_ceil(buckets);
+        let mut out = Vec::with_capacity(buckets * 2);
+        for bucket in self.samples.chunks(bucket_len) {
+            let (mut lo, mut hi) = (0, 0);
+            for (i, s) in bucket.iter().enumerate() {
+                if s.value < bucket[lo].value {
+                    lo = i;
+                }
+                if s.value > bucket[hi].value {
+                    hi = i;
+                }
+            }
+            out.push(bucket[lo.min(hi)]);
+            if lo != hi {
+                out.push(bucket[lo.max(hi)]);
+            }
+        }
+        out
+    }
+}
 #[derive(Debug)]
 pub struct WaveformView {
     pub window_secs: f32,
     pub channels: Vec<ChannelView>,
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn single_sample_spike_survives_envelope_downsampling() {
+        let samples: Vec<SamplePoint> = (0..10_000)
+            .map(|i| SamplePoint {
+                time: i as f32 / 250.0,
+                value: if i == 4_321 { 900.0 } else { ((i % 7) as f32 - 3.0) * 2.0 },
+            })
+            .collect();
+        let view = ChannelView { index: 0, y_range: (-1000.0, 1000.0), rms_u_v: 0.0, min: -6.0, max: 900.0, samples };
+        let points = view.downsample_envelope(500);
+        assert!(points.len() <= 500, "{} points", points.len());
+        assert!(points.iter().any(|p| p.value == 900.0));
+        assert!(points.windows(2).all(|w| w[0].time < w[1].time));
+        let stepped_peak = view.samples.iter().step_by(20).map(|s| s.value).fold(f32::MIN, f32::max);
+        assert!(stepped_peak < 900.0, "step_by happens to keep the spike");
+    }
+}